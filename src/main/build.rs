@@ -81,7 +81,11 @@ fn run_cbindgen(build_common: &ShadowBuildCommon) {
             include_guard: Some("main_opaque_bindings_h".into()),
             no_includes: true,
             export: cbindgen::ExportConfig {
-                include: vec!["QDiscMode".into()],
+                include: vec![
+                    "QDiscMode".into(),
+                    "FirewallAction".into(),
+                    "FirewallDirection".into(),
+                ],
                 item_types: vec![cbindgen::ItemType::OpaqueItems, cbindgen::ItemType::Enums],
                 ..base_config.export.clone()
             },
@@ -191,7 +195,10 @@ fn run_bindgen(build_common: &ShadowBuildCommon) {
         .allowlist_type("LogInfoFlags")
         .allowlist_type("SimulationTime")
         .allowlist_type("ProtocolTCPFlags")
+        .allowlist_type("ProtocolICMPType")
+        .allowlist_type("ProtocolICMPCode")
         .allowlist_type("PacketDeliveryStatusFlags")
+        .allowlist_var("CONFIG_HEADER_SIZE_IP")
         .allowlist_var("CONFIG_HEADER_SIZE_TCP")
         .allowlist_var("CONFIG_PIPE_BUFFER_SIZE")
         .allowlist_var("CONFIG_MTU")
@@ -223,6 +230,8 @@ fn run_bindgen(build_common: &ShadowBuildCommon) {
         .opaque_type("WorkerPool")
         .blocklist_type("HashSet_String")
         .blocklist_type("QDiscMode")
+        .blocklist_type("FirewallAction")
+        .blocklist_type("FirewallDirection")
         // Imported from libc crate below
         .blocklist_type("siginfo_t")
         .disable_header_comment()
@@ -232,6 +241,7 @@ fn run_bindgen(build_common: &ShadowBuildCommon) {
         .raw_line("use crate::core::main::ShadowBuildInfo;")
         .raw_line("use crate::core::support::configuration::ConfigOptions;")
         .raw_line("use crate::core::support::configuration::QDiscMode;")
+        .raw_line("use crate::core::support::configuration::{FirewallAction, FirewallDirection};")
         .raw_line("use crate::host::descriptor::File;")
         .raw_line("use crate::host::descriptor::OpenFile;")
         .raw_line("use crate::host::descriptor::socket::inet::InetSocket;")
@@ -54,7 +54,9 @@ impl<'a> Controller<'a> {
             random: Xoshiro256PlusPlus::from_rng(&mut sim_config.random).unwrap(),
             ip_assignment: sim_config.ip_assignment,
             routing_info: sim_config.routing_info,
+            topology_schedule: sim_config.topology_schedule,
             host_bandwidths: sim_config.host_bandwidths,
+            nats: sim_config.nats,
             hosts: sim_config.hosts,
         };
 
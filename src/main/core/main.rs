@@ -10,10 +10,14 @@ use signal_hook::{consts, iterator::Signals};
 
 use crate::core::controller::Controller;
 use crate::core::logger::shadow_logger;
+use crate::core::sim_config;
 use crate::core::sim_config::SimConfig;
-use crate::core::support::configuration::{CliOptions, ConfigFileOptions, ConfigOptions};
+use crate::core::support::configuration::{
+    CliOptions, ConfigFileOptions, ConfigOptions, GraphReportFormat,
+};
 use crate::core::worker;
 use crate::cshadow as c;
+use crate::network::graph::report::GraphReport;
 use crate::utility::shm_cleanup;
 
 /// Main entry point for the simulator.
@@ -91,6 +95,10 @@ pub fn run_shadow(build_info: &ShadowBuildInfo, args: Vec<&OsStr>) -> anyhow::Re
         return Ok(());
     }
 
+    if let Some(format) = options.validate_graph {
+        return validate_graph(&shadow_config, format);
+    }
+
     // run any global C configuration handlers
     unsafe { c::runConfigHandlers(&shadow_config as *const ConfigOptions) };
 
@@ -215,6 +223,60 @@ pub fn run_shadow(build_info: &ShadowBuildInfo, args: Vec<&OsStr>) -> anyhow::Re
     Ok(())
 }
 
+/// Load the network graph and print a report of the paths between the hosts' graph nodes, without
+/// running the simulation. Returns an error if any host can't reach another host.
+fn validate_graph(config: &ConfigOptions, format: GraphReportFormat) -> anyhow::Result<()> {
+    let graph = sim_config::load_graph(config)?;
+
+    // a host's additional interfaces are reported as "<host>/<index>", starting at 1
+    let hosts = config
+        .hosts
+        .iter()
+        .flat_map(|(name, host)| {
+            std::iter::once((name.clone(), host.network_node_id)).chain(
+                host.interfaces
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, x)| (format!("{name}/{}", i + 1), x.network_node_id)),
+            )
+        })
+        .collect();
+
+    let report = GraphReport::new(&graph, &hosts, config.network.use_shortest_path.unwrap())
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    for id in &report.nodes_without_self_loops {
+        eprintln!("Graph node {id} has hosts but no self-loop");
+    }
+    for (source, target) in &report.asymmetric_edges {
+        eprintln!("Graph edge {source}->{target} has no edge in the opposite direction");
+    }
+    for pair in &report.unreachable_node_pairs {
+        eprintln!(
+            "Graph node {} (hosts: {}) has no path to graph node {} (hosts: {})",
+            pair.source,
+            pair.source_hosts.join(", "),
+            pair.target,
+            pair.target_hosts.join(", "),
+        );
+    }
+
+    let stdout = std::io::stdout().lock();
+    match format {
+        GraphReportFormat::Csv => report.write_paths_csv(stdout)?,
+        GraphReportFormat::Json => report.write_json(stdout)?,
+    }
+
+    if !report.is_routable() {
+        return Err(anyhow::anyhow!(
+            "The network graph has no path between {} pairs of the hosts' graph nodes",
+            report.unreachable_node_pairs.len()
+        ));
+    }
+
+    Ok(())
+}
+
 fn load_config_file(
     filename: impl AsRef<std::path::Path>,
     extended_yaml: bool,
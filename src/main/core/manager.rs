@@ -16,13 +16,15 @@ use shadow_shim_helper_rs::HostId;
 use crate::core::controller::{Controller, ShadowStatusBarState, SimController};
 use crate::core::scheduler::runahead::Runahead;
 use crate::core::scheduler::{HostIter, Scheduler, ThreadPerCoreSched, ThreadPerHostSched};
-use crate::core::sim_config::{Bandwidth, HostInfo};
+use crate::core::sim_config::{Bandwidth, HostInfo, TopologySchedule};
 use crate::core::sim_stats;
 use crate::core::support::configuration::{self, ConfigOptions, Flatten, LogLevel};
 use crate::core::worker;
 use crate::cshadow as c;
-use crate::host::host::{Host, HostParameters};
+use crate::host::host::{Host, HostParameters, InterfaceParameters};
 use crate::network::graph::{IpAssignment, RoutingInfo};
+use crate::network::link::Links;
+use crate::network::nat::Nats;
 use crate::utility::childpid_watcher::ChildPidWatcher;
 use crate::utility::status_bar::Status;
 use crate::utility::{self, SyncSendPointer};
@@ -226,13 +228,23 @@ impl<'a> Manager<'a> {
         let bootstrap_end_time: SimulationTime = bootstrap_end_time.try_into().unwrap();
         let bootstrap_end_time = EmulatedTime::SIMULATION_START + bootstrap_end_time;
 
+        // a scheduled topology change may lower the latency of a path below the current smallest
+        // latency
         let smallest_latency = SimulationTime::from_nanos(
-            manager_config
-                .routing_info
-                .get_smallest_latency_ns()
-                .unwrap(),
+            [
+                manager_config.routing_info.get_smallest_latency_ns(),
+                manager_config
+                    .topology_schedule
+                    .smallest_changed_latency_ns(),
+            ]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap(),
         );
 
+        let mut topology_schedule = manager_config.topology_schedule;
+
         let dns = unsafe { c::dns_new() };
         assert!(!dns.is_null());
 
@@ -301,6 +313,7 @@ impl<'a> Manager<'a> {
                 ip_assignment: manager_config.ip_assignment,
                 routing_info: manager_config.routing_info,
                 host_bandwidths: manager_config.host_bandwidths,
+                nats: manager_config.nats,
                 // safe since the DNS type has an internal mutex
                 dns: unsafe { SyncSendPointer::new(dns) },
                 num_plugin_errors: AtomicU32::new(0),
@@ -318,6 +331,7 @@ impl<'a> Manager<'a> {
                     .collect(),
                 bootstrap_end_time,
                 sim_end_time: self.end_time,
+                links: Links::new(),
             });
 
         // scope used so that the scheduler is dropped before we log the global counters below
@@ -375,6 +389,23 @@ impl<'a> Manager<'a> {
 
             // the scheduling loop
             while let Some((window_start, window_end)) = window {
+                // apply any network topology changes scheduled before this round starts
+                topology_schedule.apply_events(
+                    window_start,
+                    &worker::WORKER_SHARED
+                        .borrow()
+                        .as_ref()
+                        .unwrap()
+                        .routing_info,
+                );
+
+                // end the round early if the topology changes before the round would end, so that
+                // packets sent after the change use the new paths
+                let window_end = match topology_schedule.next_event_time() {
+                    Some(t) if t < window_end => t,
+                    _ => window_end,
+                };
+
                 // update the status logger
                 let display_time = std::cmp::min(window_start, window_end);
                 worker::WORKER_SHARED
@@ -448,6 +479,20 @@ impl<'a> Manager<'a> {
                     .reduce(std::cmp::min)
                     .unwrap_or(EmulatedTime::MAX);
 
+                // queue the packets sent during this round at the capacity-limited links along
+                // their paths, and deliver the ones that aren't dropped
+                let link_next_event_time = {
+                    let shared = worker::WORKER_SHARED.borrow();
+                    let shared = shared.as_ref().unwrap();
+                    shared.links.transmit_pending(window_end, |event| {
+                        shared.push_to_host(event.host_id(), event)
+                    })
+                };
+                let min_next_event_time = match link_next_event_time {
+                    Some(t) => std::cmp::min(min_next_event_time, t),
+                    None => min_next_event_time,
+                };
+
                 log::debug!(
                     "Finished execution window [{}--{}], next event at {}",
                     (window_start - EmulatedTime::SIMULATION_START).as_nanos(),
@@ -498,6 +543,27 @@ impl<'a> Manager<'a> {
             .unwrap()
             .plugin_error_count();
 
+        // record the statistics of the capacity-limited links
+        let link_stats = worker::WORKER_SHARED
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .links
+            .stats(self.end_time - EmulatedTime::SIMULATION_START);
+        for ((source, target), stats) in &link_stats {
+            log::info!(
+                "Link {}->{}: packets={}, bytes={}, dropped_packets={}, dropped_bytes={}, utilization={:.3}",
+                source,
+                target,
+                stats.packets,
+                stats.bytes,
+                stats.dropped_packets,
+                stats.dropped_bytes,
+                stats.utilization,
+            );
+        }
+        worker::with_global_sim_stats(|stats| stats.set_link_stats(link_stats));
+
         // drop the simulation's global state
         // must drop before the allocation counters have been checked
         worker::WORKER_SHARED.borrow_mut().take();
@@ -563,6 +629,26 @@ impl<'a> Manager<'a> {
                 sim_end_time: self.end_time,
                 requested_bw_down_bits: host_info.bandwidth_down_bits.unwrap(),
                 requested_bw_up_bits: host_info.bandwidth_up_bits.unwrap(),
+                bandwidth_schedule: host_info.bandwidth_schedule.clone(),
+                bandwidth_burst_bytes: host_info.bandwidth_burst_bytes,
+                bandwidth_refill_interval: host_info.bandwidth_refill_interval,
+                loopback_latency: host_info.loopback_latency,
+                loopback_bandwidth_bits: host_info.loopback_bandwidth_bits,
+                interfaces: host_info
+                    .interfaces
+                    .iter()
+                    .map(|x| InterfaceParameters {
+                        node_id: x.network_node_id,
+                        ip_addr: match x.ip_addr.unwrap() {
+                            std::net::IpAddr::V4(ip) => ip,
+                            std::net::IpAddr::V6(_) => unreachable!("IPv6 not supported"),
+                        },
+                        requested_bw_down_bits: x.bandwidth_down_bits.unwrap(),
+                        requested_bw_up_bits: x.bandwidth_up_bits.unwrap(),
+                    })
+                    .collect(),
+                firewall: host_info.firewall.clone(),
+                router_queue: host_info.router_queue.clone(),
                 cpu_threshold: host_info.cpu_threshold,
                 cpu_precision: host_info.cpu_precision,
                 heartbeat_interval: host_info.heartbeat_interval,
@@ -587,6 +673,7 @@ impl<'a> Manager<'a> {
                 autotune_recv_buf: host_info.autotune_recv_buf,
                 init_sock_send_buf_size: host_info.send_buf_size,
                 autotune_send_buf: host_info.autotune_send_buf,
+                use_tcp_ecn: host_info.use_tcp_ecn,
                 native_tsc_frequency: self.native_tsc_frequency,
                 model_unblocked_syscall_latency: self.config.model_unblocked_syscall_latency(),
                 max_unapplied_cpu_latency: self.config.max_unapplied_cpu_latency(),
@@ -859,11 +946,17 @@ pub struct ManagerConfig {
     pub ip_assignment: IpAssignment<u32>,
 
     // routing information for paths between graph nodes
-    pub routing_info: RoutingInfo<u32>,
+    pub routing_info: RoutingInfo,
+
+    // changes to the network graph that are scheduled during the simulation
+    pub topology_schedule: TopologySchedule,
 
     // bandwidths of hosts at ip addresses
     pub host_bandwidths: HashMap<std::net::IpAddr, Bandwidth>,
 
+    // NATs of graph nodes and the host addresses behind them
+    pub nats: Nats,
+
     // a list of hosts and their processes
     pub hosts: Vec<HostInfo>,
 }
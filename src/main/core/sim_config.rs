@@ -1,23 +1,33 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ffi::{OsStr, OsString};
 use std::hash::{Hash, Hasher};
+use std::num::NonZeroU32;
 use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::Context;
 use log::warn;
+use petgraph::graph::NodeIndex;
 use rand::{Rng, SeedableRng};
 use rand_xoshiro::Xoshiro256PlusPlus;
 
 use crate::core::support::configuration::Flatten;
 use crate::core::support::configuration::{
-    parse_string_as_args, ConfigOptions, HostOptions, LogInfoFlag, LogLevel, ProcessArgs,
-    ProcessOptions, QDiscMode,
+    parse_string_as_args, BandwidthChangeOptions, BandwidthScheduleOptions, ConfigOptions,
+    FirewallRuleOptions, HostOptions, LogInfoFlag, LogLevel, ProcessArgs, ProcessOptions,
+    QDiscMode, RouterQueueOptions, RoutingMode, TopologyEventOptions,
 };
 use crate::core::support::units::{self, Unit};
-use crate::network::graph::{load_network_graph, IpAssignment, NetworkGraph, RoutingInfo};
+use crate::cshadow;
+use crate::network::graph::{
+    load_network_graph, read_file_source, ComputedPaths, EdgeChange, IpAssignment, NetworkGraph,
+    PropagationModel, RoutingInfo,
+};
+use crate::network::nat::Nats;
+use crate::network::router::validate_router_queue;
 use crate::utility::tilde_expansion;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 use shadow_shim_helper_rs::simulation_time::SimulationTime;
 
 /// The simulation configuration after processing the configuration options and network graph.
@@ -29,11 +39,17 @@ pub struct SimConfig {
     pub ip_assignment: IpAssignment<u32>,
 
     // routing information for paths between graph nodes
-    pub routing_info: RoutingInfo<u32>,
+    pub routing_info: RoutingInfo,
+
+    // changes to the network graph that are scheduled during the simulation
+    pub topology_schedule: TopologySchedule,
 
     // bandwidths of hosts at ip addresses
     pub host_bandwidths: HashMap<std::net::IpAddr, Bandwidth>,
 
+    // NATs of graph nodes and the host addresses behind them
+    pub nats: Nats,
+
     // a list of hosts and their processes
     pub hosts: Vec<HostInfo>,
 }
@@ -68,35 +84,25 @@ impl SimConfig {
         }
 
         // load and parse the network graph
-        let graph: String = load_network_graph(config.network.graph.as_ref().unwrap())
-            .map_err(|e| anyhow::anyhow!(e))
-            .context("Failed to load the network graph")?;
-        let graph = NetworkGraph::parse(&graph)
-            .map_err(|e| anyhow::anyhow!(e))
-            .context("Failed to parse the network graph")?;
+        let graph = load_graph(config)?;
 
         // check that each node ID is valid
         for host in &hosts {
-            if graph.node_id_to_index(host.network_node_id).is_none() {
-                return Err(anyhow::anyhow!(
-                    "The network node id {} for host '{}' does not exist",
-                    host.network_node_id,
-                    host.name
-                ));
+            for node_id in host.network_node_ids() {
+                if graph.node_id_to_index(node_id).is_none() {
+                    return Err(anyhow::anyhow!(
+                        "The network node id {} for host '{}' does not exist",
+                        node_id,
+                        host.name
+                    ));
+                }
             }
         }
 
-        // assign a bandwidth to every host
+        // assign a bandwidth to every host and interface
         for host in &mut hosts {
-            let node_index = graph.node_id_to_index(host.network_node_id).unwrap();
-            let node = graph.graph().node_weight(*node_index).unwrap();
-
-            let graph_bw_down_bits = node
-                .bandwidth_down
-                .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value());
-            let graph_bw_up_bits = node
-                .bandwidth_up
-                .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value());
+            let (graph_bw_down_bits, graph_bw_up_bits) =
+                node_bandwidth(&graph, host.network_node_id);
 
             host.bandwidth_down_bits = host.bandwidth_down_bits.or(graph_bw_down_bits);
             host.bandwidth_up_bits = host.bandwidth_up_bits.or(graph_bw_up_bits);
@@ -114,6 +120,22 @@ impl SimConfig {
                     host.name
                 ));
             }
+
+            // interfaces without their own bandwidth use their graph node's bandwidth, and
+            // otherwise the host's bandwidth
+            for interface in &mut host.interfaces {
+                let (graph_bw_down_bits, graph_bw_up_bits) =
+                    node_bandwidth(&graph, interface.network_node_id);
+
+                interface.bandwidth_down_bits = interface
+                    .bandwidth_down_bits
+                    .or(graph_bw_down_bits)
+                    .or(host.bandwidth_down_bits);
+                interface.bandwidth_up_bits = interface
+                    .bandwidth_up_bits
+                    .or(graph_bw_up_bits)
+                    .or(host.bandwidth_up_bits);
+            }
         }
 
         // check if any hosts in 'hosts_to_debug' don't exist
@@ -126,26 +148,56 @@ impl SimConfig {
         }
 
         // assign IP addresses to hosts and graph nodes
-        let ip_assignment = assign_ips(&mut hosts)?;
+        let ip_assignment = assign_ips(&mut hosts, &graph)?;
+
+        let nats = build_nats(&hosts, &graph)?;
+
+        let routing = RoutingOptions {
+            use_shortest_paths: config.network.use_shortest_path.unwrap(),
+            use_ecmp: config.network.use_ecmp.unwrap(),
+            mode: config.network.routing_mode.unwrap(),
+            cache_size: config.network.routing_cache_size.unwrap(),
+        };
+        if routing.use_ecmp && !routing.use_shortest_paths {
+            return Err(anyhow::anyhow!(
+                "The 'network.use_ecmp' option requires 'network.use_shortest_path'"
+            ));
+        }
 
         // generate routing info between every pair of in-use nodes
-        let routing_info = generate_routing_info(
-            &graph,
-            &ip_assignment.get_nodes(),
-            config.network.use_shortest_path.unwrap(),
-        )?;
+        let routing_info = generate_routing_info(&graph, &ip_assignment.get_nodes(), routing)?;
 
-        // get all host bandwidths
+        let topology_schedule = TopologySchedule::new(
+            graph,
+            ip_assignment.get_nodes(),
+            routing,
+            config
+                .network
+                .topology_events
+                .as_deref()
+                .unwrap_or_default(),
+        )
+        .context("Failed to schedule the network topology events")?;
+
+        // get all host and interface bandwidths
         let host_bandwidths = hosts
             .iter()
-            .map(|host| {
-                // we made sure above that every host has a bandwidth set
+            .flat_map(|host| {
+                // we made sure above that every host and interface has a bandwidth set
                 let bw = Bandwidth {
                     up_bytes: host.bandwidth_up_bits.unwrap() / 8,
                     down_bytes: host.bandwidth_down_bits.unwrap() / 8,
                 };
 
-                (host.ip_addr.unwrap(), bw)
+                std::iter::once((host.ip_addr.unwrap(), bw)).chain(host.interfaces.iter().map(
+                    |interface| {
+                        let bw = Bandwidth {
+                            up_bytes: interface.bandwidth_up_bits.unwrap() / 8,
+                            down_bytes: interface.bandwidth_down_bits.unwrap() / 8,
+                        };
+                        (interface.ip_addr.unwrap(), bw)
+                    },
+                ))
             })
             .collect();
 
@@ -153,12 +205,190 @@ impl SimConfig {
             random,
             ip_assignment,
             routing_info,
+            topology_schedule,
             host_bandwidths,
+            nats,
             hosts,
         })
     }
 }
 
+/// The downstream and upstream bandwidths in bits/s of a graph node, if it has them.
+fn node_bandwidth(graph: &NetworkGraph, node_id: u32) -> (Option<u64>, Option<u64>) {
+    let node_index = graph.node_id_to_index(node_id).unwrap();
+    let node = graph.graph().node_weight(*node_index).unwrap();
+
+    let bw_down_bits = node
+        .bandwidth_down
+        .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value());
+    let bw_up_bits = node
+        .bandwidth_up
+        .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value());
+
+    (bw_down_bits, bw_up_bits)
+}
+
+/// Load and parse the network graph.
+pub fn load_graph(config: &ConfigOptions) -> anyhow::Result<NetworkGraph> {
+    let graph_options = config.network.graph.as_ref().unwrap();
+    let seed = config.general.seed.unwrap();
+
+    let graph: String = load_network_graph(graph_options, seed.into())
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Failed to load the network graph")?;
+    let propagation = PropagationModel::new(
+        config.network.propagation_speed.unwrap(),
+        config.network.propagation_overhead.unwrap(),
+        config.network.propagation_min_latency.unwrap(),
+    )
+    .map_err(|e| anyhow::anyhow!(e))?;
+    NetworkGraph::parse_as(&graph, graph_options.into(), &propagation)
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Failed to parse the network graph")
+}
+
+/// How packets are routed between graph nodes.
+#[derive(Debug, Clone, Copy)]
+struct RoutingOptions {
+    use_shortest_paths: bool,
+    use_ecmp: bool,
+    mode: RoutingMode,
+    /// The number of source nodes whose paths are cached in the lazy routing mode.
+    cache_size: NonZeroU32,
+}
+
+/// A scheduled change to an edge of the network graph.
+#[derive(Debug, Clone)]
+struct TopologyEvent {
+    time: EmulatedTime,
+    source: u32,
+    target: u32,
+    change: EdgeChange,
+}
+
+/// The network graph and the changes to its topology that are scheduled during the simulation.
+pub struct TopologySchedule {
+    graph: NetworkGraph,
+    // gml ids of the nodes that have hosts
+    nodes: HashSet<u32>,
+    routing: RoutingOptions,
+    // sorted by time, with events at the same time in the order they were configured
+    events: VecDeque<TopologyEvent>,
+    // the smallest latency (minus jitter) of any edge after it has been changed by an event
+    smallest_changed_latency_ns: Option<u64>,
+}
+
+impl TopologySchedule {
+    fn new(
+        graph: NetworkGraph,
+        nodes: HashSet<u32>,
+        routing: RoutingOptions,
+        event_options: &[TopologyEventOptions],
+    ) -> anyhow::Result<Self> {
+        let mut events: Vec<_> = event_options
+            .iter()
+            .map(|x| TopologyEvent {
+                time: EmulatedTime::from_abs_simtime(Duration::from(x.time).try_into().unwrap()),
+                source: x.source,
+                target: x.target,
+                change: EdgeChange {
+                    up: x.up,
+                    latency: x.latency,
+                    jitter: x.jitter,
+                    packet_loss: x.packet_loss,
+                },
+            })
+            .collect();
+
+        // a stable sort so that events at the same time are applied in the order given
+        events.sort_by_key(|x| x.time);
+
+        // apply the events to a copy of the graph so that invalid events are reported before the
+        // simulation starts, and so that we know the smallest latency that may be used
+        let mut smallest_changed_latency_ns = None;
+        let mut scratch_graph = graph.clone();
+        for event in &events {
+            let properties = scratch_graph
+                .apply_edge_change(event.source, event.target, &event.change)
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(|| {
+                    format!(
+                        "Invalid change to edge {}->{} at time {} ns",
+                        event.source,
+                        event.target,
+                        event.time.to_abs_simtime().as_nanos(),
+                    )
+                })?;
+
+            if event.change.up != Some(false) {
+                smallest_changed_latency_ns = [
+                    smallest_changed_latency_ns,
+                    Some(properties.min_latency_ns()),
+                ]
+                .into_iter()
+                .flatten()
+                .min();
+            }
+        }
+
+        Ok(Self {
+            graph,
+            nodes,
+            routing,
+            events: events.into(),
+            smallest_changed_latency_ns,
+        })
+    }
+
+    /// The time of the next scheduled change to the network topology.
+    pub fn next_event_time(&self) -> Option<EmulatedTime> {
+        self.events.front().map(|x| x.time)
+    }
+
+    /// The smallest latency that a packet may have once any of the scheduled changes have been
+    /// applied.
+    pub fn smallest_changed_latency_ns(&self) -> Option<u64> {
+        self.smallest_changed_latency_ns
+    }
+
+    /// Apply all changes scheduled at or before `time`. If any changes were applied, the paths
+    /// in `routing_info` are recomputed. Pairs of nodes that are no longer connected will have
+    /// no path, and packets between them will be dropped.
+    pub fn apply_events(&mut self, time: EmulatedTime, routing_info: &RoutingInfo) {
+        let mut changed = false;
+
+        while self.next_event_time().map(|x| x <= time).unwrap_or(false) {
+            let event = self.events.pop_front().unwrap();
+
+            self.graph
+                .apply_edge_change(event.source, event.target, &event.change)
+                .expect("Topology events should have been validated at startup");
+
+            log::info!(
+                "Applied network topology change {:?} to edge {}->{}",
+                event.change,
+                event.source,
+                event.target,
+            );
+
+            changed = true;
+        }
+
+        if !changed {
+            return;
+        }
+
+        match self.routing.mode {
+            RoutingMode::Precomputed => {
+                let paths = compute_paths(&self.graph, &self.nodes, self.routing)
+                    .expect("Failed to recompute the paths between graph nodes");
+                routing_info.set_paths(paths);
+            }
+            RoutingMode::Lazy => routing_info.set_graph(self.graph.clone()),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct HostInfo {
     pub name: String,
@@ -170,7 +400,16 @@ pub struct HostInfo {
     pub cpu_precision: Option<SimulationTime>,
     pub bandwidth_down_bits: Option<u64>,
     pub bandwidth_up_bits: Option<u64>,
+    // sorted by time, with changes at the same time in the order they were configured
+    pub bandwidth_schedule: Vec<BandwidthChangeOptions>,
+    pub bandwidth_burst_bytes: u64,
+    pub bandwidth_refill_interval: SimulationTime,
+    pub loopback_latency: Option<SimulationTime>,
+    pub loopback_bandwidth_bits: Option<u64>,
     pub ip_addr: Option<std::net::IpAddr>,
+    pub interfaces: Vec<InterfaceInfo>,
+    pub firewall: Vec<FirewallRuleOptions>,
+    pub router_queue: RouterQueueOptions,
     pub log_level: Option<LogLevel>,
     pub pcap_dir: Option<PathBuf>,
     pub pcap_capture_size: u64,
@@ -181,9 +420,27 @@ pub struct HostInfo {
     pub recv_buf_size: u64,
     pub autotune_send_buf: bool,
     pub autotune_recv_buf: bool,
+    pub use_tcp_ecn: bool,
     pub qdisc: QDiscMode,
 }
 
+impl HostInfo {
+    /// The graph node IDs of the host's default interface and its additional interfaces.
+    pub fn network_node_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        std::iter::once(self.network_node_id)
+            .chain(self.interfaces.iter().map(|x| x.network_node_id))
+    }
+}
+
+/// An additional network interface of a host.
+#[derive(Clone)]
+pub struct InterfaceInfo {
+    pub network_node_id: u32,
+    pub bandwidth_down_bits: Option<u64>,
+    pub bandwidth_up_bits: Option<u64>,
+    pub ip_addr: Option<std::net::IpAddr>,
+}
+
 #[derive(Clone)]
 pub struct ProcessInfo {
     pub plugin: PathBuf,
@@ -211,12 +468,28 @@ fn build_host(
 
     // make sure we're not trying to set a single address for multiple hosts
     // this should be caught later anyways, but the check here gives a useful error message
-    if host.ip_addr.is_some() && quantity > 1 {
+    if (host.ip_addr.is_some() || host.interfaces.iter().any(|x| x.ip_addr.is_some()))
+        && quantity > 1
+    {
         return Err(anyhow::anyhow!(
             "Host has an IP address set and a quantity {quantity} greater than 1",
         ));
     }
 
+    let router_queue = host.options.router_queue.clone().unwrap();
+    validate_router_queue(&router_queue)
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Invalid router queue")?;
+
+    let bandwidth_schedule = load_bandwidth_schedule(host.bandwidth_schedule.as_ref())
+        .context("Invalid bandwidth schedule")?;
+
+    let bandwidth_burst_bytes = load_bandwidth_burst_size(host.bandwidth_burst_size)?;
+    let bandwidth_refill_interval = load_bandwidth_refill_interval(host.bandwidth_refill_interval)?;
+
+    let loopback_latency = load_loopback_latency(host.loopback_latency)?;
+    let loopback_bandwidth_bits = load_loopback_bandwidth(host.loopback_bandwidth)?;
+
     let mut hosts = Vec::with_capacity(quantity.try_into().unwrap());
 
     for host_index in 0..quantity {
@@ -260,8 +533,29 @@ fn build_host(
             bandwidth_up_bits: host
                 .bandwidth_down
                 .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value()),
+            bandwidth_schedule: bandwidth_schedule.clone(),
+            bandwidth_burst_bytes,
+            bandwidth_refill_interval,
+            loopback_latency,
+            loopback_bandwidth_bits,
 
             ip_addr: host.ip_addr.map(|x| x.into()),
+            interfaces: host
+                .interfaces
+                .iter()
+                .map(|x| InterfaceInfo {
+                    network_node_id: x.network_node_id,
+                    bandwidth_down_bits: x
+                        .bandwidth_down
+                        .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value()),
+                    bandwidth_up_bits: x
+                        .bandwidth_up
+                        .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value()),
+                    ip_addr: x.ip_addr.map(|x| x.into()),
+                })
+                .collect(),
+            firewall: host.firewall.clone(),
+            router_queue: router_queue.clone(),
             log_level: host.options.log_level.flatten(),
             pcap_dir: host
                 .options
@@ -304,6 +598,7 @@ fn build_host(
                 .value(),
             autotune_send_buf: config.experimental.socket_send_autotune.unwrap(),
             autotune_recv_buf: config.experimental.socket_recv_autotune.unwrap(),
+            use_tcp_ecn: config.experimental.use_tcp_ecn.unwrap(),
             qdisc: config.experimental.interface_qdisc.unwrap(),
         });
     }
@@ -311,6 +606,124 @@ fn build_host(
     Ok(hosts)
 }
 
+/// Load a host's bandwidth schedule, sorted by time.
+fn load_bandwidth_schedule(
+    options: Option<&BandwidthScheduleOptions>,
+) -> anyhow::Result<Vec<BandwidthChangeOptions>> {
+    let mut changes = match options {
+        None => return Ok(Vec::new()),
+        Some(BandwidthScheduleOptions::Inline(changes)) => changes.clone(),
+        Some(BandwidthScheduleOptions::File(file)) => {
+            let trace = read_file_source(file).map_err(|e| anyhow::anyhow!(e))?;
+            BandwidthChangeOptions::from_csv(&trace)
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(|| format!("Failed to parse the bandwidth trace '{}'", file.path))?
+        }
+    };
+
+    // the interface's token buckets can't be refilled at less than 1 KiB/s
+    for change in &changes {
+        for bw in [change.bandwidth_down, change.bandwidth_up]
+            .into_iter()
+            .flatten()
+        {
+            let bits = bw
+                .convert(units::SiPrefixUpper::Base)
+                .map_err(|e| anyhow::anyhow!(e))?
+                .value();
+            if bits < 8 * 1024 {
+                return Err(anyhow::anyhow!(
+                    "The bandwidth '{}' at time '{}' is less than 8 Kibit",
+                    bw,
+                    change.time,
+                ));
+            }
+        }
+    }
+
+    // a stable sort so that changes at the same time are applied in the order given
+    changes.sort_by_key(|x| Duration::from(x.time));
+
+    Ok(changes)
+}
+
+/// Load a host's token bucket burst size in bytes. Every bucket must be able to hold a full-size
+/// packet, otherwise the interface could never send or receive it.
+fn load_bandwidth_burst_size(
+    burst_size: Option<units::Bytes<units::SiPrefixUpper>>,
+) -> anyhow::Result<u64> {
+    let Some(burst_size) = burst_size else {
+        return Ok(cshadow::CONFIG_MTU.into());
+    };
+
+    let bytes = burst_size
+        .convert(units::SiPrefixUpper::Base)
+        .map_err(|e| anyhow::anyhow!(e))?
+        .value();
+    if bytes < cshadow::CONFIG_MTU.into() {
+        return Err(anyhow::anyhow!(
+            "The bandwidth burst size '{}' is less than the MTU of {} bytes",
+            burst_size,
+            cshadow::CONFIG_MTU,
+        ));
+    }
+
+    Ok(bytes)
+}
+
+/// Load a host's token bucket refill interval.
+fn load_bandwidth_refill_interval(
+    refill_interval: Option<units::Time<units::TimePrefix>>,
+) -> anyhow::Result<SimulationTime> {
+    let refill_interval: SimulationTime = refill_interval
+        .map(|x| Duration::from(x).try_into().unwrap())
+        .unwrap_or(SimulationTime::MILLISECOND);
+    if refill_interval.is_zero() {
+        return Err(anyhow::anyhow!(
+            "The bandwidth refill interval must be greater than 0"
+        ));
+    }
+
+    Ok(refill_interval)
+}
+
+/// Load the latency of a host's loopback interface, if it's modeled.
+fn load_loopback_latency(
+    latency: Option<units::Time<units::TimePrefix>>,
+) -> anyhow::Result<Option<SimulationTime>> {
+    let latency: Option<SimulationTime> = latency.map(|x| Duration::from(x).try_into().unwrap());
+    if latency.map_or(false, |x| x.is_zero()) {
+        return Err(anyhow::anyhow!(
+            "The loopback latency must be greater than 0"
+        ));
+    }
+
+    Ok(latency)
+}
+
+/// Load the bandwidth of a host's loopback interface in bits per second, if it's limited.
+fn load_loopback_bandwidth(
+    bandwidth: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+) -> anyhow::Result<Option<u64>> {
+    let Some(bandwidth) = bandwidth else {
+        return Ok(None);
+    };
+
+    // like the host's bandwidth, it's passed to the token buckets in KiB/s
+    let bits = bandwidth
+        .convert(units::SiPrefixUpper::Base)
+        .map_err(|e| anyhow::anyhow!(e))?
+        .value();
+    if bits < 8 * 1024 {
+        return Err(anyhow::anyhow!(
+            "The loopback bandwidth '{}' is less than 8 Kibit",
+            bandwidth,
+        ));
+    }
+
+    Ok(Some(bits))
+}
+
 /// For a process entry in the configuration options, build a list of `ProcessInfo` objects.
 fn build_process(proc: &ProcessOptions) -> anyhow::Result<Vec<ProcessInfo>> {
     let start_time = Duration::from(proc.start_time).try_into().unwrap();
@@ -394,37 +807,156 @@ fn build_process(proc: &ProcessOptions) -> anyhow::Result<Vec<ProcessInfo>> {
 }
 
 /// Generate an IP assignment map using hosts' configured IP addresses and graph node IDs. For hosts
-/// without IP addresses, they will be assigned an arbitrary IP address.
-fn assign_ips(hosts: &mut [HostInfo]) -> anyhow::Result<IpAssignment<u32>> {
+/// without IP addresses, they will be assigned an address from their graph node's IP prefix, or an
+/// arbitrary IP address if the node has no prefix.
+fn assign_ips(hosts: &mut [HostInfo], graph: &NetworkGraph) -> anyhow::Result<IpAssignment<u32>> {
     let mut ip_assignment = IpAssignment::new();
 
-    // first register hosts that have a specific IP address
-    for host in hosts.iter().filter(|x| x.ip_addr.is_some()) {
-        let ip = host.ip_addr.unwrap();
+    for node in graph.graph().raw_nodes() {
+        if let Some(prefix) = node.weight.ip_prefix {
+            ip_assignment.set_prefix(node.weight.id, prefix);
+        }
+    }
+
+    // the public addresses of NATs belong to their nodes, but not to any host
+    for node in graph.graph().raw_nodes() {
+        let node_id = node.weight.id;
+        if let Some(nat) = node.weight.nat {
+            let ip = nat.address.into();
+            ip_assignment.assign_ip(node_id, ip).with_context(|| {
+                format!("Failed to assign the NAT address {ip} to node '{node_id}'")
+            })?;
+        }
+    }
+
+    // first register hosts and interfaces that have a specific IP address
+    for host in hosts.iter() {
+        let hostname = &host.name;
+        let interfaces = std::iter::once((host.network_node_id, host.ip_addr)).chain(
+            host.interfaces
+                .iter()
+                .map(|x| (x.network_node_id, x.ip_addr)),
+        );
+        for (node_id, ip) in interfaces {
+            let Some(ip) = ip else { continue };
+            ip_assignment.assign_ip(node_id, ip).with_context(|| {
+                format!(
+                    "Failed to assign IP address {ip} for host '{hostname}' to node '{node_id}'"
+                )
+            })?;
+        }
+    }
+
+    // then register remaining hosts and interfaces
+    for host in hosts.iter_mut() {
         let hostname = &host.name;
-        let node_id = host.network_node_id;
-        ip_assignment.assign_ip(node_id, ip).with_context(|| {
-            format!("Failed to assign IP address {ip} for host '{hostname}' to node '{node_id}'")
-        })?;
+        let interfaces = std::iter::once((host.network_node_id, &mut host.ip_addr)).chain(
+            host.interfaces
+                .iter_mut()
+                .map(|x| (x.network_node_id, &mut x.ip_addr)),
+        );
+        for (node_id, ip) in interfaces.filter(|(_, ip)| ip.is_none()) {
+            let new_ip = ip_assignment.assign(node_id).with_context(|| {
+                format!("Failed to assign an IP address for host '{hostname}' on node '{node_id}'")
+            })?;
+            // assign the new IP to the host
+            *ip = Some(new_ip);
+        }
     }
 
-    // then register remaining hosts
-    for host in hosts.iter_mut().filter(|x| x.ip_addr.is_none()) {
-        let ip = ip_assignment.assign(host.network_node_id);
-        // assign the new IP to the host
-        host.ip_addr = Some(ip);
+    // the DNS doesn't allow hosts to use reserved addresses
+    for host in hosts.iter() {
+        let hostname = &host.name;
+        let ips = std::iter::once(host.ip_addr).chain(host.interfaces.iter().map(|x| x.ip_addr));
+        for ip in ips.flatten() {
+            let std::net::IpAddr::V4(ipv4) = ip else {
+                continue;
+            };
+            if unsafe { cshadow::dns_isRestricted(u32::from(ipv4).to_be()) } {
+                return Err(anyhow::anyhow!(
+                    "The IP address {ip} for host '{hostname}' is in a reserved address range"
+                ));
+            }
+        }
     }
 
     Ok(ip_assignment)
 }
 
+/// Find the NATs of the graph's nodes, and the host addresses behind them.
+fn build_nats(hosts: &[HostInfo], graph: &NetworkGraph) -> anyhow::Result<Nats> {
+    let nats = graph
+        .graph()
+        .raw_nodes()
+        .iter()
+        .filter_map(|node| Some((node.weight.id, node.weight.nat?)));
+
+    let addresses = hosts.iter().flat_map(|host| {
+        std::iter::once((host.network_node_id, host.ip_addr)).chain(
+            host.interfaces
+                .iter()
+                .map(|x| (x.network_node_id, x.ip_addr)),
+        )
+    });
+    let addresses = addresses.filter_map(|(node_id, ip)| match ip? {
+        std::net::IpAddr::V4(ip) => Some((node_id, ip)),
+        std::net::IpAddr::V6(_) => None,
+    });
+
+    Nats::new(nats, addresses).map_err(|e| anyhow::anyhow!(e))
+}
+
 /// Generate a map containing routing information (latency, packet loss, etc) for each pair of
-/// nodes.
+/// nodes. Returns an error if there is no path between any pair of nodes.
 fn generate_routing_info(
     graph: &NetworkGraph,
     nodes: &std::collections::HashSet<u32>,
-    use_shortest_paths: bool,
-) -> anyhow::Result<RoutingInfo<u32>> {
+    routing: RoutingOptions,
+) -> anyhow::Result<RoutingInfo> {
+    if routing.mode == RoutingMode::Lazy {
+        let indexes: Vec<_> = nodes
+            .iter()
+            .map(|x| *graph.node_id_to_index(*x).unwrap())
+            .collect();
+
+        // every pair of nodes must be routable at the start of the simulation
+        graph
+            .check_connected(&indexes, routing.use_shortest_paths)
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        return Ok(RoutingInfo::new_lazy(
+            graph.clone(),
+            nodes,
+            routing.use_shortest_paths,
+            routing.use_ecmp,
+            routing.cache_size,
+        ));
+    }
+
+    let paths = compute_paths(graph, nodes, routing)?;
+
+    // every pair of nodes must be routable at the start of the simulation
+    for src in nodes {
+        for dst in nodes {
+            if !paths.paths.contains_key(&(*src, *dst)) {
+                return Err(anyhow::anyhow!(
+                    "No path from graph node {src} to graph node {dst}"
+                ));
+            }
+        }
+    }
+
+    Ok(RoutingInfo::new(paths))
+}
+
+/// Compute the path properties and the links with a limited capacity or a bursty loss model along
+/// the path for each pair of nodes, and any equal-cost paths when using ECMP. Pairs of nodes
+/// without a path between them are not included.
+fn compute_paths(
+    graph: &NetworkGraph,
+    nodes: &std::collections::HashSet<u32>,
+    routing: RoutingOptions,
+) -> anyhow::Result<ComputedPaths> {
     // convert gml node IDs to petgraph indexes
     let nodes: Vec<_> = nodes
         .iter()
@@ -432,19 +964,23 @@ fn generate_routing_info(
         .collect();
 
     // helper to convert petgraph indexes back to gml node IDs
-    let to_ids = |((src, dst), path)| {
-        let src = graph.node_index_to_id(src).unwrap();
-        let dst = graph.node_index_to_id(dst).unwrap();
-        ((src, dst), path)
-    };
+    fn to_ids<V>(
+        graph: &NetworkGraph,
+    ) -> impl Fn(((NodeIndex, NodeIndex), V)) -> ((u32, u32), V) + '_ {
+        move |((src, dst), path)| {
+            let src = graph.node_index_to_id(src).unwrap();
+            let dst = graph.node_index_to_id(dst).unwrap();
+            ((src, dst), path)
+        }
+    }
 
-    let paths = if use_shortest_paths {
+    let paths = if routing.use_shortest_paths {
         graph
             .compute_shortest_paths(&nodes[..])
             .map_err(|e| anyhow::anyhow!(e))
             .context("Failed to compute shortest paths between graph nodes")?
             .into_iter()
-            .map(to_ids)
+            .map(to_ids(graph))
             .collect()
     } else {
         graph
@@ -452,11 +988,33 @@ fn generate_routing_info(
             .map_err(|e| anyhow::anyhow!(e))
             .context("Failed to get the direct paths between graph nodes")?
             .into_iter()
-            .map(to_ids)
+            .map(to_ids(graph))
             .collect()
     };
 
-    Ok(RoutingInfo::new(paths))
+    let links = graph
+        .compute_path_links(&nodes[..], routing.use_shortest_paths)
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Failed to find the links along the paths between graph nodes")?
+        .into_iter()
+        .map(to_ids(graph))
+        .collect();
+
+    let equal_cost = if routing.use_ecmp {
+        graph
+            .compute_equal_cost_paths(&nodes[..])
+            .into_iter()
+            .map(to_ids(graph))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    Ok(ComputedPaths {
+        paths,
+        links,
+        equal_cost,
+    })
 }
 
 /// Check that the plugin path is valid.
@@ -477,3 +1035,67 @@ fn verify_plugin_path(path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bandwidth_burst_size() {
+        let mtu: u64 = cshadow::CONFIG_MTU.into();
+        assert_eq!(load_bandwidth_burst_size(None).unwrap(), mtu);
+        assert_eq!(
+            load_bandwidth_burst_size(Some("1500 B".parse().unwrap())).unwrap(),
+            1500
+        );
+        assert_eq!(
+            load_bandwidth_burst_size(Some("64 KiB".parse().unwrap())).unwrap(),
+            64 * 1024
+        );
+
+        // a full-size packet must fit in the token bucket
+        assert!(load_bandwidth_burst_size(Some("1499 B".parse().unwrap())).is_err());
+        assert!(load_bandwidth_burst_size(Some("0 B".parse().unwrap())).is_err());
+    }
+
+    #[test]
+    fn test_bandwidth_refill_interval() {
+        assert_eq!(
+            load_bandwidth_refill_interval(None).unwrap(),
+            SimulationTime::MILLISECOND
+        );
+        assert_eq!(
+            load_bandwidth_refill_interval(Some("100 us".parse().unwrap())).unwrap(),
+            SimulationTime::from_micros(100)
+        );
+        assert!(load_bandwidth_refill_interval(Some("0 ms".parse().unwrap())).is_err());
+    }
+    #[test]
+    fn test_loopback_latency() {
+        assert_eq!(load_loopback_latency(None).unwrap(), None);
+        assert_eq!(
+            load_loopback_latency(Some("50 us".parse().unwrap())).unwrap(),
+            Some(SimulationTime::from_micros(50))
+        );
+
+        // packets must arrive after they were sent
+        assert!(load_loopback_latency(Some("0 ms".parse().unwrap())).is_err());
+    }
+
+    #[test]
+    fn test_loopback_bandwidth() {
+        assert_eq!(load_loopback_bandwidth(None).unwrap(), None);
+        assert_eq!(
+            load_loopback_bandwidth(Some("10 Gbit".parse().unwrap())).unwrap(),
+            Some(10_000_000_000)
+        );
+        assert_eq!(
+            load_loopback_bandwidth(Some("8 Kibit".parse().unwrap())).unwrap(),
+            Some(8 * 1024)
+        );
+
+        // the token buckets can't be refilled at less than 1 KiB/s
+        assert!(load_loopback_bandwidth(Some("8191 bit".parse().unwrap())).is_err());
+        assert!(load_loopback_bandwidth(Some("0 bit".parse().unwrap())).is_err());
+    }
+}
@@ -1,6 +1,10 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
 use std::sync::Mutex;
 
+use crate::network::link::LinkStats;
+use crate::network::router::RouterStats;
 use crate::utility::counter::Counter;
 
 use anyhow::Context;
@@ -12,6 +16,8 @@ pub struct LocalSimStats {
     pub alloc_counts: RefCell<Counter>,
     pub dealloc_counts: RefCell<Counter>,
     pub syscall_counts: RefCell<Counter>,
+    /// Final statistics of the upstream routers, keyed by the host name and interface address.
+    pub router_stats: RefCell<BTreeMap<(String, Ipv4Addr), RouterStats>>,
 }
 
 impl LocalSimStats {
@@ -20,6 +26,7 @@ impl LocalSimStats {
             alloc_counts: RefCell::new(Counter::new()),
             dealloc_counts: RefCell::new(Counter::new()),
             syscall_counts: RefCell::new(Counter::new()),
+            router_stats: RefCell::new(BTreeMap::new()),
         }
     }
 }
@@ -36,6 +43,11 @@ pub struct SharedSimStats {
     pub alloc_counts: Mutex<Counter>,
     pub dealloc_counts: Mutex<Counter>,
     pub syscall_counts: Mutex<Counter>,
+    /// Statistics for the capacity-limited links in the network graph, keyed by the nodes that
+    /// packets enter and leave the link from.
+    pub link_stats: Mutex<BTreeMap<(u32, u32), LinkStats>>,
+    /// Final statistics of the upstream routers, keyed by the host name and interface address.
+    pub router_stats: Mutex<BTreeMap<(String, Ipv4Addr), RouterStats>>,
 }
 
 impl SharedSimStats {
@@ -44,9 +56,16 @@ impl SharedSimStats {
             alloc_counts: Mutex::new(Counter::new()),
             dealloc_counts: Mutex::new(Counter::new()),
             syscall_counts: Mutex::new(Counter::new()),
+            link_stats: Mutex::new(BTreeMap::new()),
+            router_stats: Mutex::new(BTreeMap::new()),
         }
     }
 
+    /// Set the statistics for the capacity-limited links, replacing any previous statistics.
+    pub fn set_link_stats(&self, link_stats: BTreeMap<(u32, u32), LinkStats>) {
+        *self.link_stats.lock().unwrap() = link_stats;
+    }
+
     /// Add stats from a local object to a shared object. May reset fields of `local`.
     pub fn add_from_local_stats(&self, local: &LocalSimStats) {
         let mut shared_alloc_counts = self.alloc_counts.lock().unwrap();
@@ -64,6 +83,11 @@ impl SharedSimStats {
         *local_alloc_counts = Counter::new();
         *local_dealloc_counts = Counter::new();
         *local_syscall_counts = Counter::new();
+
+        self.router_stats
+            .lock()
+            .unwrap()
+            .append(&mut local.router_stats.borrow_mut());
     }
 }
 
@@ -78,6 +102,13 @@ impl Default for SharedSimStats {
 struct SimStatsForOutput {
     pub objects: ObjectStatsForOutput,
     pub syscalls: Counter,
+    /// Link statistics keyed by "{source}->{target}". Omitted if the network graph has no
+    /// capacity-limited links.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<String, LinkStats>,
+    /// Upstream router statistics keyed by host name and then interface address.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub routers: BTreeMap<String, BTreeMap<String, RouterStats>>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -102,6 +133,19 @@ impl SimStatsForOutput {
                 ),
             },
             syscalls: std::mem::replace(&mut stats.syscall_counts.lock().unwrap(), Counter::new()),
+            links: std::mem::take(&mut *stats.link_stats.lock().unwrap())
+                .into_iter()
+                .map(|((source, target), x)| (format!("{source}->{target}"), x))
+                .collect(),
+            routers: std::mem::take(&mut *stats.router_stats.lock().unwrap())
+                .into_iter()
+                .fold(BTreeMap::new(), |mut routers, ((host, addr), x)| {
+                    routers
+                        .entry(host)
+                        .or_insert_with(BTreeMap::new)
+                        .insert(addr.to_string(), x);
+                    routers
+                }),
         }
     }
 }
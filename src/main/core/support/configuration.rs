@@ -57,6 +57,11 @@ pub struct CliOptions {
     #[clap(long)]
     pub show_config: bool,
 
+    /// Exit after validating the network graph and printing the paths between the hosts' graph
+    /// nodes in the given format ('csv' or 'json')
+    #[clap(long, value_name = "format")]
+    pub validate_graph: Option<GraphReportFormat>,
+
     #[clap(flatten)]
     pub general: GeneralOptions,
 
@@ -276,6 +281,56 @@ pub struct NetworkOptions {
     #[clap(long, value_name = "bool")]
     #[clap(help = NETWORK_HELP.get("use_shortest_path").unwrap().as_str())]
     pub use_shortest_path: Option<bool>,
+
+    /// Spread flows over all of the shortest paths between two nodes that have the same latency
+    /// (equal-cost multipath routing), choosing the path for each flow from a hash of its source
+    /// and destination addresses, ports, and protocol. Requires use_shortest_path
+    #[serde(default = "default_some_false")]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = NETWORK_HELP.get("use_ecmp").unwrap().as_str())]
+    pub use_ecmp: Option<bool>,
+
+    /// How the paths between graph nodes are computed. "precomputed" computes the path between
+    /// every pair of nodes at startup, and "lazy" computes the paths from a node when it first
+    /// sends a packet, which uses less memory and starts faster for large graphs
+    #[serde(default = "default_some_routing_mode")]
+    #[clap(long, value_name = "mode")]
+    #[clap(help = NETWORK_HELP.get("routing_mode").unwrap().as_str())]
+    pub routing_mode: Option<RoutingMode>,
+
+    /// The number of network nodes whose paths are kept in memory when using the "lazy" routing
+    /// mode. The paths from the least recently used nodes are discarded, and are computed again if
+    /// they're needed
+    #[serde(default = "default_some_routing_cache_size")]
+    #[clap(long, value_name = "nodes")]
+    #[clap(help = NETWORK_HELP.get("routing_cache_size").unwrap().as_str())]
+    pub routing_cache_size: Option<NonZeroU32>,
+
+    /// The signal propagation speed, as a fraction of the speed of light, used to compute the
+    /// latency of edges without a latency from the coordinates of their nodes
+    #[serde(default = "default_some_propagation_speed")]
+    #[clap(long, value_name = "fraction")]
+    #[clap(help = NETWORK_HELP.get("propagation_speed").unwrap().as_str())]
+    pub propagation_speed: Option<f64>,
+
+    /// A fixed latency added to the latency of edges whose latency is computed from the
+    /// coordinates of their nodes
+    #[serde(default = "default_some_propagation_overhead")]
+    #[clap(long, value_name = "time")]
+    #[clap(help = NETWORK_HELP.get("propagation_overhead").unwrap().as_str())]
+    pub propagation_overhead: Option<units::Time<units::TimePrefix>>,
+
+    /// The smallest latency of edges whose latency is computed from the coordinates of their
+    /// nodes, so that nodes that are close together don't limit the runahead
+    #[serde(default = "default_some_propagation_min_latency")]
+    #[clap(long, value_name = "time")]
+    #[clap(help = NETWORK_HELP.get("propagation_min_latency").unwrap().as_str())]
+    pub propagation_min_latency: Option<units::Time<units::TimePrefix>>,
+
+    /// Changes to the network graph's edges that are applied at scheduled simulated times
+    #[clap(skip)]
+    #[serde(default)]
+    pub topology_events: Option<Vec<TopologyEventOptions>>,
 }
 
 impl NetworkOptions {
@@ -403,6 +458,12 @@ pub struct ExperimentalOptions {
     #[clap(help = EXP_HELP.get("socket_recv_autotune").unwrap().as_str())]
     pub socket_recv_autotune: Option<bool>,
 
+    /// Request explicit congestion notification (ECN) when opening TCP connections
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = EXP_HELP.get("use_tcp_ecn").unwrap().as_str())]
+    pub use_tcp_ecn: Option<bool>,
+
     /// The queueing discipline to use at the network interface
     #[clap(hide_short_help = true)]
     #[clap(long, value_name = "mode")]
@@ -520,6 +581,7 @@ impl Default for ExperimentalOptions {
             socket_send_autotune: Some(true),
             socket_recv_buffer: Some(units::Bytes::new(174_760, units::SiPrefixUpper::Base)),
             socket_recv_autotune: Some(true),
+            use_tcp_ecn: Some(false),
             interface_qdisc: Some(QDiscMode::Fifo),
             use_legacy_working_dir: Some(false),
             host_heartbeat_log_level: Some(LogLevel::Info),
@@ -560,6 +622,10 @@ pub struct HostDefaultOptions {
     #[clap(long, value_name = "bytes")]
     #[clap(help = HOST_HELP.get("pcap_capture_size").unwrap().as_str())]
     pub pcap_capture_size: Option<units::Bytes<units::SiPrefixUpper>>,
+
+    /// The queue that holds packets arriving from the network until the host can receive them
+    #[clap(skip)]
+    pub router_queue: Option<RouterQueueOptions>,
 }
 
 impl HostDefaultOptions {
@@ -568,6 +634,7 @@ impl HostDefaultOptions {
             log_level: None,
             pcap_directory: None,
             pcap_capture_size: None,
+            router_queue: None,
         }
     }
 
@@ -587,6 +654,7 @@ impl Default for HostDefaultOptions {
             // capture all the data available from the packet". The maximum length of an IP packet
             // (including the header) is 65535 bytes.
             pcap_capture_size: Some(units::Bytes::new(65535, units::SiPrefixUpper::Base)),
+            router_queue: Some(RouterQueueOptions::default()),
         }
     }
 }
@@ -642,10 +710,311 @@ pub struct HostOptions {
     #[serde(default)]
     pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
 
+    /// Number of bytes above the bandwidth that the host's interfaces can send or receive in a
+    /// burst (default: the MTU)
+    #[serde(default)]
+    pub bandwidth_burst_size: Option<units::Bytes<units::SiPrefixUpper>>,
+
+    /// How often the host's interfaces are allowed to send and receive more bytes (default: 1 ms)
+    #[serde(default)]
+    pub bandwidth_refill_interval: Option<units::Time<units::TimePrefix>>,
+
+    /// Changes to the bandwidth of the host's default interface during the simulation
+    #[serde(default)]
+    pub bandwidth_schedule: Option<BandwidthScheduleOptions>,
+
+    /// Latency of packets sent between the host's processes over the loopback interface
+    #[serde(default)]
+    pub loopback_latency: Option<units::Time<units::TimePrefix>>,
+
+    /// Bandwidth capacity of the host's loopback interface
+    #[serde(default)]
+    pub loopback_bandwidth: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+
+    /// Additional network interfaces, each attached to its own network graph node
+    #[serde(default)]
+    pub interfaces: Vec<HostInterfaceOptions>,
+
+    /// Firewall rules for packets sent and received by the host, in the order that they're
+    /// checked
+    #[serde(default)]
+    pub firewall: Vec<FirewallRuleOptions>,
+
     #[serde(default = "HostDefaultOptions::new_empty")]
     pub options: HostDefaultOptions,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HostInterfaceOptions {
+    /// Network graph node ID to attach the interface to
+    pub network_node_id: u32,
+
+    /// IP address to assign to the interface
+    #[serde(default)]
+    pub ip_addr: Option<std::net::Ipv4Addr>,
+
+    /// Downstream bandwidth capacity of the interface
+    #[serde(default)]
+    pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+
+    /// Upstream bandwidth capacity of the interface
+    #[serde(default)]
+    pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BandwidthScheduleOptions {
+    /// A CSV trace of bandwidth changes
+    File(FileSource),
+    Inline(Vec<BandwidthChangeOptions>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BandwidthChangeOptions {
+    /// The simulated time at which to change the bandwidth
+    pub time: units::Time<units::TimePrefix>,
+
+    /// The new downstream bandwidth
+    #[serde(default)]
+    pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+
+    /// The new upstream bandwidth
+    #[serde(default)]
+    pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+}
+
+impl BandwidthChangeOptions {
+    /// Parse a CSV bandwidth trace with one `time,bandwidth_down,bandwidth_up` row per change. The
+    /// values take the same units as an inline schedule, and a bandwidth that is empty is left
+    /// unchanged. Blank lines, comment lines starting with `#`, and a header row starting with
+    /// `time` are ignored.
+    pub fn from_csv(text: &str) -> Result<Vec<Self>, String> {
+        let mut changes = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_num = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let &[time, down, up] = &fields[..] else {
+                return Err(format!(
+                    "Line {line_num}: expected 3 columns but found {}",
+                    fields.len()
+                ));
+            };
+
+            if time == "time" {
+                continue;
+            }
+
+            let parse_bandwidth = |x: &str| {
+                if x.is_empty() {
+                    return Ok(None);
+                }
+                x.parse()
+                    .map(Some)
+                    .map_err(|e| format!("Line {line_num}: invalid bandwidth '{x}': {e}"))
+            };
+
+            changes.push(Self {
+                time: time
+                    .parse()
+                    .map_err(|e| format!("Line {line_num}: invalid time '{time}': {e}"))?,
+                bandwidth_down: parse_bandwidth(down)?,
+                bandwidth_up: parse_bandwidth(up)?,
+            });
+        }
+
+        Ok(changes)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FirewallRuleOptions {
+    /// What to do with packets that match the rule
+    pub action: FirewallAction,
+
+    /// Only match packets in this direction
+    #[serde(default)]
+    pub direction: Option<FirewallDirection>,
+
+    /// Only match packets of this protocol
+    #[serde(default)]
+    pub protocol: Option<FirewallProtocol>,
+
+    /// Only match packets from this IP address
+    #[serde(default)]
+    pub src_ip: Option<std::net::Ipv4Addr>,
+
+    /// Only match packets from this port
+    #[serde(default)]
+    pub src_port: Option<u16>,
+
+    /// Only match packets to this IP address
+    #[serde(default)]
+    pub dst_ip: Option<std::net::Ipv4Addr>,
+
+    /// Only match packets to this port
+    #[serde(default)]
+    pub dst_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[repr(C)]
+pub enum FirewallAction {
+    Allow,
+    Drop,
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[repr(C)]
+pub enum FirewallDirection {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallProtocol {
+    Tcp,
+    Udp,
+    Icmp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RouterQueueOptions {
+    Codel(CoDelQueueOptions),
+    DropTail(DropTailQueueOptions),
+    Red(RedQueueOptions),
+    FqCodel(FqCoDelQueueOptions),
+    Pie(PieQueueOptions),
+}
+
+impl Default for RouterQueueOptions {
+    fn default() -> Self {
+        Self::Codel(CoDelQueueOptions {
+            target: default_codel_target(),
+            interval: default_codel_interval(),
+            limit: None,
+        })
+    }
+}
+
+/// Drops packets once they've been queued for longer than a target delay for a full interval.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CoDelQueueOptions {
+    /// The acceptable standing queue delay
+    #[serde(default = "default_codel_target")]
+    pub target: units::Time<units::TimePrefix>,
+
+    /// The time over which the standing queue delay is measured
+    #[serde(default = "default_codel_interval")]
+    pub interval: units::Time<units::TimePrefix>,
+
+    /// The maximum number of bytes to queue
+    #[serde(default)]
+    pub limit: Option<units::Bytes<units::SiPrefixUpper>>,
+}
+
+/// Drops packets that arrive when the queue is full.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DropTailQueueOptions {
+    /// The maximum number of bytes to queue
+    pub limit: units::Bytes<units::SiPrefixUpper>,
+}
+
+/// Random early detection: drops arriving packets with a probability that grows with the average
+/// queue size.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RedQueueOptions {
+    /// The average queue size at which packets start being dropped
+    pub min_threshold: units::Bytes<units::SiPrefixUpper>,
+
+    /// The average queue size at which all packets are dropped
+    pub max_threshold: units::Bytes<units::SiPrefixUpper>,
+
+    /// The drop probability when the average queue size reaches the maximum threshold
+    #[serde(default = "default_red_max_probability")]
+    pub max_probability: f64,
+
+    /// The weight of the current queue size in the average queue size
+    #[serde(default = "default_red_weight")]
+    pub weight: f64,
+
+    /// The maximum number of bytes to queue
+    #[serde(default)]
+    pub limit: Option<units::Bytes<units::SiPrefixUpper>>,
+}
+
+/// Flow queue CoDel: schedules flows round-robin, and applies CoDel to each flow separately.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FqCoDelQueueOptions {
+    /// The number of queues that flows are hashed into
+    #[serde(default = "default_fq_codel_flows")]
+    pub flows: u32,
+
+    /// The number of bytes a flow may dequeue each round
+    #[serde(default = "default_fq_codel_quantum")]
+    pub quantum: units::Bytes<units::SiPrefixUpper>,
+
+    /// The acceptable standing queue delay of each flow
+    #[serde(default = "default_codel_target")]
+    pub target: units::Time<units::TimePrefix>,
+
+    /// The time over which the standing queue delay of each flow is measured
+    #[serde(default = "default_codel_interval")]
+    pub interval: units::Time<units::TimePrefix>,
+
+    /// The maximum number of bytes to queue across all flows
+    #[serde(default)]
+    pub limit: Option<units::Bytes<units::SiPrefixUpper>>,
+}
+
+/// Proportional integral controller enhanced: drops arriving packets with a probability that is
+/// adjusted periodically to keep the queue delay near a target.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PieQueueOptions {
+    /// The queue delay to aim for
+    #[serde(default = "default_pie_target")]
+    pub target: units::Time<units::TimePrefix>,
+
+    /// How often the drop probability is updated
+    #[serde(default = "default_pie_update_interval")]
+    pub update_interval: units::Time<units::TimePrefix>,
+
+    /// How much the drop probability changes with the distance from the target delay
+    #[serde(default = "default_pie_alpha")]
+    pub alpha: f64,
+
+    /// How much the drop probability changes with the change in queue delay
+    #[serde(default = "default_pie_beta")]
+    pub beta: f64,
+
+    /// How long bursts may be queued without drops
+    #[serde(default = "default_pie_max_burst")]
+    pub max_burst: units::Time<units::TimePrefix>,
+
+    /// The maximum number of bytes to queue
+    #[serde(default)]
+    pub limit: Option<units::Bytes<units::SiPrefixUpper>>,
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
@@ -713,6 +1082,7 @@ pub enum LogInfoFlag {
     Node,
     Socket,
     Ram,
+    Router,
 }
 
 impl LogInfoFlag {
@@ -721,6 +1091,7 @@ impl LogInfoFlag {
             Self::Node => c::_LogInfoFlags_LOG_INFO_FLAGS_NODE,
             Self::Socket => c::_LogInfoFlags_LOG_INFO_FLAGS_SOCKET,
             Self::Ram => c::_LogInfoFlags_LOG_INFO_FLAGS_RAM,
+            Self::Router => c::_LogInfoFlags_LOG_INFO_FLAGS_ROUTER,
         }
     }
 }
@@ -759,6 +1130,8 @@ fn parse_set_str(s: &str) -> Result<HashSet<String>, <String as FromStr>::Err> {
 pub enum QDiscMode {
     Fifo,
     RoundRobin,
+    Priority,
+    Sfq,
 }
 
 impl FromStr for QDiscMode {
@@ -769,10 +1142,42 @@ impl FromStr for QDiscMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RoutingMode {
+    Precomputed,
+    Lazy,
+}
+
+impl FromStr for RoutingMode {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphReportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for GraphReportFormat {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Compression {
     Xz,
+    Gzip,
+    Zstd,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -795,8 +1200,156 @@ pub enum GraphSource {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum GraphOptions {
     Gml(GraphSource),
+    Graphml(GraphSource),
+    Json(GraphSource),
     #[serde(rename = "1_gbit_switch")]
     OneGbitSwitch,
+    Star(StarGraphOptions),
+    FullMesh(FullMeshGraphOptions),
+    Dumbbell(DumbbellGraphOptions),
+    LeafSpine(LeafSpineGraphOptions),
+    RandomGeometric(RandomGeometricGraphOptions),
+}
+
+/// Leaf nodes that are each connected to a central hub node.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StarGraphOptions {
+    /// The number of leaf nodes
+    pub nodes: u32,
+
+    /// The latency of each link
+    #[serde(default = "default_graph_latency")]
+    pub latency: units::Time<units::TimePrefix>,
+
+    /// The upstream and downstream bandwidth of each node
+    #[serde(default = "default_graph_bandwidth")]
+    pub bandwidth: units::BitsPerSec<units::SiPrefixUpper>,
+}
+
+/// Nodes that are each connected to every other node.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FullMeshGraphOptions {
+    /// The number of nodes
+    pub nodes: u32,
+
+    /// The latency of each link
+    #[serde(default = "default_graph_latency")]
+    pub latency: units::Time<units::TimePrefix>,
+
+    /// The packet loss of each link
+    #[serde(default)]
+    pub packet_loss: f32,
+
+    /// The upstream and downstream bandwidth of each node
+    #[serde(default = "default_graph_bandwidth")]
+    pub bandwidth: units::BitsPerSec<units::SiPrefixUpper>,
+}
+
+/// Two groups of leaf nodes whose routers are connected by a bottleneck link.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DumbbellGraphOptions {
+    /// The number of leaf nodes on each side of the bottleneck
+    pub nodes_per_side: u32,
+
+    /// The latency of each link between a leaf node and its router
+    #[serde(default = "default_graph_latency")]
+    pub latency: units::Time<units::TimePrefix>,
+
+    /// The upstream and downstream bandwidth of each node
+    #[serde(default = "default_graph_bandwidth")]
+    pub bandwidth: units::BitsPerSec<units::SiPrefixUpper>,
+
+    /// The latency of the bottleneck link
+    #[serde(default = "default_graph_bottleneck_latency")]
+    pub bottleneck_latency: units::Time<units::TimePrefix>,
+
+    /// The capacity of the bottleneck link
+    pub bottleneck_bandwidth: units::BitsPerSec<units::SiPrefixUpper>,
+
+    /// The queue size of the bottleneck link
+    #[serde(default)]
+    pub bottleneck_queue_size: Option<units::Bytes<units::SiPrefixUpper>>,
+}
+
+/// A two-tier data center network, where each leaf node is connected to every spine node.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LeafSpineGraphOptions {
+    /// The number of leaf nodes
+    pub leaves: u32,
+
+    /// The number of spine nodes
+    pub spines: u32,
+
+    /// The latency of each link
+    #[serde(default = "default_graph_latency")]
+    pub latency: units::Time<units::TimePrefix>,
+
+    /// The upstream and downstream bandwidth of each node
+    #[serde(default = "default_graph_bandwidth")]
+    pub bandwidth: units::BitsPerSec<units::SiPrefixUpper>,
+
+    /// The capacity of each link between a leaf and a spine
+    #[serde(default)]
+    pub link_bandwidth: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+}
+
+/// Nodes placed randomly in a unit square, and connected when they're within `radius` of each
+/// other. The placement is seeded from `general.seed`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RandomGeometricGraphOptions {
+    /// The number of nodes
+    pub nodes: u32,
+
+    /// The maximum distance between connected nodes
+    pub radius: f64,
+
+    /// The latency of a link whose length is `radius`; shorter links have proportionally lower
+    /// latencies
+    #[serde(default = "default_graph_latency")]
+    pub latency: units::Time<units::TimePrefix>,
+
+    /// The smallest latency of a link, so that nodes that are very close together don't limit the
+    /// runahead
+    #[serde(default = "default_graph_min_latency")]
+    pub min_latency: units::Time<units::TimePrefix>,
+
+    /// The upstream and downstream bandwidth of each node
+    #[serde(default = "default_graph_bandwidth")]
+    pub bandwidth: units::BitsPerSec<units::SiPrefixUpper>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TopologyEventOptions {
+    /// The simulated time at which to apply the change
+    pub time: units::Time<units::TimePrefixUpper>,
+
+    /// The ID of the edge's source node
+    pub source: u32,
+
+    /// The ID of the edge's target node
+    pub target: u32,
+
+    /// Bring the edge up (true) or take it down (false)
+    #[serde(default)]
+    pub up: Option<bool>,
+
+    /// The new latency of the edge
+    #[serde(default)]
+    pub latency: Option<units::Time<units::TimePrefix>>,
+
+    /// The new jitter of the edge
+    #[serde(default)]
+    pub jitter: Option<units::Time<units::TimePrefix>>,
+
+    /// The new packet loss of the edge
+    #[serde(default)]
+    pub packet_loss: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -1050,6 +1603,107 @@ fn default_some_nullable_time_1() -> Option<NullableOption<units::Time<units::Ti
     Some(NullableOption::Value(time))
 }
 
+/// Helper function for serde default `Some(RoutingMode::Precomputed)` values.
+fn default_some_routing_mode() -> Option<RoutingMode> {
+    Some(RoutingMode::Precomputed)
+}
+
+/// Helper function for serde default `Some(1024)` values.
+fn default_some_routing_cache_size() -> Option<NonZeroU32> {
+    Some(NonZeroU32::new(1024).unwrap())
+}
+
+/// Helper function for serde default `Some(0.67)` values.
+fn default_some_propagation_speed() -> Option<f64> {
+    Some(crate::network::graph::DEFAULT_PROPAGATION_SPEED)
+}
+
+/// Helper function for serde default `Some(0 ms)` values.
+fn default_some_propagation_overhead() -> Option<units::Time<units::TimePrefix>> {
+    Some(units::Time::new(0, units::TimePrefix::Milli))
+}
+
+/// Helper function for serde default `Some(1 ms)` values.
+fn default_some_propagation_min_latency() -> Option<units::Time<units::TimePrefix>> {
+    Some(units::Time::new(1, units::TimePrefix::Milli))
+}
+
+/// Helper function for the default latency of generated graphs.
+fn default_graph_latency() -> units::Time<units::TimePrefix> {
+    units::Time::new(1, units::TimePrefix::Milli)
+}
+
+/// Helper function for the default minimum link latency of random geometric graphs.
+fn default_graph_min_latency() -> units::Time<units::TimePrefix> {
+    units::Time::new(100, units::TimePrefix::Micro)
+}
+
+/// Helper function for the default bottleneck latency of generated graphs.
+fn default_graph_bottleneck_latency() -> units::Time<units::TimePrefix> {
+    units::Time::new(10, units::TimePrefix::Milli)
+}
+
+/// Helper function for the default node bandwidth of generated graphs.
+fn default_graph_bandwidth() -> units::BitsPerSec<units::SiPrefixUpper> {
+    units::BitsPerSec::new(1, units::SiPrefixUpper::Giga)
+}
+
+/// Helper function for the default CoDel target delay. This is recommended to be 5 milliseconds
+/// in internet routers, but in Shadow we increase it to 10 milliseconds.
+fn default_codel_target() -> units::Time<units::TimePrefix> {
+    units::Time::new(10, units::TimePrefix::Milli)
+}
+
+/// Helper function for the default CoDel interval.
+fn default_codel_interval() -> units::Time<units::TimePrefix> {
+    units::Time::new(100, units::TimePrefix::Milli)
+}
+
+/// Helper function for the default RED maximum drop probability.
+fn default_red_max_probability() -> f64 {
+    0.1
+}
+
+/// Helper function for the default RED queue size weight.
+fn default_red_weight() -> f64 {
+    0.002
+}
+
+/// Helper function for the default number of FQ-CoDel flow queues.
+fn default_fq_codel_flows() -> u32 {
+    1024
+}
+
+/// Helper function for the default FQ-CoDel quantum, which is one MTU.
+fn default_fq_codel_quantum() -> units::Bytes<units::SiPrefixUpper> {
+    units::Bytes::new(1500, units::SiPrefixUpper::Base)
+}
+
+/// Helper function for the default PIE target delay (RFC 8033).
+fn default_pie_target() -> units::Time<units::TimePrefix> {
+    units::Time::new(15, units::TimePrefix::Milli)
+}
+
+/// Helper function for the default PIE drop probability update interval (RFC 8033).
+fn default_pie_update_interval() -> units::Time<units::TimePrefix> {
+    units::Time::new(15, units::TimePrefix::Milli)
+}
+
+/// Helper function for the default PIE alpha (RFC 8033).
+fn default_pie_alpha() -> f64 {
+    0.125
+}
+
+/// Helper function for the default PIE beta (RFC 8033).
+fn default_pie_beta() -> f64 {
+    1.25
+}
+
+/// Helper function for the default PIE maximum burst (RFC 8033).
+fn default_pie_max_burst() -> units::Time<units::TimePrefix> {
+    units::Time::new(150, units::TimePrefix::Milli)
+}
+
 /// Helper function for serde default `Some(LogLevel::Info)` values.
 fn default_some_info() -> Option<LogLevel> {
     Some(LogLevel::Info)
@@ -1184,6 +1838,50 @@ mod tests {
         assert!(!err_str.is_empty());
     }
 
+    #[test]
+    fn test_bandwidth_trace() {
+        let csv = "\
+            time,bandwidth_down,bandwidth_up\n\
+            # a comment\n\
+            0,10 Mbit,1 Mbit\n\
+            \n\
+            1500 ms, 5000000 ,\n\
+            2 min,,2 Mbit\n";
+
+        let bw = |x: &str| Some(units::BitsPerSec::from_str(x).unwrap());
+        let expected = vec![
+            BandwidthChangeOptions {
+                time: units::Time::from_str("0 s").unwrap(),
+                bandwidth_down: bw("10 Mbit"),
+                bandwidth_up: bw("1 Mbit"),
+            },
+            BandwidthChangeOptions {
+                time: units::Time::from_str("1500 ms").unwrap(),
+                bandwidth_down: bw("5000000 bit"),
+                bandwidth_up: None,
+            },
+            BandwidthChangeOptions {
+                time: units::Time::from_str("2 min").unwrap(),
+                bandwidth_down: None,
+                bandwidth_up: bw("2 Mbit"),
+            },
+        ];
+
+        assert_eq!(BandwidthChangeOptions::from_csv(csv).unwrap(), expected);
+        assert_eq!(BandwidthChangeOptions::from_csv("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_bandwidth_trace_error() {
+        assert!(BandwidthChangeOptions::from_csv("0,10 Mbit").is_err());
+        assert!(BandwidthChangeOptions::from_csv("0,10 Mbit,1 Mbit,1 Mbit").is_err());
+        assert!(BandwidthChangeOptions::from_csv("soon,10 Mbit,1 Mbit").is_err());
+        assert!(BandwidthChangeOptions::from_csv("0,fast,1 Mbit").is_err());
+
+        let err = BandwidthChangeOptions::from_csv("0,1 Mbit,1 Mbit\n1 s,1 Mbit").unwrap_err();
+        assert!(err.starts_with("Line 2:"));
+    }
+
     #[test]
     // can't call foreign function: process_parseArgStr
     #[cfg_attr(miri, ignore)]
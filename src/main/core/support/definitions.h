@@ -106,6 +106,11 @@ typedef struct Host Host;
  */
 #define CONFIG_HEADER_SIZE_TCP 20
 
+/**
+ * Default ICMP header size in bytes.
+ */
+#define CONFIG_HEADER_SIZE_ICMP 8
+
 /**
  * Header size in bytes of a routable packet with UDP encapsulation; includes
  * the IP and UDP headers but excludes the ethernet header and packet payload.
@@ -118,6 +123,14 @@ typedef struct Host Host;
  */
 #define CONFIG_HEADER_SIZE_TCPIP (CONFIG_HEADER_SIZE_TCP + CONFIG_HEADER_SIZE_IP)
 
+/**
+ * Header size in bytes of a routable packet with ICMP encapsulation; includes
+ * the IP and ICMP headers but excludes the ethernet header and packet payload.
+ * ICMP error messages additionally quote the IP header and first 8 bytes of
+ * the packet that caused the error.
+ */
+#define CONFIG_HEADER_SIZE_ICMPIP (CONFIG_HEADER_SIZE_ICMP + CONFIG_HEADER_SIZE_IP)
+
 /**
  * Maximum size of an IP packet without fragmenting over Ethernetv2
  */
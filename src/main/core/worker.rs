@@ -14,8 +14,11 @@ use crate::cshadow;
 use crate::host::host::Host;
 use crate::host::process::{Process, ProcessId};
 use crate::host::thread::{ThreadId, ThreadRef};
-use crate::network::graph::{IpAssignment, RoutingInfo};
-use crate::network::packet::Packet;
+use crate::network::graph::{IpAssignment, PathLink, PathProperties, Route, RoutingInfo};
+use crate::network::link::Links;
+use crate::network::nat::Nats;
+use crate::network::packet::{Packet, PacketStatus};
+use crate::network::router::RouterStats;
 use crate::utility::childpid_watcher::ChildPidWatcher;
 use crate::utility::counter::Counter;
 use crate::utility::status_bar;
@@ -26,6 +29,7 @@ use shadow_shim_helper_rs::HostId;
 
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::sync::atomic::{AtomicBool, AtomicU32};
 use std::sync::{Arc, Mutex};
 
@@ -265,6 +269,12 @@ impl Worker {
         .unwrap();
     }
 
+    /// The latency of the path between two addresses, or `None` if either address is not
+    /// assigned to a graph node.
+    pub fn latency(src: std::net::IpAddr, dst: std::net::IpAddr) -> Option<SimulationTime> {
+        Worker::with(|w| w.shared.latency(src, dst)).unwrap()
+    }
+
     pub fn reset_next_event_time() {
         Worker::with(|w| w.next_event_time.set(None)).unwrap();
     }
@@ -305,25 +315,133 @@ impl Worker {
         let src_ip = unsafe { cshadow::packet_getSourceIP(packet) };
         let dst_ip = unsafe { cshadow::packet_getDestinationIP(packet) };
         let payload_size = unsafe { cshadow::packet_getPayloadSize(packet) };
+        let total_size = unsafe { cshadow::packet_getTotalSize(packet) };
 
         let src_ip: std::net::Ipv4Addr = u32::from_be(src_ip).into();
         let dst_ip: std::net::Ipv4Addr = u32::from_be(dst_ip).into();
 
+        // there's no host with the destination address, so tell the sender
+        if !Worker::with(|w| w.shared.is_routable(src_ip.into(), dst_ip.into())).unwrap() {
+            unsafe {
+                cshadow::packet_addDeliveryStatus(
+                    packet,
+                    cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                )
+            };
+            unsafe { Self::send_host_unreachable(src_host, packet, src_ip) };
+            return;
+        }
+
+        let Some(nat_route) = (unsafe { Self::nat_route(packet, src_ip, dst_ip) }) else {
+            unsafe {
+                cshadow::packet_addDeliveryStatus(
+                    packet,
+                    cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                )
+            };
+            return;
+        };
+
+        // a packet leaving a NAT is sent from the NAT's public address; the NAT rewrites a copy
+        // since the sender may still retransmit the original
+        let nat_packet = if nat_route.is_outbound {
+            let mut nat_packet = Packet::from_raw(unsafe { cshadow::packet_copy(packet) });
+            let is_translated = Worker::with(|w| {
+                let nat = w.shared.nats.behind(src_ip).unwrap();
+                src_host
+                    .nat_table_borrow_mut(src_ip, nat)
+                    .translate_outbound(current_time, &mut nat_packet)
+            })
+            .unwrap();
+            if !is_translated {
+                unsafe {
+                    cshadow::packet_addDeliveryStatus(
+                        packet,
+                        cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                    )
+                };
+                return;
+            }
+            Some(nat_packet)
+        } else {
+            None
+        };
+        // the packet as it's sent over the network, after any NAT translation
+        let wire_packet: *const cshadow::Packet = match &nat_packet {
+            Some(x) => x.borrow_inner(),
+            None => packet,
+        };
+        let src_ip = nat_packet
+            .as_ref()
+            .map(|x| *x.source().ip())
+            .unwrap_or(src_ip);
+
+        // the packet is received on the destination host's interface with this address, which is
+        // behind the destination's NAT if the packet is sent to a NAT's public address
+        let dst_interface_ip = nat_route.internal_dst.unwrap_or(dst_ip);
+        let is_nat_inbound = nat_route.internal_dst.is_some();
+
         let dst_host_id = Worker::with(|w| {
             w.shared
-                .resolve_ip_to_host_id(dst_ip)
-                .expect("No host ID for dest address {dst_ip}")
+                .resolve_ip_to_host_id(dst_interface_ip)
+                .expect("No host ID for dest address {dst_interface_ip}")
         })
         .unwrap();
 
+        // routers see the translated source address and port of a packet that left a NAT, which
+        // `worker_getPathMTU` must also use so that sockets see the MTU of the path taken
+        let flow_hash = flow_hash(
+            unsafe { cshadow::packet_getProtocol(wire_packet) },
+            src_ip,
+            unsafe { cshadow::packet_getSourcePort(wire_packet) },
+            dst_ip,
+            unsafe { cshadow::packet_getDestinationPort(wire_packet) },
+        );
+
         let src_ip = std::net::IpAddr::V4(src_ip);
         let dst_ip = std::net::IpAddr::V4(dst_ip);
 
+        // there may currently be no path to the destination (for example during a scheduled link
+        // outage), in which case the packet is always dropped
+        let Some(route) = Worker::with(|w| w.shared.route(src_ip, dst_ip, flow_hash)).unwrap()
+        else {
+            unsafe {
+                cshadow::packet_addDeliveryStatus(
+                    packet,
+                    cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                )
+            };
+            return;
+        };
+        let path = route.properties;
+
+        // packets larger than the path MTU are split into fragments, each of which can be lost or
+        // corrupted, and are reassembled at the destination; packets with the DF flag set are
+        // dropped instead. paths without an MTU never fragment packets.
+        let mtu: Option<usize> = path_mtu(&path).map(|x| x.try_into().unwrap());
+        let num_fragments = match mtu {
+            Some(mtu) => num_fragments(total_size, mtu),
+            None => 1,
+        };
+        if num_fragments > 1 && unsafe { cshadow::packet_getDontFragment(packet) } != 0 {
+            unsafe {
+                cshadow::packet_addDeliveryStatus(
+                    packet,
+                    cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                )
+            };
+            return;
+        }
+        // every fragment has its own IP header
+        let ip_header_size: usize = cshadow::CONFIG_HEADER_SIZE_IP.try_into().unwrap();
+        let total_size = total_size + (num_fragments - 1) * ip_header_size;
+
+        // a fragmented packet is lost or corrupted if any of its fragments is
+        let packet_loss = any_fragment_affected(path.packet_loss, num_fragments);
+        let corrupt = any_fragment_affected(path.corrupt, num_fragments);
+
         // check if network reliability forces us to 'drop' the packet
-        let reliability: f64 = Worker::with(|w| w.shared.reliability(src_ip, dst_ip).unwrap())
-            .unwrap()
-            .try_into()
-            .unwrap();
+        let reliability: f64 = (1.0 - packet_loss).into();
         let chance: f64 = src_host.random_mut().gen();
 
         // don't drop control packets with length 0, otherwise congestion control has problems
@@ -339,11 +457,57 @@ impl Worker {
             return;
         }
 
-        let delay = Worker::with(|w| w.shared.latency(src_ip, dst_ip).unwrap()).unwrap();
+        let path_links = route.links;
+
+        // check if the bursty loss model of a link on the path drops the packet
+        if !is_bootstrapping && payload_size > 0 {
+            if let Some(path_links) = &path_links {
+                // every fragment steps the models, even after an earlier fragment was lost
+                let mut is_lost = false;
+                for _ in 0..num_fragments {
+                    is_lost |= Self::is_lost_in_burst(src_host, src_ip, dst_ip, path_links);
+                }
+                if is_lost {
+                    unsafe {
+                        cshadow::packet_addDeliveryStatus(
+                            packet,
+                            cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                        )
+                    };
+                    return;
+                }
+            }
+        }
+
+        let latency = SimulationTime::from_nanos(path.latency_ns);
+        let jitter = SimulationTime::from_nanos(path.jitter_ns);
+
+        // the packet's delay is drawn uniformly from [latency - jitter, latency + jitter]; we only
+        // draw from the rng when the path has jitter so that simulations without jitter use the
+        // same sequence of random values as before
+        let delay = if jitter.is_zero() {
+            latency
+        } else {
+            let min_delay = latency - jitter;
+            let offset_ns = src_host.random_mut().gen_range(0..=(2 * jitter.as_nanos()));
+            min_delay + SimulationTime::from_nanos(offset_ns.try_into().unwrap())
+        };
+
+        // a reordered packet is held back by an extra delay so that packets sent after it may
+        // overtake it; as with jitter, we only draw from the rng if the path has the impairment
+        let is_reordered = path.reorder > 0.0 && src_host.random_mut().gen::<f32>() < path.reorder;
+        let delay = if is_reordered {
+            delay + SimulationTime::from_nanos(path.reorder_delay_ns)
+        } else {
+            delay
+        };
         let deliver_time = current_time + delay;
 
+        let is_duplicated =
+            path.duplicate > 0.0 && src_host.random_mut().gen::<f32>() < path.duplicate;
+
         Worker::update_lowest_used_latency(delay);
-        Worker::with(|w| w.shared.increment_packet_count(src_ip, dst_ip)).unwrap();
+        Worker::with(|w| w.shared.increment_packet_count(src_ip, dst_ip, route.index)).unwrap();
 
         // TODO: this should change for sending to remote manager (on a different machine); this is
         // the only place where tasks are sent between separate host
@@ -355,36 +519,222 @@ impl Worker {
             )
         };
 
-        // copy the packet
-        let packet = Packet::from_raw(unsafe { cshadow::packet_copy(packet) });
-        let packet = Arc::new(AtomicCell::new(Some(packet)));
+        // packets crossing capacity-limited links are queued at the links at the end of the round,
+        // and the manager schedules the ones that aren't dropped
+        let path_links = path_links.filter(|x| x.iter().any(|link| link.capacity.is_some()));
+
+        // the destination receives a second copy of a duplicated packet
+        let num_copies = if is_duplicated { 2 } else { 1 };
+
+        for _ in 0..num_copies {
+            // each copy is corrupted independently; a corrupted packet is still delivered, but the
+            // destination discards it when verifying its checksum
+            let is_corrupted = !is_bootstrapping
+                && payload_size > 0
+                && corrupt > 0.0
+                && src_host.random_mut().gen::<f32>() < corrupt;
+
+            // copy the packet
+            let original = nat_packet.as_ref().map_or(packet, |x| x.borrow_inner());
+            let mut packet = Packet::from_raw(unsafe { cshadow::packet_copy(original) });
+            if is_corrupted {
+                packet.add_status(PacketStatus::InetCorrupted);
+            }
+            let packet = Arc::new(AtomicCell::new(Some(packet)));
+
+            let task_packet = Arc::clone(&packet);
+            let packet_task = TaskRef::new(move |host| {
+                let mut packet = task_packet.take().expect("Packet task ran twice");
+
+                // the destination's NAT only forwards packets that match one of its mappings at
+                // the time that they arrive
+                if is_nat_inbound {
+                    let now = Worker::current_time().unwrap();
+                    let is_translated = Worker::with(|w| {
+                        let nat = w.shared.nats.behind(dst_interface_ip).unwrap();
+                        host.nat_table_borrow_mut(dst_interface_ip, nat)
+                            .translate_inbound(now, &mut packet)
+                    })
+                    .unwrap();
+                    if !is_translated {
+                        packet.add_status(PacketStatus::InetDropped);
+                        return;
+                    }
+                }
+
+                let became_nonempty = {
+                    let mut router = host.upstream_router_borrow_mut(dst_interface_ip).unwrap();
+                    router.push(packet)
+                };
+
+                if became_nonempty {
+                    host.packets_are_available_to_receive(dst_interface_ip);
+                }
+            });
+
+            let mut packet_event = Event::new(packet_task, deliver_time, src_host, dst_host_id);
+
+            if let Some(path_links) = &path_links {
+                Worker::with(|w| {
+                    w.shared.links.push(
+                        current_time,
+                        total_size.try_into().unwrap(),
+                        Arc::clone(path_links),
+                        packet_event,
+                        packet,
+                    )
+                })
+                .unwrap();
+                continue;
+            }
+
+            // delay the packet until the next round
+            if deliver_time < round_end_time {
+                packet_event.set_time(round_end_time);
+            }
 
-        let packet_task = TaskRef::new(move |host| {
-            let packet = packet.take().expect("Packet task ran twice");
+            // we may have sent this packet after the destination host finished running the
+            // current round and calculated its min event time, so we put this in our min event
+            // time instead
+            Worker::update_next_event_time(packet_event.time());
+
+            debug_assert!(packet_event.time() >= round_end_time);
+            Worker::with(|w| w.shared.push_to_host(dst_host_id, packet_event)).unwrap();
+        }
+    }
+
+    /// Find how the NATs of the source and destination nodes handle a packet. Returns `None` if a
+    /// NAT drops the packet: packets from outside a NAT can't be sent to its internal addresses,
+    /// packets to a NAT's public address must be for a port that belongs to one of its internal
+    /// addresses, ICMP errors don't cross NATs, and a NAT doesn't forward packets from its internal
+    /// addresses back to its own public address.
+    unsafe fn nat_route(
+        packet: *mut cshadow::Packet,
+        src_ip: std::net::Ipv4Addr,
+        dst_ip: std::net::Ipv4Addr,
+    ) -> Option<NatRoute> {
+        Worker::with(|w| {
+            let nats = &w.shared.nats;
+            let src_nat = nats.behind(src_ip).map(|x| x.node_id);
+            let dst_nat = nats.behind(dst_ip).map(|x| x.node_id);
+            let dst_public_nat = nats.with_public_address(dst_ip);
+
+            // hosts behind the same NAT can reach each other directly
+            if dst_nat.is_some() && dst_nat != src_nat {
+                return None;
+            }
+
+            let is_outbound = src_nat.is_some() && dst_nat.is_none();
+            let is_inbound = dst_public_nat.is_some();
+
+            if is_outbound || is_inbound {
+                let is_icmp_error = unsafe { cshadow::packet_getProtocol(packet) }
+                    == cshadow::_ProtocolType_PICMP
+                    && unsafe { (*cshadow::packet_getICMPHeader(packet)).type_ }
+                        == cshadow::ProtocolICMPType_PICMP_DEST_UNREACH;
+                if is_icmp_error {
+                    return None;
+                }
+            }
+
+            let internal_dst = match dst_public_nat {
+                Some(nat) => {
+                    if src_nat == Some(nat.node_id) {
+                        return None;
+                    }
+                    let port = u16::from_be(unsafe { cshadow::packet_getDestinationPort(packet) });
+                    Some(nat.internal_address(port)?)
+                }
+                None => None,
+            };
+
+            Some(NatRoute {
+                is_outbound,
+                internal_dst,
+            })
+        })
+        .unwrap()
+    }
+
+    /// Answer a packet that can't be routed with an ICMP host unreachable error, which arrives
+    /// back on the source host's interface with address `src_ip`. ICMP errors are never answered
+    /// with another error.
+    unsafe fn send_host_unreachable(
+        src_host: &Host,
+        packet: *mut cshadow::Packet,
+        src_ip: std::net::Ipv4Addr,
+    ) {
+        let is_icmp_error = unsafe { cshadow::packet_getProtocol(packet) }
+            == cshadow::_ProtocolType_PICMP
+            && unsafe { (*cshadow::packet_getICMPHeader(packet)).type_ }
+                == cshadow::ProtocolICMPType_PICMP_DEST_UNREACH;
+        if is_icmp_error {
+            return;
+        }
+
+        let error = unsafe { cshadow::packet_new(src_host) };
+        unsafe {
+            cshadow::packet_setICMPError(
+                error,
+                cshadow::ProtocolICMPType_PICMP_DEST_UNREACH,
+                cshadow::ProtocolICMPCode_PICMP_CODE_HOST_UNREACH,
+                u32::from(src_ip).to_be(),
+                packet,
+            )
+        };
+        let error = Arc::new(AtomicCell::new(Some(Packet::from_raw(error))));
+
+        let error_task = TaskRef::new(move |host| {
+            let error = error.take().expect("Packet task ran twice");
 
             let became_nonempty = {
-                let mut router = host.upstream_router_borrow_mut();
-                router.push(packet)
+                let mut router = host.upstream_router_borrow_mut(src_ip).unwrap();
+                router.push(error)
             };
 
             if became_nonempty {
-                host.packets_are_available_to_receive();
+                host.packets_are_available_to_receive(src_ip);
             }
         });
 
-        let mut packet_event = Event::new(packet_task, deliver_time, src_host, dst_host_id);
-
-        // delay the packet until the next round
-        if deliver_time < round_end_time {
-            packet_event.set_time(round_end_time);
-        }
-
-        // we may have sent this packet after the destination host finished running the current
-        // round and calculated its min event time, so we put this in our min event time instead
-        Worker::update_next_event_time(packet_event.time());
+        src_host.schedule_task_with_delay(error_task, SimulationTime::NANOSECOND);
+    }
+
+    /// Step the bursty loss models of the links along the path, in the order that the packet
+    /// crosses them. Returns `true` if one of the links drops the packet. The state of a link's
+    /// model is tracked separately for each path from the host, so that it's only affected by
+    /// the host's own packets and random number generator.
+    fn is_lost_in_burst(
+        src_host: &Host,
+        src_ip: std::net::IpAddr,
+        dst_ip: std::net::IpAddr,
+        path_links: &[PathLink],
+    ) -> bool {
+        let path = Worker::with(|w| {
+            let src = w.shared.ip_assignment.get_node(src_ip).unwrap();
+            let dst = w.shared.ip_assignment.get_node(dst_ip).unwrap();
+            (src, dst)
+        })
+        .unwrap();
 
-        debug_assert!(packet_event.time() >= round_end_time);
-        Worker::with(|w| w.shared.push_to_host(dst_host_id, packet_event)).unwrap();
+        let mut bad_links = src_host.burst_loss_bad_links_borrow_mut();
+        let mut rng = src_host.random_mut();
+
+        // a packet that is dropped doesn't reach the remaining links, so their models don't change
+        path_links
+            .iter()
+            .filter_map(|link| Some((link, link.burst_loss?)))
+            .any(|(link, model)| {
+                let id = (path, (link.source, link.target));
+                let mut bad = bad_links.contains(&id);
+                let lost = model.step(&mut bad, &mut *rng);
+                if bad {
+                    bad_links.insert(id);
+                } else {
+                    bad_links.remove(&id);
+                }
+                lost
+            })
     }
 
     // Runs `f` with a shared reference to the current thread's Worker. Returns
@@ -449,6 +799,17 @@ impl Worker {
         });
     }
 
+    /// Record the final statistics of a host's upstream router with address `addr`.
+    pub fn add_router_stats(host_name: &str, addr: std::net::Ipv4Addr, stats: RouterStats) {
+        Worker::with(|w| {
+            w.sim_stats
+                .router_stats
+                .borrow_mut()
+                .insert((host_name.to_string(), addr), stats);
+        })
+        .unwrap()
+    }
+
     pub fn add_to_global_sim_stats() {
         Worker::with(|w| SIM_STATS.add_from_local_stats(&w.sim_stats)).unwrap()
     }
@@ -461,8 +822,9 @@ impl Worker {
 #[derive(Debug)]
 pub struct WorkerShared {
     pub ip_assignment: IpAssignment<u32>,
-    pub routing_info: RoutingInfo<u32>,
+    pub routing_info: RoutingInfo,
     pub host_bandwidths: HashMap<std::net::IpAddr, Bandwidth>,
+    pub nats: Nats,
     pub dns: SyncSendPointer<cshadow::DNS>,
     // allows for easy updating of the status bar's state
     pub status_logger_state: Option<Arc<status_bar::Status<ShadowStatusBarState>>>,
@@ -474,6 +836,8 @@ pub struct WorkerShared {
     pub event_queues: HashMap<HostId, Arc<Mutex<EventQueue>>>,
     pub bootstrap_end_time: EmulatedTime,
     pub sim_end_time: EmulatedTime,
+    // queues of the capacity-limited links in the network graph
+    pub links: Links,
 }
 
 impl WorkerShared {
@@ -498,22 +862,48 @@ impl WorkerShared {
         ))
     }
 
-    pub fn reliability(&self, src: std::net::IpAddr, dst: std::net::IpAddr) -> Option<f32> {
+    /// The MTU of the path that a packet of the flow with hash `flow_hash` takes between two
+    /// addresses. Returns `None` if the path has no MTU, if there is currently no path between
+    /// the addresses, or if either address isn't assigned to a graph node (such as a loopback
+    /// address).
+    pub fn path_mtu(
+        &self,
+        src: std::net::IpAddr,
+        dst: std::net::IpAddr,
+        flow_hash: u64,
+    ) -> Option<u32> {
+        path_mtu(&self.route(src, dst, flow_hash)?.properties)
+    }
+
+    /// The path that a packet of the flow with hash `flow_hash` takes between two addresses.
+    /// Returns `None` if there is currently no path between them.
+    pub fn route(
+        &self,
+        src: std::net::IpAddr,
+        dst: std::net::IpAddr,
+        flow_hash: u64,
+    ) -> Option<Route> {
         let src = self.ip_assignment.get_node(src)?;
         let dst = self.ip_assignment.get_node(dst)?;
 
-        Some(1.0 - self.routing_info.path(src, dst)?.packet_loss)
+        self.routing_info.route(src, dst, flow_hash)
     }
 
     pub fn bandwidth(&self, ip: std::net::IpAddr) -> Option<&Bandwidth> {
         self.host_bandwidths.get(&ip)
     }
 
-    pub fn increment_packet_count(&self, src: std::net::IpAddr, dst: std::net::IpAddr) {
+    pub fn increment_packet_count(
+        &self,
+        src: std::net::IpAddr,
+        dst: std::net::IpAddr,
+        path_index: usize,
+    ) {
         let src = self.ip_assignment.get_node(src).unwrap();
         let dst = self.ip_assignment.get_node(dst).unwrap();
 
-        self.routing_info.increment_packet_count(src, dst)
+        self.routing_info
+            .increment_packet_count(src, dst, path_index)
     }
 
     pub fn is_routable(&self, src: std::net::IpAddr, dst: std::net::IpAddr) -> bool {
@@ -525,7 +915,9 @@ impl WorkerShared {
             return false;
         }
 
-        // the network graph is required to be a connected graph, so they must be routable
+        // the network graph is required to be a connected graph at the start of the simulation,
+        // so they are routable; if a later topology change disconnects them, packets between them
+        // are dropped rather than refused
         true
     }
 
@@ -589,6 +981,63 @@ impl std::ops::Drop for WorkerShared {
     }
 }
 
+/// How the NATs of a packet's source and destination nodes handle the packet.
+struct NatRoute {
+    /// The packet leaves the source's NAT, which rewrites its source address and port.
+    is_outbound: bool,
+    /// The packet is sent to a NAT's public address, and is for this internal address.
+    internal_dst: Option<std::net::Ipv4Addr>,
+}
+
+/// The MTU of a path, which is also limited by the MTU of the hosts' interfaces. Returns `None`
+/// if no edge of the path has an MTU.
+fn path_mtu(path: &PathProperties) -> Option<u32> {
+    path.mtu.map(|mtu| std::cmp::min(mtu, cshadow::CONFIG_MTU))
+}
+
+/// The number of fragments that an IP packet of `size` bytes is split into so that each fits
+/// within `mtu`.
+fn num_fragments(size: usize, mtu: usize) -> usize {
+    if size <= mtu {
+        return 1;
+    }
+
+    // the data of every fragment except the last must be a multiple of 8 bytes
+    let ip_header_size: usize = cshadow::CONFIG_HEADER_SIZE_IP.try_into().unwrap();
+    let fragment_data_size = (mtu - ip_header_size) & !7;
+    let data_size = size - ip_header_size;
+    (data_size + fragment_data_size - 1) / fragment_data_size
+}
+
+/// The probability that at least one of a packet's `num_fragments` fragments is lost (or
+/// corrupted), if each is independently lost with `probability`.
+fn any_fragment_affected(probability: f32, num_fragments: usize) -> f32 {
+    // avoid any rounding error for unfragmented packets
+    if num_fragments == 1 {
+        return probability;
+    }
+
+    let n: i32 = num_fragments.try_into().unwrap();
+    1.0 - (1.0 - probability).powi(n)
+}
+
+/// A hash of the flow that a packet belongs to. Packets of the same flow always take the same
+/// path when there are several equal-cost paths. Ports are in network byte order.
+fn flow_hash(
+    protocol: cshadow::ProtocolType,
+    src_ip: std::net::Ipv4Addr,
+    src_port: libc::in_port_t,
+    dst_ip: std::net::Ipv4Addr,
+    dst_port: libc::in_port_t,
+) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (src_ip, dst_ip).hash(&mut hasher);
+    protocol.hash(&mut hasher);
+    src_port.hash(&mut hasher);
+    dst_port.hash(&mut hasher);
+    hasher.finish()
+}
+
 /// Enable object counters. Should be called near the beginning of the program.
 pub fn enable_object_counters() {
     USE_OBJECT_COUNTERS.store(true, std::sync::atomic::Ordering::Relaxed);
@@ -609,7 +1058,8 @@ mod export {
         Worker::with_dns(|dns| dns as *const cshadow::DNS).cast_mut()
     }
 
-    /// Addresses must be provided in network byte order.
+    /// Addresses must be provided in network byte order. Returns `SIMTIME_INVALID` if there is
+    /// currently no path between the addresses.
     #[no_mangle]
     pub extern "C" fn worker_getLatency(
         src: libc::in_addr_t,
@@ -636,6 +1086,54 @@ mod export {
         Worker::with(|w| w.shared.bandwidth(ip).unwrap().up_bytes).unwrap()
     }
 
+    /// The MTU of the path taken by packets of a flow that `host` sends. Addresses and ports must
+    /// be provided in network byte order. Returns the MTU of the hosts' interfaces if the path has
+    /// no MTU, or if there is currently no path between the addresses.
+    #[no_mangle]
+    pub extern "C" fn worker_getPathMTU(
+        host: *const Host,
+        protocol: cshadow::ProtocolType,
+        src: libc::in_addr_t,
+        src_port: libc::in_port_t,
+        dst: libc::in_addr_t,
+        dst_port: libc::in_port_t,
+    ) -> u32 {
+        let host = unsafe { host.as_ref() }.unwrap();
+        let src: std::net::Ipv4Addr = u32::from_be(src).into();
+        let dst: std::net::Ipv4Addr = u32::from_be(dst).into();
+        let now = Worker::current_time().unwrap();
+
+        // like `send_packet`, use the source address and port that the flow's packets are given by
+        // the source's NAT, if they leave one
+        let (src, src_port, dst_port) = Worker::with(|w| {
+            let nats = &w.shared.nats;
+            let Some(nat) = nats.behind(src).filter(|_| nats.behind(dst).is_none()) else {
+                return (src, src_port, dst_port);
+            };
+            let remote = std::net::SocketAddrV4::new(dst, u16::from_be(dst_port));
+            let public = host.nat_table_borrow_mut(src, nat).peek_outbound(
+                now,
+                protocol,
+                u16::from_be(src_port),
+                remote,
+            );
+            match public {
+                // echo messages use the identifier, which the NAT rewrites, as both ports
+                Some(public) if protocol == cshadow::_ProtocolType_PICMP => {
+                    (*public.ip(), public.port().to_be(), public.port().to_be())
+                }
+                Some(public) => (*public.ip(), public.port().to_be(), dst_port),
+                None => (src, src_port, dst_port),
+            }
+        })
+        .unwrap();
+        let flow_hash = flow_hash(protocol, src, src_port, dst, dst_port);
+
+        Worker::with(|w| w.shared.path_mtu(src.into(), dst.into(), flow_hash))
+            .unwrap()
+            .unwrap_or(cshadow::CONFIG_MTU)
+    }
+
     /// Addresses must be provided in network byte order.
     #[no_mangle]
     pub extern "C" fn worker_isRoutable(src: libc::in_addr_t, dst: libc::in_addr_t) -> bool {
@@ -744,3 +1242,33 @@ mod export {
         EmulatedTime::to_c_emutime(Some(Worker::max_event_runahead_time(host)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_num_fragments() {
+        // packets that fit aren't fragmented
+        assert_eq!(num_fragments(1280, 1280), 1);
+        assert_eq!(num_fragments(100, 1280), 1);
+
+        // each fragment carries up to 1256 bytes of data (a multiple of 8) and a 20 byte header
+        assert_eq!(num_fragments(1281, 1280), 2);
+        assert_eq!(num_fragments(20 + 2 * 1256, 1280), 2);
+        assert_eq!(num_fragments(20 + 2 * 1256 + 1, 1280), 3);
+        assert_eq!(num_fragments(4028, 1280), 4);
+
+        // the smallest MTU leaves 48 bytes of data per fragment
+        assert_eq!(num_fragments(20 + 480, 68), 10);
+    }
+
+    #[test]
+    fn test_any_fragment_affected() {
+        assert_eq!(any_fragment_affected(0.1, 1), 0.1);
+        assert_eq!(any_fragment_affected(0.0, 4), 0.0);
+        assert_eq!(any_fragment_affected(1.0, 4), 1.0);
+        assert!((any_fragment_affected(0.1, 2) - 0.19).abs() < 1e-6);
+        assert!((any_fragment_affected(0.5, 3) - 0.875).abs() < 1e-6);
+    }
+}
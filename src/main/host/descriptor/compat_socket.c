@@ -174,3 +174,41 @@ void compatsocket_updatePacketHeader(const CompatSocket* socket, const Host* hos
 
     utility_panic("Invalid CompatSocket type");
 }
+
+uint32_t compatsocket_getPriority(const CompatSocket* socket) {
+    switch (socket->type) {
+        case CST_LEGACY_SOCKET:
+            return legacysocket_getPriority(socket->object.as_legacy_socket);
+        case CST_INET_SOCKET:
+            return inetsocket_getPriority(socket->object.as_inet_socket);
+        case CST_NONE: utility_panic("Unexpected CompatSocket type");
+    }
+
+    utility_panic("Invalid CompatSocket type");
+}
+
+CEmulatedTime compatsocket_getNextPacedSendTime(const CompatSocket* socket) {
+    switch (socket->type) {
+        case CST_LEGACY_SOCKET:
+            return legacysocket_getNextPacedSendTime(socket->object.as_legacy_socket);
+        case CST_INET_SOCKET:
+            return inetsocket_getNextPacedSendTime(socket->object.as_inet_socket);
+        case CST_NONE: utility_panic("Unexpected CompatSocket type");
+    }
+
+    utility_panic("Invalid CompatSocket type");
+}
+
+void compatsocket_recordPacedSend(const CompatSocket* socket, size_t bytes, CEmulatedTime now) {
+    switch (socket->type) {
+        case CST_LEGACY_SOCKET:
+            legacysocket_recordPacedSend(socket->object.as_legacy_socket, bytes, now);
+            return;
+        case CST_INET_SOCKET:
+            inetsocket_recordPacedSend(socket->object.as_inet_socket, bytes, now);
+            return;
+        case CST_NONE: utility_panic("Unexpected CompatSocket type");
+    }
+
+    utility_panic("Invalid CompatSocket type");
+}
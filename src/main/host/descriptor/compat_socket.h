@@ -49,5 +49,8 @@ const Packet* compatsocket_peekNextOutPacket(const CompatSocket* socket);
 void compatsocket_pushInPacket(const CompatSocket* socket, const Host* host, Packet* packet);
 Packet* compatsocket_pullOutPacket(const CompatSocket* socket, const Host* host);
 void compatsocket_updatePacketHeader(const CompatSocket* socket, const Host* host, Packet* packet);
+uint32_t compatsocket_getPriority(const CompatSocket* socket);
+CEmulatedTime compatsocket_getNextPacedSendTime(const CompatSocket* socket);
+void compatsocket_recordPacedSend(const CompatSocket* socket, size_t bytes, CEmulatedTime now);
 
 #endif /* SRC_MAIN_HOST_DESCRIPTOR_COMPAT_SOCKET_H_ */
@@ -6,6 +6,7 @@
 
 #include <glib.h>
 #include <netinet/in.h>
+#include <netinet/ip.h>
 #include <sys/un.h>
 
 #include "main/core/support/definitions.h"
@@ -122,6 +123,9 @@ void legacysocket_init(LegacySocket* socket, const Host* host, SocketFunctionTab
     socket->outputBuffer = g_queue_new();
     socket->outputControlBuffer = g_queue_new();
     socket->outputBufferSize = sendBufferSize;
+    /* the default for UDP sockets on linux (net.ipv4.ip_no_pmtu_disc = 0) */
+    socket->mtuDiscover = IP_PMTUDISC_WANT;
+    socket->maxPacingRate = G_MAXUINT64;
 
     Tracker* tracker = host_getTracker(host);
     if (tracker != NULL) {
@@ -136,6 +140,112 @@ ProtocolType legacysocket_getProtocol(LegacySocket* socket) {
     return socket->protocol;
 }
 
+gint legacysocket_getMTUDiscover(LegacySocket* socket) {
+    MAGIC_ASSERT(socket);
+    return socket->mtuDiscover;
+}
+
+void legacysocket_setMTUDiscover(LegacySocket* socket, gint mode) {
+    MAGIC_ASSERT(socket);
+    socket->mtuDiscover = mode;
+}
+
+guint8 legacysocket_getTOS(LegacySocket* socket) {
+    MAGIC_ASSERT(socket);
+    return socket->tos;
+}
+
+void legacysocket_setTOS(LegacySocket* socket, guint8 tos) {
+    MAGIC_ASSERT(socket);
+
+    /* the priorities that linux assigns to each of the TOS values (see rt_tos2priority), which
+     * are indexed by the 4 TOS bits after the 3 precedence bits */
+    static const guint32 tosToPriority[16] = {0, 0, 0, 0, 2, 2, 2, 2, 6, 6, 6, 6, 4, 4, 4, 4};
+
+    /* like linux, the priority only changes if the TOS field does */
+    if (socket->tos != tos) {
+        socket->tos = tos;
+        socket->priority = tosToPriority[IPTOS_TOS(tos) >> 1];
+    }
+}
+
+guint32 legacysocket_getPriority(LegacySocket* socket) {
+    MAGIC_ASSERT(socket);
+    return socket->priority;
+}
+
+void legacysocket_setPriority(LegacySocket* socket, guint32 priority) {
+    MAGIC_ASSERT(socket);
+    socket->priority = priority;
+}
+
+static gboolean _legacysocket_isPaced(LegacySocket* socket) {
+    return socket->maxPacingRate != G_MAXUINT64;
+}
+
+guint64 legacysocket_getMaxPacingRate(LegacySocket* socket) {
+    MAGIC_ASSERT(socket);
+    return socket->maxPacingRate;
+}
+
+void legacysocket_setMaxPacingRate(LegacySocket* socket, guint64 rate) {
+    MAGIC_ASSERT(socket);
+    socket->maxPacingRate = rate;
+}
+
+CEmulatedTime legacysocket_getNextPacedSendTime(LegacySocket* socket) {
+    MAGIC_ASSERT(socket);
+    return _legacysocket_isPaced(socket) ? socket->nextPacedSendTime : 0;
+}
+
+void legacysocket_recordPacedSend(LegacySocket* socket, gsize bytes, CEmulatedTime now) {
+    MAGIC_ASSERT(socket);
+
+    if (!_legacysocket_isPaced(socket)) {
+        return;
+    }
+
+    /* like linux's fq qdisc, a rate of 0 allows one packet per second */
+    CSimulationTime delay = SIMTIME_ONE_SECOND;
+
+    if (socket->maxPacingRate != 0) {
+        /* round up so that the socket never sends faster than its rate */
+        guint64 byteNanos = (guint64)bytes * SIMTIME_ONE_SECOND;
+        delay = byteNanos / socket->maxPacingRate;
+        if (byteNanos % socket->maxPacingRate != 0) {
+            delay++;
+        }
+    }
+
+    /* a socket that was idle doesn't get to make up for lost time with a burst */
+    socket->nextPacedSendTime = MAX(socket->nextPacedSendTime, now) + delay;
+}
+
+gboolean legacysocket_isDontFragment(LegacySocket* socket) {
+    MAGIC_ASSERT(socket);
+    /* shadow knows the path MTU up front, so packets sent in the IP_PMTUDISC_WANT mode are
+     * fragmented in the network as if the host had already discovered the path MTU */
+    return socket->mtuDiscover == IP_PMTUDISC_DO || socket->mtuDiscover == IP_PMTUDISC_PROBE;
+}
+
+guint legacysocket_getPathMTU(LegacySocket* socket, const Host* host) {
+    MAGIC_ASSERT(socket);
+    utility_debugAssert(socket->peerIP != 0);
+
+    in_addr_t sourceIP = socket->boundAddress;
+    if (sourceIP == htonl(INADDR_ANY)) {
+        sourceIP = host_getSourceIP(host, socket->peerIP);
+    }
+
+    /* echo messages use the identifier, which is the socket's port, as both ports */
+    in_port_t peerPort = socket->protocol == PICMP ? socket->boundPort : socket->peerPort;
+
+    /* the path taken by the socket's packets, which is one of several equal-cost paths when using
+     * ECMP */
+    return worker_getPathMTU(host, socket->protocol, sourceIP, socket->boundPort, socket->peerIP,
+                             peerPort);
+}
+
 /* interface functions, implemented by subtypes */
 
 gboolean legacysocket_isFamilySupported(LegacySocket* socket, sa_family_t family) {
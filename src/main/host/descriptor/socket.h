@@ -69,6 +69,18 @@ struct _LegacySocket {
 
     gchar* unixPath;
 
+    /* the IP_MTU_DISCOVER mode, which controls the DF flag of outgoing packets */
+    gint mtuDiscover;
+
+    /* the IP_TOS field, and the SO_PRIORITY used by the interface's queuing discipline */
+    guint8 tos;
+    guint32 priority;
+
+    /* the SO_MAX_PACING_RATE in bytes per second, and the earliest time that the interface may
+     * send the socket's next packet without exceeding it */
+    guint64 maxPacingRate;
+    CEmulatedTime nextPacedSendTime;
+
     /* buffering packets readable by user */
     GQueue* inputBuffer;
     gsize inputBufferSize;
@@ -121,6 +133,23 @@ void legacysocket_setSocketName(LegacySocket* socket, in_addr_t ip, in_port_t po
 
 ProtocolType legacysocket_getProtocol(LegacySocket* socket);
 
+gint legacysocket_getMTUDiscover(LegacySocket* socket);
+void legacysocket_setMTUDiscover(LegacySocket* socket, gint mode);
+gboolean legacysocket_isDontFragment(LegacySocket* socket);
+guint8 legacysocket_getTOS(LegacySocket* socket);
+/* Also sets the priority from the TOS field if it changed, as linux does. */
+void legacysocket_setTOS(LegacySocket* socket, guint8 tos);
+guint32 legacysocket_getPriority(LegacySocket* socket);
+void legacysocket_setPriority(LegacySocket* socket, guint32 priority);
+/* A rate of G_MAXUINT64 means that the socket isn't paced. */
+guint64 legacysocket_getMaxPacingRate(LegacySocket* socket);
+void legacysocket_setMaxPacingRate(LegacySocket* socket, guint64 rate);
+CEmulatedTime legacysocket_getNextPacedSendTime(LegacySocket* socket);
+/* Delays the socket's next paced send by the time it takes to send `bytes` at its pacing rate. */
+void legacysocket_recordPacedSend(LegacySocket* socket, gsize bytes, CEmulatedTime now);
+/* The MTU of the path to the socket's peer. The socket must have a peer. */
+guint legacysocket_getPathMTU(LegacySocket* socket, const Host* host);
+
 gboolean legacysocket_isFamilySupported(LegacySocket* socket, sa_family_t family);
 gint legacysocket_connectToPeer(LegacySocket* socket, const Host* host, in_addr_t ip,
                                 in_port_t port, sa_family_t family);
@@ -151,6 +151,12 @@ impl InetSocketRef<'_> {
     enum_passthrough!(self, (packet), Tcp;
         pub fn update_packet_header(&self, packet: &mut Packet)
     );
+    enum_passthrough!(self, (), Tcp;
+        pub fn priority(&self) -> u32
+    );
+    enum_passthrough!(self, (), Tcp;
+        pub fn next_paced_send_time(&self) -> c::CEmulatedTime
+    );
 }
 
 // file functions
@@ -255,6 +261,9 @@ impl InetSocketRefMut<'_> {
     enum_passthrough!(self, (packet), Tcp;
         pub fn update_packet_header(&self, packet: &mut Packet)
     );
+    enum_passthrough!(self, (bytes, now), Tcp;
+        pub fn record_paced_send(&mut self, bytes: usize, now: c::CEmulatedTime)
+    );
 }
 
 impl std::fmt::Debug for InetSocketRef<'_> {
@@ -421,4 +430,28 @@ mod export {
         socket.borrow().update_packet_header(&mut packet);
         packet.into_inner();
     }
+
+    #[no_mangle]
+    pub extern "C" fn inetsocket_getPriority(socket: *const InetSocket) -> u32 {
+        let socket = unsafe { socket.as_ref() }.unwrap();
+        socket.borrow().priority()
+    }
+
+    #[no_mangle]
+    pub extern "C" fn inetsocket_getNextPacedSendTime(
+        socket: *const InetSocket,
+    ) -> c::CEmulatedTime {
+        let socket = unsafe { socket.as_ref() }.unwrap();
+        socket.borrow().next_paced_send_time()
+    }
+
+    #[no_mangle]
+    pub extern "C" fn inetsocket_recordPacedSend(
+        socket: *const InetSocket,
+        bytes: libc::size_t,
+        now: c::CEmulatedTime,
+    ) {
+        let socket = unsafe { socket.as_ref() }.unwrap();
+        socket.borrow_mut().record_paced_send(bytes, now)
+    }
 }
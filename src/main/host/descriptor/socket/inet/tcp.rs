@@ -156,6 +156,22 @@ impl TcpSocket {
         .unwrap();
     }
 
+    /// The priority used by the network interface's queuing discipline.
+    pub fn priority(&self) -> u32 {
+        unsafe { c::legacysocket_getPriority(self.as_legacy_socket()) }
+    }
+
+    /// The earliest time that the network interface may send the socket's next packet without
+    /// exceeding its `SO_MAX_PACING_RATE`.
+    pub fn next_paced_send_time(&self) -> c::CEmulatedTime {
+        unsafe { c::legacysocket_getNextPacedSendTime(self.as_legacy_socket()) }
+    }
+
+    pub fn record_paced_send(&mut self, bytes: usize, now: c::CEmulatedTime) {
+        let bytes = bytes.try_into().unwrap();
+        unsafe { c::legacysocket_recordPacedSend(self.as_legacy_socket(), bytes, now) }
+    }
+
     pub fn getsockname(&self) -> Result<Option<SockaddrIn>, SyscallError> {
         let mut ip: libc::in_addr_t = 0;
         let mut port: libc::in_port_t = 0;
@@ -204,6 +204,22 @@ struct _TCP {
     /* congestion object for implementing different types of congestion control (aimd, reno, cubic) */
     TCPCong cong;
 
+    /* the maximum segment size, which is derived from the MTU of the path to the peer */
+    guint mss;
+
+    /* explicit congestion notification (rfc 3168) */
+    struct {
+        /* we requested ECN in our SYN, or both sides agreed to use it during the handshake */
+        gboolean isEnabled;
+        /* we received a congestion mark, and echo it until the peer tells us it reduced its
+         * window */
+        gboolean isEchoing;
+        /* we reduced our window, and need to tell the peer on our next data packet */
+        gboolean isCWRPending;
+        /* we don't reduce our window again for echoes acknowledging data sent before this */
+        guint32 recoveryPoint;
+    } ecn;
+
     struct {
       gint rttSmoothed;
       gint rttVariance;
@@ -418,11 +434,8 @@ static guint _tcp_calculateRTT(TCP* tcp, const Host* host) {
 
     if(sourceIP == htonl(INADDR_ANY)) {
         /* source interface depends on destination */
-        if(destinationIP == htonl(INADDR_LOOPBACK)) {
-            sourceIP = htonl(INADDR_LOOPBACK);
-        } else {
-            sourceIP = host_getDefaultIP(host);
-        }
+
+        sourceIP = host_getSourceIP(host, destinationIP);
     }
 
     guint rtt = 1;
@@ -432,6 +445,12 @@ static guint _tcp_calculateRTT(TCP* tcp, const Host* host) {
         CSimulationTime srcLatency = worker_getLatency(sourceIP, destinationIP);
         CSimulationTime dstLatency = worker_getLatency(destinationIP, sourceIP);
 
+        if(srcLatency == SIMTIME_INVALID || dstLatency == SIMTIME_INVALID) {
+            /* there is currently no path between the hosts (a scheduled topology change
+             * disconnected them), so we can't estimate the rtt */
+            return rtt;
+        }
+
         /* find latency in milliseconds */
         guint sendLatency = (guint)ceil((gdouble)srcLatency / SIMTIME_ONE_MILLISECOND);
         guint receiveLatency = (guint)ceil((gdouble)dstLatency / SIMTIME_ONE_MILLISECOND);
@@ -497,11 +516,8 @@ static void _tcp_tuneInitialBufferSizes(TCP* tcp, const Host* host) {
 
     if(sourceIP == htonl(INADDR_ANY)) {
         /* source interface depends on destination */
-        if(destinationIP == htonl(INADDR_LOOPBACK)) {
-            sourceIP = htonl(INADDR_LOOPBACK);
-        } else {
-            sourceIP = host_getDefaultIP(host);
-        }
+
+        sourceIP = host_getSourceIP(host, destinationIP);
     }
 
     if(sourceIP == destinationIP) {
@@ -826,6 +842,25 @@ static void _tcp_bufferPacketIn(TCP* tcp, Packet* packet) {
     }
 }
 
+/* Update the maximum segment size from the current MTU of the path to the peer. Shadow knows the
+ * path MTU up front, so this behaves as if path MTU discovery had already completed. */
+static void _tcp_updateMSS(TCP* tcp, const Host* host) {
+    MAGIC_ASSERT(tcp);
+
+    if (!legacysocket_getPeerName(&(tcp->super), NULL, NULL)) {
+        return;
+    }
+
+    guint mtu = legacysocket_getPathMTU(&(tcp->super), host);
+    utility_debugAssert(mtu > CONFIG_HEADER_SIZE_TCPIP);
+
+    if (mtu - CONFIG_HEADER_SIZE_TCPIP != tcp->mss) {
+        trace("%s <-> %s: path MTU is %u, updating MSS from %u to %u", tcp->super.boundString,
+              tcp->super.peerString, mtu, tcp->mss, mtu - CONFIG_HEADER_SIZE_TCPIP);
+        tcp->mss = mtu - CONFIG_HEADER_SIZE_TCPIP;
+    }
+}
+
 static void _tcp_updateReceiveWindow(TCP* tcp) {
     MAGIC_ASSERT(tcp);
 
@@ -833,7 +868,7 @@ static void _tcp_updateReceiveWindow(TCP* tcp) {
      * unordered input packets should count against buffer space, so use the _tcp version. */
     //gsize space = _tcp_getBufferSpaceIn(tcp); // causes throughput problems
     gsize space = legacysocket_getInputBufferSpace(&(tcp->super));
-    gsize nPackets = space / CONFIG_TCP_MAX_SEGMENT_SIZE;
+    gsize nPackets = space / tcp->mss;
     tcp->receive.window = nPackets;
 
     /* handle window updates */
@@ -873,11 +908,8 @@ static Packet* _tcp_createPacketWithoutPayload(TCP* tcp, const Host* host,
 
     if(sourceIP == htonl(INADDR_ANY)) {
         /* source interface depends on destination */
-        if(destinationIP == htonl(INADDR_LOOPBACK)) {
-            sourceIP = htonl(INADDR_LOOPBACK);
-        } else {
-            sourceIP = host_getDefaultIP(host);
-        }
+
+        sourceIP = host_getSourceIP(host, destinationIP);
     }
 
     utility_debugAssert(sourceIP && sourcePort && destinationIP && destinationPort);
@@ -1202,6 +1234,36 @@ static void _tcp_sendShutdownFin(TCP* tcp, const Host* host) {
     }
 }
 
+/* set the ECN codepoint and flags of a packet on a connection that uses ECN (rfc 3168, section
+ * 6.1) */
+static void _tcp_setECN(TCP* tcp, Packet* packet, PacketTCPHeader* header) {
+    MAGIC_ASSERT(tcp);
+
+    /* the handshake negotiates ECN with its own flags */
+    if(header->flags & PTCP_SYN) {
+        return;
+    }
+
+    /* only new data may be marked by routers; pure acks and retransmissions are not ECN-capable */
+    gboolean isNewData = packet_getPayloadSize(packet) > 0 &&
+                         !(packet_getDeliveryStatus(packet) & PDS_SND_TCP_RETRANSMITTED);
+    packet_setECN(packet, isNewData ? PECN_ECT_0 : PECN_NOT_ECT);
+
+    /* a retransmission resends the same packet, which still has the flags it was first sent with.
+     * clear them so that it only echoes ECE if the connection still does, and never repeats a CWR,
+     * which is sent once and only on new data. */
+    header->flags &= ~(PTCP_ECE | PTCP_CWR);
+
+    if(tcp->ecn.isEchoing) {
+        header->flags |= PTCP_ECE;
+    }
+
+    if(tcp->ecn.isCWRPending && isNewData) {
+        header->flags |= PTCP_CWR;
+        tcp->ecn.isCWRPending = FALSE;
+    }
+}
+
 void tcp_networkInterfaceIsAboutToSendPacket(TCP* tcp, const Host* host, Packet* packet) {
     MAGIC_ASSERT(tcp);
 
@@ -1222,6 +1284,10 @@ void tcp_networkInterfaceIsAboutToSendPacket(TCP* tcp, const Host* host, Packet*
         tcp->send.delayedACKCounter = 0;
     }
 
+    if(tcp->ecn.isEnabled) {
+        _tcp_setECN(tcp, packet, header);
+    }
+
     if(header->sequence > 0) {
         /* store in retransmission buffer */
         _tcp_addRetransmit(tcp, packet);
@@ -1556,24 +1622,28 @@ static guint8 _tcp_getTCPInfoState(TCP* tcp) {
     }
 }
 
-void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo) {
+void tcp_getInfo(TCP* tcp, TCPInfo* info) {
     MAGIC_ASSERT(tcp);
 
-    memset(tcpinfo, 0, sizeof(struct tcp_info));
+    memset(info, 0, sizeof(*info));
+    struct tcp_info* tcpinfo = &info->base;
 
     tcpinfo->tcpi_state = (u_int8_t) _tcp_getTCPInfoState(tcp);
 //  tcpinfo->tcpi_ca_state;
 //  tcpinfo->tcpi_retransmits;
 //  tcpinfo->tcpi_probes;
 //  tcpinfo->tcpi_backoff;
-//  tcpinfo->tcpi_options;
+    /* until the handshake completes, we only know that we asked for ECN */
+    if(tcp->ecn.isEnabled && tcp->state != TCPS_SYNSENT) {
+        tcpinfo->tcpi_options |= TCPI_OPT_ECN;
+    }
 //  tcpinfo->tcpi_snd_wscale;
 //  tcpinfo->tcpi_rcv_wscale;
 
 //  tcpinfo->tcpi_rto;
 //  tcpinfo->tcpi_ato;
-    tcpinfo->tcpi_snd_mss = (u_int32_t)CONFIG_TCP_MAX_SEGMENT_SIZE;
-    tcpinfo->tcpi_rcv_mss = (u_int32_t)CONFIG_TCP_MAX_SEGMENT_SIZE;
+    tcpinfo->tcpi_snd_mss = (u_int32_t)tcp->mss;
+    tcpinfo->tcpi_rcv_mss = (u_int32_t)tcp->mss;
 
     tcpinfo->tcpi_unacked = (u_int32_t)(tcp->send.next - tcp->send.unacked);
 //  tcpinfo->tcpi_sacked;
@@ -1593,19 +1663,23 @@ void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo) {
     tcpinfo->tcpi_last_ack_recv = (u_int32_t)(tcp->info.lastAckReceived/SIMTIME_ONE_MICROSECOND);
 
     /* Metrics. */
-    tcpinfo->tcpi_pmtu = (u_int32_t)(CONFIG_MTU);
+    tcpinfo->tcpi_pmtu = (u_int32_t)(tcp->mss + CONFIG_HEADER_SIZE_TCPIP);
 //  tcpinfo->tcpi_rcv_ssthresh;
     tcpinfo->tcpi_rtt = (u_int32_t)tcp->timing.rttSmoothed;
     tcpinfo->tcpi_rttvar = (u_int32_t)tcp->timing.rttVariance;
     tcpinfo->tcpi_snd_ssthresh = (u_int32_t)tcp->cong.hooks->tcp_cong_ssthresh(tcp);
     tcpinfo->tcpi_snd_cwnd = (u_int32_t)tcp->cong.cwnd;
-    tcpinfo->tcpi_advmss = (u_int32_t)CONFIG_TCP_MAX_SEGMENT_SIZE;
+    tcpinfo->tcpi_advmss = (u_int32_t)tcp->mss;
     //  tcpinfo->tcpi_reordering;
 
     tcpinfo->tcpi_rcv_rtt = (u_int32_t)tcp->info.rtt;
     tcpinfo->tcpi_rcv_space = (u_int32_t)tcp->receive.window;
 
     tcpinfo->tcpi_total_retrans = (u_int32_t)tcp->info.retransmitCount;
+
+    /* shadow's tcp doesn't pace itself, so the socket is only paced at its SO_MAX_PACING_RATE */
+    info->tcpi_pacing_rate = legacysocket_getMaxPacingRate(&(tcp->super));
+    info->tcpi_max_pacing_rate = legacysocket_getMaxPacingRate(&(tcp->super));
 }
 
 /* Address and port must be in network byte order. */
@@ -1620,8 +1694,17 @@ static gint _tcp_connectToPeer(LegacySocket* socket, const Host* host, in_addr_t
         return errorCode;
     }
 
+    _tcp_updateMSS(tcp, host);
+
+    /* an ECN-setup SYN has both ECE and CWR set (rfc 3168, section 6.1.1) */
+    enum ProtocolTCPFlags flags = PTCP_SYN;
+    if(host_useTCPECN(host)) {
+        tcp->ecn.isEnabled = TRUE;
+        flags |= PTCP_ECE | PTCP_CWR;
+    }
+
     /* send 1st part of 3-way handshake, state->syn_sent */
-    _tcp_sendControlPacket(tcp, host, PTCP_SYN);
+    _tcp_sendControlPacket(tcp, host, flags);
 
     trace("%s <-> %s: user initiated connection", tcp->super.boundString, tcp->super.peerString);
     _tcp_setState(tcp, host, TCPS_SYNSENT);
@@ -1900,6 +1983,19 @@ TCPProcessFlags _tcp_ackProcessing(TCP* tcp, const Host* host, Packet* packet,
         tcp->retransmit.backoffCount = 0;
     }
 
+    /* the peer echoed a congestion mark; we reduce our window at most once per window of data,
+     * and tell the peer on our next data packet (rfc 3168, section 6.1.2) */
+    if(tcp->ecn.isEnabled && (header->flags & PTCP_ECE) && !(header->flags & PTCP_SYN) &&
+       header->acknowledgment > (guint)tcp->ecn.recoveryPoint) {
+        /* fast recovery already reduced the window for this window of data */
+        if(!tcp->cong.hooks->tcp_cong_fast_recovery(tcp)) {
+            debug("[CONG] congestion echo");
+            tcp->cong.hooks->tcp_cong_ecn_ev(tcp);
+        }
+        tcp->ecn.recoveryPoint = tcp->send.next;
+        tcp->ecn.isCWRPending = TRUE;
+    }
+
     if(isValidWindow) {
         /* accept the window update */
         tcp->receive.lastWindow = (guint32) header->window;
@@ -2038,6 +2134,7 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
 
                 multiplexed->child =
                     _tcpchild_new(multiplexed, tcp, handle, header->sourceIP, header->sourcePort);
+                _tcp_updateMSS(multiplexed, host);
                 utility_debugAssert(
                     g_hash_table_lookup(tcp->server->children, &(multiplexed->child->key)) == NULL);
 
@@ -2061,6 +2158,13 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 tcp = multiplexed;
                 responseFlags = PTCP_SYN|PTCP_ACK;
 
+                /* we always agree to use ECN if the peer asks for it, and an ECN-setup SYN-ACK
+                 * has only ECE set */
+                if((header->flags & PTCP_ECE) && (header->flags & PTCP_CWR)) {
+                    tcp->ecn.isEnabled = TRUE;
+                    responseFlags |= PTCP_ECE;
+                }
+
                 trace("new child state %s", _tcp_stateToAscii(tcp->state));
             }
             break;
@@ -2073,12 +2177,19 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 tcp->receive.start = header->sequence;
                 tcp->receive.next = tcp->receive.start + 1;
 
+                /* the peer agreed to use ECN if it answered our ECN-setup SYN with an ECN-setup
+                 * SYN-ACK */
+                tcp->ecn.isEnabled = tcp->ecn.isEnabled && (header->flags & PTCP_ECE) &&
+                                     !(header->flags & PTCP_CWR);
+
                 responseFlags |= PTCP_ACK;
                 _tcp_setState(tcp, host, TCPS_ESTABLISHED);
             }
             /* receive SYN, send ACK, move to SYNRECEIVED (simultaneous open) */
             else if(header->flags & PTCP_SYN) {
                 flags |= TCP_PF_PROCESSED;
+                /* we don't negotiate ECN on simultaneous opens */
+                tcp->ecn.isEnabled = FALSE;
                 tcp->receive.start = header->sequence;
                 tcp->receive.next = tcp->receive.start + 1;
 
@@ -2211,6 +2322,17 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
         flags |= _tcp_dataProcessing(tcp, packet, header);
     }
 
+    /* the network marked a packet on its way to us, so we echo the mark to the peer until it
+     * tells us that it reduced its window (rfc 3168, section 6.1.3) */
+    if(tcp->ecn.isEnabled && !(header->flags & PTCP_SYN)) {
+        if(header->flags & PTCP_CWR) {
+            tcp->ecn.isEchoing = FALSE;
+        }
+        if(packet_getECN(packet) == PECN_CE) {
+            tcp->ecn.isEchoing = TRUE;
+        }
+    }
+
     if(header->flags & PTCP_ACK) {
         flags |= _tcp_ackProcessing(tcp, host, packet, header);
     }
@@ -2366,8 +2488,11 @@ static gssize _tcp_sendUserData(LegacySocket* socket, Thread* thread, PluginVirt
     gsize space = _tcp_getBufferSpaceOut(tcp);
     gsize remaining = MIN(acceptable, space);
 
+    /* the path to the peer may have changed since we last sent */
+    _tcp_updateMSS(tcp, thread_getHost(thread));
+
     /* break data into segments and send each in a packet */
-    gsize maxPacketLength = CONFIG_TCP_MAX_SEGMENT_SIZE;
+    gsize maxPacketLength = tcp->mss;
     gsize bytesCopied = 0;
 
     /* create as many packets as needed */
@@ -2760,6 +2885,9 @@ TCP* tcp_new(const Host* host, guint receiveBufferSize, guint sendBufferSize) {
 
     tcp->autotune.isEnabled = TRUE;
 
+    /* updated once we know the peer */
+    tcp->mss = CONFIG_TCP_MAX_SEGMENT_SIZE;
+
     tcp->throttledOutput =
             priorityqueue_new((GCompareDataFunc)packet_compareTCPSequence, NULL, (GDestroyNotify)packet_unref);
     tcp->unorderedInput =
@@ -34,6 +34,16 @@ enum TCPProcessFlags {
     TCP_PF_RWND_UPDATED = 1 << 5,
 };
 
+/* Linux's struct tcp_info has grown past the one in glibc's netinet/tcp.h, so this extends the
+ * glibc struct with the newer fields that shadow supports. The layout matches linux up to the last
+ * field here, and applications that pass a shorter buffer just get the fields that fit. */
+typedef struct _TCPInfo TCPInfo;
+struct _TCPInfo {
+    struct tcp_info base;
+    u_int64_t tcpi_pacing_rate;
+    u_int64_t tcpi_max_pacing_rate;
+};
+
 typedef enum _TCPCongestionType TCPCongestionType;
 enum _TCPCongestionType {
     TCP_CC_UNKNOWN, TCP_CC_AIMD, TCP_CC_RENO, TCP_CC_CUBIC,
@@ -61,7 +71,7 @@ TCP* tcp_new(const Host* host, guint receiveBufferSize, guint sendBufferSize);
 gint tcp_getConnectionError(TCP* tcp);
 // clang-format on
 
-void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo);
+void tcp_getInfo(TCP* tcp, TCPInfo* info);
 void tcp_enterServerMode(TCP* tcp, const Host* host, const ProcessRefCell* process, gint backlog);
 void tcp_updateServerBacklog(TCP* tcp, gint backlog);
 /* Address and port must be in network byte order. */
@@ -12,6 +12,7 @@ typedef void (*TCPCongDuplicateAckEv)(TCP *tcp);
 typedef bool (*TCPCongFastRecovery)(TCP *tcp);
 typedef void (*TCPCongNewAckEv)(TCP *tcp, guint32 n);
 typedef void (*TCPCongTimeoutEv)(TCP *tcp);
+typedef void (*TCPCongECNEv)(TCP *tcp);
 typedef guint32 (*TCPCongSSThresh)(TCP *tcp);
 typedef const char* (*TCPCongNameStr)();
 
@@ -21,6 +22,7 @@ typedef struct TCPCongHooks_ {
     TCPCongFastRecovery tcp_cong_fast_recovery;
     TCPCongNewAckEv tcp_cong_new_ack_ev;
     TCPCongTimeoutEv tcp_cong_timeout_ev;
+    TCPCongECNEv tcp_cong_ecn_ev;
     TCPCongSSThresh tcp_cong_ssthresh;
     TCPCongNameStr tcp_cong_name_str;
 } TCPCongHooks;
@@ -162,6 +162,19 @@ static void tcp_cong_reno_timeout_ev_(TCP *tcp) {
     debug("[CONG] desc %p transition_to_slow_start", (LegacyFile*)tcp);
 }
 
+/* The receiver echoed a congestion mark (rfc 3168, section 6.1.2). Like a loss, but there's
+ * nothing to retransmit, so we go straight to congestion avoidance. */
+static void tcp_cong_reno_ecn_ev_(TCP *tcp) {
+
+    CAReno *reno = tcp_cong(tcp)->ca;
+
+    reno->duplicate_ack_n = 0;
+    ssthresh_halve(tcp, reno);
+    tcp_cong(tcp)->cwnd = reno->ssthresh;
+
+    transition_to_cong_avoid(tcp, reno, 0);
+}
+
 static guint32 tcp_cong_reno_ssthresh_(TCP *tcp) {
     CAReno *reno = tcp_cong(tcp)->ca;
     return reno->ssthresh;
@@ -177,6 +190,7 @@ static const struct TCPCongHooks_ reno_hooks_ = {
     .tcp_cong_fast_recovery = tcp_cong_reno_fast_recovery_,
     .tcp_cong_new_ack_ev = tcp_cong_reno_new_ack_ev_,
     .tcp_cong_timeout_ev = tcp_cong_reno_timeout_ev_,
+    .tcp_cong_ecn_ev = tcp_cong_reno_ecn_ev_,
     .tcp_cong_ssthresh = tcp_cong_reno_ssthresh_,
     .tcp_cong_name_str = tcp_cong_reno_name_str_,
 };
@@ -196,6 +210,7 @@ static const struct TCPCongHooks_ slow_start_hooks__ = {
     .tcp_cong_fast_recovery = NULL,
     .tcp_cong_new_ack_ev = ca_reno_slow_start_new_ack_ev_,
     .tcp_cong_timeout_ev = NULL,
+    .tcp_cong_ecn_ev = NULL,
     .tcp_cong_ssthresh = NULL,
     .tcp_cong_name_str = NULL,
 };
@@ -206,6 +221,7 @@ static const struct TCPCongHooks_ fast_recovery_hooks__ = {
     .tcp_cong_fast_recovery = NULL,
     .tcp_cong_new_ack_ev = ca_reno_fast_recovery_new_ack_ev_,
     .tcp_cong_timeout_ev = NULL,
+    .tcp_cong_ecn_ev = NULL,
     .tcp_cong_ssthresh = NULL,
     .tcp_cong_name_str = NULL,
 };
@@ -217,6 +233,7 @@ static const struct TCPCongHooks_ cong_avoid_hooks__ = {
     .tcp_cong_fast_recovery = NULL,
     .tcp_cong_new_ack_ev = ca_reno_cong_avoid_new_ack_ev_,
     .tcp_cong_timeout_ev = NULL,
+    .tcp_cong_ecn_ev = NULL,
     .tcp_cong_ssthresh = NULL,
     .tcp_cong_name_str = NULL,
 };
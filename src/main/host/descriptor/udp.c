@@ -8,6 +8,7 @@
 
 #include <errno.h>
 #include <netinet/in.h>
+#include <netinet/ip_icmp.h>
 #include <sys/socket.h>
 #include <sys/un.h>
 
@@ -38,6 +39,10 @@ struct _UDP {
     enum UDPState state;
     enum UDPState stateLast;
 
+    /* an error reported by an ICMP error message for the connected peer, as a positive errno
+     * value; it's returned from the next send or receive and is then cleared */
+    gint error;
+
     MAGIC_DECLARE;
 };
 
@@ -56,6 +61,36 @@ static UDP* _udp_fromLegacyFile(LegacyFile* descriptor) {
     return (UDP*)descriptor;
 }
 
+/* ping sockets send ICMP echo requests and receive the replies, rather than UDP datagrams */
+static gboolean _udp_isPing(UDP* udp) {
+    return legacysocket_getProtocol(&(udp->super)) == PICMP;
+}
+
+/* Returns the pending error as a negative errno value and clears it, or returns 0 if there is no
+ * pending error. */
+static gint _udp_takeError(UDP* udp) {
+    gint error = udp->error;
+    if (error == 0) {
+        return 0;
+    }
+
+    udp->error = 0;
+
+    /* we were readable so that blocked readers would see the error */
+    if (legacysocket_peekNextInPacket(&(udp->super)) == NULL) {
+        legacyfile_adjustStatus((LegacyFile*)udp, STATUS_FILE_READABLE, FALSE);
+    }
+
+    trace("%s <-> %s: reporting error %s", udp->super.boundString, udp->super.peerString,
+          g_strerror(error));
+    return -error;
+}
+
+gint udp_getError(UDP* udp) {
+    MAGIC_ASSERT(udp);
+    return -_udp_takeError(udp);
+}
+
 static gboolean _udp_isFamilySupported(LegacySocket* socket, sa_family_t family) {
     UDP* udp = _udp_fromLegacyFile((LegacyFile*)socket);
     MAGIC_ASSERT(udp);
@@ -83,10 +118,33 @@ static gint _udp_connectToPeer(LegacySocket* socket, const Host* host, in_addr_t
     return 0;
 }
 
+/* ICMP errors are only reported on connected sockets, and only if they're for the peer */
+static void _udp_processICMPError(UDP* udp, Packet* packet) {
+    PacketICMPHeader* header = packet_getICMPHeader(packet);
+
+    /* ping sockets have no peer port */
+    gboolean isPeer = header->originalDestinationIP == udp->super.peerIP &&
+                      (_udp_isPing(udp) || header->originalDestinationPort == udp->super.peerPort);
+
+    if (udp->state == UDPS_ESTABLISHED && isPeer) {
+        udp->error = header->code == PICMP_CODE_PORT_UNREACH ? ECONNREFUSED : EHOSTUNREACH;
+        legacyfile_adjustStatus((LegacyFile*)udp, STATUS_FILE_READABLE, TRUE);
+        packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_PROCESSED);
+    } else {
+        packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
+    }
+}
+
 static void _udp_processPacket(LegacySocket* socket, const Host* host, Packet* packet) {
     UDP* udp = _udp_fromLegacyFile((LegacyFile*)socket);
     MAGIC_ASSERT(udp);
 
+    if (packet_getProtocol(packet) == PICMP &&
+        packet_getICMPHeader(packet)->type == PICMP_DEST_UNREACH) {
+        _udp_processICMPError(udp, packet);
+        return;
+    }
+
     /* UDP packet can be buffered immediately */
     if (!legacysocket_addToInputBuffer((LegacySocket*)udp, host, packet)) {
         packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
@@ -110,11 +168,36 @@ static gssize _udp_sendUserData(LegacySocket* socket, Thread* thread, PluginVirt
     UDP* udp = _udp_fromLegacyFile((LegacyFile*)socket);
     MAGIC_ASSERT(udp);
 
+    const Host* host = thread_getHost(thread);
+
+    gint error = _udp_takeError(udp);
+    if (error != 0) {
+        return error;
+    }
+
     const gsize maxPacketLength = CONFIG_DATAGRAM_MAX_SIZE;
     if (nBytes > maxPacketLength) {
         return -EMSGSIZE;
     }
 
+    /* ping sockets are given the ICMP header by the application, followed by the payload */
+    struct icmphdr icmpHeader = {0};
+    if (_udp_isPing(udp)) {
+        if (nBytes < CONFIG_HEADER_SIZE_ICMP) {
+            return -EINVAL;
+        }
+
+        int readError =
+            process_readPtr(thread_getProcess(thread), &icmpHeader, buffer, sizeof(icmpHeader));
+        if (readError != 0) {
+            return -EFAULT;
+        }
+
+        if (icmpHeader.type != ICMP_ECHO || icmpHeader.code != 0) {
+            return -EINVAL;
+        }
+    }
+
     gsize space = legacysocket_getOutputBufferSpace(&(udp->super));
     if(space < nBytes) {
         /* not enough space to buffer the data */
@@ -132,22 +215,47 @@ static gssize _udp_sendUserData(LegacySocket* socket, Thread* thread, PluginVirt
     in_port_t sourcePort = 0;
     legacysocket_getSocketName(&(udp->super), &sourceIP, &sourcePort);
 
-    const Host* host = thread_getHost(thread);
     if (sourceIP == htonl(INADDR_ANY)) {
         /* source interface depends on destination */
-        if (destinationIP == htonl(INADDR_LOOPBACK)) {
-            sourceIP = htonl(INADDR_LOOPBACK);
-        } else {
-            sourceIP = host_getDefaultIP(host);
-        }
+        sourceIP = host_getSourceIP(host, destinationIP);
     }
 
-    utility_debugAssert(sourceIP && sourcePort && destinationIP && destinationPort);
+    /* ping sockets don't have a destination port */
+    utility_debugAssert(sourceIP && sourcePort && destinationIP &&
+                        (destinationPort || _udp_isPing(udp)));
+
+    /* the application's data includes the ICMP header for ping sockets */
+    gsize ipLength =
+        nBytes + (_udp_isPing(udp) ? CONFIG_HEADER_SIZE_IP : CONFIG_HEADER_SIZE_UDPIP);
+
+    /* datagrams that can't be fragmented must fit within the path MTU, or within the interface MTU
+     * when probing */
+    gboolean dontFragment = legacysocket_isDontFragment(&(udp->super));
+    if (dontFragment) {
+        /* echo messages use the identifier, which is the socket's port, as both ports */
+        in_port_t flowPort = _udp_isPing(udp) ? sourcePort : destinationPort;
+        guint mtu = legacysocket_getMTUDiscover(&(udp->super)) == IP_PMTUDISC_PROBE
+                        ? CONFIG_MTU
+                        : worker_getPathMTU(host, legacysocket_getProtocol(&(udp->super)),
+                                            sourceIP, sourcePort, destinationIP, flowPort);
+        if (ipLength > mtu) {
+            return -EMSGSIZE;
+        }
+    }
 
     /* create the UDP packet */
     Packet* packet = packet_new(host);
-    packet_setPayload(packet, thread, buffer, nBytes);
-    packet_setUDP(packet, PUDP_NONE, sourceIP, sourcePort, destinationIP, destinationPort);
+    if (_udp_isPing(udp)) {
+        /* the kernel uses the socket's port as the echo identifier */
+        PluginVirtualPtr payload = {.val = buffer.val + CONFIG_HEADER_SIZE_ICMP};
+        packet_setPayload(packet, thread, payload, nBytes - CONFIG_HEADER_SIZE_ICMP);
+        packet_setICMPEcho(packet, PICMP_ECHO_REQUEST, sourceIP, destinationIP, sourcePort,
+                           icmpHeader.un.echo.sequence);
+    } else {
+        packet_setPayload(packet, thread, buffer, nBytes);
+        packet_setUDP(packet, PUDP_NONE, sourceIP, sourcePort, destinationIP, destinationPort);
+    }
+    packet_setDontFragment(packet, dontFragment);
     packet_addDeliveryStatus(packet, PDS_SND_CREATED);
 
     /* buffer it in the transport layer, to be sent out when possible */
@@ -177,6 +285,11 @@ static gssize _udp_receiveUserData(LegacySocket* socket, Thread* thread, PluginV
     UDP* udp = _udp_fromLegacyFile((LegacyFile*)socket);
     MAGIC_ASSERT(udp);
 
+    gint error = _udp_takeError(udp);
+    if (error != 0) {
+        return error;
+    }
+
     if (legacysocket_peekNextInPacket(&(udp->super)) == NULL) {
         return -EWOULDBLOCK;
     }
@@ -190,10 +303,33 @@ static gssize _udp_receiveUserData(LegacySocket* socket, Thread* thread, PluginV
         return -EWOULDBLOCK;
     }
 
+    /* ping sockets give the application the echo reply's ICMP header before the payload */
+    gsize headerLength = 0;
+    if (_udp_isPing(udp)) {
+        PacketICMPHeader* packetHeader = packet_getICMPHeader(nextPacket);
+        struct icmphdr icmpHeader = {0};
+        icmpHeader.type = ICMP_ECHOREPLY;
+        icmpHeader.code = 0;
+        icmpHeader.un.echo.id = packetHeader->identifier;
+        icmpHeader.un.echo.sequence = packetHeader->sequence;
+
+        headerLength = MIN(nBytes, sizeof(icmpHeader));
+        if (headerLength > 0) {
+            int writeError = process_writePtr(
+                thread_getProcess(thread), buffer, &icmpHeader, headerLength);
+            if (writeError != 0) {
+                return -EFAULT;
+            }
+        }
+    }
+
     /* copy lesser of requested and available amount to application buffer */
     gsize packetLength = packet_getPayloadSize(nextPacket);
-    gsize copyLength = MIN(nBytes, packetLength);
-    gssize bytesCopied = packet_copyPayload(nextPacket, thread, 0, buffer, copyLength);
+    gsize copyLength = MIN(nBytes - headerLength, packetLength);
+    PluginVirtualPtr payloadBuffer = {.val = buffer.val + headerLength};
+    gssize bytesCopied = copyLength > 0
+                             ? packet_copyPayload(nextPacket, thread, 0, payloadBuffer, copyLength)
+                             : 0;
     if (bytesCopied < 0) {
         // Error writing to PluginVirtualPtr
         return bytesCopied;
@@ -209,7 +345,8 @@ static gssize _udp_receiveUserData(LegacySocket* socket, Thread* thread, PluginV
         *ip = packet_getSourceIP(packet);
     }
     if(port) {
-        *port = packet_getSourcePort(packet);
+        /* like the kernel, ping sockets don't report the identifier as the port */
+        *port = _udp_isPing(udp) ? 0 : packet_getSourcePort(packet);
     }
 
     /* destroy packet, throwing away any bytes not claimed by the app */
@@ -217,7 +354,7 @@ static gssize _udp_receiveUserData(LegacySocket* socket, Thread* thread, PluginV
 
     trace("user read %ld inbound UDP bytes", bytesCopied);
 
-    return bytesCopied;
+    return headerLength + bytesCopied;
 }
 
 static void _udp_free(LegacyFile* descriptor) {
@@ -244,7 +381,8 @@ static void _udp_close(LegacyFile* descriptor, const Host* host) {
     }
 
     /* we associate/disassociate UDP sockets without a peer */
-    host_disassociateInterface(host, PUDP, sock_ip, sock_port, 0, 0);
+    host_disassociateInterface(
+        host, legacysocket_getProtocol(&udp->super), sock_ip, sock_port, 0, 0);
 }
 
 gint udp_shutdown(UDP* udp, gint how) {
@@ -269,6 +407,12 @@ SocketFunctionTable udp_functions = {_udp_close,
                                      _udp_dropPacket,
                                      MAGIC_VALUE};
 
+UDP* udp_newPing(const Host* host, guint receiveBufferSize, guint sendBufferSize) {
+    UDP* udp = udp_new(host, receiveBufferSize, sendBufferSize);
+    udp->super.protocol = PICMP;
+    return udp;
+}
+
 UDP* udp_new(const Host* host, guint receiveBufferSize, guint sendBufferSize) {
     UDP* udp = g_new0(UDP, 1);
     MAGIC_INIT(udp);
@@ -14,6 +14,11 @@
 typedef struct _UDP UDP;
 
 UDP* udp_new(const Host* host, guint receiveBufferSize, guint sendBufferSize);
+/* A ping socket, which sends ICMP echo requests and receives the replies. */
+UDP* udp_newPing(const Host* host, guint receiveBufferSize, guint sendBufferSize);
+/* Returns and clears the pending error reported by an ICMP error message, as a positive errno
+ * value, or 0 if there is none. */
+gint udp_getError(UDP* udp);
 gint udp_shutdown(UDP* udp, gint how);
 
 #endif /* SHD_UDP_H_ */
@@ -0,0 +1,229 @@
+use std::cell::Cell;
+use std::net::SocketAddrV4;
+
+use crate::core::support::configuration::{
+    FirewallAction, FirewallDirection, FirewallProtocol, FirewallRuleOptions,
+};
+use crate::cshadow as c;
+
+/// A host's ordered list of firewall rules. The first rule that matches a packet decides what
+/// happens to it, and packets that don't match any rule are allowed.
+pub struct Firewall {
+    rules: Vec<FirewallRuleOptions>,
+    // the number of packets that each rule has matched
+    hits: Vec<Cell<u64>>,
+}
+
+impl Firewall {
+    pub fn new(rules: Vec<FirewallRuleOptions>) -> Self {
+        let hits = rules.iter().map(|_| Cell::new(0)).collect();
+        Self { rules, hits }
+    }
+
+    pub fn rules(&self) -> &[FirewallRuleOptions] {
+        &self.rules
+    }
+
+    /// The number of packets that the rule at `index` has matched.
+    pub fn hits(&self, index: usize) -> u64 {
+        self.hits[index].get()
+    }
+
+    /// Find the action to take for a packet, and count it as a hit for the matching rule.
+    pub fn filter(&self, packet: *const c::Packet, direction: FirewallDirection) -> FirewallAction {
+        match self.matching_rule(packet, direction) {
+            Some(index) => {
+                self.count_hit(index);
+                self.rules[index].action
+            }
+            None => FirewallAction::Allow,
+        }
+    }
+
+    /// Count a packet as a hit for the rule at `index`.
+    pub fn count_hit(&self, index: usize) {
+        let hits = &self.hits[index];
+        hits.set(hits.get() + 1);
+    }
+
+    /// The index of the first rule that matches a packet, without counting it as a hit.
+    pub fn matching_rule(
+        &self,
+        packet: *const c::Packet,
+        direction: FirewallDirection,
+    ) -> Option<usize> {
+        if self.rules.is_empty() {
+            return None;
+        }
+
+        assert!(!packet.is_null());
+        let protocol = match unsafe { c::packet_getProtocol(packet) } {
+            c::_ProtocolType_PTCP => Some(FirewallProtocol::Tcp),
+            c::_ProtocolType_PUDP => Some(FirewallProtocol::Udp),
+            c::_ProtocolType_PICMP => Some(FirewallProtocol::Icmp),
+            _ => None,
+        };
+        let src = unsafe {
+            SocketAddrV4::new(
+                u32::from_be(c::packet_getSourceIP(packet)).into(),
+                u16::from_be(c::packet_getSourcePort(packet)),
+            )
+        };
+        let dst = unsafe {
+            SocketAddrV4::new(
+                u32::from_be(c::packet_getDestinationIP(packet)).into(),
+                u16::from_be(c::packet_getDestinationPort(packet)),
+            )
+        };
+
+        self.rules
+            .iter()
+            .position(|rule| rule_matches(rule, direction, protocol, src, dst))
+    }
+}
+
+fn rule_matches(
+    rule: &FirewallRuleOptions,
+    direction: FirewallDirection,
+    protocol: Option<FirewallProtocol>,
+    src: SocketAddrV4,
+    dst: SocketAddrV4,
+) -> bool {
+    // ICMP packets don't have ports, so rules with a port never match them
+    let has_ports = matches!(
+        protocol,
+        Some(FirewallProtocol::Tcp) | Some(FirewallProtocol::Udp)
+    );
+
+    rule.direction.map_or(true, |x| x == direction)
+        && rule.protocol.map_or(true, |x| Some(x) == protocol)
+        && rule.src_ip.map_or(true, |x| x == *src.ip())
+        && rule.dst_ip.map_or(true, |x| x == *dst.ip())
+        && rule.src_port.map_or(true, |x| has_ports && x == src.port())
+        && rule.dst_port.map_or(true, |x| has_ports && x == dst.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+    use crate::network::packet::Packet;
+
+    fn rule(action: FirewallAction) -> FirewallRuleOptions {
+        FirewallRuleOptions {
+            action,
+            direction: None,
+            protocol: None,
+            src_ip: None,
+            src_port: None,
+            dst_ip: None,
+            dst_port: None,
+        }
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let src = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 1), 5000);
+        let dst = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 80);
+        let tcp = Some(FirewallProtocol::Tcp);
+        let icmp = Some(FirewallProtocol::Icmp);
+        let dir = FirewallDirection::In;
+
+        let any = rule(FirewallAction::Drop);
+        assert!(rule_matches(&any, dir, tcp, src, dst));
+        assert!(rule_matches(&any, dir, icmp, src, dst));
+
+        let http_in = FirewallRuleOptions {
+            direction: Some(FirewallDirection::In),
+            protocol: Some(FirewallProtocol::Tcp),
+            dst_port: Some(80),
+            ..rule(FirewallAction::Reject)
+        };
+        assert!(rule_matches(&http_in, dir, tcp, src, dst));
+        assert!(!rule_matches(
+            &http_in,
+            FirewallDirection::Out,
+            tcp,
+            src,
+            dst
+        ));
+        assert!(!rule_matches(
+            &http_in,
+            dir,
+            Some(FirewallProtocol::Udp),
+            src,
+            dst
+        ));
+        assert!(!rule_matches(&http_in, dir, tcp, dst, src));
+
+        let port_only = FirewallRuleOptions {
+            src_port: Some(5000),
+            ..rule(FirewallAction::Drop)
+        };
+        assert!(rule_matches(&port_only, dir, tcp, src, dst));
+        assert!(!rule_matches(&port_only, dir, icmp, src, dst));
+
+        let from_host = FirewallRuleOptions {
+            src_ip: Some(Ipv4Addr::new(11, 0, 0, 1)),
+            ..rule(FirewallAction::Drop)
+        };
+        assert!(rule_matches(&from_host, dir, icmp, src, dst));
+        assert!(!rule_matches(&from_host, dir, icmp, dst, src));
+    }
+
+    #[test]
+    fn test_filter() {
+        let firewall = Firewall::new(vec![
+            FirewallRuleOptions {
+                protocol: Some(FirewallProtocol::Udp),
+                dst_port: Some(80),
+                ..rule(FirewallAction::Allow)
+            },
+            FirewallRuleOptions {
+                direction: Some(FirewallDirection::In),
+                protocol: Some(FirewallProtocol::Udp),
+                ..rule(FirewallAction::Drop)
+            },
+            rule(FirewallAction::Reject),
+        ]);
+
+        let src = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 1), 5000);
+        let http = Packet::mock_new_udp(src, SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 80));
+        let other = Packet::mock_new_udp(src, SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 81));
+        let http = http.borrow_inner();
+        let other = other.borrow_inner();
+        let dir = FirewallDirection::In;
+
+        // the first matching rule decides
+        assert_eq!(firewall.filter(http, dir), FirewallAction::Allow);
+        assert_eq!(firewall.filter(other, dir), FirewallAction::Drop);
+        assert_eq!(
+            firewall.filter(other, FirewallDirection::Out),
+            FirewallAction::Reject
+        );
+        assert_eq!(firewall.filter(other, dir), FirewallAction::Drop);
+        assert_eq!(
+            [firewall.hits(0), firewall.hits(1), firewall.hits(2)],
+            [1, 2, 1]
+        );
+
+        // finding the matching rule doesn't count a hit until it's counted explicitly
+        assert_eq!(firewall.matching_rule(http, dir), Some(0));
+        assert_eq!(firewall.hits(0), 1);
+        firewall.count_hit(0);
+        assert_eq!(firewall.hits(0), 2);
+    }
+
+    #[test]
+    fn test_no_rules() {
+        let firewall = Firewall::new(vec![]);
+        let packet = Packet::mock_new();
+        let packet = packet.borrow_inner();
+        assert_eq!(firewall.matching_rule(packet, FirewallDirection::In), None);
+        assert_eq!(
+            firewall.filter(packet, FirewallDirection::Out),
+            FirewallAction::Allow
+        );
+    }
+}
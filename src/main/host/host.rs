@@ -1,18 +1,25 @@
-use crate::core::support::configuration::QDiscMode;
+use crate::core::support::configuration::{
+    BandwidthChangeOptions, FirewallRuleOptions, QDiscMode, RouterQueueOptions,
+};
+use crate::core::support::units::{self, Unit};
 use crate::core::work::event::Event;
 use crate::core::work::event_queue::EventQueue;
 use crate::core::work::task::TaskRef;
 use crate::core::worker::Worker;
 use crate::cshadow;
 use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
+use crate::host::firewall::Firewall;
 use crate::host::network_interface::{NetworkInterface, PcapOptions};
 use crate::host::process::Process;
 use crate::host::thread::ThreadId;
+use crate::network::graph::PathLinkId;
+use crate::network::nat::{Nat, NatTable};
 use crate::network::net_namespace::NetworkNamespace;
-use crate::network::router::Router;
+use crate::network::router::{new_router_queue, Router, RouterStats};
+use crate::utility::pcap_writer::PcapWriter;
 use crate::utility::{self, SyncSendPointer};
 use atomic_refcell::AtomicRefCell;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 use logger::LogLevel;
 use once_cell::unsync::OnceCell;
 use rand::SeedableRng;
@@ -28,8 +35,10 @@ use shadow_shmem::allocator::ShMemBlock;
 use shadow_shmem::scmutex::SelfContainedMutexGuard;
 use shadow_tsc::Tsc;
 use std::cell::{Cell, Ref, RefCell, RefMut, UnsafeCell};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::{CStr, CString, OsString};
+use std::fs::File;
+use std::io::BufWriter;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::num::NonZeroU8;
 use std::ops::{Deref, DerefMut};
@@ -51,6 +60,19 @@ pub struct HostParameters {
     pub sim_end_time: EmulatedTime,
     pub requested_bw_down_bits: u64,
     pub requested_bw_up_bits: u64,
+    /// Changes to the bandwidth of the default internet interface, sorted by time.
+    pub bandwidth_schedule: Vec<BandwidthChangeOptions>,
+    /// Number of bytes above the bandwidth that the interfaces' token buckets can hold.
+    pub bandwidth_burst_bytes: u64,
+    /// How often the interfaces' token buckets are refilled.
+    pub bandwidth_refill_interval: SimulationTime,
+    /// The latency of packets on the loopback interface, if it's modeled.
+    pub loopback_latency: Option<SimulationTime>,
+    /// The bandwidth of the loopback interface, if it's limited.
+    pub loopback_bandwidth_bits: Option<u64>,
+    pub interfaces: Vec<InterfaceParameters>,
+    pub firewall: Vec<FirewallRuleOptions>,
+    pub router_queue: RouterQueueOptions,
     pub cpu_frequency: u64,
     pub cpu_threshold: Option<SimulationTime>,
     pub cpu_precision: Option<SimulationTime>,
@@ -66,6 +88,7 @@ pub struct HostParameters {
     pub autotune_recv_buf: bool,
     pub init_sock_send_buf_size: u64,
     pub autotune_send_buf: bool,
+    pub use_tcp_ecn: bool,
     pub native_tsc_frequency: u64,
     pub model_unblocked_syscall_latency: bool,
     pub max_unapplied_cpu_latency: SimulationTime,
@@ -76,6 +99,14 @@ pub struct HostParameters {
     pub strace_logging_options: Option<FmtOptions>,
 }
 
+/// An additional internet interface of a multihomed host.
+pub struct InterfaceParameters {
+    pub node_id: u32,
+    pub ip_addr: Ipv4Addr,
+    pub requested_bw_down_bits: u64,
+    pub requested_bw_up_bits: u64,
+}
+
 use super::cpu::Cpu;
 use super::process::ProcessId;
 use super::syscall::formatter::FmtOptions;
@@ -109,10 +140,20 @@ pub struct Host {
 
     random: RefCell<Xoshiro256PlusPlus>,
 
-    // the upstream router that will queue packets until we can receive them.
-    // this only applies the the ethernet interface, the loopback interface
-    // does not receive packets from a router.
-    router: RefCell<Router>,
+    // the upstream routers that will queue packets until we can receive them, one for each
+    // internet interface. the loopback interface does not receive packets from a router.
+    routers: HashMap<Ipv4Addr, RefCell<Router>>,
+
+    // the links on the paths from this host that are in the bad state of their bursty loss model;
+    // all other links are in the good state
+    burst_loss_bad_links: RefCell<HashSet<PathLinkId>>,
+
+    // the mappings of the internet interfaces that are behind a NAT, which are created when the
+    // interface first sends or receives a packet through the NAT
+    nat_tables: RefCell<HashMap<Ipv4Addr, NatTable>>,
+
+    // the rules that decide which packets the internet interfaces may send and receive
+    firewall: Firewall,
 
     // a statistics tracker for in/out bytes, CPU, memory, etc.
     tracker: RefCell<Option<SyncSendPointer<cshadow::Tracker>>>,
@@ -148,6 +189,14 @@ pub struct Host {
     // track the order in which the application sent us application data
     packet_priority_counter: Cell<f64>,
 
+    // number of received packets that were discarded since they were corrupted in the network
+    checksum_error_counter: Cell<u64>,
+
+    // the current bandwidth of the default internet interface in KiB/s, which changes with the
+    // bandwidth schedule
+    bw_down_kibps: Cell<u64>,
+    bw_up_kibps: Cell<u64>,
+
     // Owned pointers to processes.
     processes: RefCell<BTreeMap<ProcessId, RootedRc<RootedRefCell<Process>>>>,
 
@@ -231,6 +280,7 @@ impl Host {
         let determinism_sequence_counter = Cell::new(0);
         // Packet priorities start at 1.0. "0.0" is used for control packets.
         let packet_priority_counter = Cell::new(1.0);
+        let checksum_error_counter = Cell::new(0);
         let tsc = Tsc::new(params.native_tsc_frequency);
 
         std::fs::create_dir_all(&data_dir_path).unwrap();
@@ -247,23 +297,52 @@ impl Host {
             .map(|x| (*x).try_into().unwrap())
             .collect();
 
+        let extra_ips: Vec<Ipv4Addr> = params.interfaces.iter().map(|x| x.ip_addr).collect();
+
         let net_ns = unsafe {
             NetworkNamespace::new(
                 params.id,
                 hostname,
                 public_ip,
+                &extra_ips,
                 Self::pcap_options(&params, &data_dir_path),
                 params.qdisc,
+                params.loopback_latency,
+                params.loopback_bandwidth_bits.is_some(),
                 dns,
             )
         };
 
+        let firewall = Firewall::new(params.firewall.clone());
+
+        // router queues that drop packets randomly have their own generators, seeded from the
+        // host's seed and the interface's address so that they don't use the host's generator
+        let routers = std::iter::once((public_ip, params.requested_bw_down_bits))
+            .chain(
+                params
+                    .interfaces
+                    .iter()
+                    .map(|x| (x.ip_addr, x.requested_bw_down_bits)),
+            )
+            .map(|(ip, bw_down_bits)| {
+                let seed = params.node_seed ^ u64::from(u32::from(ip));
+                let queue = new_router_queue(&params.router_queue, bw_down_bits, seed);
+                (ip, RefCell::new(Router::new(queue)))
+            })
+            .collect();
+
+        let bw_down_kibps = Cell::new(params.requested_bw_down_bits / (8 * 1024));
+        let bw_up_kibps = Cell::new(params.requested_bw_up_bits / (8 * 1024));
+
         let res = Self {
             info: OnceCell::new(),
             root,
             event_queue: Arc::new(Mutex::new(EventQueue::new())),
             params,
-            router: RefCell::new(Router::new()),
+            routers,
+            burst_loss_bad_links: RefCell::new(HashSet::new()),
+            nat_tables: RefCell::new(HashMap::new()),
+            firewall,
             tracker: RefCell::new(None),
             futex_table: RefCell::new(unsafe { SyncSendPointer::new(cshadow::futextable_new()) }),
             random,
@@ -277,6 +356,9 @@ impl Host {
             event_id_counter,
             packet_id_counter,
             packet_priority_counter,
+            checksum_error_counter,
+            bw_down_kibps,
+            bw_up_kibps,
             determinism_sequence_counter,
             tsc,
             processes: RefCell::new(BTreeMap::new()),
@@ -444,9 +526,93 @@ impl Host {
         crate::core::logger::log_wrapper::c_to_rust_log_level(level).map(|l| l.to_level_filter())
     }
 
+    /// Returns `None` if there is no internet interface with this address.
+    #[track_caller]
+    pub fn upstream_router_borrow_mut(
+        &self,
+        addr: Ipv4Addr,
+    ) -> Option<impl Deref<Target = Router> + DerefMut + '_> {
+        self.routers.get(&addr).map(|x| x.borrow_mut())
+    }
+
+    /// The addresses of the internet interfaces that have an upstream router, in ascending order.
+    pub fn upstream_router_addresses(&self) -> Vec<Ipv4Addr> {
+        let mut addrs: Vec<_> = self.routers.keys().copied().collect();
+        addrs.sort();
+        addrs
+    }
+
+    /// Returns `None` if there is no internet interface with this address.
+    pub fn upstream_router_stats(&self, addr: Ipv4Addr) -> Option<RouterStats> {
+        self.routers.get(&addr).map(|x| x.borrow().stats())
+    }
+
+    /// The address of the interface that packets to `dst` should be sent from when the socket
+    /// isn't bound to a specific address. A multihomed host uses the interface with the lowest
+    /// latency to `dst`, preferring the default interface.
+    pub fn source_ip(&self, dst: Ipv4Addr) -> Ipv4Addr {
+        if dst.is_loopback() {
+            return Ipv4Addr::LOCALHOST;
+        }
+
+        // packets to one of our own addresses are sent from that address
+        if self.routers.contains_key(&dst) {
+            return dst;
+        }
+
+        if self.net_ns.extra_interfaces.is_empty() {
+            return self.default_ip();
+        }
+
+        self.net_ns
+            .internet_interfaces()
+            .filter_map(|(ip, _)| Some((Worker::latency(ip.into(), dst.into())?, ip)))
+            // the first interface with the lowest latency
+            .min_by_key(|(latency, _)| *latency)
+            .map(|(_, ip)| ip)
+            .unwrap_or_else(|| self.default_ip())
+    }
+
     #[track_caller]
-    pub fn upstream_router_borrow_mut(&self) -> impl Deref<Target = Router> + DerefMut + '_ {
-        self.router.borrow_mut()
+    pub fn burst_loss_bad_links_borrow_mut(
+        &self,
+    ) -> impl Deref<Target = HashSet<PathLinkId>> + DerefMut + '_ {
+        self.burst_loss_bad_links.borrow_mut()
+    }
+
+    /// The NAT mappings of the host's interface with address `addr`, which must be behind `nat`.
+    #[track_caller]
+    pub fn nat_table_borrow_mut(
+        &self,
+        addr: Ipv4Addr,
+        nat: &Nat,
+    ) -> impl Deref<Target = NatTable> + DerefMut + '_ {
+        RefMut::map(self.nat_tables.borrow_mut(), |tables| {
+            tables
+                .entry(addr)
+                .or_insert_with(|| NatTable::new(nat, addr, self.nat_pcap(addr)))
+        })
+    }
+
+    /// A packet capture of the NAT's public side for the host's interface with address `addr`, if
+    /// the host captures packets.
+    fn nat_pcap(&self, addr: Ipv4Addr) -> Option<PcapWriter<BufWriter<File>>> {
+        let options = Self::pcap_options(&self.params, &self.data_dir_path)?;
+        let path = options
+            .path
+            .join(format!("{}-{addr}-nat.pcap", self.name()));
+
+        let file = match File::create(&path) {
+            Ok(f) => f,
+            Err(e) => {
+                warn!("Could not create pcap file {path:?}: {e}");
+                return None;
+            }
+        };
+
+        PcapWriter::new(BufWriter::new(file), options.capture_size_bytes)
+            .map_err(|e| warn!("Could not write pcap file {path:?}: {e}"))
+            .ok()
     }
 
     #[track_caller]
@@ -476,14 +642,16 @@ impl Host {
         RefMut::map(futex_table_ref, |r| unsafe { &mut *r.ptr() })
     }
 
+    /// The current upstream bandwidth of the default internet interface.
     #[allow(non_snake_case)]
     pub fn bw_up_kiBps(&self) -> u64 {
-        self.params.requested_bw_up_bits / (8 * 1024)
+        self.bw_up_kibps.get()
     }
 
+    /// The current downstream bandwidth of the default internet interface.
     #[allow(non_snake_case)]
     pub fn bw_down_kiBps(&self) -> u64 {
-        self.params.requested_bw_down_bits / (8 * 1024)
+        self.bw_down_kibps.get()
     }
 
     /// Returns `None` if there is no such interface.
@@ -541,6 +709,22 @@ impl Host {
         res
     }
 
+    /// The host's firewall rules and the number of packets that each has matched.
+    pub fn firewall(&self) -> &Firewall {
+        &self.firewall
+    }
+
+    /// Count a received packet that was discarded since it failed its checksum.
+    pub fn increment_checksum_errors(&self) {
+        self.checksum_error_counter
+            .set(self.checksum_error_counter.get() + 1);
+    }
+
+    /// The number of received packets that were discarded since they failed their checksum.
+    pub fn checksum_errors(&self) -> u64 {
+        self.checksum_error_counter.get()
+    }
+
     pub fn continue_execution_timer(&self) {
         #[cfg(feature = "perf_timers")]
         self.execution_timer.borrow_mut().start();
@@ -576,14 +760,60 @@ impl Host {
         // Start refilling the token buckets for all interfaces.
         let bw_down = self.bw_down_kiBps();
         let bw_up = self.bw_up_kiBps();
+        let burst = self.params.bandwidth_burst_bytes;
+        let refill_interval = self.params.bandwidth_refill_interval;
+        // the loopback interface only uses its token buckets if it has its own bandwidth
+        let bw_loopback = self.params.loopback_bandwidth_bits.map(|x| x / (8 * 1024));
         self.net_ns
             .localhost
             .borrow()
-            .start_refilling_token_buckets(bw_down, bw_up);
-        self.net_ns
-            .internet
-            .borrow()
-            .start_refilling_token_buckets(bw_down, bw_up);
+            .start_refilling_token_buckets(
+                bw_loopback.unwrap_or(bw_down),
+                bw_loopback.unwrap_or(bw_up),
+                burst,
+                refill_interval,
+            );
+        self.net_ns.internet.borrow().start_refilling_token_buckets(
+            bw_down,
+            bw_up,
+            burst,
+            refill_interval,
+        );
+        for (extra, params) in self
+            .net_ns
+            .extra_interfaces
+            .iter()
+            .zip(&self.params.interfaces)
+        {
+            extra.interface.borrow().start_refilling_token_buckets(
+                params.requested_bw_down_bits / (8 * 1024),
+                params.requested_bw_up_bits / (8 * 1024),
+                burst,
+                refill_interval,
+            );
+        }
+
+        // Schedule the changes to the default internet interface's bandwidth. A change that only
+        // sets one direction leaves the other at its previous bandwidth. The schedule doesn't
+        // apply to a multihomed host's additional interfaces, which keep their own bandwidth.
+        let (mut sched_bw_down, mut sched_bw_up) = (bw_down, bw_up);
+        for change in &self.params.bandwidth_schedule {
+            let to_kibps = |x: units::BitsPerSec<units::SiPrefixUpper>| {
+                x.convert(units::SiPrefixUpper::Base).unwrap().value() / (8 * 1024)
+            };
+            sched_bw_down = change.bandwidth_down.map(to_kibps).unwrap_or(sched_bw_down);
+            sched_bw_up = change.bandwidth_up.map(to_kibps).unwrap_or(sched_bw_up);
+
+            let (bw_down, bw_up) = (sched_bw_down, sched_bw_up);
+            let task = TaskRef::new(move |host| {
+                info!("Changing the bandwidth to {bw_down} KiB/s down and {bw_up} KiB/s up");
+                host.net_ns.internet.borrow().set_bandwidth(bw_down, bw_up);
+                host.bw_down_kibps.set(bw_down);
+                host.bw_up_kibps.set(bw_up);
+            });
+            let time = std::time::Duration::from(change.time).try_into().unwrap();
+            self.schedule_task_at_emulated_time(task, EmulatedTime::from_abs_simtime(time));
+        }
 
         // must be done after the default IP exists so tracker_heartbeat works
         if let Some(heartbeat_interval) = self.params.heartbeat_interval {
@@ -614,6 +844,27 @@ impl Host {
 
         assert!(self.processes.borrow().is_empty());
 
+        let checksum_errors = self.checksum_errors();
+        if checksum_errors > 0 {
+            info!(
+                "host '{}' discarded {} corrupted packets that failed their checksum",
+                self.name(),
+                checksum_errors
+            );
+        }
+
+        // the tables are sorted so that they're always logged in the same order
+        let mut nat_tables: Vec<_> = self.nat_tables.borrow_mut().drain().collect();
+        nat_tables.sort_by_key(|(addr, _)| *addr);
+        for (_, table) in nat_tables {
+            table.log_summary(self.name());
+        }
+
+        for addr in self.upstream_router_addresses() {
+            let stats = self.upstream_router_stats(addr).unwrap();
+            Worker::add_router_stats(self.name(), addr, stats);
+        }
+
         self.stop_execution_timer();
         #[cfg(feature = "perf_timers")]
         info!(
@@ -685,12 +936,13 @@ impl Host {
         self.event_queue.lock().unwrap().next_event_time()
     }
 
-    pub fn packets_are_available_to_receive(&self) {
+    /// Packets are available in the upstream router of the interface with address `addr`.
+    pub fn packets_are_available_to_receive(&self, addr: Ipv4Addr) {
         // TODO: ideally we call
         //   `self.net_ns.borrow().as_ref().unwrap().internet.receive_packets(self);`
         // but that causes a double-borrow loop. See `host_socketWantsToSend()`.
         unsafe {
-            let netif_ptr = self.net_ns.internet.borrow().borrow_inner();
+            let netif_ptr = self.net_ns.interface_borrow(addr).unwrap().borrow_inner();
             cshadow::networkinterface_receivePackets(netif_ptr, self)
         };
     }
@@ -815,6 +1067,7 @@ mod export {
     };
 
     use crate::{
+        core::support::configuration::{FirewallAction, FirewallDirection},
         cshadow::{CEmulatedTime, CSimulationTime},
         host::process::ProcessRefCell,
         network::router::Router,
@@ -888,12 +1141,92 @@ mod export {
         u32::from(ip).to_be()
     }
 
+    /// The source address (in network byte order) to use for packets to `dst_ip` (in network byte
+    /// order) from a socket that is bound to `INADDR_ANY`.
+    #[no_mangle]
+    pub unsafe extern "C" fn host_getSourceIP(hostrc: *const Host, dst_ip: in_addr_t) -> in_addr_t {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let ip = hostrc.source_ip(u32::from_be(dst_ip).into());
+        u32::from(ip).to_be()
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn host_getNextPacketPriority(hostrc: *const Host) -> f64 {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
         hostrc.get_next_packet_priority()
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn host_incrementChecksumErrors(hostrc: *const Host) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.increment_checksum_errors()
+    }
+
+    /// Finds the first firewall rule that matches the packet without counting it as a rule hit.
+    /// Returns `false` if no rule matches.
+    #[no_mangle]
+    pub unsafe extern "C" fn host_firewallMatch(
+        hostrc: *const Host,
+        packet: *const cshadow::Packet,
+        direction: FirewallDirection,
+        index: *mut usize,
+    ) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let index = unsafe { index.as_mut().unwrap() };
+        match hostrc.firewall().matching_rule(packet, direction) {
+            Some(x) => {
+                *index = x;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Counts a packet as a hit for the firewall rule at `index`.
+    #[no_mangle]
+    pub unsafe extern "C" fn host_firewallCountHit(hostrc: *const Host, index: usize) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.firewall().count_hit(index)
+    }
+
+    /// Returns the firewall action for the packet and counts it as a hit for the matching rule.
+    #[no_mangle]
+    pub unsafe extern "C" fn host_firewallFilter(
+        hostrc: *const Host,
+        packet: *const cshadow::Packet,
+        direction: FirewallDirection,
+    ) -> FirewallAction {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.firewall().filter(packet, direction)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn host_firewallNumRules(hostrc: *const Host) -> usize {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.firewall().rules().len()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn host_firewallRuleAction(
+        hostrc: *const Host,
+        index: usize,
+    ) -> FirewallAction {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.firewall().rules()[index].action
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn host_firewallRuleHits(hostrc: *const Host, index: usize) -> u64 {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.firewall().hits(index)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn host_getChecksumErrors(hostrc: *const Host) -> u64 {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.checksum_errors()
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn host_autotuneReceiveBuffer(hostrc: *const Host) -> bool {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
@@ -906,6 +1239,12 @@ mod export {
         hostrc.params.autotune_send_buf
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn host_useTCPECN(hostrc: *const Host) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.use_tcp_ecn
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn host_getConfiguredRecvBufSize(hostrc: *const Host) -> u64 {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
@@ -918,10 +1257,45 @@ mod export {
         hostrc.params.init_sock_send_buf_size
     }
 
+    /// The upstream router of the internet interface with address `ip` (in network byte order).
     #[no_mangle]
-    pub unsafe extern "C" fn host_getUpstreamRouter(hostrc: *const Host) -> *mut Router {
+    pub unsafe extern "C" fn host_getUpstreamRouter(
+        hostrc: *const Host,
+        ip: in_addr_t,
+    ) -> *mut Router {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
-        &mut *hostrc.upstream_router_borrow_mut()
+        let ip = u32::from_be(ip).into();
+        &mut *hostrc.upstream_router_borrow_mut(ip).unwrap()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn host_numUpstreamRouters(hostrc: *const Host) -> usize {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.routers.len()
+    }
+
+    /// The address (in network byte order) of the interface of the upstream router at `index`,
+    /// where the routers are ordered by their address.
+    #[no_mangle]
+    pub unsafe extern "C" fn host_getUpstreamRouterIP(
+        hostrc: *const Host,
+        index: usize,
+    ) -> in_addr_t {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let ip = hostrc.upstream_router_addresses()[index];
+        u32::from(ip).to_be()
+    }
+
+    /// The statistics of the upstream router at `index`, where the routers are ordered by their
+    /// address.
+    #[no_mangle]
+    pub unsafe extern "C" fn host_getUpstreamRouterStats(
+        hostrc: *const Host,
+        index: usize,
+    ) -> RouterStats {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let ip = hostrc.upstream_router_addresses()[index];
+        hostrc.upstream_router_stats(ip).unwrap()
     }
 
     #[no_mangle]
@@ -1256,6 +1630,18 @@ mod export {
         host.params.heartbeat_log_info
     }
 
+    #[no_mangle]
+    pub extern "C" fn host_paramsBandwidthBurstBytes(host: *const Host) -> u64 {
+        let host = unsafe { host.as_ref().unwrap() };
+        host.params.bandwidth_burst_bytes
+    }
+
+    #[no_mangle]
+    pub extern "C" fn host_paramsBandwidthRefillInterval(host: *const Host) -> CSimulationTime {
+        let host = unsafe { host.as_ref().unwrap() };
+        SimulationTime::to_c_simtime(Some(host.params.bandwidth_refill_interval))
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn host_socketWantsToSend(
         hostrc: *const Host,
@@ -44,6 +44,16 @@ struct _NetworkInterface {
     /* Transports wanting to send data out. */
     RrSocketQueue rrQueue;
     FifoSocketQueue fifoQueue;
+    PrioSocketQueue prioQueue;
+    SfqSocketQueue sfqQueue;
+
+    /* Sockets that have packets to send but are waiting for their SO_MAX_PACING_RATE to allow
+     * it. Maps each socket's canonical handle to a CompatSocket reference as a tagged pointer. */
+    GHashTable* pacedSockets;
+
+    /* Packets generated by the interface itself rather than by a socket, such as ICMP echo
+     * replies and errors. These are sent before any packets from sockets. */
+    GQueue* controlPackets;
 
     /* the outgoing token bucket implements traffic shaping, i.e.,
      * packets are delayed until they conform with outgoing rate limits.*/
@@ -57,6 +67,11 @@ struct _NetworkInterface {
     /* If we have scheduled a receive task but it has not yet executed. */
     bool tb_receive_refill_pending;
 
+    /* Packets sent to the interface's own address arrive back on the interface after this delay.
+     * If `rate_limit_local` is set, they also consume the interface's send tokens. */
+    CSimulationTime local_delay;
+    bool rate_limit_local;
+
     /* To support capturing incoming and outgoing packets */
     PcapWriter_BufWriter_File* pcap;
 
@@ -77,57 +92,74 @@ static void _compatsocket_unrefTaggedVoid(void* taggedSocketPtr) {
     compatsocket_unref(&socket);
 }
 
-static TokenBucket* _networkinterface_create_tb(uint64_t bwKiBps) {
-    uint64_t refill_interval_nanos = SIMTIME_ONE_MILLISECOND;
-    uint64_t refill_size = bwKiBps * 1024 / 1000;
-
-    // The `CONFIG_MTU` part represents a "burst allowance" which is common in
-    // token buckets. Only the `capacity` of the bucket is increased by
-    // `CONFIG_MTU`, not the `refill_size`. Therefore, the long term rate limit
-    // enforced by the token bucket (configured by `refill_size`) is not
+static TokenBucket* _networkinterface_create_tb(uint64_t bwKiBps, uint64_t burstBytes,
+                                                uint64_t refillIntervalNanos) {
+    // The `burstBytes` part represents a "burst allowance" which is common in
+    // token buckets. Only the capacity of the bucket is increased by
+    // `burstBytes`, not the refill size. Therefore, the long term rate limit
+    // enforced by the token bucket (configured by the refill size) is not
     // affected much.
     //
     // What the burst allowance ensures is that we don't lose tokens that are
     // unused because we don't fragment packets. If we set the capacity of the
-    // bucket to exactly the refill size (i.e., without the `CONFIG_MTU` burst
-    // allowance) and there are only 1499 tokens left in this sending round, a
-    // full packet would not fit. The next time the bucket refills, it adds
-    // `refill_size` tokens but in doing so 1499 tokens would fall over the top
-    // of the bucket; these tokens would represent wasted bandwidth, and could
-    // potentially accumulate in every refill interval leading to a
+    // bucket to exactly the refill size (i.e., without a burst allowance of at
+    // least `CONFIG_MTU`) and there are only 1499 tokens left in this sending
+    // round, a full packet would not fit. The next time the bucket refills, it
+    // adds refill size tokens but in doing so 1499 tokens would fall over the
+    // top of the bucket; these tokens would represent wasted bandwidth, and
+    // could potentially accumulate in every refill interval leading to a
     // significantly lower achievable bandwidth.
     //
-    // A downside of the `CONFIG_MTU` burst allowance is that the sending rate
-    // could possibly become "bursty" with a behavior such as:
-    // - interval 1: send `refill_size` + `CONFIG_MTU` bytes, sending over the
+    // A downside of the burst allowance is that the sending rate could possibly
+    // become "bursty" with a behavior such as:
+    // - interval 1: send refill size + `CONFIG_MTU` bytes, sending over the
     //   allowance by 1500 bytes
-    // - refill: `refill_size` token gets added to the bucket
-    // - interval 2: send `refill_size` - `CONFIG_MTU` bytes, sending under the
+    // - refill: refill size token gets added to the bucket
+    // - interval 2: send refill size - `CONFIG_MTU` bytes, sending under the
     //   allowance by 1500 bytes
-    // - refill: `refill_size` token gets added to the bucket
-    // - interval 3: send `refill_size` + `CONFIG_MTU` bytes, sending over the
+    // - refill: refill size token gets added to the bucket
+    // - interval 3: send refill size + `CONFIG_MTU` bytes, sending over the
     //   allowance by 1500 bytes
     // - repeat
     //
     // So it could become less smooth and more "bursty" even though the long
     // term average is maintained. But I don't think this would happen much in
-    // practice, and we are batching sends for performance reasons.
-    uint64_t capacity = refill_size + CONFIG_MTU;
+    // practice with the default burst allowance of `CONFIG_MTU`, and we are
+    // batching sends for performance reasons. Larger burst allowances can be
+    // configured to model links that are more bursty.
+    debug("creating token bucket with rate=%" G_GUINT64_FORMAT " KiB/s burst=%" G_GUINT64_FORMAT
+          " bytes refill_interval_nanos=%" G_GUINT64_FORMAT,
+          bwKiBps, burstBytes, refillIntervalNanos);
+
+    return tokenbucket_newWithRate(bwKiBps * 1024, burstBytes, refillIntervalNanos);
+}
 
-    debug("creating token bucket with capacity=%" G_GUINT64_FORMAT " refill_size=%" G_GUINT64_FORMAT
-          " refill_interval_nanos=%" G_GUINT64_FORMAT,
-          capacity, refill_size, refill_interval_nanos);
+static void _networkinterface_update_tb(TokenBucket* tb, uint64_t bwKiBps) {
+    debug("updating token bucket to rate=%" G_GUINT64_FORMAT " KiB/s", bwKiBps);
 
-    return tokenbucket_new(capacity, refill_size, refill_interval_nanos);
+    /* the burst size and refill interval don't change */
+    tokenbucket_setTokensPerSec(tb, bwKiBps * 1024);
 }
 
 void networkinterface_startRefillingTokenBuckets(NetworkInterface* interface, uint64_t bwDownKiBps,
-                                                 uint64_t bwUpKiBps) {
+                                                 uint64_t bwUpKiBps, uint64_t burstBytes,
+                                                 uint64_t refillIntervalNanos) {
     MAGIC_ASSERT(interface);
     // Set size and refill rates for token buckets.
     // This needs to be called when host is booting, i.e. when the worker exists.
-    interface->tb_send = _networkinterface_create_tb(bwUpKiBps);
-    interface->tb_receive = _networkinterface_create_tb(bwDownKiBps);
+    interface->tb_send = _networkinterface_create_tb(bwUpKiBps, burstBytes, refillIntervalNanos);
+    interface->tb_receive =
+        _networkinterface_create_tb(bwDownKiBps, burstBytes, refillIntervalNanos);
+}
+
+void networkinterface_setBandwidth(NetworkInterface* interface, uint64_t bwDownKiBps,
+                                   uint64_t bwUpKiBps) {
+    MAGIC_ASSERT(interface);
+    utility_debugAssert(interface->tb_send != NULL && interface->tb_receive != NULL);
+
+    /* the refill interval doesn't change, so any pending refill tasks are still on time */
+    _networkinterface_update_tb(interface->tb_send, bwUpKiBps);
+    _networkinterface_update_tb(interface->tb_receive, bwDownKiBps);
 }
 
 /* The address and ports must be in network byte order. */
@@ -241,22 +273,10 @@ static CompatSocket _boundsockets_lookup(GHashTable* table, gchar* key) {
     return compatsocket_fromTagged((uintptr_t)ptr);
 }
 
-static void _networkinterface_process_packet_in(const Host* host, NetworkInterface* interface,
-                                                Packet* packet) {
-    MAGIC_ASSERT(interface);
-
-    /* get the next packet */
-    utility_debugAssert(packet);
-
-    /* successfully received */
-    packet_addDeliveryStatus(packet, PDS_RCV_INTERFACE_RECEIVED);
-
-    /* hand it off to the correct socket layer */
-    ProtocolType ptype = packet_getProtocol(packet);
-    in_port_t bindPort = packet_getDestinationPort(packet);
-    in_addr_t peerIP = packet_getSourceIP(packet);
-    in_port_t peerPort = packet_getSourcePort(packet);
-
+/* The address and ports must be in network byte order. */
+static CompatSocket _networkinterface_lookupSocket(NetworkInterface* interface, ProtocolType ptype,
+                                                   in_port_t bindPort, in_addr_t peerIP,
+                                                   in_port_t peerPort) {
     /* first check for a socket with the specific association */
     gchar* key = _networkinterface_getAssociationKey(interface, ptype, bindPort, peerIP, peerPort);
     trace("looking for socket associated with specific key %s", key);
@@ -272,17 +292,139 @@ static void _networkinterface_process_packet_in(const Host* host, NetworkInterfa
         g_free(key);
     }
 
+    return socket;
+}
+
+/* Queue a packet generated by the interface itself, and send it when possible. Takes ownership
+ * of the packet reference. */
+static void _networkinterface_sendControlPacket(NetworkInterface* interface, const Host* host,
+                                                Packet* packet) {
+    packet_addDeliveryStatus(packet, PDS_SND_CREATED);
+    g_queue_push_tail(interface->controlPackets, packet);
+    _networkinterface_sendPackets(interface, host);
+}
+
+/* ICMP errors are delivered to the socket that sent the packet that caused the error. Only UDP
+ * sockets handle them; other sockets learn about failures in their own ways. */
+static CompatSocket _networkinterface_lookupICMPErrorSocket(NetworkInterface* interface,
+                                                            const PacketICMPHeader* header) {
+    CompatSocket socket = _networkinterface_lookupSocket(
+        interface, header->originalProtocol, header->originalSourcePort,
+        header->originalDestinationIP, header->originalDestinationPort);
+
+    if (socket.type != CST_LEGACY_SOCKET ||
+        legacyfile_getType((LegacyFile*)socket.object.as_legacy_socket) != DT_UDPSOCKET) {
+        socket.type = CST_NONE;
+    }
+
+    return socket;
+}
+
+/* The packet that tells the sender of a packet rejected by the firewall that it was refused: a
+ * TCP reset for TCP packets, and an ICMP port unreachable error for everything else. Returns NULL
+ * if the rejected packet is itself a reset or an ICMP error, which are dropped silently. */
+static Packet* _networkinterface_newRejection(const Host* host, const Packet* rejected) {
+    ProtocolType ptype = packet_getProtocol(rejected);
+
+    if (ptype == PTCP) {
+        const PacketTCPHeader* header = packet_getTCPHeader(rejected);
+        if (header->flags & PTCP_RST) {
+            return NULL;
+        }
+
+        Packet* rst = packet_new(host);
+        packet_setTCP(rst, PTCP_RST, header->destinationIP, header->destinationPort,
+                      header->sourceIP, header->sourcePort, header->acknowledgment);
+        return rst;
+    }
+
+    if (ptype == PICMP && packet_getICMPHeader(rejected)->type == PICMP_DEST_UNREACH) {
+        return NULL;
+    }
+
+    if (ptype != PUDP && ptype != PICMP) {
+        return NULL;
+    }
+
+    Packet* error = packet_new(host);
+    packet_setICMPError(error, PICMP_DEST_UNREACH, PICMP_CODE_PORT_UNREACH,
+                        packet_getDestinationIP(rejected), rejected);
+    return error;
+}
+
+/* Packets that arrived from the upstream router are checked against the host's firewall rules;
+ * packets that the host sent to itself are not. */
+static void _networkinterface_process_packet_in(const Host* host, NetworkInterface* interface,
+                                                Packet* packet, bool use_firewall) {
+    MAGIC_ASSERT(interface);
+
+    /* get the next packet */
+    utility_debugAssert(packet);
+
+    /* successfully received */
+    packet_addDeliveryStatus(packet, PDS_RCV_INTERFACE_RECEIVED);
+
+    /* hand it off to the correct socket layer */
+    ProtocolType ptype = packet_getProtocol(packet);
+    const PacketICMPHeader* icmpHeader = ptype == PICMP ? packet_getICMPHeader(packet) : NULL;
+
+    CompatSocket socket = {0};
+    socket.type = CST_NONE;
+
+    if (icmpHeader != NULL && icmpHeader->type == PICMP_DEST_UNREACH) {
+        socket = _networkinterface_lookupICMPErrorSocket(interface, icmpHeader);
+    } else if (icmpHeader == NULL || icmpHeader->type != PICMP_ECHO_REQUEST) {
+        /* echo requests are answered by the interface rather than by a socket */
+        socket = _networkinterface_lookupSocket(interface, ptype,
+                                                packet_getDestinationPort(packet),
+                                                packet_getSourceIP(packet),
+                                                packet_getSourcePort(packet));
+    }
+
     /* record the packet before we process it, otherwise we may send more packets before we
        record this one and the order will be incorrect */
     if (interface->pcap) {
         _networkinterface_capturePacket(interface, packet);
     }
 
-    /* if the socket closed, just drop the packet */
-    if (socket.type != CST_NONE) {
+    if (use_firewall) {
+        FirewallAction action = host_firewallFilter(host, packet, FIREWALL_DIRECTION_IN);
+        if (action != FIREWALL_ACTION_ALLOW) {
+            packet_addDeliveryStatus(packet, PDS_RCV_INTERFACE_DROPPED);
+
+            if (action == FIREWALL_ACTION_REJECT) {
+                Packet* rejection = _networkinterface_newRejection(host, packet);
+                if (rejection != NULL) {
+                    _networkinterface_sendControlPacket(interface, host, rejection);
+                }
+            }
+            return;
+        }
+    }
+
+    if (packet_getDeliveryStatus(packet) & PDS_INET_CORRUPTED) {
+        /* the packet was corrupted in the network, so it fails the TCP/UDP checksum and is
+         * discarded before it reaches the socket */
+        packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
+        host_incrementChecksumErrors(host);
+    } else if (icmpHeader != NULL && icmpHeader->type == PICMP_ECHO_REQUEST) {
+        Packet* reply = packet_new(host);
+        packet_setICMPEchoReply(reply, packet);
+        packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_PROCESSED);
+        _networkinterface_sendControlPacket(interface, host, reply);
+    } else if (socket.type != CST_NONE) {
         compatsocket_pushInPacket(&socket, host, packet);
     } else {
+        /* the socket closed, so just drop the packet */
         packet_addDeliveryStatus(packet, PDS_RCV_INTERFACE_DROPPED);
+
+        /* tell the sender that nobody is listening on the UDP port */
+        if (ptype == PUDP) {
+            Packet* error = packet_new(host);
+            packet_setICMPError(error, PICMP_DEST_UNREACH, PICMP_CODE_PORT_UNREACH,
+                                packet_getDestinationIP(packet), packet);
+            _networkinterface_sendControlPacket(interface, host, error);
+        }
     }
 
     /* count our bandwidth usage by interface, and by socket if possible */
@@ -294,7 +436,7 @@ static void _networkinterface_process_packet_in(const Host* host, NetworkInterfa
 
 static void _networkinterface_local_packet_arrived_CB(const Host* host, gpointer voidInterface,
                                                       gpointer voidPacket) {
-    _networkinterface_process_packet_in(host, voidInterface, voidPacket);
+    _networkinterface_process_packet_in(host, voidInterface, voidPacket, false);
 }
 
 static uint64_t _networkinterface_packet_tokens(const Packet* packet) {
@@ -323,7 +465,10 @@ void networkinterface_receivePackets(NetworkInterface* interface, const Host* ho
     gboolean is_bootstrapping = worker_isBootstrapActive();
     const Packet* peeked_packet = NULL;
 
-    while ((peeked_packet = router_peek(host_getUpstreamRouter(host)))) {
+    /* each of the host's internet interfaces has its own upstream router */
+    in_addr_t ip = address_toNetworkIP(interface->address);
+
+    while ((peeked_packet = router_peek(host_getUpstreamRouter(host, ip)))) {
         // Check if our rate limits allow us to receive the packet.
         if (!is_bootstrapping) {
             uint64_t required = _networkinterface_packet_tokens(peeked_packet);
@@ -346,17 +491,85 @@ void networkinterface_receivePackets(NetworkInterface* interface, const Host* ho
         }
 
         /* we are now the owner of the packet reference from the router */
-        Packet* packet = router_dequeue(host_getUpstreamRouter(host));
-        // We already peeked it, so it better be here when we pop it.
-        utility_debugAssert(packet);
+        Packet* packet = router_dequeue(host_getUpstreamRouter(host, ip));
+        // The router queue may drop packets when dequeuing, including the one we peeked.
+        if (packet == NULL) {
+            continue;
+        }
 
-        _networkinterface_process_packet_in(host, interface, packet);
+        _networkinterface_process_packet_in(host, interface, packet, true);
 
         /* release reference from router */
         packet_unref(packet);
     }
 }
 
+static void _networkinterface_continue_pacing_CB(const Host* host, gpointer voidInterface,
+                                                 gpointer voidHandle) {
+    NetworkInterface* interface = voidInterface;
+    MAGIC_ASSERT(interface);
+
+    gpointer taggedSocket = g_hash_table_lookup(interface->pacedSockets, voidHandle);
+    if (taggedSocket == NULL) {
+        return;
+    }
+
+    /* we are now the owner of the socket reference from the table */
+    g_hash_table_steal(interface->pacedSockets, voidHandle);
+    CompatSocket socket = compatsocket_fromTagged((uintptr_t)taggedSocket);
+
+    /* the socket may have closed or been flushed while it was waiting */
+    if (compatsocket_peekNextOutPacket(&socket)) {
+        networkinterface_wantsSend(interface, host, &socket);
+    }
+
+    compatsocket_unref(&socket);
+}
+
+/* Charges the packet against its socket's pacing rate, if it has one. */
+static void _networkinterface_recordPacedSend(const CompatSocket* socket, const Packet* packet) {
+    if (worker_isBootstrapActive()) {
+        return;
+    }
+
+    compatsocket_recordPacedSend(
+        socket, packet_getTotalSize(packet), worker_getCurrentEmulatedTime());
+}
+
+/* Returns true if the socket may not send its next packet yet because of its pacing rate, in
+ * which case the socket is held aside (with its own reference) until it may. */
+static bool _networkinterface_deferPacedSocket(NetworkInterface* interface, const Host* host,
+                                               const CompatSocket* socket) {
+    gpointer handle = (gpointer)compatsocket_getCanonicalHandle(socket);
+
+    if (g_hash_table_contains(interface->pacedSockets, handle)) {
+        return true;
+    }
+
+    /* pacing is disabled during bootstrapping, like the interface's rate limits */
+    if (worker_isBootstrapActive()) {
+        return false;
+    }
+
+    CEmulatedTime now = worker_getCurrentEmulatedTime();
+    CEmulatedTime sendTime = compatsocket_getNextPacedSendTime(socket);
+    if (sendTime <= now) {
+        return false;
+    }
+
+    CompatSocket newSocketRef = compatsocket_refAs(socket);
+    g_hash_table_insert(
+        interface->pacedSockets, handle, (void*)compatsocket_toTagged(&newSocketRef));
+
+    /* call back when the socket may send again */
+    TaskRef* pacingTask = taskref_new_bound(
+        host_getID(host), _networkinterface_continue_pacing_CB, interface, handle, NULL, NULL);
+    host_scheduleTaskWithDelay(host, pacingTask, (CSimulationTime)(sendTime - now));
+    taskref_drop(pacingTask);
+
+    return true;
+}
+
 /* round robin queuing discipline ($ man tc)*/
 static Packet* _networkinterface_selectRoundRobin(NetworkInterface* interface, const Host* host,
                                                   CompatSocket* socketOut) {
@@ -377,13 +590,16 @@ static Packet* _networkinterface_selectRoundRobin(NetworkInterface* interface, c
 
         if (packet) {
             compatsocket_updatePacketHeader(&socket, host, packet);
+            _networkinterface_recordPacedSend(&socket, packet);
         }
 
-        if (compatsocket_peekNextOutPacket(&socket)) {
+        if (compatsocket_peekNextOutPacket(&socket) &&
+            !_networkinterface_deferPacedSocket(interface, host, &socket)) {
             /* socket has more packets, and is still reffed from before */
             rrsocketqueue_push(&interface->rrQueue, &socket);
         } else {
-            /* socket has no more packets, unref it from the sendable queue */
+            /* socket has no more packets or must wait for its pacing rate, unref it from the
+             * sendable queue */
             compatsocket_unref(&socket);
         }
     }
@@ -413,13 +629,91 @@ static Packet* _networkinterface_selectFirstInFirstOut(NetworkInterface* interfa
 
         if (packet) {
             compatsocket_updatePacketHeader(&socket, host, packet);
+            _networkinterface_recordPacedSend(&socket, packet);
         }
 
-        if (compatsocket_peekNextOutPacket(&socket)) {
+        if (compatsocket_peekNextOutPacket(&socket) &&
+            !_networkinterface_deferPacedSocket(interface, host, &socket)) {
             /* socket has more packets, and is still reffed from before */
             fifosocketqueue_push(&interface->fifoQueue, &socket);
         } else {
-            /* socket has no more packets, unref it from the sendable queue */
+            /* socket has no more packets or must wait for its pacing rate, unref it from the
+             * sendable queue */
+            compatsocket_unref(&socket);
+        }
+    }
+
+    return packet;
+}
+
+/* strict-priority queuing discipline, like pfifo_fast ($ man tc-pfifo_fast) */
+static Packet* _networkinterface_selectPriority(NetworkInterface* interface, const Host* host,
+                                                CompatSocket* socketOut) {
+    Packet* packet = NULL;
+
+    while (!packet && !priosocketqueue_isEmpty(&interface->prioQueue)) {
+        /* get the next packet from the next socket in the highest non-empty band */
+        CompatSocket socket = {0};
+        bool found = priosocketqueue_pop(&interface->prioQueue, &socket);
+
+        if (!found) {
+            continue;
+        }
+
+        packet = compatsocket_pullOutPacket(&socket, host);
+
+        *socketOut = socket;
+
+        if (packet) {
+            compatsocket_updatePacketHeader(&socket, host, packet);
+            _networkinterface_recordPacedSend(&socket, packet);
+        }
+
+        if (compatsocket_peekNextOutPacket(&socket) &&
+            !_networkinterface_deferPacedSocket(interface, host, &socket)) {
+            /* socket has more packets, and is still reffed from before */
+            priosocketqueue_push(&interface->prioQueue, &socket);
+        } else {
+            /* socket has no more packets or must wait for its pacing rate, unref it from the
+             * sendable queue */
+            compatsocket_unref(&socket);
+        }
+    }
+
+    return packet;
+}
+
+/* stochastic fair queuing discipline ($ man tc-sfq) */
+static Packet* _networkinterface_selectStochasticFair(NetworkInterface* interface,
+                                                      const Host* host, CompatSocket* socketOut) {
+    Packet* packet = NULL;
+
+    while (!packet && !sfqsocketqueue_isEmpty(&interface->sfqQueue)) {
+        /* do round robin over the flow buckets to get the next packet */
+        CompatSocket socket = {0};
+        bool found = sfqsocketqueue_pop(&interface->sfqQueue, &socket);
+
+        if (!found) {
+            continue;
+        }
+
+        packet = compatsocket_pullOutPacket(&socket, host);
+
+        *socketOut = socket;
+
+        if (packet) {
+            compatsocket_updatePacketHeader(&socket, host, packet);
+            _networkinterface_recordPacedSend(&socket, packet);
+        }
+
+        if (compatsocket_peekNextOutPacket(&socket) &&
+            !_networkinterface_deferPacedSocket(interface, host, &socket)) {
+            /* socket has more packets, and is still reffed from before. it's hashed again since
+             * its next packet may belong to a different flow. */
+            sfqsocketqueue_push(&interface->sfqQueue, &socket);
+        } else {
+            /* socket has no more packets or must wait for its pacing rate, unref it from the
+             * sendable queue */
             compatsocket_unref(&socket);
         }
     }
@@ -430,10 +724,22 @@ static Packet* _networkinterface_selectFirstInFirstOut(NetworkInterface* interfa
 static Packet* _networkinterface_pop_next_packet_out(NetworkInterface* interface, const Host* host,
                                                      CompatSocket* socketOut) {
     MAGIC_ASSERT(interface);
+
+    /* packets generated by the interface don't belong to a socket */
+    if (!g_queue_is_empty(interface->controlPackets)) {
+        return g_queue_pop_head(interface->controlPackets);
+    }
+
     switch (interface->qdisc) {
         case Q_DISC_MODE_ROUND_ROBIN: {
             return _networkinterface_selectRoundRobin(interface, host, socketOut);
         }
+        case Q_DISC_MODE_PRIORITY: {
+            return _networkinterface_selectPriority(interface, host, socketOut);
+        }
+        case Q_DISC_MODE_SFQ: {
+            return _networkinterface_selectStochasticFair(interface, host, socketOut);
+        }
         case Q_DISC_MODE_FIFO:
         default: {
             return _networkinterface_selectFirstInFirstOut(interface, host, socketOut);
@@ -444,6 +750,10 @@ static Packet* _networkinterface_pop_next_packet_out(NetworkInterface* interface
 static const Packet* _networkinterface_peek_next_packet_out(NetworkInterface* interface) {
     MAGIC_ASSERT(interface);
 
+    if (!g_queue_is_empty(interface->controlPackets)) {
+        return g_queue_peek_head(interface->controlPackets);
+    }
+
     CompatSocket socket = {0};
     bool found = false;
 
@@ -452,6 +762,14 @@ static const Packet* _networkinterface_peek_next_packet_out(NetworkInterface* in
             found = rrsocketqueue_peek(&interface->rrQueue, &socket);
             break;
         }
+        case Q_DISC_MODE_PRIORITY: {
+            found = priosocketqueue_peek(&interface->prioQueue, &socket);
+            break;
+        }
+        case Q_DISC_MODE_SFQ: {
+            found = sfqsocketqueue_peek(&interface->sfqQueue, &socket);
+            break;
+        }
         case Q_DISC_MODE_FIFO:
         default: {
             found = fifosocketqueue_peek(&interface->fifoQueue, &socket);
@@ -482,12 +800,26 @@ static void _networkinterface_sendPackets(NetworkInterface* interface, const Hos
 
     while ((peeked_packet = _networkinterface_peek_next_packet_out(interface))) {
         // Local packets arrive on our own interface, they do not go through the
-        // upstream router and do not consume bandwidth.
+        // upstream router and do not consume bandwidth unless the interface limits them.
         bool is_local =
             address_toNetworkIP(interface->address) == packet_getDestinationIP(peeked_packet);
+        bool is_rate_limited = !is_local || interface->rate_limit_local;
+
+        // Packets that leave the host are checked against the host's firewall rules. Packets
+        // that the firewall stops never reach the wire, so they don't consume bandwidth. A
+        // rate-limited packet is peeked again later, so the rule's hit is counted once it's popped.
+        bool use_firewall = interface->uses_router && !is_local;
+        size_t firewall_rule = 0;
+        bool is_firewall_match =
+            use_firewall &&
+            host_firewallMatch(src, peeked_packet, FIREWALL_DIRECTION_OUT, &firewall_rule);
+        FirewallAction firewall_action = is_firewall_match
+                                             ? host_firewallRuleAction(src, firewall_rule)
+                                             : FIREWALL_ACTION_ALLOW;
+        bool is_blocked = firewall_action != FIREWALL_ACTION_ALLOW;
 
         // Check if our rate limits allows us to send the packet.
-        if (!is_bootstrapping && !is_local) {
+        if (!is_bootstrapping && is_rate_limited && !is_blocked) {
             uint64_t required = _networkinterface_packet_tokens(peeked_packet);
             uint64_t remaining = 0, next_refill_nanos = 0;
             if (!tokenbucket_consume(
@@ -512,6 +844,30 @@ static void _networkinterface_sendPackets(NetworkInterface* interface, const Hos
         // We already peeked it, so it better be here when we pop it.
         utility_debugAssert(packet);
 
+        if (is_firewall_match) {
+            host_firewallCountHit(src, firewall_rule);
+        }
+
+        if (is_blocked) {
+            packet_addDeliveryStatus(packet, PDS_SND_INTERFACE_DROPPED);
+
+            if (firewall_action == FIREWALL_ACTION_REJECT) {
+                // The rejection arrives directly back on our interface.
+                Packet* rejection = _networkinterface_newRejection(src, packet);
+                if (rejection != NULL) {
+                    packet_addDeliveryStatus(rejection, PDS_SND_CREATED);
+                    TaskRef* rejectionTask = taskref_new_bound(
+                        host_getID(src), _networkinterface_local_packet_arrived_CB, interface,
+                        rejection, NULL, packet_unrefTaskFreeFunc);
+                    host_scheduleTaskWithDelay(src, rejectionTask, 1);
+                    taskref_drop(rejectionTask);
+                }
+            }
+
+            packet_unref(packet);
+            continue;
+        }
+
         packet_addDeliveryStatus(packet, PDS_SND_INTERFACE_SENT);
 
         /* record the packet early before we do anything else */
@@ -526,7 +882,7 @@ static void _networkinterface_sendPackets(NetworkInterface* interface, const Hos
             TaskRef* packetTask =
                 taskref_new_bound(host_getID(src), _networkinterface_local_packet_arrived_CB,
                                   interface, packet, NULL, packet_unrefTaskFreeFunc);
-            host_scheduleTaskWithDelay(src, packetTask, 1);
+            host_scheduleTaskWithDelay(src, packetTask, interface->local_delay);
             taskref_drop(packetTask);
         } else {
             /* send to destination over the virtual internet with appropriate delays.
@@ -555,6 +911,11 @@ void networkinterface_wantsSend(NetworkInterface* interface, const Host* host,
         return;
     }
 
+    /* a paced socket is tracked again once its pacing rate allows it to send */
+    if (_networkinterface_deferPacedSocket(interface, host, socket)) {
+        return;
+    }
+
     /* track the new socket for sending if not already tracking */
     switch (interface->qdisc) {
         case Q_DISC_MODE_ROUND_ROBIN: {
@@ -564,6 +925,20 @@ void networkinterface_wantsSend(NetworkInterface* interface, const Host* host,
             }
             break;
         }
+        case Q_DISC_MODE_PRIORITY: {
+            if (!priosocketqueue_find(&interface->prioQueue, socket)) {
+                CompatSocket newSocketRef = compatsocket_refAs(socket);
+                priosocketqueue_push(&interface->prioQueue, &newSocketRef);
+            }
+            break;
+        }
+        case Q_DISC_MODE_SFQ: {
+            if (!sfqsocketqueue_find(&interface->sfqQueue, socket)) {
+                CompatSocket newSocketRef = compatsocket_refAs(socket);
+                sfqsocketqueue_push(&interface->sfqQueue, &newSocketRef);
+            }
+            break;
+        }
         case Q_DISC_MODE_FIFO:
         default: {
             if (!fifosocketqueue_find(&interface->fifoQueue, socket)) {
@@ -578,8 +953,19 @@ void networkinterface_wantsSend(NetworkInterface* interface, const Host* host,
     _networkinterface_sendPackets(interface, host);
 }
 
+static const gchar* _networkinterface_qdiscName(QDiscMode qdisc) {
+    switch (qdisc) {
+        case Q_DISC_MODE_FIFO: return "fifo";
+        case Q_DISC_MODE_ROUND_ROBIN: return "rr";
+        case Q_DISC_MODE_PRIORITY: return "prio";
+        case Q_DISC_MODE_SFQ: return "sfq";
+    }
+    return "unknown";
+}
+
 NetworkInterface* networkinterface_new(Address* address, const gchar* pcapDir,
-                                       guint32 pcapCaptureSize, QDiscMode qdisc, bool uses_router) {
+                                       guint32 pcapCaptureSize, QDiscMode qdisc, bool uses_router,
+                                       CSimulationTime localDelay, bool rateLimitLocal) {
     NetworkInterface* interface = g_new0(NetworkInterface, 1);
     MAGIC_INIT(interface);
 
@@ -593,6 +979,12 @@ NetworkInterface* networkinterface_new(Address* address, const gchar* pcapDir,
     /* sockets tell us when they want to start sending */
     rrsocketqueue_init(&interface->rrQueue);
     fifosocketqueue_init(&interface->fifoQueue);
+    priosocketqueue_init(&interface->prioQueue);
+    sfqsocketqueue_init(&interface->sfqQueue);
+    interface->pacedSockets =
+        g_hash_table_new_full(g_direct_hash, g_direct_equal, NULL, _compatsocket_unrefTaggedVoid);
+
+    interface->controlPackets = g_queue_new();
 
     /* parse queuing discipline */
     interface->qdisc = qdisc;
@@ -615,9 +1007,14 @@ NetworkInterface* networkinterface_new(Address* address, const gchar* pcapDir,
 
     interface->uses_router = uses_router;
 
+    /* local packets must arrive after they were sent */
+    utility_debugAssert(localDelay > 0);
+    interface->local_delay = localDelay;
+    interface->rate_limit_local = rateLimitLocal;
+
     debug("bringing up network interface '%s' at '%s' using queuing discipline %s",
           address_toHostName(interface->address), address_toHostIPString(interface->address),
-          interface->qdisc == Q_DISC_MODE_ROUND_ROBIN ? "rr" : "fifo");
+          _networkinterface_qdiscName(interface->qdisc));
 
     worker_count_allocation(NetworkInterface);
     return interface;
@@ -629,6 +1026,11 @@ void networkinterface_free(NetworkInterface* interface) {
     /* unref all sockets wanting to send */
     rrsocketqueue_destroy(&interface->rrQueue, compatsocket_unref);
     fifosocketqueue_destroy(&interface->fifoQueue, compatsocket_unref);
+    priosocketqueue_destroy(&interface->prioQueue, compatsocket_unref);
+    sfqsocketqueue_destroy(&interface->sfqQueue, compatsocket_unref);
+    g_hash_table_destroy(interface->pacedSockets);
+
+    g_queue_free_full(interface->controlPackets, packet_unrefTaskFreeFunc);
 
     g_hash_table_destroy(interface->boundSockets);
 
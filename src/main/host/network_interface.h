@@ -19,7 +19,8 @@ typedef struct _NetworkInterface NetworkInterface;
 #include "main/routing/address.h"
 
 NetworkInterface* networkinterface_new(Address* address, const gchar* pcapDir,
-                                       guint32 pcapCaptureSize, QDiscMode qdisc, bool uses_router);
+                                       guint32 pcapCaptureSize, QDiscMode qdisc, bool uses_router,
+                                       CSimulationTime localDelay, bool rateLimitLocal);
 void networkinterface_free(NetworkInterface* interface);
 
 /* The address and ports must be in network byte order. */
@@ -36,7 +37,12 @@ void networkinterface_wantsSend(NetworkInterface* interface, const Host* host,
                                 const CompatSocket* socket);
 
 void networkinterface_startRefillingTokenBuckets(NetworkInterface* interface, uint64_t bwDownKiBps,
-                                                 uint64_t bwUpKiBps);
+                                                 uint64_t bwUpKiBps, uint64_t burstBytes,
+                                                 uint64_t refillIntervalNanos);
+/* Changes the interface's bandwidth, which must be called after the token buckets are
+ * started. */
+void networkinterface_setBandwidth(NetworkInterface* interface, uint64_t bwDownKiBps,
+                                   uint64_t bwUpKiBps);
 
 void networkinterface_receivePackets(NetworkInterface* interface, const Host* host);
 
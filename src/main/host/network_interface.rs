@@ -5,6 +5,7 @@ use crate::core::support::configuration::QDiscMode;
 use crate::cshadow as c;
 use crate::host::host::Host;
 use crate::utility::{self, HostTreePointer};
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::HostId;
 
 /// Represents a network device that can send and receive packets. All accesses
@@ -14,7 +15,9 @@ pub struct NetworkInterface {
 }
 
 impl NetworkInterface {
-    /// Create a new network interface for `host_id` with the assigned `addr`.
+    /// Create a new network interface for `host_id` with the assigned `addr`. Packets sent to
+    /// `addr` arrive back on the interface after `local_latency`, and are only limited by the
+    /// interface's bandwidth if `limit_local_bandwidth` is set.
     ///
     /// # Safety
     ///
@@ -28,6 +31,8 @@ impl NetworkInterface {
         pcap_options: Option<PcapOptions>,
         qdisc: QDiscMode,
         uses_router: bool,
+        local_latency: SimulationTime,
+        limit_local_bandwidth: bool,
     ) -> NetworkInterface {
         let maybe_pcap_dir = pcap_options
             .as_ref()
@@ -42,7 +47,15 @@ impl NetworkInterface {
             .unwrap_or(0);
 
         let c_ptr = unsafe {
-            c::networkinterface_new(addr, pcap_dir_cptr, pcap_capture_size, qdisc, uses_router)
+            c::networkinterface_new(
+                addr,
+                pcap_dir_cptr,
+                pcap_capture_size,
+                qdisc,
+                uses_router,
+                SimulationTime::to_c_simtime(Some(local_latency)),
+                limit_local_bandwidth,
+            )
         };
 
         NetworkInterface {
@@ -99,16 +112,30 @@ impl NetworkInterface {
         }) != 0
     }
 
-    pub fn start_refilling_token_buckets(&self, bw_down_kibps: u64, bw_up_kibps: u64) {
+    pub fn start_refilling_token_buckets(
+        &self,
+        bw_down_kibps: u64,
+        bw_up_kibps: u64,
+        burst_bytes: u64,
+        refill_interval: SimulationTime,
+    ) {
         unsafe {
             c::networkinterface_startRefillingTokenBuckets(
                 self.c_ptr.ptr(),
                 bw_down_kibps,
                 bw_up_kibps,
+                burst_bytes,
+                refill_interval.as_nanos().try_into().unwrap(),
             )
         };
     }
 
+    /// Change the bandwidth of the interface. Must be called after
+    /// [`start_refilling_token_buckets`](Self::start_refilling_token_buckets).
+    pub fn set_bandwidth(&self, bw_down_kibps: u64, bw_up_kibps: u64) {
+        unsafe { c::networkinterface_setBandwidth(self.c_ptr.ptr(), bw_down_kibps, bw_up_kibps) };
+    }
+
     pub fn wants_send(&self, socket_ptr: &c::CompatSocket, host: &Host) {
         unsafe {
             c::networkinterface_wantsSend(
@@ -183,3 +183,209 @@ bool fifosocketqueue_find(FifoSocketQueue* self, const CompatSocket* socket) {
     utility_debugAssert(self->queue != NULL);
     return priorityqueue_find(self->queue, (void*)compatsocket_toTagged(socket));
 }
+
+/* The band of each priority, as in the default priomap of linux's pfifo_fast qdisc. Band 0 has
+ * the highest priority. */
+static const guint _prioBands[16] = {1, 2, 2, 2, 1, 2, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1};
+
+static guint _priosocketqueue_band(const CompatSocket* socket) {
+    /* linux uses the lowest 4 bits of the priority (TC_PRIO_MAX) */
+    return _prioBands[compatsocket_getPriority(socket) & 15];
+}
+
+void priosocketqueue_init(PrioSocketQueue* self) {
+    utility_debugAssert(self != NULL);
+    for (size_t i = 0; i < PRIO_SOCKET_QUEUE_BANDS; i++) {
+        fifosocketqueue_init(&self->bands[i]);
+    }
+}
+
+void priosocketqueue_destroy(PrioSocketQueue* self, void (*fn_processItem)(const CompatSocket*)) {
+    utility_debugAssert(self != NULL);
+    for (size_t i = 0; i < PRIO_SOCKET_QUEUE_BANDS; i++) {
+        fifosocketqueue_destroy(&self->bands[i], fn_processItem);
+    }
+}
+
+bool priosocketqueue_isEmpty(PrioSocketQueue* self) {
+    utility_debugAssert(self != NULL);
+    for (size_t i = 0; i < PRIO_SOCKET_QUEUE_BANDS; i++) {
+        if (!fifosocketqueue_isEmpty(&self->bands[i])) {
+            return false;
+        }
+    }
+    return true;
+}
+
+bool priosocketqueue_peek(PrioSocketQueue* self, CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    for (size_t i = 0; i < PRIO_SOCKET_QUEUE_BANDS; i++) {
+        if (fifosocketqueue_peek(&self->bands[i], socket)) {
+            return true;
+        }
+    }
+    return false;
+}
+
+bool priosocketqueue_pop(PrioSocketQueue* self, CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    for (size_t i = 0; i < PRIO_SOCKET_QUEUE_BANDS; i++) {
+        if (fifosocketqueue_pop(&self->bands[i], socket)) {
+            return true;
+        }
+    }
+    return false;
+}
+
+void priosocketqueue_push(PrioSocketQueue* self, const CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(socket->type != CST_NONE);
+    /* the band is chosen when the socket is pushed, so a socket that changes its priority moves
+     * to its new band after it's next served */
+    fifosocketqueue_push(&self->bands[_priosocketqueue_band(socket)], socket);
+}
+
+bool priosocketqueue_find(PrioSocketQueue* self, const CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    for (size_t i = 0; i < PRIO_SOCKET_QUEUE_BANDS; i++) {
+        if (fifosocketqueue_find(&self->bands[i], socket)) {
+            return true;
+        }
+    }
+    return false;
+}
+
+/* The number of hash buckets in a stochastic fair socket queue is 2^SFQ_BUCKET_BITS, which is the
+ * default divisor of linux's sfq qdisc. */
+#define SFQ_BUCKET_BITS 10
+
+static guint _sfqsocketqueue_bucket(const CompatSocket* socket) {
+    const Packet* packet = compatsocket_peekNextOutPacket(socket);
+    utility_debugAssert(packet != NULL);
+
+    guint hash = packet_getProtocol(packet);
+    hash = hash * 31 + packet_getSourceIP(packet);
+    hash = hash * 31 + packet_getSourcePort(packet);
+    hash = hash * 31 + packet_getDestinationIP(packet);
+    hash = hash * 31 + packet_getDestinationPort(packet);
+
+    /* fibonacci hashing, so that the bucket depends on all of the bits of the hash */
+    return (guint)((guint32)(hash * 2654435769u) >> (32 - SFQ_BUCKET_BITS));
+}
+
+static void _sfqsocketqueue_freeBucket(gpointer bucket) {
+    GQueue* queue = bucket;
+    utility_debugAssert(g_queue_is_empty(queue));
+    g_queue_free(queue);
+}
+
+void sfqsocketqueue_init(SfqSocketQueue* self) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(self->buckets == NULL);
+    utility_debugAssert(self->activeBuckets == NULL);
+    self->buckets = g_hash_table_new_full(g_direct_hash, g_direct_equal, NULL,
+                                          _sfqsocketqueue_freeBucket);
+    self->activeBuckets = g_queue_new();
+}
+
+void sfqsocketqueue_destroy(SfqSocketQueue* self, void (*fn_processItem)(const CompatSocket*)) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(self->buckets != NULL);
+    utility_debugAssert(self->activeBuckets != NULL);
+
+    while (!sfqsocketqueue_isEmpty(self)) {
+        CompatSocket socket = {0};
+        bool found = sfqsocketqueue_pop(self, &socket);
+
+        utility_debugAssert(found);
+        if (!found || fn_processItem == NULL) {
+            continue;
+        }
+
+        fn_processItem(&socket);
+    }
+
+    g_hash_table_destroy(self->buckets);
+    self->buckets = NULL;
+    g_queue_free(self->activeBuckets);
+    self->activeBuckets = NULL;
+}
+
+bool sfqsocketqueue_isEmpty(SfqSocketQueue* self) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(self->activeBuckets != NULL);
+    return g_queue_is_empty(self->activeBuckets);
+}
+
+bool sfqsocketqueue_peek(SfqSocketQueue* self, CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(self->buckets != NULL);
+
+    if (sfqsocketqueue_isEmpty(self)) {
+        return false;
+    }
+
+    GQueue* bucket = g_hash_table_lookup(self->buckets, g_queue_peek_head(self->activeBuckets));
+    utility_debugAssert(bucket != NULL && !g_queue_is_empty(bucket));
+
+    *socket = compatsocket_fromTagged((uintptr_t)g_queue_peek_head(bucket));
+    return true;
+}
+
+bool sfqsocketqueue_pop(SfqSocketQueue* self, CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(self->buckets != NULL);
+
+    if (sfqsocketqueue_isEmpty(self)) {
+        return false;
+    }
+
+    gpointer index = g_queue_pop_head(self->activeBuckets);
+    GQueue* bucket = g_hash_table_lookup(self->buckets, index);
+    utility_debugAssert(bucket != NULL && !g_queue_is_empty(bucket));
+
+    *socket = compatsocket_fromTagged((uintptr_t)g_queue_pop_head(bucket));
+
+    /* the bucket goes to the back of the line if it has more sockets */
+    if (g_queue_is_empty(bucket)) {
+        g_hash_table_remove(self->buckets, index);
+    } else {
+        g_queue_push_tail(self->activeBuckets, index);
+    }
+
+    return true;
+}
+
+void sfqsocketqueue_push(SfqSocketQueue* self, const CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(self->buckets != NULL);
+    utility_debugAssert(socket->type != CST_NONE);
+
+    gpointer index = GUINT_TO_POINTER(_sfqsocketqueue_bucket(socket));
+    GQueue* bucket = g_hash_table_lookup(self->buckets, index);
+
+    if (bucket == NULL) {
+        bucket = g_queue_new();
+        g_hash_table_insert(self->buckets, index, bucket);
+        g_queue_push_tail(self->activeBuckets, index);
+    }
+
+    g_queue_push_tail(bucket, (void*)compatsocket_toTagged(socket));
+}
+
+bool sfqsocketqueue_find(SfqSocketQueue* self, const CompatSocket* socket) {
+    utility_debugAssert(self != NULL);
+    utility_debugAssert(self->buckets != NULL);
+
+    GHashTableIter iter;
+    gpointer bucket = NULL;
+    g_hash_table_iter_init(&iter, self->buckets);
+
+    while (g_hash_table_iter_next(&iter, NULL, &bucket)) {
+        if (g_queue_find(bucket, (void*)compatsocket_toTagged(socket))) {
+            return true;
+        }
+    }
+
+    return false;
+}
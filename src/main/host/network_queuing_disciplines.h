@@ -24,6 +24,28 @@ struct _FifoSocketQueue {
     PriorityQueue* queue;
 };
 
+/* The number of bands in a priority socket queue, as in linux's pfifo_fast qdisc. */
+#define PRIO_SOCKET_QUEUE_BANDS 3
+
+/* A strict-priority socket queue. Sockets are placed into bands by their priority, and a band is
+ * only served when all of the bands with a higher priority are empty. Sockets within a band are
+ * served first-in-first-out. */
+typedef struct _PrioSocketQueue PrioSocketQueue;
+struct _PrioSocketQueue {
+    FifoSocketQueue bands[PRIO_SOCKET_QUEUE_BANDS];
+};
+
+/* A stochastic fair socket queue. Sockets are hashed into buckets by the flow of their next
+ * packet, and the buckets are served round-robin. Unlike linux's sfq qdisc the hash is never
+ * perturbed, so that simulations are deterministic. */
+typedef struct _SfqSocketQueue SfqSocketQueue;
+struct _SfqSocketQueue {
+    /* Maps a bucket index to the queue of sockets in that bucket. Only holds non-empty buckets. */
+    GHashTable* buckets;
+    /* The indices of the non-empty buckets, in the order that they will be served. */
+    GQueue* activeBuckets;
+};
+
 void rrsocketqueue_init(RrSocketQueue* self);
 void rrsocketqueue_destroy(RrSocketQueue* self, void (*fn_processItem)(const CompatSocket*));
 
@@ -42,4 +64,22 @@ bool fifosocketqueue_pop(FifoSocketQueue* self, CompatSocket* socket);
 void fifosocketqueue_push(FifoSocketQueue* self, const CompatSocket* socket);
 bool fifosocketqueue_find(FifoSocketQueue* self, const CompatSocket* socket);
 
+void priosocketqueue_init(PrioSocketQueue* self);
+void priosocketqueue_destroy(PrioSocketQueue* self, void (*fn_processItem)(const CompatSocket*));
+
+bool priosocketqueue_isEmpty(PrioSocketQueue* self);
+bool priosocketqueue_peek(PrioSocketQueue* self, CompatSocket* socket);
+bool priosocketqueue_pop(PrioSocketQueue* self, CompatSocket* socket);
+void priosocketqueue_push(PrioSocketQueue* self, const CompatSocket* socket);
+bool priosocketqueue_find(PrioSocketQueue* self, const CompatSocket* socket);
+
+void sfqsocketqueue_init(SfqSocketQueue* self);
+void sfqsocketqueue_destroy(SfqSocketQueue* self, void (*fn_processItem)(const CompatSocket*));
+
+bool sfqsocketqueue_isEmpty(SfqSocketQueue* self);
+bool sfqsocketqueue_peek(SfqSocketQueue* self, CompatSocket* socket);
+bool sfqsocketqueue_pop(SfqSocketQueue* self, CompatSocket* socket);
+void sfqsocketqueue_push(SfqSocketQueue* self, const CompatSocket* socket);
+bool sfqsocketqueue_find(SfqSocketQueue* self, const CompatSocket* socket);
+
 #endif /* SRC_MAIN_HOST_NETWORK_QUEUING_DISCIPLINES_H_ */
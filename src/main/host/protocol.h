@@ -8,7 +8,8 @@
 #define SHD_PROTOCOL_H_
 
 typedef enum _ProtocolType ProtocolType;
-enum _ProtocolType { PNONE, PLOCAL, PTCP, PUDP, PMOCK };
+/* new protocols are added at the end so that the values of the existing ones don't change */
+enum _ProtocolType { PNONE, PLOCAL, PTCP, PUDP, PMOCK, PICMP };
 
 enum ProtocolLocalFlags {
     PLOCAL_NONE = 0,
@@ -26,6 +27,30 @@ enum ProtocolTCPFlags {
     PTCP_SACK = 1 << 4,
     PTCP_FIN =  1 << 5,
     PTCP_DUPACK =  1 << 6,
+    PTCP_ECE = 1 << 7,
+    PTCP_CWR = 1 << 8,
+};
+
+/* the values are the ECN codepoints of the IP header on the wire (rfc 3168) */
+enum ProtocolECN {
+    PECN_NOT_ECT = 0,
+    PECN_ECT_1 = 1,
+    PECN_ECT_0 = 2,
+    PECN_CE = 3,
+};
+
+/* the values are the ICMP message types on the wire */
+enum ProtocolICMPType {
+    PICMP_ECHO_REPLY = 0,
+    PICMP_DEST_UNREACH = 3,
+    PICMP_ECHO_REQUEST = 8,
+};
+
+/* the values are the ICMP destination unreachable codes on the wire */
+enum ProtocolICMPCode {
+    PICMP_CODE_NONE = 0,
+    PICMP_CODE_HOST_UNREACH = 1,
+    PICMP_CODE_PORT_UNREACH = 3,
 };
 
 #endif /* SHD_PROTOCOL_H_ */
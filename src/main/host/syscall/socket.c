@@ -9,6 +9,7 @@
 #include <fcntl.h>
 #include <glib.h>
 #include <netinet/in.h>
+#include <netinet/ip.h>
 #include <stdbool.h>
 #include <sys/socket.h>
 #include <sys/types.h>
@@ -283,7 +284,7 @@ static int _syscallhandler_bindHelper(SysCallHandler* sys, LegacySocket* socket_
     /* we associate/disassociate UDP sockets without a peer since if the peer is changed with
      * `connect()`, we wouldn't be able to disassociate again later. See
      * https://github.com/shadow/shadow/issues/2590 */
-    if (ptype == PUDP) {
+    if (ptype == PUDP || ptype == PICMP) {
         peerAddr = 0;
         peerPort = 0;
     }
@@ -299,7 +300,7 @@ static int _syscallhandler_getTCPOptHelper(SysCallHandler* sys, TCP* tcp, int op
                                            socklen_t* optlen) {
     switch (optname) {
         case TCP_INFO: {
-            struct tcp_info info;
+            TCPInfo info;
             tcp_getInfo(tcp, &info);
 
             int num_bytes = MIN(*optlen, sizeof(info));
@@ -375,12 +376,38 @@ static int _syscallhandler_getSocketOptHelper(SysCallHandler* sys, LegacySocket*
                 if (connerr == -ECONNRESET || connerr == -ECONNREFUSED) {
                     error = -connerr; // result is a positive errcode
                 }
+            } else if (legacyfile_getType((LegacyFile*)sock) == DT_UDPSOCKET) {
+                /* Return and clear errors reported by ICMP error messages. */
+                error = udp_getError((UDP*)sock);
             }
             int num_bytes = MIN(*optlen, sizeof(error));
             memcpy(optval, &error, num_bytes);
             *optlen = num_bytes;
             return 0;
         }
+        case SO_PRIORITY: {
+            int priority = legacysocket_getPriority(sock);
+            int num_bytes = MIN(*optlen, sizeof(priority));
+            memcpy(optval, &priority, num_bytes);
+            *optlen = num_bytes;
+            return 0;
+        }
+        case SO_MAX_PACING_RATE: {
+            uint64_t rate = legacysocket_getMaxPacingRate(sock);
+
+            // Like Linux, the rate is returned as 64 bits only if the buffer is large enough,
+            // otherwise it's capped to 32 bits.
+            if (*optlen >= sizeof(rate)) {
+                memcpy(optval, &rate, sizeof(rate));
+                *optlen = sizeof(rate);
+            } else {
+                unsigned int rate32 = MIN(rate, G_MAXUINT);
+                int num_bytes = MIN(*optlen, sizeof(rate32));
+                memcpy(optval, &rate32, num_bytes);
+                *optlen = num_bytes;
+            }
+            return 0;
+        }
         case SO_TYPE: {
             int sock_type = -1;
 
@@ -399,7 +426,8 @@ static int _syscallhandler_getSocketOptHelper(SysCallHandler* sys, LegacySocket*
                     sock_type = SOCK_STREAM;
                     break;
                 }
-                case PUDP: {
+                case PUDP:
+                case PICMP: {
                     sock_type = SOCK_DGRAM;
                     break;
                 }
@@ -421,6 +449,42 @@ static int _syscallhandler_getSocketOptHelper(SysCallHandler* sys, LegacySocket*
     }
 }
 
+static int _syscallhandler_getIPOptHelper(SysCallHandler* sys, LegacySocket* sock, int optname,
+                                          void* optval, socklen_t* optlen) {
+    switch (optname) {
+        case IP_MTU: {
+            /* the path MTU is only known for connected sockets */
+            if (!legacysocket_getPeerName(sock, NULL, NULL)) {
+                return -ENOTCONN;
+            }
+
+            int mtu = legacysocket_getPathMTU(sock, _syscallhandler_getHost(sys));
+            int num_bytes = MIN(*optlen, sizeof(mtu));
+            memcpy(optval, &mtu, num_bytes);
+            *optlen = num_bytes;
+            return 0;
+        }
+        case IP_MTU_DISCOVER: {
+            int mode = legacysocket_getMTUDiscover(sock);
+            int num_bytes = MIN(*optlen, sizeof(mode));
+            memcpy(optval, &mode, num_bytes);
+            *optlen = num_bytes;
+            return 0;
+        }
+        case IP_TOS: {
+            int tos = legacysocket_getTOS(sock);
+            int num_bytes = MIN(*optlen, sizeof(tos));
+            memcpy(optval, &tos, num_bytes);
+            *optlen = num_bytes;
+            return 0;
+        }
+        default: {
+            warning("getsockopt at level SOL_IP called with unsupported option %i", optname);
+            return -ENOPROTOOPT;
+        }
+    }
+}
+
 static int _syscallhandler_setTCPOptHelper(SysCallHandler* sys, TCP* tcp, int optname,
                                            PluginPtr optvalPtr, socklen_t optlen) {
     switch (optname) {
@@ -484,6 +548,54 @@ static int _syscallhandler_setTCPOptHelper(SysCallHandler* sys, TCP* tcp, int op
     return 0;
 }
 
+static int _syscallhandler_setIPOptHelper(SysCallHandler* sys, LegacySocket* sock, int optname,
+                                          PluginPtr optvalPtr, socklen_t optlen) {
+    switch (optname) {
+        case IP_MTU_DISCOVER: {
+            if (optlen < sizeof(int)) {
+                return -EINVAL;
+            }
+
+            int mode = 0;
+            int errcode = process_readPtr(sys->process, &mode, optvalPtr, sizeof(int));
+            if (errcode != 0) {
+                return errcode;
+            }
+
+            // the same range that linux accepts
+            if (mode < IP_PMTUDISC_DONT || mode > IP_PMTUDISC_OMIT) {
+                return -EINVAL;
+            }
+
+            legacysocket_setMTUDiscover(sock, mode);
+            return 0;
+        }
+        case IP_TOS: {
+            if (optlen < sizeof(int)) {
+                return -EINVAL;
+            }
+
+            int tos = 0;
+            int errcode = process_readPtr(sys->process, &tos, optvalPtr, sizeof(int));
+            if (errcode != 0) {
+                return errcode;
+            }
+
+            /* like linux, tcp manages the ECN bits itself */
+            if (legacyfile_getType((LegacyFile*)sock) == DT_TCPSOCKET) {
+                tos &= ~IPTOS_ECN_MASK;
+            }
+
+            legacysocket_setTOS(sock, (guint8)tos);
+            return 0;
+        }
+        default: {
+            warning("setsockopt on level SOL_IP called with unsupported option %i", optname);
+            return -ENOPROTOOPT;
+        }
+    }
+}
+
 static int _syscallhandler_setSocketOptHelper(SysCallHandler* sys, LegacySocket* sock, int optname,
                                               PluginPtr optvalPtr, socklen_t optlen) {
     if (optlen < sizeof(int)) {
@@ -534,6 +646,44 @@ static int _syscallhandler_setSocketOptHelper(SysCallHandler* sys, LegacySocket*
             }
             return 0;
         }
+        case SO_PRIORITY: {
+            int priority = 0;
+            int errcode = process_readPtr(sys->process, &priority, optvalPtr, sizeof(int));
+            if (errcode != 0) {
+                return errcode;
+            }
+
+            // Linux requires CAP_NET_ADMIN for priorities outside of 0 to 6, but shadow doesn't
+            // model capabilities so we allow any non-negative priority.
+            if (priority < 0) {
+                return -EPERM;
+            }
+
+            legacysocket_setPriority(sock, priority);
+            return 0;
+        }
+        case SO_MAX_PACING_RATE: {
+            uint64_t rate = 0;
+
+            // Like Linux, a 32-bit rate of ~0U means unlimited, and a 64-bit rate is read if the
+            // buffer is large enough.
+            if (optlen >= sizeof(rate)) {
+                int errcode = process_readPtr(sys->process, &rate, optvalPtr, sizeof(rate));
+                if (errcode != 0) {
+                    return errcode;
+                }
+            } else {
+                unsigned int rate32 = 0;
+                int errcode = process_readPtr(sys->process, &rate32, optvalPtr, sizeof(rate32));
+                if (errcode != 0) {
+                    return errcode;
+                }
+                rate = (rate32 == G_MAXUINT) ? G_MAXUINT64 : rate32;
+            }
+
+            legacysocket_setMaxPacingRate(sock, rate);
+            return 0;
+        }
         case SO_REUSEADDR: {
             // TODO implement this, tor and tgen use it
             trace("setsockopt SO_REUSEADDR not yet implemented");
@@ -723,11 +873,14 @@ SysCallReturn _syscallhandler_sendtoHelper(SysCallHandler* sys, int sockfd,
     errcode = 0;
 
     if (legacyfile_getType(desc) == DT_UDPSOCKET) {
+        /* ping sockets don't need a destination port */
+        bool needsPort = legacysocket_getProtocol(socket_desc) != PICMP;
+
         /* make sure that we have somewhere to send it */
-        if (dest_ip == 0 || dest_port == 0) {
+        if (dest_ip == 0 || (dest_port == 0 && needsPort)) {
             /* its ok if they setup a default destination with connect() */
             legacysocket_getPeerName(socket_desc, &dest_ip, &dest_port);
-            if (dest_ip == 0 || dest_port == 0) {
+            if (dest_ip == 0 || (dest_port == 0 && needsPort)) {
                 /* we have nowhere to send it */
                 return syscallreturn_makeDoneErrno(EDESTADDRREQ);
             }
@@ -738,10 +891,7 @@ SysCallReturn _syscallhandler_sendtoHelper(SysCallHandler* sys, int sockfd,
             ProtocolType ptype = legacysocket_getProtocol(socket_desc);
 
             /* We don't bind to peer ip/port since that might change later. */
-            in_addr_t bindAddr =
-                (dest_ip == htonl(INADDR_LOOPBACK))
-                    ? htonl(INADDR_LOOPBACK)
-                    : address_toNetworkIP(host_getDefaultAddress(_syscallhandler_getHost(sys)));
+            in_addr_t bindAddr = host_getSourceIP(_syscallhandler_getHost(sys), dest_ip);
             in_port_t bindPort =
                 host_getRandomFreePort(_syscallhandler_getHost(sys), ptype, bindAddr, 0, 0);
 
@@ -943,11 +1093,14 @@ SysCallReturn syscallhandler_connect(SysCallHandler* sys,
         peerAddr = loopbackAddr;
     }
 
-    /* make sure we will be able to route this later */
-    if (peerAddr != loopbackAddr) {
-        const Address* myAddress = host_getDefaultAddress(_syscallhandler_getHost(sys));
+    /* make sure we will be able to route this later. Like Linux, connecting a UDP (or ping)
+     * socket doesn't contact the peer and succeeds even if there is no host with the address: its
+     * datagrams are answered with ICMP host unreachable errors, which the socket reports as
+     * EHOSTUNREACH. */
+    if (peerAddr != loopbackAddr &&
+        legacyfile_getType((LegacyFile*)socket_desc) != DT_UDPSOCKET) {
         const Address* peerAddress = worker_resolveIPToAddress(peerAddr);
-        in_addr_t myAddr = htonl(address_toHostIP(myAddress));
+        in_addr_t myAddr = host_getSourceIP(_syscallhandler_getHost(sys), peerAddr);
         if (!peerAddress || !worker_isRoutable(myAddr, peerAddr)) {
             /* can't route it - there is no node with this address */
             gchar* peerAddressString = address_ipToNewString(peerAddr);
@@ -961,10 +1114,8 @@ SysCallReturn syscallhandler_connect(SysCallHandler* sys,
 
     if (!legacysocket_isBound(socket_desc)) {
         /* do an implicit bind to a random ephemeral port.
-         * use default interface unless the remote peer is on loopback */
-        in_addr_t bindAddr = (loopbackAddr == peerAddr)
-                                 ? loopbackAddr
-                                 : host_getDefaultIP(_syscallhandler_getHost(sys));
+         * use the interface that the remote peer is reached through */
+        in_addr_t bindAddr = host_getSourceIP(_syscallhandler_getHost(sys), peerAddr);
         errcode = _syscallhandler_bindHelper(
             sys, socket_desc, bindAddr, 0, peerAddr, peerPort);
         if (errcode < 0) {
@@ -1091,7 +1242,7 @@ SysCallReturn syscallhandler_getsockname(SysCallHandler* sys,
         in_addr_t peerIP = 0;
         if (legacysocket_getPeerName(socket_desc, &peerIP, NULL) &&
             peerIP != htonl(INADDR_LOOPBACK)) {
-            inet_addr->sin_addr.s_addr = host_getDefaultIP(_syscallhandler_getHost(sys));
+            inet_addr->sin_addr.s_addr = host_getSourceIP(_syscallhandler_getHost(sys), peerIP);
         }
     }
 
@@ -1156,6 +1307,10 @@ SysCallReturn syscallhandler_getsockopt(SysCallHandler* sys,
                 _syscallhandler_getSocketOptHelper(sys, socket_desc, optname, optval, &optlen);
             break;
         }
+        case SOL_IP: {
+            errcode = _syscallhandler_getIPOptHelper(sys, socket_desc, optname, optval, &optlen);
+            break;
+        }
         default:
             warning("getsockopt called with unsupported level %i with opt %i", level, optname);
             errcode = -ENOPROTOOPT;
@@ -1280,6 +1435,11 @@ SysCallReturn syscallhandler_setsockopt(SysCallHandler* sys,
                 sys, socket_desc, optname, optvalPtr, optlen);
             break;
         }
+        case SOL_IP: {
+            errcode =
+                _syscallhandler_setIPOptHelper(sys, socket_desc, optname, optvalPtr, optlen);
+            break;
+        }
         default:
             warning("setsockopt called with unsupported level %i with opt %i", level, optname);
             errcode = -ENOPROTOOPT;
@@ -1349,9 +1509,10 @@ SysCallReturn syscallhandler_socket(SysCallHandler* sys,
         warning(
             "unsupported socket protocol \"%i\", we only support IPPROTO_TCP on sockets of type SOCK_STREAM", protocol);
         return syscallreturn_makeDoneErrno(EPROTONOSUPPORT);
-    } else if (type_no_flags == SOCK_DGRAM && protocol != 0 && protocol != IPPROTO_UDP) {
+    } else if (type_no_flags == SOCK_DGRAM && protocol != 0 && protocol != IPPROTO_UDP &&
+               protocol != IPPROTO_ICMP) {
         warning(
-            "unsupported socket protocol \"%i\", we only support IPPROTO_UDP on sockets of type SOCK_DGRAM", protocol);
+            "unsupported socket protocol \"%i\", we only support IPPROTO_UDP and IPPROTO_ICMP on sockets of type SOCK_DGRAM", protocol);
         return syscallreturn_makeDoneErrno(EPROTONOSUPPORT);
     }
 
@@ -1364,8 +1525,14 @@ SysCallReturn syscallhandler_socket(SysCallHandler* sys,
     }
 
     utility_alwaysAssert(type_no_flags == SOCK_DGRAM);
-    LegacySocket* sock_desc = sock_desc =
-        (LegacySocket*)udp_new(_syscallhandler_getHost(sys), recvBufSize, sendBufSize);
+    LegacySocket* sock_desc = NULL;
+    if (protocol == IPPROTO_ICMP) {
+        /* an unprivileged "ping" socket */
+        sock_desc =
+            (LegacySocket*)udp_newPing(_syscallhandler_getHost(sys), recvBufSize, sendBufSize);
+    } else {
+        sock_desc = (LegacySocket*)udp_new(_syscallhandler_getHost(sys), recvBufSize, sendBufSize);
+    }
 
     int descFlags = 0;
     if (type & SOCK_CLOEXEC) {
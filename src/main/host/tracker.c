@@ -55,6 +55,12 @@ struct _Tracker {
     gboolean didLogNodeHeader;
     gboolean didLogRAMHeader;
     gboolean didLogSocketHeader;
+    gboolean didLogFirewallHeader;
+    gboolean didLogRouterHeader;
+    gboolean didLogTokenBucket;
+
+    /* the host's count of packets that failed their checksum at the last heartbeat */
+    guint64 checksumErrorsLastHeartbeat;
 
     uint64_t processingTimeTotalNanos;
     uint64_t processingTimeLastIntervalNanos;
@@ -431,7 +437,8 @@ static gchar* _tracker_getCounterString(Counters* c) {
     return g_string_free(buffer, FALSE);
 }
 
-static void _tracker_logNode(Tracker* tracker, LogLevel level, CSimulationTime interval) {
+static void _tracker_logNode(Tracker* tracker, const Host* host, LogLevel level,
+                             CSimulationTime interval) {
     guint seconds = (guint) (interval / SIMTIME_ONE_SECOND);
     gdouble cpuutil =
         (gdouble)(((gdouble)tracker->processingTimeLastIntervalNanos) / ((gdouble)interval));
@@ -442,13 +449,17 @@ static void _tracker_logNode(Tracker* tracker, LogLevel level, CSimulationTime i
         avgdelayms = (gdouble) (delayms / ((gdouble) tracker->numDelayedLastInterval));
     }
 
+    guint64 checksumErrors = host_getChecksumErrors(host);
+    guint64 checksumErrorsLastInterval = checksumErrors - tracker->checksumErrorsLastHeartbeat;
+    tracker->checksumErrorsLastHeartbeat = checksumErrors;
+
     if(!tracker->didLogNodeHeader) {
         tracker->didLogNodeHeader = TRUE;
         logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
                    // clang-format off (Tries to break at -'s)
                    "[shadow-heartbeat] [node-header] "
                    "interval-seconds,recv-bytes,send-bytes,cpu-percent,"
-                   "delayed-count,avgdelay-milliseconds;"
+                   "delayed-count,avgdelay-milliseconds,checksum-errors;"
                    "inbound-localhost-counters;outbound-localhost-counters;"
                    "inbound-remote-counters;outbound-remote-counters "
                    "where counters are: %s",
@@ -466,8 +477,11 @@ static void _tracker_logNode(Tracker* tracker, LogLevel level, CSimulationTime i
 
     GString* buffer = g_string_new("[shadow-heartbeat] [node] ");
 
-    g_string_append_printf(buffer, "%u,%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT",%f,%"G_GSIZE_FORMAT",%f;",
-            seconds, totalRecvBytes, totalSendBytes, cpuutil, tracker->numDelayedLastInterval, avgdelayms);
+    g_string_append_printf(buffer,
+                           "%u,%" G_GSIZE_FORMAT ",%" G_GSIZE_FORMAT ",%f,%" G_GSIZE_FORMAT
+                           ",%f,%" G_GUINT64_FORMAT ";",
+                           seconds, totalRecvBytes, totalSendBytes, cpuutil,
+                           tracker->numDelayedLastInterval, avgdelayms, checksumErrorsLastInterval);
     g_string_append_printf(buffer, "%s;%s;%s;%s", inLocal, outLocal, inRemote, outRemote);
 
     logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
@@ -531,7 +545,7 @@ static void _tracker_logSocket(Tracker* tracker, LogLevel level, CSimulationTime
                 "%s;%s;%s;%s",
                 ss->socket, /*inet_ntoa((struct in_addr){socket->peerIP})*/
                 ss->type == PTCP ? "TCP" : ss->type == PUDP ? "UDP" :
-                    ss->type == PLOCAL ? "LOCAL" : "UNKNOWN",
+                    ss->type == PICMP ? "ICMP" : ss->type == PLOCAL ? "LOCAL" : "UNKNOWN",
                 ss->peerHostname, ss->peerPort,
                 ss->inputBufferLength, ss->inputBufferSize,
                 ss->outputBufferLength, ss->outputBufferSize,
@@ -579,12 +593,116 @@ static void _tracker_logRAM(Tracker* tracker, LogLevel level, CSimulationTime in
         tracker->allocatedBytesTotal, numptrs, tracker->numFailedFrees);
 }
 
+static const gchar* _tracker_firewallActionString(FirewallAction action) {
+    switch (action) {
+        case FIREWALL_ACTION_ALLOW: return "allow";
+        case FIREWALL_ACTION_DROP: return "drop";
+        case FIREWALL_ACTION_REJECT: return "reject";
+    }
+    return "unknown";
+}
+
+static void _tracker_logFirewall(Tracker* tracker, const Host* host, LogLevel level) {
+    size_t numRules = host_firewallNumRules(host);
+
+    if(!tracker->didLogFirewallHeader) {
+        tracker->didLogFirewallHeader = TRUE;
+
+        GString* header = g_string_new("[shadow-heartbeat] [firewall-header] ");
+        for (size_t i = 0; i < numRules; i++) {
+            g_string_append_printf(header, "%srule%zu-%s-hits", i == 0 ? "" : ",", i,
+                                   _tracker_firewallActionString(host_firewallRuleAction(host, i)));
+        }
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+                   "%s", header->str);
+        g_string_free(header, TRUE);
+    }
+
+    /* the hit counts are cumulative over the whole simulation */
+    GString* buffer = g_string_new("[shadow-heartbeat] [firewall] ");
+    for (size_t i = 0; i < numRules; i++) {
+        g_string_append_printf(buffer, "%s%" G_GUINT64_FORMAT, i == 0 ? "" : ",",
+                               host_firewallRuleHits(host, i));
+    }
+    logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+               "%s", buffer->str);
+    g_string_free(buffer, TRUE);
+}
+
+static void _tracker_logTokenBucket(Tracker* tracker, const Host* host, LogLevel level) {
+    /* the token bucket config doesn't change, so we only log it once */
+    if (tracker->didLogTokenBucket) {
+        return;
+    }
+    tracker->didLogTokenBucket = TRUE;
+
+    logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+               "[shadow-heartbeat] [token-bucket-header] "
+               "burst-bytes,refill-interval-milliseconds");
+
+    /* every interface of the host uses the same burst size and refill interval */
+    logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+               "[shadow-heartbeat] [token-bucket] %" G_GUINT64_FORMAT ",%f",
+               host_paramsBandwidthBurstBytes(host),
+               (gdouble)host_paramsBandwidthRefillInterval(host) / SIMTIME_ONE_MILLISECOND);
+}
+
+static void _tracker_logRouter(Tracker* tracker, const Host* host, LogLevel level) {
+    if(!tracker->didLogRouterHeader) {
+        tracker->didLogRouterHeader = TRUE;
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+                "[shadow-heartbeat] [router-header] ip-address;"
+                "enqueued-packets,enqueued-bytes,dequeued-packets,dequeued-bytes;"
+                "dropped-packets,dropped-bytes,overlimit-packets,overlimit-bytes,"
+                "marked-packets,marked-bytes;"
+                "mean-sojourn-milliseconds,max-sojourn-milliseconds;"
+                "queued-packets,queued-bytes|..." /* for each router */);
+    }
+
+    /* the counters are cumulative over the whole simulation */
+    GString* buffer = g_string_new("[shadow-heartbeat] [router] ");
+    size_t numRouters = host_numUpstreamRouters(host);
+
+    for (size_t i = 0; i < numRouters; i++) {
+        gchar* ipString = address_ipToNewString(host_getUpstreamRouterIP(host, i));
+        RouterStats stats = host_getUpstreamRouterStats(host, i);
+
+        g_string_append_printf(
+            buffer,
+            "%s%s;"
+            "%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT ";"
+            "%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT ","
+            "%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT ";"
+            "%f,%f;"
+            "%" G_GUINT64_FORMAT ",%" G_GUINT64_FORMAT,
+            i == 0 ? "" : "|", ipString, stats.enqueued_packets, stats.enqueued_bytes,
+            stats.dequeued_packets, stats.dequeued_bytes, stats.dropped_packets,
+            stats.dropped_bytes, stats.overlimit_packets, stats.overlimit_bytes,
+            stats.marked_packets, stats.marked_bytes,
+            (gdouble)stats.mean_sojourn_time_ns / SIMTIME_ONE_MILLISECOND,
+            (gdouble)stats.max_sojourn_time_ns / SIMTIME_ONE_MILLISECOND, stats.queued_packets,
+            stats.queued_bytes);
+
+        g_free(ipString);
+    }
+
+    logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+               "%s", buffer->str);
+    g_string_free(buffer, TRUE);
+}
+
 void tracker_heartbeat(Tracker* tracker, const Host* host) {
     MAGIC_ASSERT(tracker);
 
     /* check to see if node info is being logged */
     if(tracker->loginfo & LOG_INFO_FLAGS_NODE) {
-        _tracker_logNode(tracker, tracker->loglevel, tracker->interval);
+        _tracker_logNode(tracker, host, tracker->loglevel, tracker->interval);
+
+        _tracker_logTokenBucket(tracker, host, tracker->loglevel);
+
+        if (host_firewallNumRules(host) > 0) {
+            _tracker_logFirewall(tracker, host, tracker->loglevel);
+        }
     }
 
     /* check to see if socket buffer info is being logged */
@@ -597,6 +715,11 @@ void tracker_heartbeat(Tracker* tracker, const Host* host) {
         _tracker_logRAM(tracker, tracker->loglevel, tracker->interval);
     }
 
+    /* check to see if router queue info is being logged */
+    if(tracker->loginfo & LOG_INFO_FLAGS_ROUTER) {
+        _tracker_logRouter(tracker, host, tracker->loglevel);
+    }
+
     /* clear interval stats */
     tracker->processingTimeLastIntervalNanos = 0;
     tracker->delayTimeLastInterval = 0;
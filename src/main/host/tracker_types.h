@@ -15,6 +15,7 @@ enum _LogInfoFlags {
     LOG_INFO_FLAGS_NODE = 1 << 0,
     LOG_INFO_FLAGS_SOCKET = 1 << 1,
     LOG_INFO_FLAGS_RAM = 1 << 2,
+    LOG_INFO_FLAGS_ROUTER = 1 << 3,
 };
 
 #endif /* SHD_TRACKER_TYPES_H_ */
@@ -0,0 +1,350 @@
+//! Generators for the built-in parametric network graphs. Each generator returns an undirected
+//! GML graph, so that generated graphs are loaded and validated in the same way as graph files.
+//!
+//! Nodes that hosts are expected to be assigned to are numbered first, followed by any hub,
+//! router, or spine nodes. Every node has a self-loop so that hosts assigned to the same node can
+//! communicate.
+
+use std::fmt::Write;
+
+use rand::{Rng, SeedableRng};
+
+use crate::core::support::configuration::{
+    DumbbellGraphOptions, FullMeshGraphOptions, LeafSpineGraphOptions, RandomGeometricGraphOptions,
+    StarGraphOptions,
+};
+use crate::core::support::units::{self, Unit};
+
+/// Writes a generated undirected graph in the GML format.
+struct GmlWriter {
+    text: String,
+}
+
+impl GmlWriter {
+    fn new() -> Self {
+        Self {
+            text: "graph [\n  directed 0\n".to_string(),
+        }
+    }
+
+    fn node(&mut self, id: u32, bandwidth: units::BitsPerSec<units::SiPrefixUpper>) {
+        write!(
+            self.text,
+            "  node [\n    id {id}\n    host_bandwidth_up \"{bandwidth}\"\n    \
+             host_bandwidth_down \"{bandwidth}\"\n  ]\n"
+        )
+        .unwrap();
+    }
+
+    /// Add an edge with a latency in nanoseconds. The `other` attributes must already be
+    /// formatted as GML values.
+    fn edge(&mut self, source: u32, target: u32, latency_ns: u64, other: &[(&str, String)]) {
+        write!(
+            self.text,
+            "  edge [\n    source {source}\n    target {target}\n    latency \"{latency_ns} ns\"\n"
+        )
+        .unwrap();
+        for (name, value) in other {
+            writeln!(self.text, "    {name} {value}").unwrap();
+        }
+        self.text.push_str("  ]\n");
+    }
+
+    fn finish(mut self) -> String {
+        self.text.push_str("]\n");
+        self.text
+    }
+}
+
+fn latency_ns(latency: units::Time<units::TimePrefix>) -> Result<u64, String> {
+    Ok(latency.convert(units::TimePrefix::Nano)?.value())
+}
+
+fn check_nonzero(name: &str, x: u32) -> Result<(), String> {
+    if x == 0 {
+        return Err(format!("The graph option '{name}' must be at least 1"));
+    }
+    Ok(())
+}
+
+/// Generate a star graph. Leaf nodes are numbered `0..nodes`, and the hub node is `nodes`.
+pub fn star(options: &StarGraphOptions) -> Result<String, String> {
+    check_nonzero("nodes", options.nodes)?;
+    let latency = latency_ns(options.latency)?;
+    let hub = options.nodes;
+
+    let mut gml = GmlWriter::new();
+    for id in 0..=hub {
+        gml.node(id, options.bandwidth);
+        gml.edge(id, id, latency, &[]);
+    }
+    for id in 0..hub {
+        gml.edge(id, hub, latency, &[]);
+    }
+
+    Ok(gml.finish())
+}
+
+/// Generate a full mesh graph with nodes numbered `0..nodes`.
+pub fn full_mesh(options: &FullMeshGraphOptions) -> Result<String, String> {
+    check_nonzero("nodes", options.nodes)?;
+    let latency = latency_ns(options.latency)?;
+    let other = [("packet_loss", format!("{:?}", options.packet_loss))];
+
+    let mut gml = GmlWriter::new();
+    for id in 0..options.nodes {
+        gml.node(id, options.bandwidth);
+    }
+    for source in 0..options.nodes {
+        for target in source..options.nodes {
+            gml.edge(source, target, latency, &other);
+        }
+    }
+
+    Ok(gml.finish())
+}
+
+/// Generate a dumbbell graph. The leaf nodes on the left side are numbered
+/// `0..nodes_per_side` and those on the right side are numbered
+/// `nodes_per_side..2*nodes_per_side`, followed by the left and right routers.
+pub fn dumbbell(options: &DumbbellGraphOptions) -> Result<String, String> {
+    check_nonzero("nodes_per_side", options.nodes_per_side)?;
+    let latency = latency_ns(options.latency)?;
+    let bottleneck_latency = latency_ns(options.bottleneck_latency)?;
+
+    let per_side = options.nodes_per_side;
+    let (left_router, right_router) = (2 * per_side, 2 * per_side + 1);
+
+    let mut gml = GmlWriter::new();
+    for id in 0..=right_router {
+        gml.node(id, options.bandwidth);
+        gml.edge(id, id, latency, &[]);
+    }
+    for id in 0..per_side {
+        gml.edge(id, left_router, latency, &[]);
+        gml.edge(per_side + id, right_router, latency, &[]);
+    }
+
+    let mut bottleneck = vec![("bandwidth", format!("\"{}\"", options.bottleneck_bandwidth))];
+    if let Some(queue_size) = options.bottleneck_queue_size {
+        bottleneck.push(("queue_size", format!("\"{queue_size}\"")));
+    }
+    gml.edge(left_router, right_router, bottleneck_latency, &bottleneck);
+
+    Ok(gml.finish())
+}
+
+/// Generate a leaf/spine graph. Leaf nodes are numbered `0..leaves` and spine nodes are numbered
+/// `leaves..leaves+spines`.
+pub fn leaf_spine(options: &LeafSpineGraphOptions) -> Result<String, String> {
+    check_nonzero("leaves", options.leaves)?;
+    check_nonzero("spines", options.spines)?;
+    let latency = latency_ns(options.latency)?;
+
+    let mut link = vec![];
+    if let Some(bandwidth) = options.link_bandwidth {
+        link.push(("bandwidth", format!("\"{bandwidth}\"")));
+    }
+
+    let mut gml = GmlWriter::new();
+    for id in 0..(options.leaves + options.spines) {
+        gml.node(id, options.bandwidth);
+        gml.edge(id, id, latency, &[]);
+    }
+    for leaf in 0..options.leaves {
+        for spine in options.leaves..(options.leaves + options.spines) {
+            gml.edge(leaf, spine, latency, &link);
+        }
+    }
+
+    Ok(gml.finish())
+}
+
+/// Generate a random geometric graph with nodes numbered `0..nodes`. The graph may not be
+/// connected if `radius` is small.
+pub fn random_geometric(
+    options: &RandomGeometricGraphOptions,
+    seed: u64,
+) -> Result<String, String> {
+    check_nonzero("nodes", options.nodes)?;
+    if !(options.radius > 0.0 && options.radius.is_finite()) {
+        return Err("The graph option 'radius' must be a positive number".to_string());
+    }
+    let latency = latency_ns(options.latency)?;
+    let min_latency = latency_ns(options.min_latency)?;
+    if min_latency == 0 || min_latency > latency {
+        return Err(
+            "The graph option 'min_latency' must be greater than 0 and at most 'latency'"
+                .to_string(),
+        );
+    }
+
+    let mut rng = rand_xoshiro::Xoshiro256PlusPlus::seed_from_u64(seed);
+    let positions: Vec<(f64, f64)> = (0..options.nodes).map(|_| rng.gen()).collect();
+
+    let mut gml = GmlWriter::new();
+    for id in 0..options.nodes {
+        gml.node(id, options.bandwidth);
+        gml.edge(id, id, latency, &[]);
+    }
+    for (source, (x1, y1)) in positions.iter().enumerate() {
+        for (target, (x2, y2)) in positions.iter().enumerate().skip(source + 1) {
+            let distance = f64::hypot(x2 - x1, y2 - y1);
+            if distance > options.radius {
+                continue;
+            }
+            // nodes that are close together still need a realistic latency
+            let edge_latency = (latency as f64 * distance / options.radius).round() as u64;
+            let edge_latency = std::cmp::max(edge_latency, min_latency);
+            gml.edge(
+                source.try_into().unwrap(),
+                target.try_into().unwrap(),
+                edge_latency,
+                &[],
+            );
+        }
+    }
+
+    Ok(gml.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::graph::NetworkGraph;
+
+    fn ns(time: units::Time<units::TimePrefix>) -> u64 {
+        latency_ns(time).unwrap()
+    }
+
+    fn edge_count(gml: &str) -> usize {
+        NetworkGraph::parse(gml).unwrap().graph().raw_edges().len()
+    }
+
+    #[test]
+    fn test_star() {
+        let options: StarGraphOptions = serde_json::from_str(r#"{"nodes": 3}"#).unwrap();
+        // 4 self-loops and 3 spokes
+        assert_eq!(edge_count(&star(&options).unwrap()), 7);
+
+        let options: StarGraphOptions = serde_json::from_str(r#"{"nodes": 0}"#).unwrap();
+        assert!(star(&options).is_err());
+    }
+
+    #[test]
+    fn test_full_mesh() {
+        let options: FullMeshGraphOptions =
+            serde_json::from_str(r#"{"nodes": 4, "latency": "5 ms", "packet_loss": 0.1}"#).unwrap();
+        let graph = NetworkGraph::parse(&full_mesh(&options).unwrap()).unwrap();
+        assert_eq!(graph.graph().raw_edges().len(), 10);
+        for edge in graph.graph().raw_edges() {
+            assert_eq!(ns(edge.weight.latency), 5_000_000);
+            assert_eq!(edge.weight.packet_loss, 0.1);
+        }
+
+        // the same validation applies as for graph files
+        let options: FullMeshGraphOptions =
+            serde_json::from_str(r#"{"nodes": 4, "packet_loss": 1.5}"#).unwrap();
+        assert!(NetworkGraph::parse(&full_mesh(&options).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_dumbbell() {
+        let options: DumbbellGraphOptions = serde_json::from_str(
+            r#"{"nodes_per_side": 2, "bottleneck_bandwidth": "10 Mbit", "bottleneck_queue_size": "64 KB"}"#,
+        )
+        .unwrap();
+        let graph = NetworkGraph::parse(&dumbbell(&options).unwrap()).unwrap();
+
+        // 6 self-loops, 4 access links, and the bottleneck
+        assert_eq!(graph.graph().raw_edges().len(), 11);
+
+        let limited: Vec<_> = graph
+            .graph()
+            .raw_edges()
+            .iter()
+            .map(|e| &e.weight)
+            .filter(|e| e.bandwidth.is_some())
+            .collect();
+        assert_eq!(limited.len(), 1);
+        assert_eq!((limited[0].source, limited[0].target), (4, 5));
+        assert_eq!(ns(limited[0].latency), 10_000_000);
+        assert_eq!(limited[0].bandwidth, Some("10 Mbit".parse().unwrap()));
+        assert_eq!(limited[0].queue_size, Some("64 KB".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_leaf_spine() {
+        let options: LeafSpineGraphOptions =
+            serde_json::from_str(r#"{"leaves": 4, "spines": 2, "link_bandwidth": "10 Gbit"}"#)
+                .unwrap();
+        let graph = NetworkGraph::parse(&leaf_spine(&options).unwrap()).unwrap();
+
+        // 6 self-loops and 8 leaf-spine links
+        assert_eq!(graph.graph().raw_edges().len(), 14);
+        assert_eq!(
+            graph
+                .graph()
+                .raw_edges()
+                .iter()
+                .filter(|e| e.weight.bandwidth.is_some())
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_random_geometric() {
+        let options: RandomGeometricGraphOptions =
+            serde_json::from_str(r#"{"nodes": 20, "radius": 0.3}"#).unwrap();
+
+        // the same seed always generates the same graph
+        assert_eq!(
+            random_geometric(&options, 1).unwrap(),
+            random_geometric(&options, 1).unwrap()
+        );
+        assert_ne!(
+            random_geometric(&options, 1).unwrap(),
+            random_geometric(&options, 2).unwrap()
+        );
+
+        let graph = NetworkGraph::parse(&random_geometric(&options, 1).unwrap()).unwrap();
+        for edge in graph.graph().raw_edges() {
+            assert!((100_000..=1_000_000).contains(&ns(edge.weight.latency)));
+        }
+
+        // every link gets at least the minimum latency
+        let options: RandomGeometricGraphOptions = serde_json::from_str(
+            r#"{"nodes": 20, "radius": 0.3, "latency": "10 ms", "min_latency": "5 ms"}"#,
+        )
+        .unwrap();
+        let graph = NetworkGraph::parse(&random_geometric(&options, 1).unwrap()).unwrap();
+        for edge in graph.graph().raw_edges() {
+            assert!((5_000_000..=10_000_000).contains(&ns(edge.weight.latency)));
+        }
+        assert!(graph
+            .graph()
+            .raw_edges()
+            .iter()
+            .any(|x| x.source() != x.target() && ns(x.weight.latency) == 5_000_000));
+
+        let options: RandomGeometricGraphOptions =
+            serde_json::from_str(r#"{"nodes": 20, "radius": 0.3, "min_latency": "0 ms"}"#).unwrap();
+        assert!(random_geometric(&options, 1).is_err());
+        let options: RandomGeometricGraphOptions =
+            serde_json::from_str(r#"{"nodes": 20, "radius": 0.3, "min_latency": "2 ms"}"#).unwrap();
+        assert!(random_geometric(&options, 1).is_err());
+
+        // a radius larger than the diagonal of the unit square connects every node
+        let options: RandomGeometricGraphOptions =
+            serde_json::from_str(r#"{"nodes": 20, "radius": 1.5}"#).unwrap();
+        assert_eq!(
+            edge_count(&random_geometric(&options, 1).unwrap()),
+            20 * 21 / 2
+        );
+
+        let options: RandomGeometricGraphOptions =
+            serde_json::from_str(r#"{"nodes": 20, "radius": 0.0}"#).unwrap();
+        assert!(random_geometric(&options, 1).is_err());
+    }
+}
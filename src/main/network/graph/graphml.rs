@@ -0,0 +1,197 @@
+//! Conversion of [GraphML](http://graphml.graphdrawing.org/) graphs to the GML graph
+//! representation, so that they can be loaded and validated in the same way as GML graphs.
+//!
+//! Node and edge attributes are declared by `<key>` elements, and are named by their
+//! `attr.name` and typed by their `attr.type`. Node ids and edge endpoints must be unsigned
+//! integers.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use gml_parser::gml::{Edge, Gml, Node, Value};
+
+/// A GraphML attribute declared by a `<key>` element.
+#[derive(Debug, Clone)]
+struct Key {
+    name: String,
+    ty: String,
+    default: Option<Value<'static>>,
+}
+
+/// Parse a GraphML graph into a [`Gml`] graph.
+pub fn parse(graph_text: &str) -> Result<Gml<'static>, String> {
+    let doc = roxmltree::Document::parse(graph_text)
+        .map_err(|e| format!("Could not parse GraphML: {e}"))?;
+
+    let root = doc.root_element();
+    if !root.has_tag_name("graphml") {
+        return Err(format!(
+            "Expected a 'graphml' root element, but found '{}'",
+            root.tag_name().name()
+        ));
+    }
+
+    // keys are declared separately for nodes and edges, and keys for "all" apply to both
+    let mut node_keys = HashMap::new();
+    let mut edge_keys = HashMap::new();
+
+    for key in root.children().filter(|x| x.has_tag_name("key")) {
+        let id = key
+            .attribute("id")
+            .ok_or("GraphML key is missing an 'id'")?;
+        let name = key.attribute("attr.name").unwrap_or(id);
+        let ty = key.attribute("attr.type").unwrap_or("string");
+
+        let default = key
+            .children()
+            .find(|x| x.has_tag_name("default"))
+            .map(|x| parse_value(name, ty, x.text().unwrap_or("")))
+            .transpose()?;
+
+        let key_info = Key {
+            name: name.to_string(),
+            ty: ty.to_string(),
+            default,
+        };
+
+        match key.attribute("for").unwrap_or("all") {
+            "node" => {
+                node_keys.insert(id, key_info);
+            }
+            "edge" => {
+                edge_keys.insert(id, key_info);
+            }
+            "all" => {
+                node_keys.insert(id, key_info.clone());
+                edge_keys.insert(id, key_info);
+            }
+            // keys for the graph, ports, etc are not used
+            _ => {}
+        }
+    }
+
+    let mut graphs = root.children().filter(|x| x.has_tag_name("graph"));
+    let graph = graphs.next().ok_or("GraphML does not contain a 'graph'")?;
+    if graphs.next().is_some() {
+        return Err("GraphML must contain only one 'graph'".to_string());
+    }
+
+    let directed = match graph.attribute("edgedefault").unwrap_or("directed") {
+        "directed" => true,
+        "undirected" => false,
+        x => return Err(format!("GraphML 'edgedefault' has an invalid value '{x}'")),
+    };
+
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+
+    for element in graph.children().filter(|x| x.is_element()) {
+        match element.tag_name().name() {
+            "node" => {
+                if element.children().any(|x| x.has_tag_name("graph")) {
+                    return Err("GraphML nested graphs are not supported".to_string());
+                }
+
+                let id = parse_id("node", "id", element.attribute("id"))?;
+                let other = parse_data(element, &node_keys)?;
+
+                nodes.push(Node::new(Some(id), other));
+            }
+            "edge" => {
+                if let Some(x) = element.attribute("directed") {
+                    let edge_directed = match x {
+                        "true" => true,
+                        "false" => false,
+                        x => {
+                            return Err(format!(
+                                "GraphML edge 'directed' has an invalid value '{x}'"
+                            ))
+                        }
+                    };
+                    if edge_directed != directed {
+                        return Err(
+                            "GraphML edges must all be directed or all be undirected".to_string()
+                        );
+                    }
+                }
+
+                let source = parse_id("edge", "source", element.attribute("source"))?;
+                let target = parse_id("edge", "target", element.attribute("target"))?;
+                let other = parse_data(element, &edge_keys)?;
+
+                edges.push(Edge::new(source, target, other));
+            }
+            "hyperedge" => return Err("GraphML hyperedges are not supported".to_string()),
+            // graph-level data, descriptions, etc
+            _ => {}
+        }
+    }
+
+    Ok(Gml {
+        directed,
+        nodes,
+        edges,
+        other: HashMap::new(),
+    })
+}
+
+/// Parse a node id or edge endpoint.
+fn parse_id(element: &str, attr: &str, id: Option<&str>) -> Result<u32, String> {
+    let id = id.ok_or(format!("GraphML {element} is missing a '{attr}'"))?;
+    id.parse()
+        .map_err(|_| format!("GraphML {element} '{attr}' '{id}' is not an unsigned integer"))
+}
+
+/// Get the attributes of a node or edge from its `<data>` elements and the defaults of `keys`.
+fn parse_data(
+    element: roxmltree::Node,
+    keys: &HashMap<&str, Key>,
+) -> Result<HashMap<Cow<'static, str>, Value<'static>>, String> {
+    let mut other = HashMap::new();
+
+    for data in element.children().filter(|x| x.has_tag_name("data")) {
+        let id = data
+            .attribute("key")
+            .ok_or("GraphML data is missing a 'key'")?;
+        let key = keys
+            .get(id)
+            .ok_or(format!("GraphML data key '{id}' was not declared"))?;
+
+        let value = parse_value(&key.name, &key.ty, data.text().unwrap_or(""))?;
+        if other.insert(Cow::Owned(key.name.clone()), value).is_some() {
+            return Err(format!("GraphML attribute '{}' was given twice", key.name));
+        }
+    }
+
+    for key in keys.values() {
+        if let Some(default) = &key.default {
+            other
+                .entry(Cow::Owned(key.name.clone()))
+                .or_insert_with(|| default.clone());
+        }
+    }
+
+    Ok(other)
+}
+
+/// Convert the text of a GraphML attribute to a value of the attribute's type.
+fn parse_value(name: &str, ty: &str, text: &str) -> Result<Value<'static>, String> {
+    let text = text.trim();
+    let err = || format!("GraphML attribute '{name}' is not a valid {ty}: '{text}'");
+
+    Ok(match ty {
+        "string" => Value::Str(Cow::Owned(text.to_string())),
+        "int" | "long" => Value::Int(text.parse().map_err(|_| err())?),
+        "float" | "double" => Value::Float(text.parse().map_err(|_| err())?),
+        "boolean" => match text {
+            "true" => Value::Int(1),
+            "false" => Value::Int(0),
+            _ => return Err(err()),
+        },
+        _ => {
+            return Err(format!(
+                "GraphML attribute '{name}' has unknown type '{ty}'"
+            ))
+        }
+    })
+}
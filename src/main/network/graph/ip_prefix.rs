@@ -0,0 +1,157 @@
+//! IPv4 address prefixes in CIDR notation, such as `10.3.0.0/16`, which graph nodes can declare
+//! so that the hosts assigned to them are given addresses from the prefix.
+
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+/// An IPv4 address prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpPrefix {
+    network: Ipv4Addr,
+    prefix_len: u8,
+}
+
+impl IpPrefix {
+    /// Returns an error if the prefix length is greater than 32, or if `network` has any bits set
+    /// after the prefix.
+    pub fn new(network: Ipv4Addr, prefix_len: u8) -> Result<Self, String> {
+        if prefix_len > 32 {
+            return Err(format!("The prefix length {prefix_len} is greater than 32"));
+        }
+
+        let prefix = Self {
+            network,
+            prefix_len,
+        };
+        if u32::from(network) & !prefix.mask() != 0 {
+            return Err(format!(
+                "The address {network} has bits set after the prefix length {prefix_len}"
+            ));
+        }
+
+        Ok(prefix)
+    }
+
+    pub fn network(&self) -> Ipv4Addr {
+        self.network
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    fn mask(&self) -> u32 {
+        u32::MAX
+            .checked_shl(32 - u32::from(self.prefix_len))
+            .unwrap_or(0)
+    }
+
+    /// Whether the prefix contains `addr`.
+    pub fn contains(&self, addr: Ipv4Addr) -> bool {
+        u32::from(addr) & self.mask() == u32::from(self.network)
+    }
+
+    /// Whether any address is in both prefixes.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.contains(other.network) || other.contains(self.network)
+    }
+
+    /// The last address in the prefix, which is the broadcast address for prefixes shorter than
+    /// /31.
+    pub fn last_address(&self) -> Ipv4Addr {
+        (u32::from(self.network) | !self.mask()).into()
+    }
+
+    /// The first and last addresses that can be assigned to hosts. The network and broadcast
+    /// addresses are excluded, except for /31 and /32 prefixes which have no such addresses.
+    pub fn host_range(&self) -> (Ipv4Addr, Ipv4Addr) {
+        let first = u32::from(self.network);
+        let last = u32::from(self.last_address());
+        if self.prefix_len >= 31 {
+            (first.into(), last.into())
+        } else {
+            ((first + 1).into(), (last - 1).into())
+        }
+    }
+}
+
+impl FromStr for IpPrefix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (network, prefix_len) = s.split_once('/').ok_or(format!(
+            "The IP prefix '{s}' is not in the form 'address/length'"
+        ))?;
+        let network = network
+            .parse()
+            .map_err(|e| format!("The IP prefix '{s}' has an invalid address: {e}"))?;
+        let prefix_len = prefix_len
+            .parse()
+            .map_err(|e| format!("The IP prefix '{s}' has an invalid length: {e}"))?;
+        Self::new(network, prefix_len).map_err(|e| format!("Invalid IP prefix '{s}': {e}"))
+    }
+}
+
+impl std::fmt::Display for IpPrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let prefix: IpPrefix = "10.3.0.0/16".parse().unwrap();
+        assert_eq!(prefix.network(), Ipv4Addr::new(10, 3, 0, 0));
+        assert_eq!(prefix.prefix_len(), 16);
+        assert_eq!(prefix.to_string(), "10.3.0.0/16");
+
+        assert!("0.0.0.0/0".parse::<IpPrefix>().is_ok());
+        assert!("10.3.0.1/32".parse::<IpPrefix>().is_ok());
+
+        assert!("10.3.0.0".parse::<IpPrefix>().is_err());
+        assert!("10.3.0.0/33".parse::<IpPrefix>().is_err());
+        assert!("10.3.0/16".parse::<IpPrefix>().is_err());
+        // host bits must not be set
+        assert!("10.3.0.1/16".parse::<IpPrefix>().is_err());
+    }
+
+    #[test]
+    fn test_contains() {
+        let prefix: IpPrefix = "10.3.0.0/16".parse().unwrap();
+        assert!(prefix.contains(Ipv4Addr::new(10, 3, 0, 0)));
+        assert!(prefix.contains(Ipv4Addr::new(10, 3, 255, 255)));
+        assert!(!prefix.contains(Ipv4Addr::new(10, 4, 0, 0)));
+
+        assert!(prefix.overlaps(&"10.0.0.0/8".parse().unwrap()));
+        assert!(prefix.overlaps(&"10.3.7.0/24".parse().unwrap()));
+        assert!(!prefix.overlaps(&"10.4.0.0/16".parse().unwrap()));
+
+        let all: IpPrefix = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(Ipv4Addr::new(255, 255, 255, 255)));
+    }
+
+    #[test]
+    fn test_host_range() {
+        let range = |s: &str| s.parse::<IpPrefix>().unwrap().host_range();
+        assert_eq!(
+            range("10.3.0.0/16"),
+            (Ipv4Addr::new(10, 3, 0, 1), Ipv4Addr::new(10, 3, 255, 254))
+        );
+        assert_eq!(
+            range("10.3.0.0/31"),
+            (Ipv4Addr::new(10, 3, 0, 0), Ipv4Addr::new(10, 3, 0, 1))
+        );
+        assert_eq!(
+            "10.3.0.0/16".parse::<IpPrefix>().unwrap().last_address(),
+            Ipv4Addr::new(10, 3, 255, 255)
+        );
+        assert_eq!(
+            range("10.3.0.7/32"),
+            (Ipv4Addr::new(10, 3, 0, 7), Ipv4Addr::new(10, 3, 0, 7))
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Conversion of JSON node-link graphs (as written by networkx's `node_link_data`) to the GML
+//! graph representation, so that they can be loaded and validated in the same way as GML graphs.
+//!
+//! ```json
+//! {
+//!   "directed": false,
+//!   "nodes": [{"id": 0, "host_bandwidth_down": "1 Gbit", "host_bandwidth_up": "1 Gbit"}],
+//!   "links": [{"source": 0, "target": 0, "latency": "1 ms"}]
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use gml_parser::gml::{Edge, Gml, Node, Value};
+use serde::Deserialize;
+
+type Attributes = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Debug, Deserialize)]
+struct NodeLinkGraph {
+    #[serde(default)]
+    directed: bool,
+    nodes: Vec<Attributes>,
+    /// Newer versions of networkx name these "edges".
+    #[serde(alias = "edges")]
+    links: Vec<Attributes>,
+}
+
+/// Parse a JSON node-link graph into a [`Gml`] graph.
+pub fn parse(graph_text: &str) -> Result<Gml<'static>, String> {
+    let graph: NodeLinkGraph =
+        serde_json::from_str(graph_text).map_err(|e| format!("Could not parse JSON graph: {e}"))?;
+
+    let nodes = graph
+        .nodes
+        .into_iter()
+        .map(|mut attrs| {
+            let id = attrs
+                .remove("id")
+                .map(|x| parse_id("node", "id", x))
+                .transpose()?;
+            Ok(Node::new(id, parse_attributes(attrs)?))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let edges = graph
+        .links
+        .into_iter()
+        .map(|mut attrs| {
+            let source = attrs
+                .remove("source")
+                .ok_or("JSON edge is missing a 'source'")?;
+            let target = attrs
+                .remove("target")
+                .ok_or("JSON edge is missing a 'target'")?;
+            Ok(Edge::new(
+                parse_id("edge", "source", source)?,
+                parse_id("edge", "target", target)?,
+                parse_attributes(attrs)?,
+            ))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(Gml {
+        directed: graph.directed,
+        nodes,
+        edges,
+        other: HashMap::new(),
+    })
+}
+
+/// Parse a node id or edge endpoint.
+fn parse_id(element: &str, attr: &str, id: serde_json::Value) -> Result<u32, String> {
+    id.as_u64().and_then(|x| x.try_into().ok()).ok_or(format!(
+        "JSON {element} '{attr}' {id} is not an unsigned integer"
+    ))
+}
+
+/// Convert the attributes of a node or edge to GML values.
+fn parse_attributes(
+    attrs: Attributes,
+) -> Result<HashMap<Cow<'static, str>, Value<'static>>, String> {
+    attrs
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(x) => Value::Str(Cow::Owned(x)),
+                serde_json::Value::Bool(x) => Value::Int(x.into()),
+                // integers and floats are kept distinct, as they are in GML
+                serde_json::Value::Number(x) if x.is_f64() => {
+                    Value::Float(x.as_f64().unwrap() as f32)
+                }
+                serde_json::Value::Number(x) => Value::Int(
+                    x.as_i64()
+                        .and_then(|x| x.try_into().ok())
+                        .ok_or(format!("JSON attribute '{name}' {x} is out of range"))?,
+                ),
+                x => return Err(format!("JSON attribute '{name}' has unsupported value {x}")),
+            };
+            Ok((Cow::Owned(name), value))
+        })
+        .collect()
+}
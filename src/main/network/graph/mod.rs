@@ -1,9 +1,16 @@
+mod generate;
+mod graphml;
+mod ip_prefix;
+mod json;
+mod nat;
 mod petgraph_wrapper;
+pub mod report;
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::hash::Hash;
+use std::num::NonZeroU32;
 
 use crate::core::support::configuration::{
     self, Compression, FileSource, GraphOptions, GraphSource,
@@ -12,19 +19,43 @@ use crate::core::support::{units, units::Unit};
 use crate::network::graph::petgraph_wrapper::GraphWrapper;
 use crate::utility::tilde_expansion;
 
+pub use ip_prefix::IpPrefix;
+pub use nat::{NatConfig, NatType};
+
 use anyhow::Context;
 use log::*;
 use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 type NetGraphError = Box<dyn Error + Send + Sync + 'static>;
 
+/// The smallest MTU that an edge can have, which is the smallest MTU allowed for IPv4 (RFC 791).
+const MIN_MTU: u32 = 68;
+/// The largest MTU that an edge can have, which is the largest possible IPv4 packet.
+const MAX_MTU: u32 = 65535;
+
+/// The default signal propagation speed as a fraction of the speed of light, which is roughly the
+/// speed of light in optical fibre.
+pub const DEFAULT_PROPAGATION_SPEED: f64 = 0.67;
+/// The default smallest latency of an edge whose latency is computed from the coordinates of its
+/// nodes. The smallest edge latency limits the runahead, so nearby nodes must not get a tiny
+/// latency.
+const DEFAULT_MIN_PROPAGATION_LATENCY_NS: u64 = 1_000_000;
+
 /// A graph node.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ShadowNode {
     pub id: u32,
     pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
     pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+    /// The geographic location of the node, used to compute the latency of edges that don't
+    /// have a latency.
+    pub coordinates: Option<Coordinates>,
+    /// The prefix that the addresses of hosts assigned to this node are taken from.
+    pub ip_prefix: Option<IpPrefix>,
+    /// The NAT that translates the addresses of hosts assigned to this node.
+    pub nat: Option<NatConfig>,
 }
 
 impl TryFrom<gml_parser::gml::Node<'_>> for ShadowNode {
@@ -57,18 +88,162 @@ impl TryFrom<gml_parser::gml::Node<'_>> for ShadowNode {
                         .map_err(|e| format!("Node 'host_bandwidth_up' is not a valid unit: {}", e))
                 })
                 .transpose()?,
+            coordinates: Coordinates::from_gml_node(&mut gml_node)?,
+            ip_prefix: gml_node
+                .other
+                .remove("ip_prefix")
+                .map(|prefix| {
+                    prefix
+                        .as_str()
+                        .ok_or("Node 'ip_prefix' is not a string")?
+                        .parse()
+                        .map_err(|e| format!("Node 'ip_prefix' is not valid: {e}"))
+                })
+                .transpose()?,
+            nat: NatConfig::from_gml_node(&mut gml_node)?,
+        })
+    }
+}
+
+/// The latitude and longitude of a node in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f32,
+    pub longitude: f32,
+}
+
+impl Coordinates {
+    /// Get the coordinates from the node's `latitude` and `longitude` attributes, if it has them.
+    fn from_gml_node(gml_node: &mut gml_parser::gml::Node) -> Result<Option<Self>, String> {
+        let mut get = |name| match gml_node.other.remove(name) {
+            Some(gml_parser::gml::Value::Float(x)) => Ok(Some(x)),
+            Some(gml_parser::gml::Value::Int(x)) => Ok(Some(x as f32)),
+            Some(_) => Err(format!("Node '{name}' is not a number")),
+            None => Ok(None),
+        };
+
+        let (latitude, longitude) = match (get("latitude")?, get("longitude")?) {
+            (Some(latitude), Some(longitude)) => (latitude, longitude),
+            (None, None) => return Ok(None),
+            _ => return Err("Node 'latitude' and 'longitude' must be given together".into()),
+        };
+
+        if !(-90.0..=90.0).contains(&latitude) {
+            return Err("Node 'latitude' must be between -90 and 90".into());
+        }
+        if !(-180.0..=180.0).contains(&longitude) {
+            return Err("Node 'longitude' must be between -180 and 180".into());
+        }
+
+        Ok(Some(Self {
+            latitude,
+            longitude,
+        }))
+    }
+
+    /// The great-circle distance to `other` in kilometres.
+    pub fn distance_km(&self, other: &Self) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+
+        let (lat1, lon1) = (
+            f64::from(self.latitude).to_radians(),
+            f64::from(self.longitude).to_radians(),
+        );
+        let (lat2, lon2) = (
+            f64::from(other.latitude).to_radians(),
+            f64::from(other.longitude).to_radians(),
+        );
+
+        // the haversine formula
+        let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+    }
+}
+
+/// How the latency of an edge without a latency is computed from the coordinates of its nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PropagationModel {
+    /// The signal propagation speed as a fraction of the speed of light.
+    speed: f64,
+    /// A fixed latency added to every computed latency.
+    overhead_ns: u64,
+    /// The smallest computed latency.
+    min_latency_ns: u64,
+}
+
+impl PropagationModel {
+    pub fn new(
+        speed: f64,
+        overhead: units::Time<units::TimePrefix>,
+        min_latency: units::Time<units::TimePrefix>,
+    ) -> Result<Self, String> {
+        if !(speed > 0.0 && speed <= 1.0) {
+            return Err(format!(
+                "The propagation speed {speed} must be greater than 0 and at most 1"
+            ));
+        }
+
+        let min_latency_ns = min_latency.convert(units::TimePrefix::Nano)?.value();
+        if min_latency_ns == 0 {
+            return Err("The minimum propagation latency must be greater than 0".to_string());
+        }
+
+        Ok(Self {
+            speed,
+            overhead_ns: overhead.convert(units::TimePrefix::Nano)?.value(),
+            min_latency_ns,
         })
     }
+
+    /// The latency of an edge between nodes at `a` and `b`, which is at least the model's minimum
+    /// latency.
+    pub fn latency_ns(&self, a: &Coordinates, b: &Coordinates) -> u64 {
+        const SPEED_OF_LIGHT_KM_PER_NS: f64 = 299_792.458 / 1_000_000_000.0;
+
+        let propagation_ns = a.distance_km(b) / (SPEED_OF_LIGHT_KM_PER_NS * self.speed);
+        let latency_ns = (propagation_ns.round() as u64).saturating_add(self.overhead_ns);
+
+        // nodes that are close together or at the same location still need a realistic latency
+        std::cmp::max(latency_ns, self.min_latency_ns)
+    }
+}
+
+impl Default for PropagationModel {
+    /// The propagation speed in optical fibre, without any overhead and with a minimum latency of
+    /// 1 ms.
+    fn default() -> Self {
+        Self {
+            speed: DEFAULT_PROPAGATION_SPEED,
+            overhead_ns: 0,
+            min_latency_ns: DEFAULT_MIN_PROPAGATION_LATENCY_NS,
+        }
+    }
 }
 
 /// A graph edge.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ShadowEdge {
     pub source: u32,
     pub target: u32,
     pub latency: units::Time<units::TimePrefix>,
     pub jitter: units::Time<units::TimePrefix>,
     pub packet_loss: f32,
+    /// Probability that a packet is held back by `reorder_delay`.
+    pub packet_reorder: f32,
+    pub reorder_delay: units::Time<units::TimePrefix>,
+    /// Probability that a packet is duplicated.
+    pub packet_duplicate: f32,
+    /// Probability that a packet is corrupted.
+    pub packet_corrupt: f32,
+    /// The capacity of the link. Links without a bandwidth have unlimited capacity.
+    pub bandwidth: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+    /// The maximum number of bytes that can be queued at the link.
+    pub queue_size: Option<units::Bytes<units::SiPrefixUpper>>,
+    /// A bursty loss model applied in addition to `packet_loss`.
+    pub burst_loss: Option<GilbertElliott>,
+    /// The largest IP packet in bytes that can be sent over the link without being fragmented.
+    pub mtu: Option<u32>,
 }
 
 impl TryFrom<gml_parser::gml::Edge<'_>> for ShadowEdge {
@@ -98,26 +273,282 @@ impl TryFrom<gml_parser::gml::Edge<'_>> for ShadowEdge {
                 Some(x) => x.as_float().ok_or("Edge 'packet_loss' is not a float")?,
                 None => 0.0,
             },
+            packet_reorder: match gml_edge.other.remove("packet_reorder") {
+                Some(x) => x.as_float().ok_or("Edge 'packet_reorder' is not a float")?,
+                None => 0.0,
+            },
+            reorder_delay: match gml_edge.other.remove("reorder_delay") {
+                Some(x) => x
+                    .as_str()
+                    .ok_or("Edge 'reorder_delay' is not a string")?
+                    .parse()
+                    .map_err(|e| format!("Edge 'reorder_delay' is not a valid unit: {}", e))?,
+                None => units::Time::new(0, units::TimePrefix::Milli),
+            },
+            packet_duplicate: match gml_edge.other.remove("packet_duplicate") {
+                Some(x) => x
+                    .as_float()
+                    .ok_or("Edge 'packet_duplicate' is not a float")?,
+                None => 0.0,
+            },
+            packet_corrupt: match gml_edge.other.remove("packet_corrupt") {
+                Some(x) => x.as_float().ok_or("Edge 'packet_corrupt' is not a float")?,
+                None => 0.0,
+            },
+            bandwidth: gml_edge
+                .other
+                .remove("bandwidth")
+                .map(|bandwidth| {
+                    bandwidth
+                        .as_str()
+                        .ok_or("Edge 'bandwidth' is not a string")?
+                        .parse()
+                        .map_err(|e| format!("Edge 'bandwidth' is not a valid unit: {}", e))
+                })
+                .transpose()?,
+            queue_size: gml_edge
+                .other
+                .remove("queue_size")
+                .map(|size| {
+                    size.as_str()
+                        .ok_or("Edge 'queue_size' is not a string")?
+                        .parse()
+                        .map_err(|e| format!("Edge 'queue_size' is not a valid unit: {}", e))
+                })
+                .transpose()?,
+            burst_loss: GilbertElliott::from_gml_edge(&mut gml_edge)?,
+            mtu: gml_edge
+                .other
+                .remove("mtu")
+                .map(|mtu| {
+                    let mtu = mtu.as_int().ok_or("Edge 'mtu' is not an integer")?;
+                    u32::try_from(mtu).map_err(|_| "Edge 'mtu' must not be negative")
+                })
+                .transpose()?,
         };
 
-        if rv.packet_loss < 0f32 || rv.packet_loss > 1f32 {
-            return Err("Edge 'packet_loss' is not in the range [0,1]".into());
+        rv.validate()?;
+
+        Ok(rv)
+    }
+}
+
+impl ShadowEdge {
+    /// Check that the edge's properties are valid.
+    fn validate(&self) -> Result<(), String> {
+        for (name, x) in [
+            ("packet_loss", self.packet_loss),
+            ("packet_reorder", self.packet_reorder),
+            ("packet_duplicate", self.packet_duplicate),
+            ("packet_corrupt", self.packet_corrupt),
+        ] {
+            if !(0f32..=1f32).contains(&x) {
+                return Err(format!("Edge '{name}' is not in the range [0,1]"));
+            }
+        }
+
+        if self.packet_reorder > 0f32 && self.reorder_delay.value() == 0 {
+            return Err("Edge 'reorder_delay' must not be 0 if 'packet_reorder' is set".into());
         }
 
-        if rv.latency.value() == 0 {
+        if self.latency.value() == 0 {
             return Err("Edge 'latency' must not be 0".into());
         }
 
-        Ok(rv)
+        // the latency minus the jitter is the smallest possible packet delay, which must be
+        // positive
+        let latency_ns = self
+            .latency
+            .convert(units::TimePrefix::Nano)
+            .unwrap()
+            .value();
+        let jitter_ns = self
+            .jitter
+            .convert(units::TimePrefix::Nano)
+            .unwrap()
+            .value();
+        if jitter_ns >= latency_ns {
+            return Err("Edge 'jitter' must be less than the edge 'latency'".into());
+        }
+
+        if let Some(bandwidth) = self.bandwidth {
+            if bandwidth.value() == 0 {
+                return Err("Edge 'bandwidth' must not be 0".into());
+            }
+        }
+
+        if let Some(queue_size) = self.queue_size {
+            if self.bandwidth.is_none() {
+                return Err("Edge 'queue_size' requires the edge 'bandwidth' to be set".into());
+            }
+            if queue_size.value() == 0 {
+                return Err("Edge 'queue_size' must not be 0".into());
+            }
+        }
+
+        if let Some(burst_loss) = &self.burst_loss {
+            burst_loss.validate()?;
+        }
+
+        if let Some(mtu) = self.mtu {
+            if !(MIN_MTU..=MAX_MTU).contains(&mtu) {
+                return Err(format!(
+                    "Edge 'mtu' is not in the range [{MIN_MTU},{MAX_MTU}]"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The capacity of the link, or `None` if the link has unlimited capacity. If no queue size
+    /// was given, the queue holds 100 ms worth of traffic at the link's capacity, and at least one
+    /// full-sized packet.
+    pub fn capacity(&self) -> Option<LinkCapacity> {
+        let bits_per_sec = self
+            .bandwidth?
+            .convert(units::SiPrefixUpper::Base)
+            .unwrap()
+            .value();
+
+        let queue_bytes = match self.queue_size {
+            Some(size) => size.convert(units::SiPrefixUpper::Base).unwrap().value(),
+            None => std::cmp::max(bits_per_sec / 8 / 10, 1500),
+        };
+
+        Some(LinkCapacity {
+            bandwidth_bits: bits_per_sec,
+            queue_size_bytes: queue_bytes,
+        })
+    }
+}
+
+/// A two-state (Gilbert-Elliott) Markov model of bursty packet loss. The model is either in a
+/// good state or a bad state, and packets are lost with a different probability in each state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GilbertElliott {
+    /// Probability of moving from the good state to the bad state before each packet.
+    pub good_to_bad: f32,
+    /// Probability of moving from the bad state to the good state before each packet.
+    pub bad_to_good: f32,
+    /// Probability that a packet is lost in the good state.
+    pub loss_good: f32,
+    /// Probability that a packet is lost in the bad state.
+    pub loss_bad: f32,
+}
+
+impl GilbertElliott {
+    /// Remove the loss model attributes from a gml edge. Returns `None` if the edge doesn't have
+    /// a bursty loss model.
+    fn from_gml_edge(gml_edge: &mut gml_parser::gml::Edge) -> Result<Option<Self>, String> {
+        let mut take_float = |name: &str| {
+            gml_edge
+                .other
+                .remove(name)
+                .map(|x| {
+                    x.as_float()
+                        .ok_or_else(|| format!("Edge '{name}' is not a float"))
+                })
+                .transpose()
+        };
+
+        let good_to_bad = take_float("loss_good_to_bad")?;
+        let bad_to_good = take_float("loss_bad_to_good")?;
+        let loss_good = take_float("loss_good")?;
+        let loss_bad = take_float("loss_bad")?;
+
+        let Some(good_to_bad) = good_to_bad else {
+            if bad_to_good.is_some() || loss_good.is_some() || loss_bad.is_some() {
+                return Err(
+                    "Edge bursty loss attributes require the edge 'loss_good_to_bad' to be set"
+                        .into(),
+                );
+            }
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            good_to_bad,
+            bad_to_good: bad_to_good
+                .ok_or("Edge 'loss_bad_to_good' must be set when 'loss_good_to_bad' is set")?,
+            loss_good: loss_good.unwrap_or(0.0),
+            loss_bad: loss_bad.unwrap_or(1.0),
+        }))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, x) in [
+            ("loss_good_to_bad", self.good_to_bad),
+            ("loss_bad_to_good", self.bad_to_good),
+            ("loss_good", self.loss_good),
+            ("loss_bad", self.loss_bad),
+        ] {
+            if !(0f32..=1f32).contains(&x) {
+                return Err(format!("Edge '{name}' is not in the range [0,1]"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Move the model to its state for the next packet, and then decide if the packet is lost.
+    /// The current state is given by `bad` and is updated in place. Returns `true` if the packet
+    /// is lost.
+    pub fn step(&self, bad: &mut bool, rng: &mut impl rand::Rng) -> bool {
+        let transition = if *bad {
+            self.bad_to_good
+        } else {
+            self.good_to_bad
+        };
+        if rng.gen::<f32>() < transition {
+            *bad = !*bad;
+        }
+
+        let loss = if *bad { self.loss_bad } else { self.loss_good };
+        rng.gen::<f32>() < loss
+    }
+}
+
+/// A change to the state or properties of a graph edge. Properties that are `None` are left
+/// unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeChange {
+    pub up: Option<bool>,
+    pub latency: Option<units::Time<units::TimePrefix>>,
+    pub jitter: Option<units::Time<units::TimePrefix>>,
+    pub packet_loss: Option<f32>,
+}
+
+impl EdgeChange {
+    /// Apply the property changes to an edge, returning an error if the resulting edge is not
+    /// valid.
+    fn apply_to(&self, edge: &ShadowEdge) -> Result<ShadowEdge, String> {
+        let mut edge = edge.clone();
+
+        if let Some(latency) = self.latency {
+            edge.latency = latency;
+        }
+        if let Some(jitter) = self.jitter {
+            edge.jitter = jitter;
+        }
+        if let Some(packet_loss) = self.packet_loss {
+            edge.packet_loss = packet_loss;
+        }
+
+        edge.validate()?;
+        Ok(edge)
     }
 }
 
 /// A network graph containing the petgraph graph and a map from gml node ids to petgraph node
 /// indexes.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NetworkGraph {
     graph: GraphWrapper<ShadowNode, ShadowEdge, u32>,
     node_id_to_index_map: HashMap<u32, NodeIndex>,
+    /// Edges that have been taken down, keyed by their gml source and target node ids. These are
+    /// removed from the graph until they are brought back up.
+    down_edges: HashMap<(u32, u32), ShadowEdge>,
 }
 
 impl NetworkGraph {
@@ -133,9 +564,32 @@ impl NetworkGraph {
         self.graph.node_weight(index).map(|w| w.id)
     }
 
+    /// Parse a GML graph, using the default [`PropagationModel`].
     pub fn parse(graph_text: &str) -> Result<Self, NetGraphError> {
-        let gml_graph = gml_parser::parse(graph_text)?;
+        Self::from_gml(gml_parser::parse(graph_text)?, &PropagationModel::default())
+    }
+
+    /// Parse a graph in the given format. The latencies of edges without a latency are computed
+    /// from the coordinates of their nodes using `propagation`.
+    pub fn parse_as(
+        graph_text: &str,
+        format: GraphFormat,
+        propagation: &PropagationModel,
+    ) -> Result<Self, NetGraphError> {
+        let gml_graph = match format {
+            GraphFormat::Gml => gml_parser::parse(graph_text)?,
+            GraphFormat::Graphml => graphml::parse(graph_text)?,
+            GraphFormat::Json => json::parse(graph_text)?,
+        };
+        Self::from_gml(gml_graph, propagation)
+    }
 
+    /// Build the graph from a parsed GML graph. Graphs in other formats are converted to GML
+    /// graphs so that their nodes and edges are validated in the same way.
+    fn from_gml(
+        gml_graph: gml_parser::gml::Gml,
+        propagation: &PropagationModel,
+    ) -> Result<Self, NetGraphError> {
         let mut g = match gml_graph.directed {
             true => GraphWrapper::Directed(
                 petgraph::graph::Graph::<_, _, petgraph::Directed, _>::with_capacity(
@@ -163,7 +617,48 @@ impl NetworkGraph {
             id_map.insert(gml_id, petgraph_id);
         }
 
-        for x in gml_graph.edges.into_iter() {
+        // each address must belong to at most one node's prefix; since prefixes are either nested
+        // or disjoint, it's enough to compare each prefix with the next one in address order
+        let mut prefixes: Vec<(IpPrefix, u32)> = g
+            .raw_nodes()
+            .iter()
+            .filter_map(|x| Some((x.weight.ip_prefix?, x.weight.id)))
+            .collect();
+        prefixes.sort_by_key(|(prefix, _)| (prefix.network(), prefix.prefix_len()));
+        for pair in prefixes.windows(2) {
+            let ((a, a_id), (b, b_id)) = (pair[0], pair[1]);
+            if a.overlaps(&b) {
+                return Err(format!(
+                    "The IP prefix {a} of node {a_id} overlaps the IP prefix {b} of node {b_id}"
+                )
+                .into());
+            }
+        }
+
+        for mut x in gml_graph.edges.into_iter() {
+            // compute the latency of edges without a latency from the coordinates of their nodes
+            if !x.other.contains_key("latency") {
+                let coordinates = |id| {
+                    let node = id_map.get(&id).and_then(|x| g.node_weight(*x));
+                    node.and_then(|x: &ShadowNode| x.coordinates)
+                };
+                if let (Some(a), Some(b)) = (coordinates(x.source), coordinates(x.target)) {
+                    // a self-loop models the network within a node, not the distance to another
+                    if x.source == x.target {
+                        return Err(format!(
+                            "The self-loop edge of node {} must have a 'latency'",
+                            x.source
+                        )
+                        .into());
+                    }
+                    let latency = propagation.latency_ns(&a, &b);
+                    x.other.insert(
+                        "latency".into(),
+                        gml_parser::gml::Value::Str(format!("{latency} ns").into()),
+                    );
+                }
+            }
+
             let x: ShadowEdge = x.try_into()?;
 
             let source = *id_map
@@ -179,46 +674,80 @@ impl NetworkGraph {
         Ok(Self {
             graph: g,
             node_id_to_index_map: id_map,
+            down_edges: HashMap::new(),
         })
     }
 
+    /// Change the state or properties of the edge between two nodes. Returns the properties of
+    /// the edge after the change.
+    pub fn apply_edge_change(
+        &mut self,
+        source: u32,
+        target: u32,
+        change: &EdgeChange,
+    ) -> Result<PathProperties, NetGraphError> {
+        let src = *self
+            .node_id_to_index(source)
+            .ok_or(format!("Node {} doesn't exist", source))?;
+        let dst = *self
+            .node_id_to_index(target)
+            .ok_or(format!("Node {} doesn't exist", target))?;
+
+        if let Some(index) = self.graph.find_edge(src, dst) {
+            let edge = change.apply_to(self.graph.edge_weight(index).unwrap())?;
+            let properties = (&edge).into();
+
+            if change.up == Some(false) {
+                self.graph.remove_edge(index).unwrap();
+                self.down_edges.insert((edge.source, edge.target), edge);
+            } else {
+                *self.graph.edge_weight_mut(index).unwrap() = edge;
+            }
+
+            return Ok(properties);
+        }
+
+        // in an undirected graph, the edge may have been given in the opposite direction
+        let key = [(source, target), (target, source)]
+            .into_iter()
+            .take(if self.graph.is_directed() { 1 } else { 2 })
+            .find(|key| self.down_edges.contains_key(key))
+            .ok_or(format!("No edge connecting node {} to {}", source, target))?;
+
+        let edge = change.apply_to(&self.down_edges[&key])?;
+        let properties = (&edge).into();
+
+        if change.up == Some(true) {
+            self.down_edges.remove(&key);
+            self.graph.add_edge(src, dst, edge);
+        } else {
+            self.down_edges.insert(key, edge);
+        }
+
+        Ok(properties)
+    }
+
     pub fn compute_shortest_paths(
         &self,
         nodes: &[NodeIndex],
     ) -> Result<HashMap<(NodeIndex, NodeIndex), PathProperties>, NetGraphError> {
         let start = std::time::Instant::now();
 
+        let dsts: HashSet<NodeIndex> = nodes.iter().copied().collect();
+
         // calculate shortest paths
-        let mut paths: HashMap<(_, _), PathProperties> = nodes
+        let paths: Vec<_> = nodes
             .into_par_iter()
-            .flat_map(|src| {
-                match &self.graph {
-                    GraphWrapper::Directed(graph) => {
-                        petgraph::algo::dijkstra(&graph, *src, None, |e| e.weight().into())
-                    }
-                    GraphWrapper::Undirected(graph) => {
-                        petgraph::algo::dijkstra(&graph, *src, None, |e| e.weight().into())
-                    }
-                }
-                .into_iter()
-                // ignore nodes that aren't in use
-                .filter(|(dst, _)| nodes.contains(dst))
-                // include the src node
-                .map(|(dst, path)| ((*src, dst), path))
-                .collect::<HashMap<(_, _), _>>()
+            .map(|src| {
+                let distances = self.distances_from(*src);
+                let paths = self.paths_from(*src, Some(&distances), &dsts)?;
+                Ok(paths.into_iter().map(|(dst, path)| ((*src, dst), path)))
             })
-            .collect();
-
-        // use the self-loop for paths from a node to itself
-        for node in nodes {
-            // the dijkstra shortest path from node -> node will always be 0
-            assert_eq!(paths[&(*node, *node)], PathProperties::default());
-
-            // there must be a single self-loop for each node
-            paths.insert((*node, *node), self.get_edge_weight(node, node)?.into());
-        }
+            .collect::<Result<_, NetGraphError>>()?;
+        let paths: HashMap<_, _> = paths.into_iter().flatten().collect();
 
-        assert_eq!(paths.len(), nodes.len().pow(2));
+        // pairs of nodes that aren't connected have no path
+        assert!(paths.len() <= nodes.len().pow(2));
 
         debug!(
             "Finished computing shortest paths: {} seconds, {} entries",
@@ -235,14 +764,16 @@ impl NetworkGraph {
     ) -> Result<HashMap<(NodeIndex, NodeIndex), PathProperties>, NetGraphError> {
         let start = std::time::Instant::now();
 
-        let paths: HashMap<_, _> = nodes
-            .iter()
-            .flat_map(|src| nodes.iter().map(move |dst| (*src, *dst)))
-            // we require the graph to be connected with exactly one edge between any two nodes
-            .map(|(src, dst)| Ok(((src, dst), self.get_edge_weight(&src, &dst)?.into())))
-            .collect::<Result<_, NetGraphError>>()?;
+        let dsts: HashSet<NodeIndex> = nodes.iter().copied().collect();
+
+        let mut paths = HashMap::new();
+        for src in nodes {
+            for (dst, path) in self.paths_from(*src, None, &dsts)? {
+                paths.insert((*src, dst), path);
+            }
+        }
 
-        assert_eq!(paths.len(), nodes.len().pow(2));
+        assert!(paths.len() <= nodes.len().pow(2));
 
         debug!(
             "Finished computing direct paths: {} seconds, {} entries",
@@ -253,101 +784,667 @@ impl NetworkGraph {
         Ok(paths)
     }
 
-    /// Get the weight for the edge between two nodes. Returns an error if there
-    /// is not exactly one edge between them.
-    fn get_edge_weight(
+    /// Get the links along the path between each pair of nodes that have a limited capacity or a
+    /// bursty loss model. The paths are the same as those returned by
+    /// [`Self::compute_shortest_paths`] or [`Self::get_direct_paths`]. Paths that don't cross any
+    /// such links are not included.
+    pub fn compute_path_links(
         &self,
-        src: &NodeIndex,
-        dst: &NodeIndex,
-    ) -> Result<&ShadowEdge, NetGraphError> {
-        let src_id = self.node_index_to_id(*src).unwrap();
-        let dst_id = self.node_index_to_id(*dst).unwrap();
-        match &self.graph {
-            GraphWrapper::Directed(graph) => {
-                let mut edges = graph.edges_connecting(*src, *dst);
-                let edge = edges
-                    .next()
-                    .ok_or(format!("No edge connecting node {} to {}", src_id, dst_id))?;
-                if edges.count() != 0 {
-                    return Err(format!(
-                        "More than one edge connecting node {} to {}",
-                        src_id, dst_id
-                    )
-                    .into());
-                }
-                Ok(edge.weight())
-            }
-            GraphWrapper::Undirected(graph) => {
-                let mut edges = graph.edges_connecting(*src, *dst);
-                let edge = edges
-                    .next()
-                    .ok_or(format!("No edge connecting node {} to {}", src_id, dst_id))?;
-                if edges.count() != 0 {
-                    return Err(format!(
-                        "More than one edge connecting node {} to {}",
-                        src_id, dst_id
-                    )
-                    .into());
-                }
-                Ok(edge.weight())
-            }
+        nodes: &[NodeIndex],
+        use_shortest_paths: bool,
+    ) -> Result<HashMap<(NodeIndex, NodeIndex), Vec<PathLink>>, NetGraphError> {
+        if !self.has_stateful_links() {
+            return Ok(HashMap::new());
         }
-    }
-}
 
-/// Network characteristics for a path between two nodes.
-#[derive(Debug, Default, Clone, Copy)]
-pub struct PathProperties {
-    /// Latency in nanoseconds.
-    pub latency_ns: u64,
-    /// Packet loss as fraction.
-    pub packet_loss: f32,
-}
+        let start = std::time::Instant::now();
 
-impl PartialOrd for PathProperties {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        // order by lowest latency first, then by lowest packet loss
-        match self.latency_ns.cmp(&other.latency_ns) {
-            std::cmp::Ordering::Equal => self.packet_loss.partial_cmp(&other.packet_loss),
-            x => Some(x),
+        let dsts: HashSet<NodeIndex> = nodes.iter().copied().collect();
+
+        let mut links = HashMap::new();
+        for src in nodes {
+            let distances = use_shortest_paths.then(|| self.distances_from(*src));
+            for (dst, path_links) in self.links_from(*src, distances.as_ref(), &dsts)? {
+                links.insert((*src, dst), path_links);
+            }
         }
-    }
-}
 
-impl PartialEq for PathProperties {
-    fn eq(&self, other: &Self) -> bool {
-        // PartialEq must be consistent with PartialOrd
-        self.partial_cmp(other) == Some(std::cmp::Ordering::Equal)
+        debug!(
+            "Finished computing path links: {} seconds, {} entries",
+            (std::time::Instant::now() - start).as_secs(),
+            links.len()
+        );
+
+        Ok(links)
     }
-}
 
-impl core::ops::Add for PathProperties {
-    type Output = Self;
+    /// Compute the paths from a single node to each of `dsts`, and the links along them that have
+    /// a limited capacity or a bursty loss model. Destinations without a path are not included.
+    pub fn compute_paths_from(
+        &self,
+        src: NodeIndex,
+        dsts: &HashSet<NodeIndex>,
+        use_shortest_paths: bool,
+        use_ecmp: bool,
+    ) -> Result<SourcePaths, NetGraphError> {
+        let distances = use_shortest_paths.then(|| self.distances_from(src));
+
+        let links = if self.has_stateful_links() {
+            self.links_from(src, distances.as_ref(), dsts)?
+        } else {
+            HashMap::new()
+        };
 
-    fn add(self, other: Self) -> Self::Output {
-        Self {
-            latency_ns: self.latency_ns + other.latency_ns,
-            packet_loss: 1f32 - (1f32 - self.packet_loss) * (1f32 - other.packet_loss),
-        }
-    }
-}
+        let equal_cost = match &distances {
+            Some(distances) if use_ecmp => self.equal_cost_paths_from(src, distances, dsts),
+            _ => HashMap::new(),
+        };
 
-impl std::convert::From<&ShadowEdge> for PathProperties {
-    fn from(e: &ShadowEdge) -> Self {
-        Self {
-            latency_ns: e.latency.convert(units::TimePrefix::Nano).unwrap().value(),
-            packet_loss: e.packet_loss,
-        }
+        Ok(SourcePaths {
+            paths: self.paths_from(src, distances.as_ref(), dsts)?,
+            links,
+            equal_cost,
+        })
     }
-}
 
-#[derive(Debug)]
-pub struct IpPreviouslyAssignedError;
-impl std::error::Error for IpPreviouslyAssignedError {}
+    /// Get every shortest path between each pair of nodes, for pairs of nodes that have more than
+    /// one path with the same latency. See [`EqualCostPath`].
+    pub fn compute_equal_cost_paths(
+        &self,
+        nodes: &[NodeIndex],
+    ) -> HashMap<(NodeIndex, NodeIndex), Vec<EqualCostPath>> {
+        let start = std::time::Instant::now();
+
+        let dsts: HashSet<NodeIndex> = nodes.iter().copied().collect();
+
+        let paths: HashMap<_, _> = nodes
+            .into_par_iter()
+            .flat_map_iter(|src| {
+                let distances = self.distances_from(*src);
+                self.equal_cost_paths_from(*src, &distances, &dsts)
+                    .into_iter()
+                    .map(|(dst, paths)| ((*src, dst), paths))
+            })
+            .collect();
+
+        debug!(
+            "Finished computing equal-cost paths: {} seconds, {} entries",
+            (std::time::Instant::now() - start).as_secs(),
+            paths.len()
+        );
+
+        paths
+    }
+
+    /// Check that there is a path between every pair of `nodes`, without computing the paths.
+    pub fn check_connected(
+        &self,
+        nodes: &[NodeIndex],
+        use_shortest_paths: bool,
+    ) -> Result<(), NetGraphError> {
+        let no_path = |src: NodeIndex, dst: NodeIndex| -> NetGraphError {
+            format!(
+                "No path from graph node {} to graph node {}",
+                self.node_index_to_id(src).unwrap(),
+                self.node_index_to_id(dst).unwrap(),
+            )
+            .into()
+        };
+
+        if !use_shortest_paths {
+            for src in nodes {
+                for dst in nodes {
+                    if self.get_edge_weight(src, dst)?.is_none() {
+                        return Err(no_path(*src, *dst));
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        // the path from a node to itself is its self-loop
+        for node in nodes {
+            if self.get_edge_weight(node, node)?.is_none() {
+                return Err(no_path(*node, *node));
+            }
+        }
+
+        let Some(root) = nodes.first() else {
+            return Ok(());
+        };
+
+        // if every node can reach the root and the root can reach every node, then every node
+        // can reach every other node
+        let (from_root, to_root) = match &self.graph {
+            GraphWrapper::Directed(graph) => (
+                reachable(graph, *root),
+                reachable(petgraph::visit::Reversed(graph), *root),
+            ),
+            GraphWrapper::Undirected(graph) => {
+                let from_root = reachable(graph, *root);
+                (from_root.clone(), from_root)
+            }
+        };
+
+        for node in nodes {
+            if !from_root.contains(node) {
+                return Err(no_path(*root, *node));
+            }
+            if !to_root.contains(node) {
+                return Err(no_path(*node, *root));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether any edge has a limited capacity or a bursty loss model.
+    fn has_stateful_links(&self) -> bool {
+        self.graph
+            .raw_edges()
+            .iter()
+            .any(|e| e.weight.bandwidth.is_some() || e.weight.burst_loss.is_some())
+    }
+
+    /// Run Dijkstra's shortest path algorithm from `src`.
+    fn distances_from(&self, src: NodeIndex) -> HashMap<NodeIndex, PathProperties> {
+        match &self.graph {
+            GraphWrapper::Directed(graph) => {
+                petgraph::algo::dijkstra(&graph, src, None, |e| e.weight().into())
+            }
+            GraphWrapper::Undirected(graph) => {
+                petgraph::algo::dijkstra(&graph, src, None, |e| e.weight().into())
+            }
+        }
+    }
+
+    /// Get the paths from `src` to each of `dsts`. If `distances` from `src` are given, the
+    /// shortest paths are used. Otherwise the direct edges between nodes are used.
+    fn paths_from(
+        &self,
+        src: NodeIndex,
+        distances: Option<&HashMap<NodeIndex, PathProperties>>,
+        dsts: &HashSet<NodeIndex>,
+    ) -> Result<HashMap<NodeIndex, PathProperties>, NetGraphError> {
+        let Some(distances) = distances else {
+            let mut paths = HashMap::new();
+            for dst in dsts {
+                // pairs of nodes without an edge between them have no path
+                if let Some(edge) = self.get_edge_weight(&src, dst)? {
+                    paths.insert(*dst, edge.into());
+                }
+            }
+            return Ok(paths);
+        };
+
+        let mut paths: HashMap<_, _> = distances
+            .iter()
+            // ignore nodes that aren't in use
+            .filter(|(dst, _)| dsts.contains(dst))
+            .map(|(dst, path)| (*dst, *path))
+            .collect();
+
+        // use the self-loop for the path from the node to itself
+        if dsts.contains(&src) {
+            // the dijkstra shortest path from node -> node will always be 0
+            assert_eq!(paths[&src], PathProperties::default());
+
+            // there must be at most one self-loop for each node, and without one there is no path
+            // from the node to itself
+            match self.get_edge_weight(&src, &src)? {
+                Some(edge) => paths.insert(src, edge.into()),
+                None => paths.remove(&src),
+            };
+        }
+
+        Ok(paths)
+    }
+
+    /// Get the links from `src` to each of `dsts` that have a limited capacity or a bursty loss
+    /// model. If `distances` from `src` are given, the shortest paths are used. Otherwise the
+    /// direct edges between nodes are used.
+    fn links_from(
+        &self,
+        src: NodeIndex,
+        distances: Option<&HashMap<NodeIndex, PathProperties>>,
+        dsts: &HashSet<NodeIndex>,
+    ) -> Result<HashMap<NodeIndex, Vec<PathLink>>, NetGraphError> {
+        let mut links = HashMap::new();
+
+        for dst in dsts {
+            let edges = match distances {
+                Some(distances) if src != *dst => {
+                    if !distances.contains_key(dst) {
+                        continue;
+                    }
+                    match &self.graph {
+                        GraphWrapper::Directed(graph) => {
+                            shortest_path_edges(graph, src, *dst, distances)
+                        }
+                        GraphWrapper::Undirected(graph) => {
+                            shortest_path_edges(graph, src, *dst, distances)
+                        }
+                    }
+                }
+                // direct paths and paths from a node to itself (the self-loop) are a single edge
+                _ => match self.get_edge_weight(&src, dst)? {
+                    Some(edge) => vec![(src, *dst, edge)],
+                    None => continue,
+                },
+            };
+
+            let path_links = self.stateful_links(&edges);
+            if !path_links.is_empty() {
+                links.insert(*dst, path_links);
+            }
+        }
+
+        Ok(links)
+    }
+
+    /// Get every path from `src` to each of `dsts` that has the same latency as the shortest
+    /// path, given the shortest path distances from `src`. Destinations with only one such path
+    /// are not included. At most [`MAX_EQUAL_COST_PATHS`] paths are kept for each destination,
+    /// ordered from lowest to highest packet loss.
+    fn equal_cost_paths_from(
+        &self,
+        src: NodeIndex,
+        distances: &HashMap<NodeIndex, PathProperties>,
+        dsts: &HashSet<NodeIndex>,
+    ) -> HashMap<NodeIndex, Vec<EqualCostPath>> {
+        let mut equal_cost = HashMap::new();
+
+        for dst in dsts {
+            // the path from a node to itself is always its self-loop
+            if *dst == src || !distances.contains_key(dst) {
+                continue;
+            }
+
+            let paths = match &self.graph {
+                GraphWrapper::Directed(graph) => {
+                    equal_cost_path_edges(graph, src, *dst, distances, MAX_EQUAL_COST_PATHS)
+                }
+                GraphWrapper::Undirected(graph) => {
+                    equal_cost_path_edges(graph, src, *dst, distances, MAX_EQUAL_COST_PATHS)
+                }
+            };
+
+            if paths.len() <= 1 {
+                continue;
+            }
+
+            let mut paths: Vec<_> = paths
+                .into_iter()
+                .map(|edges| {
+                    let properties = edges
+                        .iter()
+                        .map(|(_, _, edge)| PathProperties::from(*edge))
+                        .fold(PathProperties::default(), |a, b| a + b);
+                    let nodes = std::iter::once(src)
+                        .chain(edges.iter().map(|(_, to, _)| *to))
+                        .map(|x| self.node_index_to_id(x).unwrap())
+                        .collect();
+                    let links = self.stateful_links(&edges);
+                    EqualCostPath {
+                        nodes,
+                        properties,
+                        links: (!links.is_empty()).then(|| links.into()),
+                    }
+                })
+                .collect();
+
+            // a stable sort so that paths with the same loss keep the order they were found in
+            paths.sort_by(|a, b| {
+                a.properties
+                    .packet_loss
+                    .partial_cmp(&b.properties.packet_loss)
+                    .unwrap()
+            });
+
+            equal_cost.insert(*dst, paths);
+        }
+
+        equal_cost
+    }
+
+    /// Get the links of `edges` that have a limited capacity or a bursty loss model, where
+    /// `edges` are the edges of a path in the order that packets cross them.
+    fn stateful_links(&self, edges: &[(NodeIndex, NodeIndex, &ShadowEdge)]) -> Vec<PathLink> {
+        let mut offset_ns = 0;
+        let mut path_links = Vec::new();
+        for (from, to, edge) in edges {
+            if edge.bandwidth.is_some() || edge.burst_loss.is_some() {
+                path_links.push(PathLink {
+                    source: self.node_index_to_id(*from).unwrap(),
+                    target: self.node_index_to_id(*to).unwrap(),
+                    offset_ns,
+                    capacity: edge.capacity(),
+                    burst_loss: edge.burst_loss,
+                });
+            }
+            offset_ns += PathProperties::from(*edge).latency_ns;
+        }
+        path_links
+    }
+
+    /// Get the weight for the edge between two nodes. Returns `None` if there is no edge between
+    /// them, and an error if there is more than one edge between them.
+    fn get_edge_weight(
+        &self,
+        src: &NodeIndex,
+        dst: &NodeIndex,
+    ) -> Result<Option<&ShadowEdge>, NetGraphError> {
+        let src_id = self.node_index_to_id(*src).unwrap();
+        let dst_id = self.node_index_to_id(*dst).unwrap();
+        match &self.graph {
+            GraphWrapper::Directed(graph) => {
+                let mut edges = graph.edges_connecting(*src, *dst);
+                let Some(edge) = edges.next() else {
+                    return Ok(None);
+                };
+                if edges.count() != 0 {
+                    return Err(format!(
+                        "More than one edge connecting node {} to {}",
+                        src_id, dst_id
+                    )
+                    .into());
+                }
+                Ok(Some(edge.weight()))
+            }
+            GraphWrapper::Undirected(graph) => {
+                let mut edges = graph.edges_connecting(*src, *dst);
+                let Some(edge) = edges.next() else {
+                    return Ok(None);
+                };
+                if edges.count() != 0 {
+                    return Err(format!(
+                        "More than one edge connecting node {} to {}",
+                        src_id, dst_id
+                    )
+                    .into());
+                }
+                Ok(Some(edge.weight()))
+            }
+        }
+    }
+}
+
+/// Network characteristics for a path between two nodes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PathProperties {
+    /// Latency in nanoseconds.
+    pub latency_ns: u64,
+    /// Maximum deviation from the latency in nanoseconds. Always less than the latency.
+    pub jitter_ns: u64,
+    /// Packet loss as fraction.
+    pub packet_loss: f32,
+    /// Fraction of packets that are held back by an extra `reorder_delay_ns`.
+    pub reorder: f32,
+    /// Extra delay of reordered packets in nanoseconds.
+    pub reorder_delay_ns: u64,
+    /// Fraction of packets that are duplicated.
+    pub duplicate: f32,
+    /// Fraction of packets that are corrupted.
+    pub corrupt: f32,
+    /// The smallest MTU of the path's edges, or `None` if no edge has an MTU.
+    pub mtu: Option<u32>,
+}
+
+impl PathProperties {
+    /// The smallest possible delay for a packet sent along this path, in nanoseconds.
+    pub fn min_latency_ns(&self) -> u64 {
+        self.latency_ns - self.jitter_ns
+    }
+}
+
+impl PartialOrd for PathProperties {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        // order by lowest latency first, then by lowest packet loss
+        match self.latency_ns.cmp(&other.latency_ns) {
+            std::cmp::Ordering::Equal => self.packet_loss.partial_cmp(&other.packet_loss),
+            x => Some(x),
+        }
+    }
+}
+
+impl PartialEq for PathProperties {
+    fn eq(&self, other: &Self) -> bool {
+        // PartialEq must be consistent with PartialOrd
+        self.partial_cmp(other) == Some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl core::ops::Add for PathProperties {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self {
+            latency_ns: self.latency_ns + other.latency_ns,
+            // the jitter bounds of each edge add up, so the path's jitter is still less than its
+            // latency
+            jitter_ns: self.jitter_ns + other.jitter_ns,
+            packet_loss: 1f32 - (1f32 - self.packet_loss) * (1f32 - other.packet_loss),
+            // a packet is held back at most once on a path, by the largest delay of its edges
+            reorder: 1f32 - (1f32 - self.reorder) * (1f32 - other.reorder),
+            reorder_delay_ns: std::cmp::max(self.reorder_delay_ns, other.reorder_delay_ns),
+            duplicate: 1f32 - (1f32 - self.duplicate) * (1f32 - other.duplicate),
+            corrupt: 1f32 - (1f32 - self.corrupt) * (1f32 - other.corrupt),
+            mtu: match (self.mtu, other.mtu) {
+                (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+}
+
+impl std::convert::From<&ShadowEdge> for PathProperties {
+    fn from(e: &ShadowEdge) -> Self {
+        Self {
+            latency_ns: e.latency.convert(units::TimePrefix::Nano).unwrap().value(),
+            jitter_ns: e.jitter.convert(units::TimePrefix::Nano).unwrap().value(),
+            packet_loss: e.packet_loss,
+            reorder: e.packet_reorder,
+            reorder_delay_ns: e
+                .reorder_delay
+                .convert(units::TimePrefix::Nano)
+                .unwrap()
+                .value(),
+            duplicate: e.packet_duplicate,
+            corrupt: e.packet_corrupt,
+            mtu: e.mtu,
+        }
+    }
+}
+
+/// Get the nodes that can be reached from `src`, including `src`.
+fn reachable<G>(graph: G, src: NodeIndex) -> HashSet<NodeIndex>
+where
+    G: petgraph::visit::IntoNeighbors<NodeId = NodeIndex> + petgraph::visit::Visitable,
+{
+    let mut nodes = HashSet::new();
+    let mut bfs = petgraph::visit::Bfs::new(graph, src);
+    while let Some(node) = bfs.next(graph) {
+        nodes.insert(node);
+    }
+    nodes
+}
+
+/// Find the edges along each path from `src` to `dst` that has the same latency as the shortest
+/// path, given the shortest path distances from `src`. At most `max_paths` paths are returned,
+/// each in order from `src` to `dst`.
+fn equal_cost_path_edges<'a, Ty: petgraph::EdgeType>(
+    graph: &'a petgraph::graph::Graph<ShadowNode, ShadowEdge, Ty, u32>,
+    src: NodeIndex,
+    dst: NodeIndex,
+    distances: &HashMap<NodeIndex, PathProperties>,
+    max_paths: usize,
+) -> Vec<Vec<(NodeIndex, NodeIndex, &'a ShadowEdge)>> {
+    let mut paths = Vec::new();
+    let mut stack = vec![(dst, Vec::new())];
+
+    // walk backwards from the destination, following every edge whose latency is on a shortest
+    // path; edges have a non-zero latency, so this can't loop
+    while let Some((current, edges)) = stack.pop() {
+        if current == src {
+            let mut edges = edges;
+            edges.reverse();
+            paths.push(edges);
+            if paths.len() == max_paths {
+                break;
+            }
+            continue;
+        }
+
+        let latency_ns = distances[&current].latency_ns;
+        let incoming: Vec<_> = graph
+            .edges_directed(current, petgraph::Direction::Incoming)
+            .filter(|e| {
+                e.source() != current
+                    && distances
+                        .get(&e.source())
+                        .map(|d| {
+                            d.latency_ns + PathProperties::from(e.weight()).latency_ns == latency_ns
+                        })
+                        .unwrap_or(false)
+            })
+            .collect();
+
+        // pushed in reverse so that the edges are followed in the graph's order
+        for e in incoming.into_iter().rev() {
+            let mut edges = edges.clone();
+            edges.push((e.source(), current, e.weight()));
+            stack.push((e.source(), edges));
+        }
+    }
+
+    paths
+}
+
+/// Find the edges along a shortest path from `src` to `dst` given the shortest path distances
+/// from `src`, in order from `src` to `dst`. Each edge is returned along with the nodes it's
+/// traversed from and to.
+fn shortest_path_edges<'a, Ty: petgraph::EdgeType>(
+    graph: &'a petgraph::graph::Graph<ShadowNode, ShadowEdge, Ty, u32>,
+    src: NodeIndex,
+    dst: NodeIndex,
+    distances: &HashMap<NodeIndex, PathProperties>,
+) -> Vec<(NodeIndex, NodeIndex, &'a ShadowEdge)> {
+    let mut edges = Vec::new();
+    let mut current = dst;
+
+    // walk backwards from the destination, following any edge that lies on a shortest path
+    while current != src {
+        let edge = graph
+            .edges_directed(current, petgraph::Direction::Incoming)
+            .find(|e| {
+                e.source() != current
+                    && distances
+                        .get(&e.source())
+                        .map(|d| *d + e.weight().into() == distances[&current])
+                        .unwrap_or(false)
+            })
+            .expect("A shortest path should exist to the destination");
+
+        edges.push((edge.source(), current, edge.weight()));
+        current = edge.source();
+    }
+
+    edges.reverse();
+    edges
+}
+
+/// The capacity of a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkCapacity {
+    /// Capacity of the link in bits per second.
+    pub bandwidth_bits: u64,
+    /// Maximum number of bytes that can be queued at the link.
+    pub queue_size_bytes: u64,
+}
+
+/// A link along a path between two nodes that has a limited capacity or a bursty loss model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathLink {
+    /// The node that packets enter the link from.
+    pub source: u32,
+    /// The node that packets leave the link to.
+    pub target: u32,
+    /// Latency from the start of the path to the start of the link in nanoseconds.
+    pub offset_ns: u64,
+    /// The capacity of the link, or `None` if its capacity is unlimited.
+    pub capacity: Option<LinkCapacity>,
+    /// The bursty loss model of the link.
+    pub burst_loss: Option<GilbertElliott>,
+}
+
+/// The paths from a single node, as returned by [`NetworkGraph::compute_paths_from`].
+#[derive(Debug, Clone, Default)]
+pub struct SourcePaths {
+    /// The path to each destination node.
+    pub paths: HashMap<NodeIndex, PathProperties>,
+    /// The links along each path that have a limited capacity or a bursty loss model. Paths
+    /// without any are not included.
+    pub links: HashMap<NodeIndex, Vec<PathLink>>,
+    /// Every path to each destination node that has the same latency as the shortest path, for
+    /// destinations with more than one such path.
+    pub equal_cost: HashMap<NodeIndex, Vec<EqualCostPath>>,
+}
+
+/// The most paths with the same latency that are kept between two nodes when using equal-cost
+/// multipath routing.
+pub const MAX_EQUAL_COST_PATHS: usize = 16;
+
+/// One of several paths between two nodes that have the same latency. Packets of a flow always
+/// take the same path, but different flows may take different paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EqualCostPath {
+    /// The nodes along the path, including the source and destination nodes.
+    pub nodes: Vec<u32>,
+    pub properties: PathProperties,
+    /// The links along the path that have a limited capacity or a bursty loss model, if any.
+    pub links: Option<PathLinks>,
+}
+
+/// Identifies a link along a path by the path's (source, destination) nodes and the link's
+/// (source, target) nodes.
+pub type PathLinkId = ((u32, u32), (u32, u32));
+
+/// The links along a path that have a limited capacity or a bursty loss model, shared between
+/// threads.
+pub type PathLinks = std::sync::Arc<[PathLink]>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpAssignmentError {
+    /// The address has already been assigned.
+    PreviouslyAssigned,
+    /// The address is in the IP prefix of a different node.
+    InOtherPrefix { prefix: IpPrefix, node: String },
+    /// Every address in the node's IP prefix has already been assigned.
+    PrefixExhausted { prefix: IpPrefix },
+    /// Every address outside of the nodes' IP prefixes has already been assigned.
+    AddressSpaceExhausted,
+}
+
+impl std::error::Error for IpAssignmentError {}
 
-impl std::fmt::Display for IpPreviouslyAssignedError {
+impl std::fmt::Display for IpAssignmentError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "IP address has already been assigned")
+        match self {
+            Self::PreviouslyAssigned => write!(f, "IP address has already been assigned"),
+            Self::InOtherPrefix { prefix, node } => {
+                write!(f, "IP address is in the IP prefix {prefix} of node {node}")
+            }
+            Self::PrefixExhausted { prefix } => {
+                write!(
+                    f,
+                    "All addresses in the IP prefix {prefix} have been assigned"
+                )
+            }
+            Self::AddressSpaceExhausted => write!(
+                f,
+                "All addresses outside of the nodes' IP prefixes have been assigned"
+            ),
+        }
     }
 }
 
@@ -358,6 +1455,10 @@ pub struct IpAssignment<T: Copy + Eq + Hash + std::fmt::Display> {
     map: HashMap<std::net::IpAddr, T>,
     /// The last dynamically assigned address.
     last_assigned_addr: std::net::IpAddr,
+    /// The IP prefixes of nodes that have one.
+    prefixes: HashMap<T, IpPrefix>,
+    /// The last dynamically assigned address in each node's prefix.
+    last_assigned_in_prefix: HashMap<T, std::net::Ipv4Addr>,
 }
 
 impl<T: Copy + Eq + Hash + std::fmt::Display> IpAssignment<T> {
@@ -365,36 +1466,106 @@ impl<T: Copy + Eq + Hash + std::fmt::Display> IpAssignment<T> {
         Self {
             map: HashMap::new(),
             last_assigned_addr: std::net::IpAddr::V4(std::net::Ipv4Addr::new(11, 0, 0, 0)),
+            prefixes: HashMap::new(),
+            last_assigned_in_prefix: HashMap::new(),
         }
     }
 
+    /// Assign addresses to the node from `prefix`. Nodes without a prefix are never assigned an
+    /// address in another node's prefix. This must be called before any addresses are assigned,
+    /// and the prefixes of different nodes must not overlap.
+    pub fn set_prefix(&mut self, node_id: T, prefix: IpPrefix) {
+        assert!(self.map.is_empty());
+        debug_assert!(self
+            .prefixes
+            .iter()
+            .all(|(id, x)| *id == node_id || !x.overlaps(&prefix)));
+        self.prefixes.insert(node_id, prefix);
+    }
+
     /// Get an unused address and assign it to a node.
-    pub fn assign(&mut self, node_id: T) -> std::net::IpAddr {
+    pub fn assign(&mut self, node_id: T) -> Result<std::net::IpAddr, IpAssignmentError> {
+        if let Some(prefix) = self.prefixes.get(&node_id) {
+            return self.assign_in_prefix(node_id, *prefix);
+        }
+
         // loop until we find an unused address
         loop {
-            let ip_addr = Self::increment_address(&self.last_assigned_addr);
+            let ip_addr = Self::increment_address(&self.last_assigned_addr)
+                .ok_or(IpAssignmentError::AddressSpaceExhausted)?;
             self.last_assigned_addr = ip_addr;
+            // skip over any prefix of another node
+            if let Some((_, prefix)) = self.prefix_of(ip_addr) {
+                self.last_assigned_addr = prefix.last_address().into();
+                continue;
+            }
             if let std::collections::hash_map::Entry::Vacant(e) = self.map.entry(ip_addr) {
                 e.insert(node_id);
-                break ip_addr;
+                break Ok(ip_addr);
+            }
+        }
+    }
+
+    fn assign_in_prefix(
+        &mut self,
+        node_id: T,
+        prefix: IpPrefix,
+    ) -> Result<std::net::IpAddr, IpAssignmentError> {
+        let (first, last) = prefix.host_range();
+        let start = match self.last_assigned_in_prefix.get(&node_id) {
+            Some(x) if *x == last => {
+                return Err(IpAssignmentError::PrefixExhausted { prefix });
+            }
+            Some(x) => u32::from(*x) + 1,
+            None => u32::from(first),
+        };
+
+        for addr in start..=u32::from(last) {
+            let addr = std::net::Ipv4Addr::from(addr);
+            self.last_assigned_in_prefix.insert(node_id, addr);
+            if let Entry::Vacant(e) = self.map.entry(addr.into()) {
+                e.insert(node_id);
+                return Ok(addr.into());
             }
         }
+
+        Err(IpAssignmentError::PrefixExhausted { prefix })
     }
 
-    /// Assign an address to a node.
+    /// Assign an address to a node. The address must not be in the IP prefix of a different node.
     pub fn assign_ip(
         &mut self,
         node_id: T,
         ip_addr: std::net::IpAddr,
-    ) -> Result<(), IpPreviouslyAssignedError> {
+    ) -> Result<(), IpAssignmentError> {
+        if let Some((other_id, prefix)) = self.prefix_of(ip_addr) {
+            if other_id != node_id {
+                return Err(IpAssignmentError::InOtherPrefix {
+                    prefix,
+                    node: other_id.to_string(),
+                });
+            }
+        }
+
         let entry = self.map.entry(ip_addr);
         if let Entry::Occupied(_) = &entry {
-            return Err(IpPreviouslyAssignedError);
+            return Err(IpAssignmentError::PreviouslyAssigned);
         }
         entry.or_insert(node_id);
         Ok(())
     }
 
+    /// The node whose IP prefix contains the address, if any.
+    fn prefix_of(&self, ip_addr: std::net::IpAddr) -> Option<(T, IpPrefix)> {
+        let std::net::IpAddr::V4(ip_addr) = ip_addr else {
+            return None;
+        };
+        self.prefixes
+            .iter()
+            .find(|(_, prefix)| prefix.contains(ip_addr))
+            .map(|(id, prefix)| (*id, *prefix))
+    }
+
     /// Get the node that an address is assigned to.
     pub fn get_node(&self, ip_addr: std::net::IpAddr) -> Option<T> {
         self.map.get(&ip_addr).copied()
@@ -405,15 +1576,17 @@ impl<T: Copy + Eq + Hash + std::fmt::Display> IpAssignment<T> {
         self.map.values().copied().collect()
     }
 
-    fn increment_address(addr: &std::net::IpAddr) -> std::net::IpAddr {
+    /// The next address after `addr` that can be assigned to a host, or `None` if there are no
+    /// more addresses.
+    fn increment_address(addr: &std::net::IpAddr) -> Option<std::net::IpAddr> {
         match addr {
             std::net::IpAddr::V4(mut x) => loop {
                 // increment the address
-                x = std::net::Ipv4Addr::from(u32::from(x) + 1);
+                x = std::net::Ipv4Addr::from(u32::from(x).checked_add(1)?);
                 match x.octets()[3] {
                     // if the address ends in ".0" or ".255" (broadcast), try the next
                     0 | 255 => {}
-                    _ => break std::net::IpAddr::V4(x),
+                    _ => break Some(std::net::IpAddr::V4(x)),
                 }
             },
             std::net::IpAddr::V6(_) => unimplemented!(),
@@ -427,59 +1600,453 @@ impl<T: Copy + Eq + Hash + std::fmt::Display> Default for IpAssignment<T> {
     }
 }
 
+/// The paths between every pair of nodes, as given to [`RoutingInfo::new`].
+#[derive(Debug, Default)]
+pub struct ComputedPaths {
+    pub paths: HashMap<(u32, u32), PathProperties>,
+    /// The links along each path that have a limited capacity or a bursty loss model. Paths
+    /// without any are not included.
+    pub links: HashMap<(u32, u32), Vec<PathLink>>,
+    /// Every path between each pair of nodes that has the same latency as the shortest path, when
+    /// using equal-cost multipath routing. Pairs with only one such path are not included.
+    pub equal_cost: HashMap<(u32, u32), Vec<EqualCostPath>>,
+}
+
+/// The path chosen for a packet by [`RoutingInfo::route`].
+#[derive(Debug, Clone)]
+pub struct Route {
+    /// The index of the path among the equal-cost paths between the nodes, or 0 if there's only
+    /// one path.
+    pub index: usize,
+    pub properties: PathProperties,
+    /// The links along the path that have a limited capacity or a bursty loss model, if any.
+    pub links: Option<PathLinks>,
+}
+
 /// Routing information for paths between nodes.
 #[derive(Debug)]
-pub struct RoutingInfo<T: Eq + Hash + std::fmt::Display + Clone + Copy> {
-    paths: HashMap<(T, T), PathProperties>,
-    packet_counters: std::sync::RwLock<HashMap<(T, T), u64>>,
+pub struct RoutingInfo {
+    paths: std::sync::RwLock<Paths>,
+    /// The number of packets sent from each source node along each path, keyed by the
+    /// destination node and the index of the path among the equal-cost paths. Counters are
+    /// grouped by source node so that sending a packet doesn't usually require a write lock.
+    packet_counters: std::sync::RwLock<HashMap<u32, std::sync::Mutex<PacketCounters>>>,
 }
 
-impl<T: Eq + Hash + std::fmt::Display + Clone + Copy> RoutingInfo<T> {
-    pub fn new(paths: HashMap<(T, T), PathProperties>) -> Self {
-        Self {
-            paths,
-            packet_counters: std::sync::RwLock::new(HashMap::new()),
-        }
-    }
+/// The number of packets sent from a node, keyed by the destination node and the index of the
+/// path.
+type PacketCounters = HashMap<(u32, usize), u64>;
 
-    /// Get properties for the path from one node to another.
-    pub fn path(&self, start: T, end: T) -> Option<PathProperties> {
-        self.paths.get(&(start, end)).copied()
-    }
+/// The paths between nodes.
+#[derive(Debug)]
+enum Paths {
+    /// The path between every pair of nodes, computed up front.
+    Precomputed {
+        paths: HashMap<(u32, u32), PathProperties>,
+        /// The links along each path that have a limited capacity or a bursty loss model. Paths
+        /// without any are not included.
+        links: HashMap<(u32, u32), PathLinks>,
+        /// Pairs of nodes with more than one equal-cost path.
+        equal_cost: HashMap<(u32, u32), std::sync::Arc<[EqualCostPath]>>,
+    },
+    /// The paths from each source node, computed when they're first needed.
+    Lazy(LazyPaths),
+}
 
-    /// Increment the number of packets sent from one node to another.
-    pub fn increment_packet_count(&self, start: T, end: T) {
-        let key = (start, end);
-        let mut packet_counters = self.packet_counters.write().unwrap();
-        match packet_counters.get_mut(&key) {
-            Some(x) => *x = x.saturating_add(1),
-            None => assert!(packet_counters.insert(key, 1).is_none()),
+/// The number of shards of the cache of lazily computed paths. Each shard has its own lock, so
+/// threads looking up the paths from different source nodes rarely contend.
+const PATH_CACHE_SHARDS: u32 = 64;
+
+/// Paths that are computed from a graph one source node at a time, and cached. The cache holds
+/// the paths from a limited number of source nodes, and evicts the least recently used ones.
+#[derive(Debug)]
+struct LazyPaths {
+    graph: NetworkGraph,
+    /// The nodes that paths are computed to.
+    nodes: HashSet<NodeIndex>,
+    use_shortest_paths: bool,
+    use_ecmp: bool,
+    /// The cached paths, sharded by source node.
+    shards: Vec<PathCacheShard>,
+    /// The largest number of source nodes whose paths are cached in each shard.
+    shard_capacity: usize,
+}
+
+/// A shard of the cache of lazily computed paths.
+#[derive(Debug, Default)]
+struct PathCacheShard {
+    trees: std::sync::RwLock<HashMap<u32, CachedPathTree>>,
+    /// Incremented on every lookup, to order the cached trees by when they were last used.
+    clock: std::sync::atomic::AtomicU64,
+}
+
+/// The cached paths from a source node.
+#[derive(Debug)]
+struct CachedPathTree {
+    tree: std::sync::Arc<PathTree>,
+    /// The shard's clock when the paths were last used. This is atomic so that a cache hit
+    /// only needs a read lock.
+    last_used: std::sync::atomic::AtomicU64,
+}
+
+/// The paths from a single source node.
+#[derive(Debug)]
+struct PathTree {
+    paths: HashMap<u32, PathProperties>,
+    links: HashMap<u32, PathLinks>,
+    equal_cost: HashMap<u32, std::sync::Arc<[EqualCostPath]>>,
+}
+
+impl LazyPaths {
+    fn new(
+        graph: NetworkGraph,
+        nodes: &HashSet<u32>,
+        use_shortest_paths: bool,
+        use_ecmp: bool,
+        cache_size: NonZeroU32,
+    ) -> Self {
+        let nodes = nodes
+            .iter()
+            .map(|x| *graph.node_id_to_index(*x).unwrap())
+            .collect();
+
+        // use fewer shards for small caches so that the total size isn't exceeded
+        let num_shards = std::cmp::min(cache_size.get(), PATH_CACHE_SHARDS);
+
+        Self {
+            graph,
+            nodes,
+            use_shortest_paths,
+            use_ecmp,
+            shards: (0..num_shards).map(|_| PathCacheShard::default()).collect(),
+            shard_capacity: (cache_size.get() / num_shards).try_into().unwrap(),
+        }
+    }
+
+    fn shard(&self, src: u32) -> &PathCacheShard {
+        &self.shards[src as usize % self.shards.len()]
+    }
+
+    /// Get the paths from a node, computing them if they aren't cached.
+    fn tree(&self, src: u32) -> Option<std::sync::Arc<PathTree>> {
+        use std::sync::atomic::Ordering;
+
+        let shard = self.shard(src);
+
+        if let Some(cached) = shard.trees.read().unwrap().get(&src) {
+            let now = shard.clock.fetch_add(1, Ordering::Relaxed);
+            cached.last_used.store(now, Ordering::Relaxed);
+            return Some(std::sync::Arc::clone(&cached.tree));
+        }
+
+        let index = *self.graph.node_id_to_index(src)?;
+        let source_paths = self
+            .graph
+            .compute_paths_from(index, &self.nodes, self.use_shortest_paths, self.use_ecmp)
+            .expect("The network graph should have been validated at startup");
+
+        let to_id = |x| self.graph.node_index_to_id(x).unwrap();
+        let tree = PathTree {
+            paths: source_paths
+                .paths
+                .into_iter()
+                .map(|(dst, path)| (to_id(dst), path))
+                .collect(),
+            links: source_paths
+                .links
+                .into_iter()
+                .map(|(dst, links)| (to_id(dst), links.into()))
+                .collect(),
+            equal_cost: source_paths
+                .equal_cost
+                .into_iter()
+                .map(|(dst, paths)| (to_id(dst), paths.into()))
+                .collect(),
+        };
+
+        let mut trees = shard.trees.write().unwrap();
+
+        // another thread may have computed the same paths in the meantime, which is fine since
+        // they'll be identical
+        if let Some(cached) = trees.get(&src) {
+            return Some(std::sync::Arc::clone(&cached.tree));
+        }
+
+        if trees.len() >= self.shard_capacity {
+            let (&lru, _) = trees
+                .iter()
+                .min_by_key(|(_, x)| x.last_used.load(Ordering::Relaxed))
+                .unwrap();
+            trees.remove(&lru);
+        }
+
+        let tree = std::sync::Arc::new(tree);
+        let now = shard.clock.fetch_add(1, Ordering::Relaxed);
+        trees.insert(
+            src,
+            CachedPathTree {
+                tree: std::sync::Arc::clone(&tree),
+                last_used: now.into(),
+            },
+        );
+        Some(tree)
+    }
+
+    /// The number of source nodes whose paths are cached.
+    #[cfg(test)]
+    fn num_cached(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|x| x.trees.read().unwrap().len())
+            .sum()
+    }
+
+    /// Discard all cached paths.
+    fn clear(&mut self) {
+        for shard in &mut self.shards {
+            shard.trees.get_mut().unwrap().clear();
+        }
+    }
+}
+
+impl RoutingInfo {
+    /// Routing information where the path between every pair of nodes has been computed up front.
+    pub fn new(paths: ComputedPaths) -> Self {
+        Self::with_paths(Self::precomputed(paths))
+    }
+
+    /// Routing information where the paths from a node to each of `nodes` are computed from
+    /// `graph` when they're first needed. The paths from at most `cache_size` source nodes are
+    /// kept. The graph must already have been checked for paths between every pair of nodes, for
+    /// example with [`NetworkGraph::check_connected`].
+    pub fn new_lazy(
+        graph: NetworkGraph,
+        nodes: &HashSet<u32>,
+        use_shortest_paths: bool,
+        use_ecmp: bool,
+        cache_size: NonZeroU32,
+    ) -> Self {
+        Self::with_paths(Paths::Lazy(LazyPaths::new(
+            graph,
+            nodes,
+            use_shortest_paths,
+            use_ecmp,
+            cache_size,
+        )))
+    }
+
+    fn with_paths(paths: Paths) -> Self {
+        Self {
+            paths: std::sync::RwLock::new(paths),
+            packet_counters: std::sync::RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn precomputed(paths: ComputedPaths) -> Paths {
+        Paths::Precomputed {
+            paths: paths.paths,
+            links: paths
+                .links
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+            equal_cost: paths
+                .equal_cost
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+        }
+    }
+
+    /// Get properties for the path from one node to another. Returns `None` if there is
+    /// currently no path between the nodes. If there are several equal-cost paths, this is the
+    /// one with the lowest packet loss.
+    pub fn path(&self, start: u32, end: u32) -> Option<PathProperties> {
+        match &*self.paths.read().unwrap() {
+            Paths::Precomputed { paths, .. } => paths.get(&(start, end)).copied(),
+            Paths::Lazy(lazy) => lazy.tree(start)?.paths.get(&end).copied(),
+        }
+    }
+
+    /// Get the links along the path from one node to another that have a limited capacity or a
+    /// bursty loss model, in the order that packets cross them. Returns `None` if the path has no
+    /// such links.
+    pub fn path_links(&self, start: u32, end: u32) -> Option<PathLinks> {
+        match &*self.paths.read().unwrap() {
+            Paths::Precomputed { links, .. } => links.get(&(start, end)).cloned(),
+            Paths::Lazy(lazy) => lazy.tree(start)?.links.get(&end).cloned(),
+        }
+    }
+
+    /// Choose the path from one node to another for a packet of the flow with hash `flow_hash`.
+    /// If there are several equal-cost paths between the nodes, packets of the same flow always
+    /// take the same one. Returns `None` if there is currently no path between the nodes.
+    pub fn route(&self, start: u32, end: u32, flow_hash: u64) -> Option<Route> {
+        let choose = |paths: &[EqualCostPath]| {
+            let index = (flow_hash % u64::try_from(paths.len()).unwrap()) as usize;
+            Route {
+                index,
+                properties: paths[index].properties,
+                links: paths[index].links.clone(),
+            }
+        };
+
+        match &*self.paths.read().unwrap() {
+            Paths::Precomputed {
+                paths,
+                links,
+                equal_cost,
+            } => {
+                if let Some(equal_cost) = equal_cost.get(&(start, end)) {
+                    return Some(choose(equal_cost));
+                }
+                Some(Route {
+                    index: 0,
+                    properties: *paths.get(&(start, end))?,
+                    links: links.get(&(start, end)).cloned(),
+                })
+            }
+            Paths::Lazy(lazy) => {
+                let tree = lazy.tree(start)?;
+                if let Some(equal_cost) = tree.equal_cost.get(&end) {
+                    return Some(choose(equal_cost));
+                }
+                Some(Route {
+                    index: 0,
+                    properties: *tree.paths.get(&end)?,
+                    links: tree.links.get(&end).cloned(),
+                })
+            }
         }
     }
 
+    /// Get the equal-cost paths from one node to another. Returns `None` if there aren't several
+    /// equal-cost paths between the nodes.
+    fn equal_cost_paths(&self, start: u32, end: u32) -> Option<std::sync::Arc<[EqualCostPath]>> {
+        match &*self.paths.read().unwrap() {
+            Paths::Precomputed { equal_cost, .. } => equal_cost.get(&(start, end)).cloned(),
+            Paths::Lazy(lazy) => lazy.tree(start)?.equal_cost.get(&end).cloned(),
+        }
+    }
+
+    /// Replace the paths between nodes, for example after the network topology has changed. The
+    /// packet counts are kept.
+    pub fn set_paths(&self, paths: ComputedPaths) {
+        *self.paths.write().unwrap() = Self::precomputed(paths);
+    }
+
+    /// Replace the graph that paths are lazily computed from, for example after the network
+    /// topology has changed. Any paths computed from the previous graph are discarded. The packet
+    /// counts are kept.
+    pub fn set_graph(&self, graph: NetworkGraph) {
+        let mut paths = self.paths.write().unwrap();
+        let Paths::Lazy(lazy) = &mut *paths else {
+            panic!("Paths are not computed lazily");
+        };
+
+        lazy.graph = graph;
+        lazy.clear();
+    }
+
+    /// Increment the number of packets sent from one node to another along the path with the
+    /// given index among the equal-cost paths (see [`Route::index`]).
+    pub fn increment_packet_count(&self, start: u32, end: u32, path_index: usize) {
+        let increment = |counters: &mut PacketCounters| {
+            let count = counters.entry((end, path_index)).or_insert(0);
+            *count = count.saturating_add(1);
+        };
+
+        if let Some(counters) = self.packet_counters.read().unwrap().get(&start) {
+            increment(&mut counters.lock().unwrap());
+            return;
+        }
+
+        let mut packet_counters = self.packet_counters.write().unwrap();
+        increment(packet_counters.entry(start).or_default().get_mut().unwrap());
+    }
+
     /// Log the number of packets sent between nodes.
     pub fn log_packet_counts(&self) {
+        let mut packet_counters: Vec<_> = self
+            .packet_counters
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|(start, counters)| {
+                counters
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|((end, index), count)| (*start, *end, *index, *count))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        packet_counters.sort();
+
         // only logs paths that have transmitted at least one packet
-        for ((start, end), count) in self.packet_counters.read().unwrap().iter() {
-            let path = self.paths.get(&(*start, *end)).unwrap();
+        for (start, end, index, count) in packet_counters {
+            // the path may have been removed or changed by a later topology change
+            let equal_cost = self.equal_cost_paths(start, end);
+            let (path, via) = match equal_cost.as_ref().and_then(|x| x.get(index)) {
+                Some(x) => (Some(x.properties), format!(" via {:?}", x.nodes)),
+                None => (self.path(start, end).filter(|_| index == 0), String::new()),
+            };
+
+            let Some(path) = path else {
+                log::debug!(
+                    "Found path {}->{}: no current route, path_index={}, packet_count={}",
+                    start,
+                    end,
+                    index,
+                    count
+                );
+                continue;
+            };
             log::debug!(
-                "Found path {}->{}: latency={}ns, packet_loss={}, packet_count={}",
+                "Found path {}->{}{}: latency={}ns, jitter={}ns, packet_loss={}, packet_count={}",
                 start,
                 end,
+                via,
                 path.latency_ns,
+                path.jitter_ns,
                 path.packet_loss,
                 count,
             );
         }
     }
 
+    /// Get the smallest delay that any packet can have, taking the jitter of each path into
+    /// account. When paths are computed lazily, this is the smallest delay of any edge in the
+    /// graph, since no path can be shorter.
     pub fn get_smallest_latency_ns(&self) -> Option<u64> {
-        self.paths.values().map(|x| x.latency_ns).min()
+        match &*self.paths.read().unwrap() {
+            Paths::Precomputed {
+                paths, equal_cost, ..
+            } => paths
+                .values()
+                .chain(
+                    equal_cost
+                        .values()
+                        .flat_map(|x| x.iter().map(|x| &x.properties)),
+                )
+                .map(|x| x.min_latency_ns())
+                .min(),
+            Paths::Lazy(lazy) => lazy
+                .graph
+                .graph
+                .raw_edges()
+                .iter()
+                .map(|e| PathProperties::from(&e.weight).min_latency_ns())
+                .min(),
+        }
     }
 }
 
 /// Read and decompress a file.
-fn read_xz<P: AsRef<std::path::Path>>(path: P) -> Result<String, NetGraphError> {
+fn read_compressed<P: AsRef<std::path::Path>>(
+    path: P,
+    compression: &Compression,
+) -> Result<String, NetGraphError> {
     let path = path.as_ref();
 
     let mut f = std::io::BufReader::new(
@@ -487,26 +2054,89 @@ fn read_xz<P: AsRef<std::path::Path>>(path: P) -> Result<String, NetGraphError>
     );
 
     let mut decomp: Vec<u8> = Vec::new();
-    lzma_rs::xz_decompress(&mut f, &mut decomp).context("Failed to decompress file")?;
+    match compression {
+        Compression::Xz => {
+            lzma_rs::xz_decompress(&mut f, &mut decomp).context("Failed to decompress file")?
+        }
+        Compression::Gzip => {
+            std::io::Read::read_to_end(&mut flate2::bufread::MultiGzDecoder::new(f), &mut decomp)
+                .context("Failed to decompress file")?;
+        }
+        Compression::Zstd => {
+            // a zstd file may contain several concatenated frames
+            while !std::io::BufRead::fill_buf(&mut f)
+                .context("Failed to read file")?
+                .is_empty()
+            {
+                let mut decoder =
+                    ruzstd::StreamingDecoder::new(&mut f).context("Failed to decompress file")?;
+                std::io::Read::read_to_end(&mut decoder, &mut decomp)
+                    .context("Failed to decompress file")?;
+            }
+        }
+    }
     decomp.shrink_to_fit();
 
     Ok(String::from_utf8(decomp)?)
 }
 
-/// Get the network graph as a string.
-pub fn load_network_graph(graph_options: &GraphOptions) -> Result<String, NetGraphError> {
-    Ok(match graph_options {
-        GraphOptions::Gml(GraphSource::File(FileSource {
+/// The format of a network graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Gml,
+    Graphml,
+    Json,
+}
+
+impl From<&GraphOptions> for GraphFormat {
+    fn from(graph_options: &GraphOptions) -> Self {
+        match graph_options {
+            GraphOptions::Graphml(_) => Self::Graphml,
+            GraphOptions::Json(_) => Self::Json,
+            // built-in and generated graphs are GML graphs
+            _ => Self::Gml,
+        }
+    }
+}
+
+/// Get the network graph as a string. The graph's format is given by [`GraphFormat`]. Generated
+/// graphs that are random use `seed`.
+pub fn load_network_graph(
+    graph_options: &GraphOptions,
+    seed: u64,
+) -> Result<String, NetGraphError> {
+    let source = match graph_options {
+        GraphOptions::Gml(source) | GraphOptions::Graphml(source) | GraphOptions::Json(source) => {
+            source
+        }
+        GraphOptions::OneGbitSwitch => return Ok(configuration::ONE_GBIT_SWITCH_GRAPH.to_string()),
+        GraphOptions::Star(options) => return Ok(generate::star(options)?),
+        GraphOptions::FullMesh(options) => return Ok(generate::full_mesh(options)?),
+        GraphOptions::Dumbbell(options) => return Ok(generate::dumbbell(options)?),
+        GraphOptions::LeafSpine(options) => return Ok(generate::leaf_spine(options)?),
+        GraphOptions::RandomGeometric(options) => {
+            return Ok(generate::random_geometric(options, seed)?)
+        }
+    };
+
+    Ok(match source {
+        GraphSource::File(file) => read_file_source(file)?,
+        GraphSource::Inline(s) => s.clone(),
+    })
+}
+
+/// Read a file given in the configuration options, decompressing it if needed.
+pub fn read_file_source(source: &FileSource) -> Result<String, NetGraphError> {
+    Ok(match source {
+        FileSource {
             compression: None,
             path: f,
-        })) => std::fs::read_to_string(tilde_expansion(f))
+        } => std::fs::read_to_string(tilde_expansion(f))
             .with_context(|| format!("Failed to read file: {f}"))?,
-        GraphOptions::Gml(GraphSource::File(FileSource {
-            compression: Some(Compression::Xz),
+        FileSource {
+            compression: Some(compression),
             path: f,
-        })) => read_xz(tilde_expansion(f))?,
-        GraphOptions::Gml(GraphSource::Inline(s)) => s.clone(),
-        GraphOptions::OneGbitSwitch => configuration::ONE_GBIT_SWITCH_GRAPH.to_string(),
+        } => read_compressed(tilde_expansion(f), compression)?,
     })
 }
 
@@ -518,16 +2148,39 @@ mod tests {
     fn test_path_add() {
         let p1 = PathProperties {
             latency_ns: 23,
+            jitter_ns: 5,
             packet_loss: 0.35,
+            reorder: 0.5,
+            reorder_delay_ns: 7,
+            duplicate: 0.0,
+            corrupt: 0.1,
+            mtu: Some(1500),
         };
         let p2 = PathProperties {
             latency_ns: 11,
+            jitter_ns: 2,
             packet_loss: 0.85,
+            reorder: 0.5,
+            reorder_delay_ns: 3,
+            duplicate: 0.2,
+            corrupt: 0.0,
+            mtu: Some(1280),
         };
 
         let p3 = p1 + p2;
         assert_eq!(p3.latency_ns, 34);
+        assert_eq!(p3.jitter_ns, 7);
+        assert_eq!(p3.min_latency_ns(), 27);
         assert!((p3.packet_loss - 0.9025).abs() < 0.01);
+        assert!((p3.reorder - 0.75).abs() < 0.01);
+        assert_eq!(p3.reorder_delay_ns, 7);
+        assert!((p3.duplicate - 0.2).abs() < 0.01);
+        assert!((p3.corrupt - 0.1).abs() < 0.01);
+        assert_eq!(p3.mtu, Some(1280));
+
+        let p4 = PathProperties { mtu: None, ..p1 };
+        assert_eq!((p4 + p2).mtu, Some(1280));
+        assert_eq!((p4 + p4).mtu, None);
     }
 
     #[test]
@@ -558,6 +2211,124 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_jitter_less_than_latency() {
+        for (jitter, is_valid) in &[("0 ns", true), ("9 ns", true), ("10 ns", false)] {
+            let graph = format!(
+                r#"graph [
+                node [
+                  id 0
+                ]
+                edge [
+                  source 0
+                  target 0
+                  latency "10 ns"
+                  jitter "{}"
+                ]
+            ]"#,
+                jitter
+            );
+
+            if *is_valid {
+                NetworkGraph::parse(&graph).unwrap();
+            } else {
+                NetworkGraph::parse(&graph).unwrap_err();
+            }
+        }
+    }
+
+    // disabled under miri due to https://github.com/rayon-rs/rayon/issues/952
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_edge_change() {
+        for directed in &[true, false] {
+            let graph = format!(
+                r#"graph [
+                  directed {}
+                  node [
+                    id 0
+                  ]
+                  node [
+                    id 1
+                  ]
+                  edge [
+                    source 0
+                    target 0
+                    latency "1 ns"
+                  ]
+                  edge [
+                    source 1
+                    target 1
+                    latency "1 ns"
+                  ]
+                  edge [
+                    source 0
+                    target 1
+                    latency "3 ns"
+                  ]
+                  {}
+                ]"#,
+                if *directed { 1 } else { 0 },
+                // an undirected graph must not have two edges between the same nodes
+                if *directed {
+                    "edge [\n source 1\n target 0\n latency \"5 ns\"\n ]"
+                } else {
+                    ""
+                },
+            );
+            let mut graph = NetworkGraph::parse(&graph).unwrap();
+            let node_0 = *graph.node_id_to_index(0).unwrap();
+            let node_1 = *graph.node_id_to_index(1).unwrap();
+            let nodes = [node_0, node_1];
+
+            let down = EdgeChange {
+                up: Some(false),
+                ..Default::default()
+            };
+            let up = EdgeChange {
+                up: Some(true),
+                ..Default::default()
+            };
+            let slower = EdgeChange {
+                latency: Some(units::Time::new(300, units::TimePrefix::Nano)),
+                ..Default::default()
+            };
+
+            // take down the edge from 0 to 1, and change its latency while it's down
+            graph.apply_edge_change(0, 1, &down).unwrap();
+            graph.apply_edge_change(0, 1, &slower).unwrap();
+            let paths = graph.compute_shortest_paths(&nodes).unwrap();
+            assert!(!paths.contains_key(&(node_0, node_1)));
+            if *directed {
+                assert_eq!(paths[&(node_1, node_0)].latency_ns, 5);
+            } else {
+                assert!(!paths.contains_key(&(node_1, node_0)));
+            }
+
+            // bring the edge back up, giving the edge in the opposite direction if undirected
+            if *directed {
+                graph.apply_edge_change(0, 1, &up).unwrap();
+            } else {
+                graph.apply_edge_change(1, 0, &up).unwrap();
+            }
+            let paths = graph.compute_shortest_paths(&nodes).unwrap();
+            assert_eq!(paths[&(node_0, node_1)].latency_ns, 300);
+
+            // a self-loop that is down leaves no path from the node to itself
+            graph.apply_edge_change(1, 1, &down).unwrap();
+            let paths = graph.compute_shortest_paths(&nodes).unwrap();
+            assert!(!paths.contains_key(&(node_1, node_1)));
+
+            // invalid changes are rejected
+            let invalid = EdgeChange {
+                packet_loss: Some(1.5),
+                ..Default::default()
+            };
+            graph.apply_edge_change(0, 0, &invalid).unwrap_err();
+            graph.apply_edge_change(0, 2, &up).unwrap_err();
+        }
+    }
+
     // disabled under miri due to https://github.com/rayon-rs/rayon/issues/952
     #[test]
     #[cfg_attr(miri, ignore)]
@@ -647,4 +2418,1090 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_path_links() {
+        for directed in &[true, false] {
+            // an undirected graph can't have multiple edges between two nodes
+            let reverse_edges = if *directed {
+                r#"edge [
+                    source 1
+                    target 0
+                    latency "3 ns"
+                  ]
+                  edge [
+                    source 2
+                    target 1
+                    latency "5 ns"
+                    bandwidth "20 Mbit"
+                    queue_size "64 KB"
+                  ]"#
+            } else {
+                ""
+            };
+            let graph = format!(
+                r#"graph [
+                  directed {}
+                  node [
+                    id 0
+                  ]
+                  node [
+                    id 1
+                  ]
+                  node [
+                    id 2
+                  ]
+                  edge [
+                    source 2
+                    target 2
+                    latency "7 ns"
+                    bandwidth "1 Gbit"
+                  ]
+                  edge [
+                    source 0
+                    target 1
+                    latency "3 ns"
+                  ]
+                  edge [
+                    source 1
+                    target 2
+                    latency "5 ns"
+                    bandwidth "10 Mbit"
+                  ]
+                  {}
+                ]"#,
+                if *directed { 1 } else { 0 },
+                reverse_edges,
+            );
+            let graph = NetworkGraph::parse(&graph).unwrap();
+            let node_0 = *graph.node_id_to_index(0).unwrap();
+            let node_1 = *graph.node_id_to_index(1).unwrap();
+            let node_2 = *graph.node_id_to_index(2).unwrap();
+            let nodes = [node_0, node_1, node_2];
+
+            let shortest_links = graph.compute_path_links(&nodes, true).unwrap();
+            let direct_links = graph.compute_path_links(&nodes, false).unwrap();
+
+            let link = |source, target, offset_ns, bandwidth_bits, queue_size_bytes| PathLink {
+                source,
+                target,
+                offset_ns,
+                capacity: Some(LinkCapacity {
+                    bandwidth_bits,
+                    queue_size_bytes,
+                }),
+                burst_loss: None,
+            };
+
+            // paths without capacity-limited or lossy links aren't included
+            assert!(!shortest_links.contains_key(&(node_0, node_1)));
+            assert!(!direct_links.contains_key(&(node_0, node_1)));
+            assert!(!direct_links.contains_key(&(node_0, node_2)));
+
+            // the default queue holds 100 ms of traffic
+            assert_eq!(
+                shortest_links[&(node_0, node_2)],
+                vec![link(1, 2, 3, 10_000_000, 125_000)]
+            );
+            assert_eq!(
+                direct_links[&(node_1, node_2)],
+                vec![link(1, 2, 0, 10_000_000, 125_000)]
+            );
+            assert_eq!(
+                shortest_links[&(node_2, node_2)],
+                vec![link(2, 2, 0, 1_000_000_000, 12_500_000)]
+            );
+
+            if *directed {
+                assert_eq!(
+                    shortest_links[&(node_2, node_0)],
+                    vec![link(2, 1, 0, 20_000_000, 64_000)]
+                );
+            } else {
+                // both directions of an undirected edge have the same capacity
+                assert_eq!(
+                    shortest_links[&(node_2, node_0)],
+                    vec![link(2, 1, 0, 10_000_000, 125_000)]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lazy_routing() {
+        let graph = r#"graph [
+          directed 1
+          node [
+            id 0
+          ]
+          node [
+            id 1
+          ]
+          node [
+            id 2
+          ]
+          edge [
+            source 0
+            target 0
+            latency "2 ns"
+          ]
+          edge [
+            source 1
+            target 1
+            latency "2 ns"
+          ]
+          edge [
+            source 2
+            target 2
+            latency "2 ns"
+          ]
+          edge [
+            source 0
+            target 1
+            latency "3 ns"
+            jitter "1 ns"
+          ]
+          edge [
+            source 1
+            target 2
+            latency "5 ns"
+            bandwidth "10 Mbit"
+          ]
+          edge [
+            source 2
+            target 0
+            latency "7 ns"
+          ]
+        ]"#;
+        let graph = NetworkGraph::parse(graph).unwrap();
+        let ids = [0, 1, 2];
+        let indexes: Vec<_> = ids
+            .iter()
+            .map(|x| *graph.node_id_to_index(*x).unwrap())
+            .collect();
+        let to_ids = |(src, dst)| {
+            (
+                graph.node_index_to_id(src).unwrap(),
+                graph.node_index_to_id(dst).unwrap(),
+            )
+        };
+
+        let paths: HashMap<_, _> = graph
+            .compute_shortest_paths(&indexes)
+            .unwrap()
+            .into_iter()
+            .map(|(k, v)| (to_ids(k), v))
+            .collect();
+        let links: HashMap<_, _> = graph
+            .compute_path_links(&indexes, true)
+            .unwrap()
+            .into_iter()
+            .map(|(k, v)| (to_ids(k), v))
+            .collect();
+
+        let precomputed = RoutingInfo::new(ComputedPaths {
+            paths,
+            links,
+            ..Default::default()
+        });
+        let lazy = RoutingInfo::new_lazy(
+            graph.clone(),
+            &ids.into_iter().collect(),
+            true,
+            false,
+            NonZeroU32::new(16).unwrap(),
+        );
+
+        // the lazily computed paths are the same as the precomputed paths
+        for src in ids {
+            for dst in ids {
+                assert_eq!(precomputed.path(src, dst), lazy.path(src, dst));
+                assert_eq!(precomputed.path_links(src, dst), lazy.path_links(src, dst));
+            }
+        }
+        assert_eq!(lazy.path(0, 2).unwrap().latency_ns, 8);
+        assert_eq!(lazy.path_links(0, 2).unwrap()[0].offset_ns, 3);
+        assert!(lazy.path(0, 3).is_none());
+        assert!(lazy.path(3, 0).is_none());
+
+        // no path is shorter than the shortest edge
+        assert_eq!(precomputed.get_smallest_latency_ns(), Some(2));
+        assert_eq!(lazy.get_smallest_latency_ns(), Some(2));
+
+        // the cached paths are discarded when the graph changes
+        let mut changed = graph.clone();
+        let change = EdgeChange {
+            up: Some(false),
+            latency: None,
+            jitter: None,
+            packet_loss: None,
+        };
+        changed.apply_edge_change(1, 2, &change).unwrap();
+        lazy.set_graph(changed);
+        assert!(lazy.path(0, 2).is_none());
+        assert_eq!(lazy.path(0, 1).unwrap().latency_ns, 3);
+
+        lazy.increment_packet_count(0, 1, 0);
+        lazy.increment_packet_count(0, 1, 0);
+        lazy.increment_packet_count(0, 2, 0);
+        let counters = lazy.packet_counters.read().unwrap();
+        assert_eq!(counters[&0].lock().unwrap()[&(1, 0)], 2);
+        assert_eq!(counters[&0].lock().unwrap()[&(2, 0)], 1);
+    }
+
+    #[test]
+    fn test_lazy_routing_cache() {
+        let graph = r#"graph [
+          directed 0
+          node [
+            id 0
+          ]
+          node [
+            id 1
+          ]
+          node [
+            id 2
+          ]
+          edge [
+            source 0
+            target 0
+            latency "1 ns"
+          ]
+          edge [
+            source 1
+            target 1
+            latency "1 ns"
+          ]
+          edge [
+            source 2
+            target 2
+            latency "1 ns"
+          ]
+          edge [
+            source 0
+            target 1
+            latency "2 ns"
+          ]
+          edge [
+            source 1
+            target 2
+            latency "3 ns"
+          ]
+        ]"#;
+        let graph = NetworkGraph::parse(graph).unwrap();
+        let nodes = [0, 1, 2].into_iter().collect();
+        let latency = |lazy: &LazyPaths, src, dst| lazy.tree(src).unwrap().paths[&dst].latency_ns;
+
+        // the cache never holds more than its size, and evicted paths are computed again
+        let lazy = LazyPaths::new(graph.clone(), &nodes, true, false, 1.try_into().unwrap());
+        for _ in 0..2 {
+            assert_eq!(latency(&lazy, 0, 2), 5);
+            assert_eq!(lazy.num_cached(), 1);
+            assert_eq!(latency(&lazy, 1, 0), 2);
+            assert_eq!(lazy.num_cached(), 1);
+            assert_eq!(latency(&lazy, 2, 1), 3);
+            assert_eq!(lazy.num_cached(), 1);
+        }
+
+        // with more shards than entries per shard, the total size is still respected
+        let lazy = LazyPaths::new(graph.clone(), &nodes, true, false, 2.try_into().unwrap());
+        for src in [0, 1, 2] {
+            lazy.tree(src).unwrap();
+            assert!(lazy.num_cached() <= 2);
+        }
+
+        // the least recently used paths are evicted
+        let mut lazy = LazyPaths::new(graph, &nodes, true, false, 2.try_into().unwrap());
+        lazy.shards.truncate(1);
+        lazy.shard_capacity = 2;
+        let cached =
+            |lazy: &LazyPaths, src| lazy.shards[0].trees.read().unwrap().contains_key(&src);
+        lazy.tree(0).unwrap();
+        lazy.tree(1).unwrap();
+        lazy.tree(0).unwrap();
+        lazy.tree(2).unwrap();
+        assert!(cached(&lazy, 0));
+        assert!(!cached(&lazy, 1));
+        assert!(cached(&lazy, 2));
+        assert_eq!(lazy.num_cached(), 2);
+
+        lazy.clear();
+        assert_eq!(lazy.num_cached(), 0);
+    }
+
+    #[test]
+    fn test_equal_cost_paths() {
+        for directed in [true, false] {
+            let graph = format!(
+                r#"graph [
+                  directed {}
+                  node [
+                    id 0
+                  ]
+                  node [
+                    id 1
+                  ]
+                  node [
+                    id 2
+                  ]
+                  node [
+                    id 3
+                  ]
+                  edge [
+                    source 0
+                    target 1
+                    latency "2 ns"
+                    packet_loss 0.5
+                  ]
+                  edge [
+                    source 0
+                    target 2
+                    latency "3 ns"
+                    jitter "1 ns"
+                  ]
+                  edge [
+                    source 1
+                    target 3
+                    latency "3 ns"
+                  ]
+                  edge [
+                    source 2
+                    target 3
+                    latency "2 ns"
+                    bandwidth "10 Mbit"
+                  ]
+                  edge [
+                    source 0
+                    target 3
+                    latency "6 ns"
+                  ]
+                ]"#,
+                if directed { 1 } else { 0 },
+            );
+            let graph = NetworkGraph::parse(&graph).unwrap();
+            let node_0 = *graph.node_id_to_index(0).unwrap();
+            let node_3 = *graph.node_id_to_index(3).unwrap();
+
+            let equal_cost = graph.compute_equal_cost_paths(&[node_0, node_3]);
+
+            // the paths are ordered by their packet loss
+            let paths = &equal_cost[&(node_0, node_3)];
+            assert_eq!(paths.len(), 2);
+            assert_eq!(paths[0].nodes, [0, 2, 3]);
+            assert_eq!(paths[0].properties.latency_ns, 5);
+            assert_eq!(paths[0].properties.jitter_ns, 1);
+            assert_eq!(paths[0].links.as_ref().unwrap()[0].offset_ns, 3);
+            assert_eq!(paths[1].nodes, [0, 1, 3]);
+            assert_eq!(paths[1].properties.packet_loss, 0.5);
+            assert!(paths[1].links.is_none());
+
+            // the direct edge is longer, and there's only one path from 3 to 0 in a directed graph
+            if directed {
+                assert_eq!(equal_cost.len(), 1);
+            } else {
+                assert_eq!(equal_cost[&(node_3, node_0)][0].nodes, [3, 2, 0]);
+            }
+
+            let ids: HashSet<u32> = [0, 3].into_iter().collect();
+            let precomputed = RoutingInfo::new(ComputedPaths {
+                paths: [((0, 3), paths[0].properties)].into_iter().collect(),
+                equal_cost: [((0, 3), paths.clone())].into_iter().collect(),
+                ..Default::default()
+            });
+            let cache_size = NonZeroU32::new(16).unwrap();
+            let lazy = RoutingInfo::new_lazy(graph.clone(), &ids, true, true, cache_size);
+
+            for routing in [&precomputed, &lazy] {
+                // flows are spread over the paths, and a flow always takes the same path
+                let route = routing.route(0, 3, 0).unwrap();
+                assert_eq!(route.index, 0);
+                assert_eq!(route.properties.packet_loss, 0.0);
+                assert!(route.links.is_some());
+                let route = routing.route(0, 3, 7).unwrap();
+                assert_eq!(route.index, 1);
+                assert_eq!(route.properties.packet_loss, 0.5);
+                assert!(route.links.is_none());
+            }
+
+            // the equal-cost path with jitter can be faster than the other paths
+            assert_eq!(precomputed.get_smallest_latency_ns(), Some(4));
+
+            // without ECMP, a single path is used
+            let cache_size = NonZeroU32::new(16).unwrap();
+            let lazy = RoutingInfo::new_lazy(graph, &ids, true, false, cache_size);
+            assert_eq!(lazy.route(0, 3, 7).unwrap().index, 0);
+        }
+    }
+
+    #[test]
+    fn test_check_connected() {
+        let graph = r#"graph [
+          directed 1
+          node [
+            id 0
+          ]
+          node [
+            id 1
+          ]
+          node [
+            id 2
+          ]
+          edge [
+            source 0
+            target 0
+            latency "1 ns"
+          ]
+          edge [
+            source 1
+            target 1
+            latency "1 ns"
+          ]
+          edge [
+            source 0
+            target 1
+            latency "1 ns"
+          ]
+          edge [
+            source 1
+            target 2
+            latency "1 ns"
+          ]
+        ]"#;
+        let graph = NetworkGraph::parse(graph).unwrap();
+        let node_0 = *graph.node_id_to_index(0).unwrap();
+        let node_1 = *graph.node_id_to_index(1).unwrap();
+        let node_2 = *graph.node_id_to_index(2).unwrap();
+
+        // there is no path from 1 to 0
+        assert!(graph.check_connected(&[node_0], true).is_ok());
+        assert!(graph.check_connected(&[node_0, node_1], true).is_err());
+        assert!(graph.check_connected(&[node_1, node_0], true).is_err());
+
+        // node 2 has no self-loop
+        assert!(graph.check_connected(&[node_2], true).is_err());
+
+        // without shortest paths, every pair of nodes needs an edge
+        assert!(graph.check_connected(&[node_0], false).is_ok());
+        assert!(graph.check_connected(&[node_0, node_1], false).is_err());
+    }
+
+    #[test]
+    fn test_queue_size_requires_bandwidth() {
+        let graph = r#"graph [
+          node [
+            id 0
+          ]
+          edge [
+            source 0
+            target 0
+            latency "1 ms"
+            queue_size "64 KB"
+          ]
+        ]"#;
+        assert!(NetworkGraph::parse(graph).is_err());
+    }
+
+    #[test]
+    fn test_mtu() {
+        let graph = |mtu: &str| {
+            format!(
+                r#"graph [
+                  directed 1
+                  node [
+                    id 0
+                  ]
+                  node [
+                    id 1
+                  ]
+                  node [
+                    id 2
+                  ]
+                  edge [
+                    source 0
+                    target 1
+                    latency "1 ms"
+                    {mtu}
+                  ]
+                  edge [
+                    source 1
+                    target 2
+                    latency "1 ms"
+                    mtu 1400
+                  ]
+                  edge [
+                    source 2
+                    target 0
+                    latency "1 ms"
+                  ]
+                ]"#
+            )
+        };
+
+        let graph_1 = NetworkGraph::parse(&graph("mtu 9000")).unwrap();
+        let node_0 = *graph_1.node_id_to_index(0).unwrap();
+        let node_1 = *graph_1.node_id_to_index(1).unwrap();
+        let node_2 = *graph_1.node_id_to_index(2).unwrap();
+        let paths = graph_1
+            .compute_shortest_paths(&[node_0, node_1, node_2])
+            .unwrap();
+
+        // the path MTU is the smallest MTU of the path's edges
+        assert_eq!(paths[&(node_0, node_1)].mtu, Some(9000));
+        assert_eq!(paths[&(node_0, node_2)].mtu, Some(1400));
+        assert_eq!(paths[&(node_2, node_1)].mtu, Some(9000));
+        assert_eq!(paths[&(node_2, node_0)].mtu, None);
+
+        assert!(NetworkGraph::parse(&graph("")).is_ok());
+        assert!(NetworkGraph::parse(&graph("mtu 67")).is_err());
+        assert!(NetworkGraph::parse(&graph("mtu 65536")).is_err());
+        assert!(NetworkGraph::parse(&graph("mtu -1500")).is_err());
+        assert!(NetworkGraph::parse(&graph("mtu \"1500\"")).is_err());
+    }
+
+    #[test]
+    fn test_burst_loss() {
+        let edge = |attributes: &str| {
+            let graph = format!(
+                r#"graph [
+                  node [
+                    id 0
+                  ]
+                  edge [
+                    source 0
+                    target 0
+                    latency "1 ms"
+                    {}
+                  ]
+                ]"#,
+                attributes
+            );
+            NetworkGraph::parse(&graph).map(|graph| {
+                let node = *graph.node_id_to_index(0).unwrap();
+                graph
+                    .get_edge_weight(&node, &node)
+                    .unwrap()
+                    .unwrap()
+                    .clone()
+            })
+        };
+
+        assert_eq!(edge("").unwrap().burst_loss, None);
+        assert_eq!(
+            edge("loss_good_to_bad 0.1\nloss_bad_to_good 0.5")
+                .unwrap()
+                .burst_loss,
+            Some(GilbertElliott {
+                good_to_bad: 0.1,
+                bad_to_good: 0.5,
+                loss_good: 0.0,
+                loss_bad: 1.0,
+            })
+        );
+
+        // both transition probabilities are required
+        assert!(edge("loss_good_to_bad 0.1").is_err());
+        assert!(edge("loss_bad_to_good 0.1").is_err());
+        assert!(edge("loss_bad 0.5").is_err());
+        assert!(edge("loss_good_to_bad 0.1\nloss_bad_to_good 1.5").is_err());
+    }
+
+    #[test]
+    fn test_burst_loss_step() {
+        use rand::SeedableRng;
+        let mut rng = rand_xoshiro::Xoshiro256PlusPlus::seed_from_u64(0);
+
+        // always moves to and stays in the bad state, where every packet is lost
+        let model = GilbertElliott {
+            good_to_bad: 1.0,
+            bad_to_good: 0.0,
+            loss_good: 0.0,
+            loss_bad: 1.0,
+        };
+        let mut bad = false;
+        for _ in 0..100 {
+            assert!(model.step(&mut bad, &mut rng));
+            assert!(bad);
+        }
+
+        // never leaves the good state, where no packets are lost
+        let model = GilbertElliott {
+            good_to_bad: 0.0,
+            bad_to_good: 1.0,
+            loss_good: 0.0,
+            loss_bad: 1.0,
+        };
+        let mut bad = true;
+        for _ in 0..100 {
+            assert!(!model.step(&mut bad, &mut rng));
+            assert!(!bad);
+        }
+
+        // losses should happen in bursts with an average length of 1 / bad_to_good
+        let model = GilbertElliott {
+            good_to_bad: 0.01,
+            bad_to_good: 0.25,
+            loss_good: 0.0,
+            loss_bad: 1.0,
+        };
+        let mut bad = false;
+        let lost: Vec<bool> = (0..100_000)
+            .map(|_| model.step(&mut bad, &mut rng))
+            .collect();
+        let num_lost = lost.iter().filter(|x| **x).count();
+        let num_bursts = lost.windows(2).filter(|x| !x[0] && x[1]).count();
+        let avg_burst_len = num_lost as f64 / num_bursts as f64;
+        assert!((3.5..4.5).contains(&avg_burst_len), "{avg_burst_len}");
+    }
+
+    #[test]
+    fn test_impairments() {
+        let path = |attributes: &str| {
+            let graph = format!(
+                r#"graph [
+                  node [
+                    id 0
+                  ]
+                  edge [
+                    source 0
+                    target 0
+                    latency "1 ms"
+                    {}
+                  ]
+                ]"#,
+                attributes
+            );
+            NetworkGraph::parse(&graph).map(|graph| {
+                let node = *graph.node_id_to_index(0).unwrap();
+                PathProperties::from(graph.get_edge_weight(&node, &node).unwrap().unwrap())
+            })
+        };
+
+        let p = path(
+            "packet_reorder 0.1\nreorder_delay \"5 ms\"\npacket_duplicate 0.2\npacket_corrupt 0.3",
+        )
+        .unwrap();
+        assert_eq!(p.reorder, 0.1);
+        assert_eq!(p.reorder_delay_ns, 5_000_000);
+        assert_eq!(p.duplicate, 0.2);
+        assert_eq!(p.corrupt, 0.3);
+
+        let p = path("").unwrap();
+        assert_eq!(p.reorder, 0.0);
+        assert_eq!(p.duplicate, 0.0);
+        assert_eq!(p.corrupt, 0.0);
+
+        // reordering requires an extra delay
+        assert!(path("packet_reorder 0.1").is_err());
+        assert!(path("packet_duplicate 1.5").is_err());
+        assert!(path("packet_corrupt -0.5").is_err());
+    }
+
+    #[test]
+    fn test_coordinates() {
+        let graph = |edge: &str, coordinates: &str| {
+            format!(
+                r#"graph [
+                  directed 1
+                  node [
+                    id 0
+                    latitude 40.71
+                    longitude -74.01
+                  ]
+                  node [
+                    id 1
+                    {coordinates}
+                  ]
+                  edge [
+                    source 0
+                    target 1
+                    {edge}
+                  ]
+                ]"#
+            )
+        };
+        let latency_ns = |graph: &NetworkGraph| {
+            let edge = &graph.graph().raw_edges()[0].weight;
+            edge.latency
+                .convert(units::TimePrefix::Nano)
+                .unwrap()
+                .value()
+        };
+
+        // new york to london is about 5570 km
+        let london = "latitude 51.51\nlongitude -0.13";
+        let g = NetworkGraph::parse(&graph("", london)).unwrap();
+        assert!((27_500_000..28_000_000).contains(&latency_ns(&g)));
+
+        let propagation =
+            PropagationModel::new(1.0, "10 ms".parse().unwrap(), "1 ms".parse().unwrap()).unwrap();
+        let g = NetworkGraph::parse_as(&graph("", london), GraphFormat::Gml, &propagation).unwrap();
+        assert!((28_500_000..29_000_000).contains(&latency_ns(&g)));
+
+        // an edge's latency takes precedence over its nodes' coordinates
+        let g = NetworkGraph::parse(&graph(r#"latency "1 ms""#, london)).unwrap();
+        assert_eq!(latency_ns(&g), 1_000_000);
+
+        // nodes that are close together or at the same location get the minimum latency
+        let g = NetworkGraph::parse(&graph("", "latitude 40.71\nlongitude -74.01")).unwrap();
+        assert_eq!(latency_ns(&g), DEFAULT_MIN_PROPAGATION_LATENCY_NS);
+        let g = NetworkGraph::parse(&graph("", "latitude 40.75\nlongitude -73.99")).unwrap();
+        assert_eq!(latency_ns(&g), DEFAULT_MIN_PROPAGATION_LATENCY_NS);
+
+        // nodes about 4.6 km apart are about 23 us apart, which a smaller minimum latency allows
+        let propagation =
+            PropagationModel::new(0.67, "0 ms".parse().unwrap(), "1 us".parse().unwrap()).unwrap();
+        let close = graph("", "latitude 40.75\nlongitude -73.99");
+        let g = NetworkGraph::parse_as(&close, GraphFormat::Gml, &propagation).unwrap();
+        assert!((20_000..26_000).contains(&latency_ns(&g)));
+
+        // self-loops need an explicit latency
+        let self_loop = |edge: &str| {
+            format!(
+                r#"graph [
+                  directed 1
+                  node [
+                    id 0
+                    latitude 40.71
+                    longitude -74.01
+                  ]
+                  edge [
+                    source 0
+                    target 0
+                    {edge}
+                  ]
+                ]"#
+            )
+        };
+        assert!(NetworkGraph::parse(&self_loop("")).is_err());
+        assert!(NetworkGraph::parse(&self_loop(r#"latency "1 ms""#)).is_ok());
+
+        // both nodes need coordinates
+        assert!(NetworkGraph::parse(&graph("", "")).is_err());
+        assert!(NetworkGraph::parse(&graph("", "latitude 51.51")).is_err());
+        assert!(NetworkGraph::parse(&graph("", "latitude 91\nlongitude 0")).is_err());
+        assert!(NetworkGraph::parse(&graph("", "latitude \"51\"\nlongitude 0")).is_err());
+
+        let zero = || "0 ms".parse().unwrap();
+        let one_ms = || "1 ms".parse().unwrap();
+        assert!(PropagationModel::new(0.0, zero(), one_ms()).is_err());
+        assert!(PropagationModel::new(1.5, zero(), one_ms()).is_err());
+        assert!(PropagationModel::new(0.67, zero(), zero()).is_err());
+    }
+
+    #[test]
+    fn test_ip_prefix() {
+        let graph = |prefix_0: &str, prefix_1: &str| {
+            format!(
+                r#"graph [
+                  node [
+                    id 0
+                    {prefix_0}
+                  ]
+                  node [
+                    id 1
+                    {prefix_1}
+                  ]
+                ]"#
+            )
+        };
+
+        let g = NetworkGraph::parse(&graph(r#"ip_prefix "12.3.0.0/16""#, "")).unwrap();
+        let node = |id| {
+            let index = g.node_id_to_index(id).unwrap();
+            g.graph().node_weight(*index).unwrap()
+        };
+        assert_eq!(node(0).ip_prefix, Some("12.3.0.0/16".parse().unwrap()));
+        assert_eq!(node(1).ip_prefix, None);
+
+        assert!(NetworkGraph::parse(&graph(r#"ip_prefix "12.3.0.1/16""#, "")).is_err());
+        assert!(NetworkGraph::parse(&graph("ip_prefix 12", "")).is_err());
+
+        // prefixes of different nodes must not overlap
+        assert!(NetworkGraph::parse(&graph(
+            r#"ip_prefix "12.3.0.0/16""#,
+            r#"ip_prefix "12.4.0.0/16""#
+        ))
+        .is_ok());
+        assert!(NetworkGraph::parse(&graph(
+            r#"ip_prefix "12.3.0.0/16""#,
+            r#"ip_prefix "12.0.0.0/8""#
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_nat() {
+        use std::net::Ipv4Addr;
+        use std::time::Duration;
+
+        let graph = |attrs: &str| {
+            format!(
+                r#"graph [
+                  node [
+                    id 0
+                    {attrs}
+                  ]
+                ]"#
+            )
+        };
+        let nat = |attrs: &[&str]| {
+            let g = NetworkGraph::parse(&graph(&attrs.join("\n")))?;
+            let index = g.node_id_to_index(0).unwrap();
+            Ok::<_, NetGraphError>(g.graph().node_weight(*index).unwrap().nat)
+        };
+
+        assert_eq!(nat(&[]).unwrap(), None);
+        assert_eq!(
+            nat(&[r#"nat "symmetric""#, r#"nat_address "1.2.3.4""#]).unwrap(),
+            Some(NatConfig {
+                nat_type: NatType::Symmetric,
+                address: Ipv4Addr::new(1, 2, 3, 4),
+                timeout: Duration::from_secs(60),
+            })
+        );
+        assert_eq!(
+            nat(&[
+                r#"nat "restricted_cone""#,
+                r#"nat_address "1.2.3.4""#,
+                r#"nat_timeout "30 s""#
+            ])
+            .unwrap()
+            .unwrap()
+            .timeout,
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            nat(&[r#"nat "full_cone""#, r#"nat_address "1.2.3.4""#])
+                .unwrap()
+                .unwrap()
+                .nat_type,
+            NatType::FullCone
+        );
+
+        // the NAT type and address are required
+        assert!(nat(&[r#"nat "cone""#, r#"nat_address "1.2.3.4""#]).is_err());
+        assert!(nat(&[r#"nat "symmetric""#]).is_err());
+        assert!(nat(&[r#"nat_address "1.2.3.4""#]).is_err());
+        assert!(nat(&[r#"nat "symmetric""#, r#"nat_address "1.2.3""#]).is_err());
+        assert!(nat(&[
+            r#"nat "symmetric""#,
+            r#"nat_address "1.2.3.4""#,
+            r#"nat_timeout "0 s""#
+        ])
+        .is_err());
+        assert!(nat(&[
+            r#"nat "symmetric""#,
+            r#"nat_address "1.2.3.4""#,
+            r#"nat_timeout "soon""#
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_ip_assignment() {
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let ip = |a, b, c, d| IpAddr::V4(Ipv4Addr::new(a, b, c, d));
+
+        let mut assignment = IpAssignment::new();
+        assignment.set_prefix(1, "11.0.0.0/30".parse().unwrap());
+        assignment.set_prefix(2, "12.3.0.0/16".parse().unwrap());
+
+        // manually assigned addresses can't be in another node's prefix
+        assert_eq!(
+            assignment.assign_ip(0, ip(12, 3, 0, 1)),
+            Err(IpAssignmentError::InOtherPrefix {
+                prefix: "12.3.0.0/16".parse().unwrap(),
+                node: "2".to_string(),
+            })
+        );
+        assignment.assign_ip(2, ip(12, 3, 0, 2)).unwrap();
+        assert_eq!(
+            assignment.assign_ip(2, ip(12, 3, 0, 2)),
+            Err(IpAssignmentError::PreviouslyAssigned)
+        );
+
+        // addresses are assigned from the node's prefix, skipping manually assigned addresses
+        assert_eq!(assignment.assign(2), Ok(ip(12, 3, 0, 1)));
+        assert_eq!(assignment.assign(2), Ok(ip(12, 3, 0, 3)));
+
+        // nodes without a prefix are never assigned addresses in another node's prefix
+        assert_eq!(assignment.assign(0), Ok(ip(11, 0, 0, 4)));
+        assert_eq!(assignment.assign(0), Ok(ip(11, 0, 0, 5)));
+
+        // a /30 prefix has two host addresses
+        assert_eq!(assignment.assign(1), Ok(ip(11, 0, 0, 1)));
+        assert_eq!(assignment.assign(1), Ok(ip(11, 0, 0, 2)));
+        assert_eq!(
+            assignment.assign(1),
+            Err(IpAssignmentError::PrefixExhausted {
+                prefix: "11.0.0.0/30".parse().unwrap(),
+            })
+        );
+
+        assert_eq!(assignment.get_node(ip(12, 3, 0, 3)), Some(2));
+        assert_eq!(assignment.get_node(ip(11, 0, 0, 2)), Some(1));
+
+        // skipping a prefix at the end of the address space leaves no more addresses
+        let mut assignment = IpAssignment::new();
+        assignment.set_prefix(1, "255.255.255.0/24".parse().unwrap());
+        assignment.last_assigned_addr = ip(255, 255, 254, 253);
+        assert_eq!(assignment.assign(0), Ok(ip(255, 255, 254, 254)));
+        assert_eq!(
+            assignment.assign(0),
+            Err(IpAssignmentError::AddressSpaceExhausted)
+        );
+        assert_eq!(assignment.assign(1), Ok(ip(255, 255, 255, 1)));
+
+        // a prefix that contains every address leaves none for nodes without a prefix
+        let mut assignment = IpAssignment::new();
+        assignment.set_prefix(1, "0.0.0.0/0".parse().unwrap());
+        assert_eq!(
+            assignment.assign(0),
+            Err(IpAssignmentError::AddressSpaceExhausted)
+        );
+        assert_eq!(assignment.assign(1), Ok(ip(0, 0, 0, 1)));
+    }
+
+    #[test]
+    fn test_graphml() {
+        let graph = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="node" attr.name="host_bandwidth_down" attr.type="string"/>
+              <key id="d1" for="edge" attr.name="latency" attr.type="string">
+                <default>5 ms</default>
+              </key>
+              <key id="d2" for="edge" attr.name="packet_loss" attr.type="double"/>
+              <graph edgedefault="undirected">
+                <node id="0">
+                  <data key="d0">1 Gbit</data>
+                </node>
+                <node id="1"/>
+                <edge source="0" target="1">
+                  <data key="d1">10 ms</data>
+                  <data key="d2">0.5</data>
+                </edge>
+                <edge source="1" target="1"/>
+              </graph>
+            </graphml>"#;
+
+        let graph =
+            NetworkGraph::parse_as(graph, GraphFormat::Graphml, &Default::default()).unwrap();
+        assert!(!graph.graph().is_directed());
+
+        let node = graph
+            .graph()
+            .node_weight(*graph.node_id_to_index(0).unwrap());
+        assert_eq!(
+            node.unwrap().bandwidth_down,
+            Some("1 Gbit".parse().unwrap())
+        );
+
+        let mut edges: Vec<_> = graph
+            .graph()
+            .raw_edges()
+            .iter()
+            .map(|e| &e.weight)
+            .collect();
+        edges.sort_by_key(|e| (e.source, e.target));
+        assert_eq!(edges[0].latency, "10 ms".parse().unwrap());
+        assert_eq!(edges[0].packet_loss, 0.5);
+        assert_eq!(edges[1].latency, "5 ms".parse().unwrap());
+        assert_eq!(edges[1].packet_loss, 0.0);
+
+        // the same validation applies as for GML graphs
+        for (data, is_valid) in [
+            (r#"<data key="d2">0.5</data>"#, true),
+            (r#"<data key="d2">1.5</data>"#, false),
+            (r#"<data key="d2">abc</data>"#, false),
+            (r#"<data key="d3">0.5</data>"#, false),
+        ] {
+            let graph = format!(
+                r#"<graphml>
+                  <key id="d1" for="edge" attr.name="latency">
+                    <default>1 ms</default>
+                  </key>
+                  <key id="d2" for="edge" attr.name="packet_loss" attr.type="float"/>
+                  <graph edgedefault="directed">
+                    <node id="0"/>
+                    <edge source="0" target="0">
+                      {data}
+                    </edge>
+                  </graph>
+                </graphml>"#
+            );
+            assert_eq!(
+                NetworkGraph::parse_as(&graph, GraphFormat::Graphml, &Default::default()).is_ok(),
+                is_valid,
+                "{data}"
+            );
+        }
+
+        // node ids must be integers
+        let graph = r#"<graphml><graph edgedefault="directed"><node id="n0"/></graph></graphml>"#;
+        assert!(NetworkGraph::parse_as(graph, GraphFormat::Graphml, &Default::default()).is_err());
+    }
+
+    #[test]
+    fn test_json() {
+        let graph = r#"{
+            "directed": true,
+            "multigraph": false,
+            "graph": {},
+            "nodes": [
+                {"id": 0, "host_bandwidth_up": "10 Mbit"},
+                {"id": 1}
+            ],
+            "links": [
+                {"source": 0, "target": 1, "latency": "10 ms", "packet_loss": 0.5},
+                {"source": 1, "target": 0, "latency": "20 ms"}
+            ]
+        }"#;
+
+        let graph = NetworkGraph::parse_as(graph, GraphFormat::Json, &Default::default()).unwrap();
+        assert!(graph.graph().is_directed());
+
+        let node = graph
+            .graph()
+            .node_weight(*graph.node_id_to_index(0).unwrap());
+        assert_eq!(node.unwrap().bandwidth_up, Some("10 Mbit".parse().unwrap()));
+
+        let mut edges: Vec<_> = graph
+            .graph()
+            .raw_edges()
+            .iter()
+            .map(|e| &e.weight)
+            .collect();
+        edges.sort_by_key(|e| (e.source, e.target));
+        assert_eq!(edges[0].latency, "10 ms".parse().unwrap());
+        assert_eq!(edges[0].packet_loss, 0.5);
+        assert_eq!(edges[1].latency, "20 ms".parse().unwrap());
+
+        // the same validation applies as for GML graphs, and newer networkx versions use "edges"
+        for (edge, is_valid) in [
+            (r#"{"source": 0, "target": 0, "latency": "1 ms"}"#, true),
+            (r#"{"source": 0, "target": 0}"#, false),
+            (
+                r#"{"source": 0, "target": 0, "latency": "1 ms", "packet_loss": 2.0}"#,
+                false,
+            ),
+            (
+                r#"{"source": 0, "target": 0, "latency": "1 ms", "packet_loss": "0.5"}"#,
+                false,
+            ),
+            (
+                r#"{"source": 0, "target": 0, "latency": "1 ms", "packet_loss": [0.5]}"#,
+                false,
+            ),
+            (r#"{"source": 0, "target": 1, "latency": "1 ms"}"#, false),
+            (r#"{"source": "0", "target": 0, "latency": "1 ms"}"#, false),
+        ] {
+            let graph = format!(r#"{{"nodes": [{{"id": 0}}], "edges": [{edge}]}}"#);
+            assert_eq!(
+                NetworkGraph::parse_as(&graph, GraphFormat::Json, &Default::default()).is_ok(),
+                is_valid,
+                "{edge}"
+            );
+        }
+    }
 }
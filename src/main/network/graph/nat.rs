@@ -0,0 +1,117 @@
+//! Graph nodes that are network address translators (NATs), which rewrite the source addresses and
+//! ports of packets sent by the hosts assigned to them.
+
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::core::support::units::{self, Unit};
+
+/// The default time after which a mapping that hasn't been used expires.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Which inbound packets a NAT forwards to an internal host that has a mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NatType {
+    /// A mapping is shared by all remote hosts, and any remote host can send to it.
+    FullCone,
+    /// A mapping is shared by all remote hosts, but only remote hosts that the internal host has
+    /// sent to can send to it.
+    RestrictedCone,
+    /// Each remote address and port has its own mapping, and only that remote address and port
+    /// can send to it.
+    Symmetric,
+}
+
+impl FromStr for NatType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full_cone" => Ok(Self::FullCone),
+            "restricted_cone" => Ok(Self::RestrictedCone),
+            "symmetric" => Ok(Self::Symmetric),
+            _ => Err(format!(
+                "'{s}' is not one of 'full_cone', 'restricted_cone', or 'symmetric'"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for NatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::FullCone => write!(f, "full_cone"),
+            Self::RestrictedCone => write!(f, "restricted_cone"),
+            Self::Symmetric => write!(f, "symmetric"),
+        }
+    }
+}
+
+/// The NAT of a graph node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NatConfig {
+    pub nat_type: NatType,
+    /// The public address that the NAT's internal hosts are translated to.
+    pub address: Ipv4Addr,
+    /// The time after which a mapping that hasn't been used expires.
+    pub timeout: Duration,
+}
+
+impl NatConfig {
+    /// Get the NAT from the node's `nat`, `nat_address`, and `nat_timeout` attributes, if it has
+    /// them.
+    pub(super) fn from_gml_node(
+        gml_node: &mut gml_parser::gml::Node,
+    ) -> Result<Option<Self>, String> {
+        let mut get = |name| {
+            gml_node
+                .other
+                .remove(name)
+                .map(|x| {
+                    x.as_str()
+                        .map(|x| x.to_string())
+                        .ok_or(format!("Node '{name}' is not a string"))
+                })
+                .transpose()
+        };
+
+        let nat_type = get("nat")?;
+        let address = get("nat_address")?;
+        let timeout = get("nat_timeout")?;
+
+        let Some(nat_type) = nat_type else {
+            if address.is_some() || timeout.is_some() {
+                return Err("Node 'nat_address' and 'nat_timeout' require 'nat'".into());
+            }
+            return Ok(None);
+        };
+
+        let nat_type = nat_type
+            .parse()
+            .map_err(|e| format!("Node 'nat' is not valid: {e}"))?;
+        let address = address
+            .ok_or("Node 'nat' requires 'nat_address'")?
+            .parse()
+            .map_err(|e| format!("Node 'nat_address' is not a valid IPv4 address: {e}"))?;
+        let timeout = match timeout {
+            Some(timeout) => {
+                let timeout: units::Time<units::TimePrefix> = timeout
+                    .parse()
+                    .map_err(|e| format!("Node 'nat_timeout' is not a valid unit: {e}"))?;
+                Duration::from_nanos(timeout.convert(units::TimePrefix::Nano)?.value())
+            }
+            None => DEFAULT_TIMEOUT,
+        };
+
+        if timeout.is_zero() {
+            return Err("Node 'nat_timeout' must be greater than 0".into());
+        }
+
+        Ok(Some(Self {
+            nat_type,
+            address,
+            timeout,
+        }))
+    }
+}
@@ -1,7 +1,7 @@
 use petgraph::graph::{EdgeIndex, Graph, IndexType, NodeIndex};
 use petgraph::{Directed, Undirected};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum GraphWrapper<N, E, Ix: IndexType> {
     Directed(Graph<N, E, Directed, Ix>),
     Undirected(Graph<N, E, Undirected, Ix>),
@@ -26,4 +26,19 @@ impl<N, E, Ix: IndexType> GraphWrapper<N, E, Ix> {
     enum_passthrough!(self, (a, b), Directed, Undirected;
         pub fn find_edge(&self, a: NodeIndex<Ix>, b: NodeIndex<Ix>) -> Option<EdgeIndex<Ix>>
     );
+    enum_passthrough!(self, (edge), Directed, Undirected;
+        pub fn edge_weight_mut(&mut self, edge: EdgeIndex<Ix>) -> Option<&mut E>
+    );
+    enum_passthrough!(self, (edge), Directed, Undirected;
+        pub fn remove_edge(&mut self, edge: EdgeIndex<Ix>) -> Option<E>
+    );
+    enum_passthrough!(self, (), Directed, Undirected;
+        pub fn raw_nodes(&self) -> &[petgraph::graph::Node<N, Ix>]
+    );
+    enum_passthrough!(self, (), Directed, Undirected;
+        pub fn raw_edges(&self) -> &[petgraph::graph::Edge<E, Ix>]
+    );
+    enum_passthrough!(self, (), Directed, Undirected;
+        pub fn is_directed(&self) -> bool
+    );
 }
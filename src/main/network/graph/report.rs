@@ -0,0 +1,261 @@
+//! A report on the network graph and the paths between the graph nodes that hosts are assigned
+//! to, so that problems with a graph can be found without running a simulation.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use petgraph::graph::NodeIndex;
+use serde::Serialize;
+
+use super::{NetGraphError, NetworkGraph};
+
+/// The path between two graph nodes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PathEntry {
+    pub source: u32,
+    pub target: u32,
+    pub latency_ns: u64,
+    pub jitter_ns: u64,
+    pub packet_loss: f32,
+}
+
+/// A pair of graph nodes with no path between them, and the hosts assigned to each.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnreachableNodePair {
+    pub source: u32,
+    pub target: u32,
+    pub source_hosts: Vec<String>,
+    pub target_hosts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphReport {
+    /// Pairs of graph nodes that hosts are assigned to, but that have no path between them.
+    pub unreachable_node_pairs: Vec<UnreachableNodePair>,
+    /// Graph nodes that hosts are assigned to, but that have no self-loop.
+    pub nodes_without_self_loops: Vec<u32>,
+    /// Edges of a directed graph without an edge in the opposite direction.
+    pub asymmetric_edges: Vec<(u32, u32)>,
+    /// The paths between each pair of graph nodes that hosts are assigned to.
+    pub paths: Vec<PathEntry>,
+}
+
+impl GraphReport {
+    /// Build a report for `hosts`, which maps each host's name to its graph node id.
+    pub fn new(
+        graph: &NetworkGraph,
+        hosts: &BTreeMap<String, u32>,
+        use_shortest_paths: bool,
+    ) -> Result<Self, NetGraphError> {
+        for (name, id) in hosts {
+            if graph.node_id_to_index(*id).is_none() {
+                return Err(
+                    format!("The network node id {id} for host '{name}' does not exist").into(),
+                );
+            }
+        }
+
+        // the hosts assigned to each graph node, sorted by name
+        let mut node_hosts: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for (name, id) in hosts {
+            node_hosts.entry(*id).or_default().push(name.clone());
+        }
+
+        let nodes: Vec<u32> = node_hosts.keys().copied().collect();
+        let indexes: Vec<NodeIndex> = nodes
+            .iter()
+            .map(|x| *graph.node_id_to_index(*x).unwrap())
+            .collect();
+
+        let paths = if use_shortest_paths {
+            graph.compute_shortest_paths(&indexes)?
+        } else {
+            graph.get_direct_paths(&indexes)?
+        };
+
+        // reachability is checked per pair of graph nodes rather than per pair of hosts, since
+        // there may be many more hosts than nodes
+        let mut path_entries = Vec::new();
+        let mut unreachable_node_pairs = Vec::new();
+        for (source, source_index) in nodes.iter().zip(&indexes) {
+            for (target, target_index) in nodes.iter().zip(&indexes) {
+                if let Some(path) = paths.get(&(*source_index, *target_index)) {
+                    path_entries.push(PathEntry {
+                        source: *source,
+                        target: *target,
+                        latency_ns: path.latency_ns,
+                        jitter_ns: path.jitter_ns,
+                        packet_loss: path.packet_loss,
+                    });
+                } else {
+                    unreachable_node_pairs.push(UnreachableNodePair {
+                        source: *source,
+                        target: *target,
+                        source_hosts: node_hosts[source].clone(),
+                        target_hosts: node_hosts[target].clone(),
+                    });
+                }
+            }
+        }
+
+        let nodes_without_self_loops = nodes
+            .iter()
+            .zip(&indexes)
+            .filter(|(_, index)| graph.graph().find_edge(**index, **index).is_none())
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut asymmetric_edges: Vec<_> = if graph.graph().is_directed() {
+            graph
+                .graph()
+                .raw_edges()
+                .iter()
+                .filter(|e| e.source() != e.target())
+                .filter(|e| graph.graph().find_edge(e.target(), e.source()).is_none())
+                .map(|e| (e.weight.source, e.weight.target))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        asymmetric_edges.sort();
+
+        Ok(Self {
+            unreachable_node_pairs,
+            nodes_without_self_loops,
+            asymmetric_edges,
+            paths: path_entries,
+        })
+    }
+
+    /// Whether every host can reach every other host.
+    pub fn is_routable(&self) -> bool {
+        self.unreachable_node_pairs.is_empty()
+    }
+
+    /// Write the paths as CSV, one path per row.
+    pub fn write_paths_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "source,target,latency_ns,jitter_ns,packet_loss")?;
+        for path in &self.paths {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                path.source, path.target, path.latency_ns, path.jitter_ns, path.packet_loss
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the whole report as JSON.
+    pub fn write_json(&self, writer: impl Write) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(writer, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let graph = r#"graph [
+          directed 1
+          node [
+            id 0
+          ]
+          node [
+            id 1
+          ]
+          node [
+            id 2
+          ]
+          edge [
+            source 0
+            target 0
+            latency "1 ms"
+          ]
+          edge [
+            source 0
+            target 1
+            latency "2 ms"
+            packet_loss 0.5
+          ]
+          edge [
+            source 1
+            target 0
+            latency "3 ms"
+          ]
+          edge [
+            source 1
+            target 2
+            latency "4 ms"
+          ]
+        ]"#;
+        let graph = NetworkGraph::parse(graph).unwrap();
+
+        let hosts: BTreeMap<String, u32> = [("a", 0), ("b", 1), ("c", 2), ("d", 2)]
+            .into_iter()
+            .map(|(name, id)| (name.to_string(), id))
+            .collect();
+
+        let report = GraphReport::new(&graph, &hosts, true).unwrap();
+        assert!(!report.is_routable());
+        let unreachable: Vec<_> = report
+            .unreachable_node_pairs
+            .iter()
+            .map(|x| {
+                (
+                    x.source,
+                    x.target,
+                    x.source_hosts.join(","),
+                    x.target_hosts.join(","),
+                )
+            })
+            .collect();
+        assert_eq!(
+            unreachable,
+            [
+                (1, 1, "b", "b"),
+                (2, 0, "c,d", "a"),
+                (2, 1, "c,d", "b"),
+                (2, 2, "c,d", "c,d"),
+            ]
+            .map(|(s, t, a, b)| (s, t, a.to_string(), b.to_string()))
+        );
+        assert_eq!(report.nodes_without_self_loops, [1, 2]);
+        assert_eq!(report.asymmetric_edges, [(1, 2)]);
+
+        let path = |source, target| {
+            report
+                .paths
+                .iter()
+                .find(|x| (x.source, x.target) == (source, target))
+        };
+        assert_eq!(path(0, 1).unwrap().latency_ns, 2_000_000);
+        assert_eq!(path(0, 1).unwrap().packet_loss, 0.5);
+        assert_eq!(path(1, 0).unwrap().latency_ns, 3_000_000);
+        assert_eq!(path(0, 2).unwrap().latency_ns, 6_000_000);
+        assert!(path(2, 0).is_none());
+
+        // without shortest paths, only direct edges are used
+        let report = GraphReport::new(&graph, &hosts, false).unwrap();
+        assert!(report.paths.iter().all(|x| (x.source, x.target) != (0, 2)));
+
+        let mut csv = Vec::new();
+        report.write_paths_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().next().unwrap(),
+            "source,target,latency_ns,jitter_ns,packet_loss"
+        );
+        assert!(csv.lines().any(|x| x == "0,1,2000000,0,0.5"));
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["asymmetric_edges"], serde_json::json!([[1, 2]]));
+
+        // hosts must be assigned to nodes that exist
+        let hosts = [("a".to_string(), 3)].into_iter().collect();
+        assert!(GraphReport::new(&graph, &hosts, true).is_err());
+    }
+}
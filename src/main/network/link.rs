@@ -0,0 +1,343 @@
+//! Capacity-limited links between nodes of the network graph.
+//!
+//! Packets that are sent along a path crossing one or more capacity-limited links are held back
+//! until the end of the scheduling round. Between rounds, the packets are passed through their
+//! links in the order they arrive at each link. Each link transmits one packet at a time at its
+//! capacity, and drops packets that don't fit in its queue.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crossbeam::atomic::AtomicCell;
+use serde::Serialize;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::core::work::event::Event;
+use crate::network::graph::{PathLink, PathLinks};
+use crate::network::packet::{Packet, PacketStatus};
+
+/// A packet that still needs to cross the capacity-limited links along its path.
+struct PendingPacket {
+    send_time: EmulatedTime,
+    size_bytes: u64,
+    links: PathLinks,
+    /// The packet delivery event, scheduled for the time the packet would arrive if the links
+    /// were idle.
+    event: Event,
+    /// The packet that `event` delivers, which is marked as dropped if a link drops it.
+    packet: Arc<AtomicCell<Option<Packet>>>,
+}
+
+// `AtomicCell` is only `Debug` for `Copy` types
+impl std::fmt::Debug for PendingPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingPacket")
+            .field("send_time", &self.send_time)
+            .field("size_bytes", &self.size_bytes)
+            .field("links", &self.links)
+            .field("event", &self.event)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The state of a link's transmit queue.
+#[derive(Debug, Default)]
+struct LinkState {
+    /// The time at which the link will have finished transmitting all of its queued packets.
+    busy_until: Option<EmulatedTime>,
+    packets: u64,
+    bytes: u64,
+    dropped_packets: u64,
+    dropped_bytes: u64,
+    /// Total time spent transmitting packets in nanoseconds.
+    busy_ns: u64,
+}
+
+/// Statistics for a link, to be output at the end of the simulation.
+#[derive(Serialize, Clone, Debug)]
+pub struct LinkStats {
+    pub packets: u64,
+    pub bytes: u64,
+    pub dropped_packets: u64,
+    pub dropped_bytes: u64,
+    /// Fraction of the simulation that the link was transmitting packets.
+    pub utilization: f64,
+}
+
+/// The capacity-limited links of the network graph, keyed by the nodes that packets enter and
+/// leave them from. In an undirected graph, each direction of an edge is a separate link.
+#[derive(Debug, Default)]
+pub struct Links {
+    pending: Mutex<Vec<PendingPacket>>,
+    links: Mutex<HashMap<(u32, u32), LinkState>>,
+}
+
+impl Links {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold a packet delivery event until the end of the round, when the packet is passed through
+    /// the links along its path. The `packet` is the packet that `event` delivers.
+    pub fn push(
+        &self,
+        send_time: EmulatedTime,
+        size_bytes: u64,
+        links: PathLinks,
+        event: Event,
+        packet: Arc<AtomicCell<Option<Packet>>>,
+    ) {
+        self.pending.lock().unwrap().push(PendingPacket {
+            send_time,
+            size_bytes,
+            links,
+            event,
+            packet,
+        });
+    }
+
+    /// Pass all packets held during the round through their links, and deliver the packets that
+    /// weren't dropped using `deliver`. Packets are never delivered before `round_end`. Returns
+    /// the earliest delivery time of the delivered packets.
+    pub fn transmit_pending(
+        &self,
+        round_end: EmulatedTime,
+        mut deliver: impl FnMut(Event),
+    ) -> Option<EmulatedTime> {
+        let mut pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let mut links = self.links.lock().unwrap();
+
+        // packets were pushed by worker threads in a non-deterministic order
+        pending.sort_by(|a, b| {
+            a.send_time
+                .cmp(&b.send_time)
+                .then_with(|| a.event.partial_cmp(&b.event).unwrap())
+        });
+
+        let delays = {
+            let packets: Vec<_> = pending
+                .iter()
+                .map(|x| (x.send_time, x.size_bytes, &x.links[..]))
+                .collect();
+            transmit(&mut links, &packets)
+        };
+
+        let mut min_time = None;
+
+        for (mut packet, delay) in pending.into_iter().zip(delays) {
+            let Some(delay) = delay else {
+                // the packet won't be delivered, so its event is dropped along with it
+                if let Some(mut dropped) = packet.packet.take() {
+                    dropped.add_status(PacketStatus::InetDropped);
+                }
+                continue;
+            };
+
+            let time = std::cmp::max(packet.event.time() + delay, round_end);
+            packet.event.set_time(time);
+
+            min_time = [min_time, Some(time)].into_iter().flatten().min();
+            deliver(packet.event);
+        }
+
+        min_time
+    }
+
+    /// Get the statistics for each link that packets have crossed, where `duration` is the
+    /// amount of time that has been simulated.
+    pub fn stats(&self, duration: SimulationTime) -> BTreeMap<(u32, u32), LinkStats> {
+        self.links
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, state)| {
+                let utilization = if duration.is_zero() {
+                    0.0
+                } else {
+                    // the last packets may finish transmitting after the simulation has ended
+                    f64::min(state.busy_ns as f64 / duration.as_nanos_f64(), 1.0)
+                };
+                let stats = LinkStats {
+                    packets: state.packets,
+                    bytes: state.bytes,
+                    dropped_packets: state.dropped_packets,
+                    dropped_bytes: state.dropped_bytes,
+                    utilization,
+                };
+                (*id, stats)
+            })
+            .collect()
+    }
+}
+
+/// Pass packets through the capacity-limited links along their paths, where each packet is given
+/// as its send time, size in bytes, and links. Each link transmits packets in the order that they
+/// arrive at it, and packets that arrive at the same time are transmitted in the order given.
+/// Returns the queueing and transmission delay of each packet, or `None` if a link dropped it.
+fn transmit(
+    links: &mut HashMap<(u32, u32), LinkState>,
+    packets: &[(EmulatedTime, u64, &[PathLink])],
+) -> Vec<Option<SimulationTime>> {
+    // links with unlimited capacity don't queue packets
+    let next_hop =
+        |path: &[PathLink], from: usize| (from..path.len()).find(|&i| path[i].capacity.is_some());
+
+    let mut delays = vec![Some(SimulationTime::ZERO); packets.len()];
+
+    // the arrival time of each packet at the next link along its path
+    let mut arrivals = BinaryHeap::new();
+    for (index, (send_time, _, path)) in packets.iter().enumerate() {
+        if let Some(hop) = next_hop(path, 0) {
+            let arrival = *send_time + SimulationTime::from_nanos(path[hop].offset_ns);
+            arrivals.push(Reverse((arrival, index, hop)));
+        }
+    }
+
+    while let Some(Reverse((arrival, index, hop))) = arrivals.pop() {
+        let (send_time, size_bytes, path) = packets[index];
+        let link = &path[hop];
+        let capacity = link.capacity.unwrap();
+        let state = links.entry((link.source, link.target)).or_default();
+
+        let start = std::cmp::max(arrival, state.busy_until.unwrap_or(arrival));
+        let wait = start - arrival;
+
+        // the number of bytes that are still queued ahead of this packet
+        let queued_bytes =
+            wait.as_nanos() * u128::from(capacity.bandwidth_bits) / 8 / 1_000_000_000;
+        if queued_bytes + u128::from(size_bytes) > u128::from(capacity.queue_size_bytes) {
+            state.dropped_packets += 1;
+            state.dropped_bytes += size_bytes;
+            log::trace!(
+                "Dropped packet of {} bytes at full link {}->{}",
+                size_bytes,
+                link.source,
+                link.target,
+            );
+            delays[index] = None;
+            continue;
+        }
+
+        // round up so that the link never exceeds its capacity
+        let bits = u128::from(capacity.bandwidth_bits);
+        let transmit_ns = (u128::from(size_bytes) * 8 * 1_000_000_000 + bits - 1) / bits;
+        let transmit_ns: u64 = transmit_ns.try_into().unwrap();
+        let transmit = SimulationTime::from_nanos(transmit_ns);
+
+        state.busy_until = Some(start + transmit);
+        state.busy_ns += transmit_ns;
+        state.packets += 1;
+        state.bytes += size_bytes;
+
+        let delay = delays[index].unwrap() + wait + transmit;
+        delays[index] = Some(delay);
+
+        if let Some(hop) = next_hop(path, hop + 1) {
+            let arrival = send_time + SimulationTime::from_nanos(path[hop].offset_ns) + delay;
+            arrivals.push(Reverse((arrival, index, hop)));
+        }
+    }
+
+    delays
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::graph::LinkCapacity;
+    use crate::network::tests::mock_time_millis;
+
+    fn link(source: u32, target: u32, offset_ms: u64, queue_size_bytes: u64) -> PathLink {
+        PathLink {
+            source,
+            target,
+            offset_ns: offset_ms * 1_000_000,
+            // one byte per microsecond
+            capacity: Some(LinkCapacity {
+                bandwidth_bits: 8_000_000,
+                queue_size_bytes,
+            }),
+            burst_loss: None,
+        }
+    }
+
+    #[test]
+    fn test_transmit_in_arrival_order() {
+        let mut links = HashMap::new();
+
+        // the first packet is sent earlier, but arrives at the 2->3 link after the second packet
+        let path_1 = [link(1, 2, 0, 10_000), link(2, 3, 10, 10_000)];
+        let path_2 = [link(2, 3, 0, 10_000)];
+        let packets = [
+            (mock_time_millis(0), 1000, &path_1[..]),
+            (mock_time_millis(1), 1000, &path_2[..]),
+        ];
+
+        let delays = transmit(&mut links, &packets);
+        assert_eq!(
+            delays,
+            [
+                Some(SimulationTime::from_millis(2)),
+                Some(SimulationTime::from_millis(1)),
+            ]
+        );
+        assert_eq!(links[&(2, 3)].packets, 2);
+    }
+
+    #[test]
+    fn test_transmit_queueing() {
+        let mut links = HashMap::new();
+
+        // packets arriving at the same time are queued in the order given
+        let path = [link(1, 2, 0, 10_000)];
+        let packets = [
+            (mock_time_millis(0), 1000, &path[..]),
+            (mock_time_millis(0), 500, &path[..]),
+            (mock_time_millis(2), 1000, &path[..]),
+        ];
+
+        let delays = transmit(&mut links, &packets);
+        assert_eq!(
+            delays,
+            [
+                Some(SimulationTime::from_millis(1)),
+                Some(SimulationTime::from_micros(1500)),
+                Some(SimulationTime::from_millis(1)),
+            ]
+        );
+
+        // the link stays busy in later rounds
+        let delays = transmit(&mut links, &[(mock_time_millis(2), 1000, &path[..])]);
+        assert_eq!(delays, [Some(SimulationTime::from_millis(2))]);
+    }
+
+    #[test]
+    fn test_transmit_drops() {
+        let mut links = HashMap::new();
+
+        let path = [link(1, 2, 0, 1500), link(2, 3, 5, 1500)];
+        let packets = [
+            (mock_time_millis(0), 1000, &path[..]),
+            (mock_time_millis(0), 1000, &path[..]),
+            (mock_time_millis(0), 400, &path[..]),
+        ];
+
+        // the second packet doesn't fit behind the first in the queue of the first link, so it
+        // never reaches the second link
+        let delays = transmit(&mut links, &packets);
+        assert_eq!(
+            delays,
+            [
+                Some(SimulationTime::from_millis(2)),
+                None,
+                Some(SimulationTime::from_micros(2400)),
+            ]
+        );
+        assert_eq!(links[&(1, 2)].dropped_packets, 1);
+        assert_eq!(links[&(1, 2)].dropped_bytes, 1000);
+        assert_eq!(links[&(2, 3)].packets, 2);
+        assert_eq!(links[&(2, 3)].dropped_packets, 0);
+    }
+}
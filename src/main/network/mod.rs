@@ -1,4 +1,6 @@
 pub mod graph;
+pub mod link;
+pub mod nat;
 pub mod net_namespace;
 pub mod packet;
 mod relay;
@@ -0,0 +1,704 @@
+//! Network address translation for hosts that are assigned to graph nodes with a NAT.
+//!
+//! Each internal address behind a NAT is given its own block of the NAT's public ports, so the
+//! internal address that an inbound packet is for can be found from its destination port without
+//! any state that's shared between hosts. The mappings themselves are kept in a [`NatTable`] by
+//! the host that owns the internal address, and are only used from that host's thread so that the
+//! simulation stays deterministic.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::ops::RangeInclusive;
+
+use log::{debug, trace, warn};
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::cshadow as c;
+use crate::network::graph::{NatConfig, NatType};
+use crate::network::packet::Packet;
+use crate::utility::pcap_writer::{PacketDisplay, PcapWriter};
+
+/// The lowest public port that a NAT assigns to mappings. Lower ports are never used.
+const FIRST_PORT: u16 = 1024;
+
+/// The NATs of the network graph and the internal addresses behind them. This doesn't change
+/// during the simulation.
+#[derive(Debug, Default)]
+pub struct Nats {
+    nats: Vec<Nat>,
+    by_public_address: HashMap<Ipv4Addr, usize>,
+    by_internal_address: HashMap<Ipv4Addr, usize>,
+}
+
+impl Nats {
+    /// The NATs of graph nodes, and the addresses of all host interfaces with their graph nodes.
+    /// Returns an error if a NAT has more internal addresses than public ports.
+    pub fn new(
+        nats: impl IntoIterator<Item = (u32, NatConfig)>,
+        addresses: impl IntoIterator<Item = (u32, Ipv4Addr)>,
+    ) -> Result<Self, String> {
+        let mut addresses_by_node: HashMap<u32, Vec<Ipv4Addr>> = HashMap::new();
+        for (node_id, addr) in addresses {
+            addresses_by_node.entry(node_id).or_default().push(addr);
+        }
+
+        let mut nats: Vec<_> = nats.into_iter().collect();
+        nats.sort_by_key(|(node_id, _)| *node_id);
+
+        let mut rv = Self::default();
+        for (node_id, config) in nats {
+            let mut internal_addresses = addresses_by_node.remove(&node_id).unwrap_or_default();
+            internal_addresses.sort();
+
+            let num_ports = u32::from(u16::MAX - FIRST_PORT) + 1;
+            let num_addresses = u32::try_from(internal_addresses.len()).unwrap();
+            if num_addresses > num_ports {
+                return Err(format!(
+                    "The NAT of node {node_id} has {num_addresses} internal addresses, but only \
+                     {num_ports} public ports"
+                ));
+            }
+            let ports_per_address = num_ports
+                .checked_div(num_addresses)
+                .unwrap_or(0)
+                .try_into()
+                .unwrap();
+
+            let index = rv.nats.len();
+            rv.by_public_address.insert(config.address, index);
+            for addr in &internal_addresses {
+                rv.by_internal_address.insert(*addr, index);
+            }
+            rv.nats.push(Nat {
+                node_id,
+                config,
+                internal_addresses,
+                ports_per_address,
+            });
+        }
+
+        Ok(rv)
+    }
+
+    /// The NAT that an internal address is behind.
+    pub fn behind(&self, addr: Ipv4Addr) -> Option<&Nat> {
+        self.by_internal_address.get(&addr).map(|x| &self.nats[*x])
+    }
+
+    /// The NAT with the public address.
+    pub fn with_public_address(&self, addr: Ipv4Addr) -> Option<&Nat> {
+        self.by_public_address.get(&addr).map(|x| &self.nats[*x])
+    }
+}
+
+/// The NAT of a graph node.
+#[derive(Debug)]
+pub struct Nat {
+    pub node_id: u32,
+    pub config: NatConfig,
+    /// The internal addresses in ascending order, which are each given an equal block of the
+    /// public ports in that order.
+    internal_addresses: Vec<Ipv4Addr>,
+    ports_per_address: u16,
+}
+
+impl Nat {
+    /// The public ports of an internal address. Panics if the address isn't behind this NAT.
+    pub fn ports(&self, internal_address: Ipv4Addr) -> RangeInclusive<u16> {
+        let index = self
+            .internal_addresses
+            .binary_search(&internal_address)
+            .unwrap();
+        let index = u16::try_from(index).unwrap();
+        let first = FIRST_PORT + index * self.ports_per_address;
+        first..=(first + (self.ports_per_address - 1))
+    }
+
+    /// The internal address that a public port belongs to, if any.
+    pub fn internal_address(&self, public_port: u16) -> Option<Ipv4Addr> {
+        if self.ports_per_address == 0 {
+            return None;
+        }
+        let index = public_port.checked_sub(FIRST_PORT)? / self.ports_per_address;
+        self.internal_addresses.get(usize::from(index)).copied()
+    }
+}
+
+/// The NAT mappings of a host interface that is behind a NAT. Packets sent from the interface
+/// create and refresh mappings, and packets sent to the NAT's public address are only forwarded to
+/// the interface if they match a mapping.
+pub struct NatTable {
+    config: NatConfig,
+    internal_address: Ipv4Addr,
+    ports: RangeInclusive<u16>,
+    /// The port that the next mapping will try to use.
+    next_port: u16,
+    mappings: HashMap<MappingKey, Mapping>,
+    /// The mapping of each protocol and public port in use.
+    public_ports: HashMap<(c::ProtocolType, u16), MappingKey>,
+    /// Packets as they are on the NAT's public side.
+    pcap: Option<PcapWriter<BufWriter<File>>>,
+    num_mappings: u64,
+    num_dropped: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct MappingKey {
+    protocol: c::ProtocolType,
+    internal_port: u16,
+    /// Only a symmetric NAT has a separate mapping for each remote address and port.
+    remote: Option<SocketAddrV4>,
+}
+
+#[derive(Debug)]
+struct Mapping {
+    public_port: u16,
+    last_used: EmulatedTime,
+    /// The remote addresses that the internal host has sent packets to using this mapping.
+    remotes: HashSet<Ipv4Addr>,
+}
+
+impl NatTable {
+    pub fn new(
+        nat: &Nat,
+        internal_address: Ipv4Addr,
+        pcap: Option<PcapWriter<BufWriter<File>>>,
+    ) -> Self {
+        let ports = nat.ports(internal_address);
+        Self {
+            config: nat.config,
+            internal_address,
+            next_port: *ports.start(),
+            ports,
+            mappings: HashMap::new(),
+            public_ports: HashMap::new(),
+            pcap,
+            num_mappings: 0,
+            num_dropped: 0,
+        }
+    }
+
+    /// Rewrite the source of a packet sent from the internal address to the NAT's public address
+    /// and the port of its mapping. Returns `false` if there's no free port for a new mapping, in
+    /// which case the packet should be dropped.
+    pub fn translate_outbound(&mut self, now: EmulatedTime, packet: &mut Packet) -> bool {
+        let protocol = packet.protocol();
+        let source = packet.source();
+        let remote = packet.destination();
+        debug_assert_eq!(*source.ip(), self.internal_address);
+
+        let key = MappingKey {
+            protocol,
+            internal_port: source.port(),
+            remote: (self.config.nat_type == NatType::Symmetric).then_some(remote),
+        };
+
+        if self.is_expired(&key, now) {
+            self.remove(&key);
+        }
+
+        let public_port = match self.mappings.get(&key) {
+            Some(mapping) => mapping.public_port,
+            None => {
+                let Some(public_port) = self.allocate_port(protocol, now) else {
+                    debug!(
+                        "NAT {} has no free port for {} {source}",
+                        self.config.address,
+                        protocol_name(protocol),
+                    );
+                    self.num_dropped += 1;
+                    return false;
+                };
+
+                debug!(
+                    "NAT {} mapped {} {source} to {}:{public_port}",
+                    self.config.address,
+                    protocol_name(protocol),
+                    self.config.address,
+                );
+                self.mappings.insert(
+                    key,
+                    Mapping {
+                        public_port,
+                        last_used: now,
+                        remotes: HashSet::new(),
+                    },
+                );
+                self.public_ports.insert((protocol, public_port), key);
+                self.num_mappings += 1;
+                public_port
+            }
+        };
+
+        let mapping = self.mappings.get_mut(&key).unwrap();
+        mapping.last_used = now;
+        mapping.remotes.insert(*remote.ip());
+
+        packet.set_source(SocketAddrV4::new(self.config.address, public_port));
+        self.capture(now, packet);
+        true
+    }
+
+    /// The public address and port that a packet of `protocol` sent from `internal_port` to
+    /// `remote` would be given by [`translate_outbound`](Self::translate_outbound), without
+    /// creating or refreshing a mapping. If there's no mapping yet, this is the port that the next
+    /// mapping would use. Returns `None` if there's no free port for a new mapping.
+    pub fn peek_outbound(
+        &self,
+        now: EmulatedTime,
+        protocol: c::ProtocolType,
+        internal_port: u16,
+        remote: SocketAddrV4,
+    ) -> Option<SocketAddrV4> {
+        let key = MappingKey {
+            protocol,
+            internal_port,
+            remote: (self.config.nat_type == NatType::Symmetric).then_some(remote),
+        };
+
+        let public_port = match self.mappings.get(&key) {
+            Some(mapping) if !self.is_expired(&key, now) => mapping.public_port,
+            _ => self.next_free_port(protocol, now)?,
+        };
+
+        Some(SocketAddrV4::new(self.config.address, public_port))
+    }
+
+    /// Rewrite the destination of a packet sent to the NAT's public address to the internal
+    /// address and port of its mapping. Returns `false` if the packet doesn't match a mapping that
+    /// allows it, in which case the packet should be dropped.
+    pub fn translate_inbound(&mut self, now: EmulatedTime, packet: &mut Packet) -> bool {
+        self.capture(now, packet);
+
+        let protocol = packet.protocol();
+        let remote = packet.source();
+        let public_port = packet.destination().port();
+
+        let Some(key) = self.public_ports.get(&(protocol, public_port)).copied() else {
+            trace!(
+                "NAT {} dropped unsolicited {} packet from {remote} to port {public_port}",
+                self.config.address,
+                protocol_name(protocol),
+            );
+            self.num_dropped += 1;
+            return false;
+        };
+
+        if self.is_expired(&key, now) {
+            self.remove(&key);
+            trace!(
+                "NAT {} dropped {} packet from {remote} to expired port {public_port}",
+                self.config.address,
+                protocol_name(protocol),
+            );
+            self.num_dropped += 1;
+            return false;
+        }
+
+        let mapping = self.mappings.get_mut(&key).unwrap();
+        let is_allowed = match self.config.nat_type {
+            NatType::FullCone => true,
+            NatType::RestrictedCone => mapping.remotes.contains(remote.ip()),
+            NatType::Symmetric => key.remote == Some(remote),
+        };
+
+        if !is_allowed {
+            trace!(
+                "NAT {} dropped {} packet from {remote} to port {public_port} that isn't allowed \
+                 by its {} mapping",
+                self.config.address,
+                protocol_name(protocol),
+                self.config.nat_type,
+            );
+            self.num_dropped += 1;
+            return false;
+        }
+
+        mapping.last_used = now;
+        packet.set_destination(SocketAddrV4::new(self.internal_address, key.internal_port));
+        true
+    }
+
+    /// Log the number of mappings and dropped packets.
+    pub fn log_summary(&self, hostname: &str) {
+        log::info!(
+            "host '{hostname}' address {} behind {} NAT {} created {} mappings and had {} packets \
+             dropped",
+            self.internal_address,
+            self.config.nat_type,
+            self.config.address,
+            self.num_mappings,
+            self.num_dropped,
+        );
+    }
+
+    fn is_expired(&self, key: &MappingKey, now: EmulatedTime) -> bool {
+        let timeout = SimulationTime::try_from(self.config.timeout).unwrap();
+        self.mappings
+            .get(key)
+            .map(|x| now > x.last_used + timeout)
+            .unwrap_or(false)
+    }
+
+    fn remove(&mut self, key: &MappingKey) {
+        let mapping = self.mappings.remove(key).unwrap();
+        self.public_ports
+            .remove(&(key.protocol, mapping.public_port));
+        debug!(
+            "NAT {} mapping of {} {}:{} to port {} expired",
+            self.config.address,
+            protocol_name(key.protocol),
+            self.internal_address,
+            key.internal_port,
+            mapping.public_port,
+        );
+    }
+
+    /// Find a public port that isn't used by a mapping for the protocol, reusing the ports of
+    /// expired mappings.
+    fn allocate_port(&mut self, protocol: c::ProtocolType, now: EmulatedTime) -> Option<u16> {
+        let port = self.next_free_port(protocol, now)?;
+        if let Some(key) = self.public_ports.get(&(protocol, port)).copied() {
+            self.remove(&key);
+        }
+
+        self.next_port = if port == *self.ports.end() {
+            *self.ports.start()
+        } else {
+            port + 1
+        };
+        Some(port)
+    }
+
+    /// The first public port starting at `next_port` that isn't used by an unexpired mapping for
+    /// the protocol.
+    fn next_free_port(&self, protocol: c::ProtocolType, now: EmulatedTime) -> Option<u16> {
+        let (start, end) = (*self.ports.start(), *self.ports.end());
+        (self.next_port..=end)
+            .chain(start..self.next_port)
+            .find(|port| match self.public_ports.get(&(protocol, *port)) {
+                None => true,
+                Some(key) => self.is_expired(key, now),
+            })
+    }
+
+    fn capture(&mut self, now: EmulatedTime, packet: &Packet) {
+        let Some(pcap) = &mut self.pcap else {
+            return;
+        };
+
+        let now = now.to_abs_simtime();
+        let ts_sec = u32::try_from(now.as_secs()).unwrap();
+        let ts_usec = u32::try_from(now.subsec_micros()).unwrap();
+        let packet: *const c::Packet = packet.borrow_inner();
+        let packet_len = unsafe { c::packet_getTotalSize(packet) }
+            .try_into()
+            .unwrap();
+
+        if let Err(e) = pcap.write_packet_fmt(ts_sec, ts_usec, packet_len, |writer| {
+            packet.display_bytes(writer)
+        }) {
+            warn!("Unable to write packet to NAT pcap output, so stopping: {e}");
+            self.pcap = None;
+        }
+    }
+}
+
+fn protocol_name(protocol: c::ProtocolType) -> &'static str {
+    match protocol {
+        c::_ProtocolType_PTCP => "TCP",
+        c::_ProtocolType_PUDP => "UDP",
+        c::_ProtocolType_PICMP => "ICMP",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nat_config() -> NatConfig {
+        NatConfig {
+            nat_type: NatType::FullCone,
+            address: Ipv4Addr::new(1, 2, 3, 4),
+            timeout: std::time::Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_port_blocks() {
+        let a = Ipv4Addr::new(10, 0, 0, 1);
+        let b = Ipv4Addr::new(10, 0, 0, 2);
+        let c = Ipv4Addr::new(10, 0, 0, 3);
+        let other = Ipv4Addr::new(11, 0, 0, 1);
+
+        let nats = Nats::new([(0, nat_config())], [(0, c), (0, a), (1, other), (0, b)]).unwrap();
+        let nat = nats.with_public_address(Ipv4Addr::new(1, 2, 3, 4)).unwrap();
+        assert_eq!(nat.node_id, 0);
+        assert_eq!(nats.behind(a).unwrap().node_id, 0);
+        assert!(nats.behind(other).is_none());
+
+        // the blocks are in address order, and don't overlap
+        assert_eq!(nat.ports(a), 1024..=22527);
+        assert_eq!(nat.ports(b), 22528..=44031);
+        assert_eq!(nat.ports(c), 44032..=65535);
+
+        assert_eq!(nat.internal_address(1023), None);
+        assert_eq!(nat.internal_address(1024), Some(a));
+        assert_eq!(nat.internal_address(22527), Some(a));
+        assert_eq!(nat.internal_address(22528), Some(b));
+        assert_eq!(nat.internal_address(65535), Some(c));
+    }
+
+    #[test]
+    fn test_no_internal_addresses() {
+        let nats = Nats::new([(0, nat_config())], []).unwrap();
+        let nat = nats.with_public_address(Ipv4Addr::new(1, 2, 3, 4)).unwrap();
+        assert_eq!(nat.internal_address(1024), None);
+    }
+
+    const PUBLIC: Ipv4Addr = Ipv4Addr::new(1, 2, 3, 4);
+    const INTERNAL: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 5000);
+    const REMOTE_1: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 1), 80);
+    const REMOTE_2: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 80);
+
+    /// The NAT table of [`INTERNAL`], which is behind a NAT with `num_internal_addresses`
+    /// addresses.
+    fn nat_table(nat_type: NatType, num_internal_addresses: u32) -> NatTable {
+        let config = NatConfig {
+            nat_type,
+            ..nat_config()
+        };
+        let addresses = (0..num_internal_addresses).map(|x| (0, Ipv4Addr::from(0x0a00_0001 + x)));
+        let nats = Nats::new([(0, config)], addresses).unwrap();
+        NatTable::new(nats.behind(*INTERNAL.ip()).unwrap(), *INTERNAL.ip(), None)
+    }
+
+    fn time(secs: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_secs(secs)
+    }
+
+    /// Send a packet from `source` to `remote`, and return the public port that the NAT sent it
+    /// from.
+    fn outbound(
+        table: &mut NatTable,
+        now: EmulatedTime,
+        source: SocketAddrV4,
+        remote: SocketAddrV4,
+    ) -> Option<u16> {
+        let mut packet = Packet::mock_new_udp(source, remote);
+        if !table.translate_outbound(now, &mut packet) {
+            return None;
+        }
+        assert_eq!(*packet.source().ip(), PUBLIC);
+        assert_eq!(packet.destination(), remote);
+        Some(packet.source().port())
+    }
+
+    /// Send a packet from `remote` to the public port, and return the internal address and port
+    /// that the NAT forwarded it to.
+    fn inbound(
+        table: &mut NatTable,
+        now: EmulatedTime,
+        remote: SocketAddrV4,
+        public_port: u16,
+    ) -> Option<SocketAddrV4> {
+        let mut packet = Packet::mock_new_udp(remote, SocketAddrV4::new(PUBLIC, public_port));
+        if !table.translate_inbound(now, &mut packet) {
+            return None;
+        }
+        assert_eq!(packet.source(), remote);
+        Some(packet.destination())
+    }
+
+    #[test]
+    fn test_full_cone() {
+        let mut table = nat_table(NatType::FullCone, 1);
+
+        // unsolicited packets are dropped
+        assert_eq!(inbound(&mut table, time(0), REMOTE_1, 1024), None);
+
+        let port = outbound(&mut table, time(0), INTERNAL, REMOTE_1).unwrap();
+        assert_eq!(port, 1024);
+
+        // the mapping is reused for other remotes, and forwards packets from any remote
+        assert_eq!(
+            outbound(&mut table, time(0), INTERNAL, REMOTE_2),
+            Some(port)
+        );
+        let other_port = SocketAddrV4::new(*REMOTE_1.ip(), 81);
+        let unknown = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 3), 80);
+        for remote in [REMOTE_1, REMOTE_2, other_port, unknown] {
+            assert_eq!(inbound(&mut table, time(0), remote, port), Some(INTERNAL));
+        }
+
+        // packets to ports without a mapping are dropped
+        assert_eq!(inbound(&mut table, time(0), REMOTE_1, port + 1), None);
+
+        // another internal port gets its own mapping
+        let source = SocketAddrV4::new(*INTERNAL.ip(), 5001);
+        let port_2 = outbound(&mut table, time(0), source, REMOTE_1).unwrap();
+        assert_ne!(port_2, port);
+        assert_eq!(inbound(&mut table, time(0), REMOTE_1, port_2), Some(source));
+    }
+
+    #[test]
+    fn test_restricted_cone() {
+        let mut table = nat_table(NatType::RestrictedCone, 1);
+
+        let port = outbound(&mut table, time(0), INTERNAL, REMOTE_1).unwrap();
+
+        // only packets from addresses that were sent to are forwarded, from any of their ports
+        assert_eq!(inbound(&mut table, time(0), REMOTE_1, port), Some(INTERNAL));
+        let other_port = SocketAddrV4::new(*REMOTE_1.ip(), 81);
+        assert_eq!(
+            inbound(&mut table, time(0), other_port, port),
+            Some(INTERNAL)
+        );
+        assert_eq!(inbound(&mut table, time(0), REMOTE_2, port), None);
+
+        // the mapping is reused for other remotes, which are then allowed
+        assert_eq!(
+            outbound(&mut table, time(0), INTERNAL, REMOTE_2),
+            Some(port)
+        );
+        assert_eq!(inbound(&mut table, time(0), REMOTE_2, port), Some(INTERNAL));
+    }
+
+    #[test]
+    fn test_symmetric() {
+        let mut table = nat_table(NatType::Symmetric, 1);
+
+        // each remote address and port gets its own mapping
+        let port_1 = outbound(&mut table, time(0), INTERNAL, REMOTE_1).unwrap();
+        let port_2 = outbound(&mut table, time(0), INTERNAL, REMOTE_2).unwrap();
+        assert_ne!(port_1, port_2);
+        assert_eq!(
+            outbound(&mut table, time(0), INTERNAL, REMOTE_1),
+            Some(port_1)
+        );
+
+        // and only forwards packets from that remote address and port
+        assert_eq!(
+            inbound(&mut table, time(0), REMOTE_1, port_1),
+            Some(INTERNAL)
+        );
+        assert_eq!(
+            inbound(&mut table, time(0), REMOTE_2, port_2),
+            Some(INTERNAL)
+        );
+        assert_eq!(inbound(&mut table, time(0), REMOTE_2, port_1), None);
+        let other_port = SocketAddrV4::new(*REMOTE_1.ip(), 81);
+        assert_eq!(inbound(&mut table, time(0), other_port, port_1), None);
+    }
+
+    #[test]
+    fn test_mapping_timeout() {
+        // the mappings of the test config time out after 60 seconds
+        let mut table = nat_table(NatType::FullCone, 1);
+
+        let port = outbound(&mut table, time(0), INTERNAL, REMOTE_1).unwrap();
+
+        // inbound and outbound packets both refresh the mapping
+        assert_eq!(
+            inbound(&mut table, time(60), REMOTE_1, port),
+            Some(INTERNAL)
+        );
+        assert_eq!(
+            outbound(&mut table, time(120), INTERNAL, REMOTE_1),
+            Some(port)
+        );
+        assert_eq!(
+            inbound(&mut table, time(180), REMOTE_1, port),
+            Some(INTERNAL)
+        );
+
+        // the mapping expires once it hasn't been used for longer than the timeout
+        assert_eq!(inbound(&mut table, time(241), REMOTE_1, port), None);
+
+        // and the next outbound packet creates a new mapping
+        let new_port = outbound(&mut table, time(242), INTERNAL, REMOTE_1).unwrap();
+        assert_ne!(new_port, port);
+        assert_eq!(inbound(&mut table, time(242), REMOTE_1, port), None);
+        assert_eq!(
+            inbound(&mut table, time(242), REMOTE_1, new_port),
+            Some(INTERNAL)
+        );
+        assert_eq!(table.num_mappings, 2);
+    }
+
+    #[test]
+    fn test_peek_outbound() {
+        let mut table = nat_table(NatType::Symmetric, 64512 / 2);
+        let udp = c::_ProtocolType_PUDP;
+        let public = |port| Some(SocketAddrV4::new(PUBLIC, port));
+
+        // before there's a mapping, the port that the next mapping will use is returned without
+        // creating one
+        let peeked = table.peek_outbound(time(0), udp, INTERNAL.port(), REMOTE_1);
+        assert_eq!(peeked, public(1024));
+        assert_eq!(
+            table.peek_outbound(time(0), udp, INTERNAL.port(), REMOTE_1),
+            peeked
+        );
+        assert_eq!(table.num_mappings, 0);
+        assert_eq!(
+            outbound(&mut table, time(0), INTERNAL, REMOTE_1),
+            Some(1024)
+        );
+
+        // an existing mapping's port is returned, and a symmetric NAT uses another port for another
+        // remote
+        assert_eq!(
+            table.peek_outbound(time(0), udp, INTERNAL.port(), REMOTE_1),
+            public(1024)
+        );
+        assert_eq!(
+            table.peek_outbound(time(0), udp, INTERNAL.port(), REMOTE_2),
+            public(1025)
+        );
+        assert_eq!(
+            outbound(&mut table, time(0), INTERNAL, REMOTE_2),
+            Some(1025)
+        );
+
+        // there's no free port for another mapping until the others expire
+        assert_eq!(table.peek_outbound(time(0), udp, 5001, REMOTE_1), None);
+        assert_eq!(
+            table.peek_outbound(time(61), udp, INTERNAL.port(), REMOTE_1),
+            public(1024)
+        );
+        assert_eq!(
+            outbound(&mut table, time(61), INTERNAL, REMOTE_1),
+            Some(1024)
+        );
+    }
+
+    #[test]
+    fn test_port_exhaustion() {
+        // give each internal address a block of 4 ports
+        let mut table = nat_table(NatType::FullCone, 64512 / 4);
+        assert_eq!(table.ports.clone().count(), 4);
+
+        let mut ports = HashSet::new();
+        for internal_port in 5000..5004 {
+            let source = SocketAddrV4::new(*INTERNAL.ip(), internal_port);
+            ports.insert(outbound(&mut table, time(0), source, REMOTE_1).unwrap());
+        }
+        assert_eq!(ports.len(), 4);
+
+        // there's no free port for another mapping, but the existing mappings still work
+        let source = SocketAddrV4::new(*INTERNAL.ip(), 5004);
+        assert_eq!(outbound(&mut table, time(0), source, REMOTE_1), None);
+        assert_eq!(table.num_dropped, 1);
+        assert!(outbound(&mut table, time(30), INTERNAL, REMOTE_1).is_some());
+
+        // once the other mappings expire, their ports are reused
+        let port = outbound(&mut table, time(61), source, REMOTE_1).unwrap();
+        assert!(ports.contains(&port));
+        assert_eq!(inbound(&mut table, time(61), REMOTE_1, port), Some(source));
+    }
+}
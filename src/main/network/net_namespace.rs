@@ -6,6 +6,7 @@ use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 
 use atomic_refcell::AtomicRefCell;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::HostId;
 
 use crate::core::support::configuration::QDiscMode;
@@ -18,6 +19,10 @@ use crate::utility::SyncSendPointer;
 // specify the port it wants to bind to, and for client connections.
 const MIN_RANDOM_PORT: u16 = 10000;
 
+// The delay of packets that a host sends to its own address, unless the host configures a latency
+// for its loopback interface.
+const LOCAL_LATENCY: SimulationTime = SimulationTime::NANOSECOND;
+
 /// Represents a network namespace. Can be thought of as roughly equivalent to a Linux `struct net`.
 /// Shadow doesn't support multiple network namespaces, but this `NetworkNamespace` allows us to
 /// consolidate the host's networking objects, and hopefully might make it easier to support
@@ -33,11 +38,18 @@ pub struct NetworkNamespace {
     pub default_address: SyncSendPointer<cshadow::Address>,
     pub default_ip: Ipv4Addr,
 
+    // the internet interfaces of a multihomed host other than the default interface
+    pub extra_interfaces: Vec<ExtraInterface>,
+
     // used for debugging to make sure we've cleaned up before being dropped
     has_run_cleanup: Cell<bool>,
 }
 
 impl NetworkNamespace {
+    /// Packets on the loopback interface are delayed by `loopback_latency` if set, and otherwise
+    /// arrive almost immediately. They are only limited by the loopback interface's bandwidth if
+    /// `limit_loopback_bandwidth` is set.
+    ///
     /// # Safety
     ///
     /// `dns` must be a valid pointer.
@@ -45,8 +57,11 @@ impl NetworkNamespace {
         host_id: HostId,
         hostname: Vec<NonZeroU8>,
         public_ip: Ipv4Addr,
+        extra_ips: &[Ipv4Addr],
         pcap: Option<PcapOptions>,
         qdisc: QDiscMode,
+        loopback_latency: Option<SimulationTime>,
+        limit_loopback_bandwidth: bool,
         dns: *mut cshadow::DNS,
     ) -> Self {
         let (localhost, local_addr) = unsafe {
@@ -58,6 +73,8 @@ impl NetworkNamespace {
                     uses_router: false,
                     pcap: pcap.clone(),
                     qdisc,
+                    local_latency: loopback_latency.unwrap_or(LOCAL_LATENCY),
+                    limit_local_bandwidth: limit_loopback_bandwidth,
                 },
                 dns,
             )
@@ -69,22 +86,51 @@ impl NetworkNamespace {
             Self::setup_net_interface(
                 &InterfaceOptions {
                     host_id,
-                    hostname,
+                    hostname: hostname.clone(),
                     ip: public_ip,
                     uses_router: true,
-                    pcap,
+                    pcap: pcap.clone(),
                     qdisc,
+                    local_latency: LOCAL_LATENCY,
+                    limit_local_bandwidth: false,
                 },
                 dns,
             )
         };
 
+        let extra_interfaces = extra_ips
+            .iter()
+            .map(|ip| {
+                let (interface, addr) = unsafe {
+                    Self::setup_net_interface(
+                        &InterfaceOptions {
+                            host_id,
+                            hostname: hostname.clone(),
+                            ip: *ip,
+                            uses_router: true,
+                            pcap: pcap.clone(),
+                            qdisc,
+                            local_latency: LOCAL_LATENCY,
+                            limit_local_bandwidth: false,
+                        },
+                        dns,
+                    )
+                };
+                ExtraInterface {
+                    ip: *ip,
+                    interface: RefCell::new(interface),
+                    address: unsafe { SyncSendPointer::new(addr) },
+                }
+            })
+            .collect();
+
         Self {
             unix: Arc::new(AtomicRefCell::new(AbstractUnixNamespace::new())),
             localhost: RefCell::new(localhost),
             internet: RefCell::new(internet),
             default_address: unsafe { SyncSendPointer::new(public_addr) },
             default_ip: public_ip,
+            extra_interfaces,
             has_run_cleanup: Cell::new(false),
         }
     }
@@ -111,6 +157,8 @@ impl NetworkNamespace {
                 options.pcap.clone(),
                 options.qdisc,
                 options.uses_router,
+                options.local_latency,
+                options.limit_local_bandwidth,
             )
         };
 
@@ -128,23 +176,42 @@ impl NetworkNamespace {
         unsafe {
             cshadow::dns_deregister(dns.cast_mut(), self.default_address.ptr());
         }
+        for extra in &self.extra_interfaces {
+            unsafe { cshadow::dns_deregister(dns.cast_mut(), extra.address.ptr()) };
+        }
 
         self.has_run_cleanup.set(true);
     }
 
+    /// The addresses and interfaces of all internet (non-loopback) interfaces, starting with the
+    /// default interface.
+    pub fn internet_interfaces(
+        &self,
+    ) -> impl Iterator<Item = (Ipv4Addr, &RefCell<NetworkInterface>)> + '_ {
+        std::iter::once((self.default_ip, &self.internet)).chain(
+            self.extra_interfaces
+                .iter()
+                .map(|extra| (extra.ip, &extra.interface)),
+        )
+    }
+
+    fn interface(&self, addr: Ipv4Addr) -> Option<&RefCell<NetworkInterface>> {
+        if addr.is_loopback() {
+            Some(&self.localhost)
+        } else {
+            self.internet_interfaces()
+                .find(|(ip, _)| *ip == addr)
+                .map(|(_, interface)| interface)
+        }
+    }
+
     /// Returns `None` if there is no such interface.
     #[track_caller]
     pub fn interface_borrow(
         &self,
         addr: Ipv4Addr,
     ) -> Option<impl Deref<Target = NetworkInterface> + '_> {
-        if addr.is_loopback() {
-            Some(self.localhost.borrow())
-        } else if addr == self.default_ip {
-            Some(self.internet.borrow())
-        } else {
-            None
-        }
+        self.interface(addr).map(|x| x.borrow())
     }
 
     /// Returns `None` if there is no such interface.
@@ -153,13 +220,7 @@ impl NetworkNamespace {
         &self,
         addr: Ipv4Addr,
     ) -> Option<impl Deref<Target = NetworkInterface> + DerefMut + '_> {
-        if addr.is_loopback() {
-            Some(self.localhost.borrow_mut())
-        } else if addr == self.default_ip {
-            Some(self.internet.borrow_mut())
-        } else {
-            None
-        }
+        self.interface(addr).map(|x| x.borrow_mut())
     }
 
     pub fn is_interface_available(
@@ -174,10 +235,11 @@ impl NetworkNamespace {
                 .localhost
                 .borrow()
                 .is_associated(protocol_type, src.port(), dst)
-                && !self
-                    .internet
-                    .borrow()
-                    .is_associated(protocol_type, src.port(), dst)
+                && !self.internet_interfaces().any(|(_, interface)| {
+                    interface
+                        .borrow()
+                        .is_associated(protocol_type, src.port(), dst)
+                })
         } else {
             // The interface is not available if it does not exist.
             match self.interface_borrow(*src.ip()) {
@@ -248,9 +310,11 @@ impl NetworkNamespace {
             self.localhost
                 .borrow()
                 .associate(socket, protocol, bind_addr.port(), peer_addr);
-            self.internet
-                .borrow()
-                .associate(socket, protocol, bind_addr.port(), peer_addr);
+            for (_, interface) in self.internet_interfaces() {
+                interface
+                    .borrow()
+                    .associate(socket, protocol, bind_addr.port(), peer_addr);
+            }
         } else {
             // TODO: return error if interface does not exist
             if let Some(iface) = self.interface_borrow(*bind_addr.ip()) {
@@ -271,9 +335,11 @@ impl NetworkNamespace {
                 .borrow()
                 .disassociate(protocol, bind_addr.port(), peer_addr);
 
-            self.internet
-                .borrow()
-                .disassociate(protocol, bind_addr.port(), peer_addr);
+            for (_, interface) in self.internet_interfaces() {
+                interface
+                    .borrow()
+                    .disassociate(protocol, bind_addr.port(), peer_addr);
+            }
         } else {
             // TODO: return error if interface does not exist
             if let Some(iface) = self.interface_borrow(*bind_addr.ip()) {
@@ -286,6 +352,9 @@ impl NetworkNamespace {
 impl std::ops::Drop for NetworkNamespace {
     fn drop(&mut self) {
         unsafe { cshadow::address_unref(self.default_address.ptr()) };
+        for extra in &self.extra_interfaces {
+            unsafe { cshadow::address_unref(extra.address.ptr()) };
+        }
 
         if !self.has_run_cleanup.get() && !std::thread::panicking() {
             debug_panic!("Dropped the network namespace before it has been cleaned up");
@@ -293,6 +362,13 @@ impl std::ops::Drop for NetworkNamespace {
     }
 }
 
+/// An internet interface of a multihomed host, in addition to its default interface.
+pub struct ExtraInterface {
+    pub ip: Ipv4Addr,
+    pub interface: RefCell<NetworkInterface>,
+    address: SyncSendPointer<cshadow::Address>,
+}
+
 struct InterfaceOptions {
     pub host_id: HostId,
     pub hostname: Vec<NonZeroU8>,
@@ -300,4 +376,6 @@ struct InterfaceOptions {
     pub uses_router: bool,
     pub pcap: Option<PcapOptions>,
     pub qdisc: QDiscMode,
+    pub local_latency: SimulationTime,
+    pub limit_local_bandwidth: bool,
 }
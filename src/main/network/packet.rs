@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddrV4};
 
 use crate::cshadow as c;
 use crate::utility::pcap_writer::PacketDisplay;
@@ -8,6 +9,9 @@ pub enum PacketStatus {
     RouterEnqueued = c::_PacketDeliveryStatusFlags_PDS_ROUTER_ENQUEUED as isize,
     RouterDequeued = c::_PacketDeliveryStatusFlags_PDS_ROUTER_DEQUEUED as isize,
     RouterDropped = c::_PacketDeliveryStatusFlags_PDS_ROUTER_DROPPED as isize,
+    RouterMarked = c::_PacketDeliveryStatusFlags_PDS_ROUTER_MARKED as isize,
+    InetDropped = c::_PacketDeliveryStatusFlags_PDS_INET_DROPPED as isize,
+    InetCorrupted = c::_PacketDeliveryStatusFlags_PDS_INET_CORRUPTED as isize,
 }
 
 pub struct Packet {
@@ -29,6 +33,31 @@ impl Packet {
         Packet::from_raw(c_ptr)
     }
 
+    #[cfg(test)]
+    /// Creates an empty packet for unit tests that was sent by an ECN-capable transport.
+    pub fn mock_new_ecn_capable() -> Packet {
+        let packet = Packet::mock_new();
+        unsafe { c::packet_setECN(packet.c_ptr.ptr(), c::ProtocolECN_PECN_ECT_0) };
+        packet
+    }
+
+    #[cfg(test)]
+    /// Creates a UDP packet without a payload for unit tests.
+    pub fn mock_new_udp(source: SocketAddrV4, destination: SocketAddrV4) -> Packet {
+        let c_ptr = unsafe { c::packet_new_inner(1, 1) };
+        unsafe {
+            c::packet_setUDP(
+                c_ptr,
+                c::ProtocolUDPFlags_PUDP_NONE,
+                u32::from(*source.ip()).to_be(),
+                source.port().to_be(),
+                u32::from(*destination.ip()).to_be(),
+                destination.port().to_be(),
+            )
+        };
+        Packet::from_raw(c_ptr)
+    }
+
     pub fn size(&self) -> usize {
         assert!(!self.c_ptr.ptr().is_null());
         let sz = unsafe { c::packet_getTotalSize(self.c_ptr.ptr()) };
@@ -47,6 +76,53 @@ impl Packet {
         sz as usize
     }
 
+    pub fn protocol(&self) -> c::ProtocolType {
+        assert!(!self.c_ptr.ptr().is_null());
+        unsafe { c::packet_getProtocol(self.c_ptr.ptr()) }
+    }
+
+    pub fn source(&self) -> SocketAddrV4 {
+        assert!(!self.c_ptr.ptr().is_null());
+        let ip = unsafe { c::packet_getSourceIP(self.c_ptr.ptr()) };
+        let port = unsafe { c::packet_getSourcePort(self.c_ptr.ptr()) };
+        SocketAddrV4::new(Ipv4Addr::from(u32::from_be(ip)), u16::from_be(port))
+    }
+
+    pub fn destination(&self) -> SocketAddrV4 {
+        assert!(!self.c_ptr.ptr().is_null());
+        let ip = unsafe { c::packet_getDestinationIP(self.c_ptr.ptr()) };
+        let port = unsafe { c::packet_getDestinationPort(self.c_ptr.ptr()) };
+        SocketAddrV4::new(Ipv4Addr::from(u32::from_be(ip)), u16::from_be(port))
+    }
+
+    /// Rewrite the source address and port of a UDP, TCP, or ICMP echo packet.
+    pub fn set_source(&mut self, addr: SocketAddrV4) {
+        assert!(!self.c_ptr.ptr().is_null());
+        let ip = u32::from(*addr.ip()).to_be();
+        unsafe { c::packet_setSource(self.c_ptr.ptr(), ip, addr.port().to_be()) };
+    }
+
+    /// Rewrite the destination address and port of a UDP, TCP, or ICMP echo packet.
+    pub fn set_destination(&mut self, addr: SocketAddrV4) {
+        assert!(!self.c_ptr.ptr().is_null());
+        let ip = u32::from(*addr.ip()).to_be();
+        unsafe { c::packet_setDestination(self.c_ptr.ptr(), ip, addr.port().to_be()) };
+    }
+
+    /// Whether the packet was sent by an ECN-capable transport, in which case a router signals
+    /// congestion by marking the packet instead of dropping it.
+    pub fn is_ecn_capable(&self) -> bool {
+        assert!(!self.c_ptr.ptr().is_null());
+        let ecn = unsafe { c::packet_getECN(self.c_ptr.ptr()) };
+        ecn != c::ProtocolECN_PECN_NOT_ECT
+    }
+
+    /// Set the "congestion experienced" codepoint of an ECN-capable packet.
+    pub fn mark_congestion_experienced(&mut self) {
+        assert!(self.is_ecn_capable());
+        unsafe { c::packet_setECN(self.c_ptr.ptr(), c::ProtocolECN_PECN_CE) };
+    }
+
     pub fn add_status(&mut self, status: PacketStatus) {
         assert!(!self.c_ptr.ptr().is_null());
         let status_flag = status as c::PacketDeliveryStatusFlags;
@@ -102,16 +178,12 @@ impl PacketDisplay for *const c::Packet {
         // write the IP header
 
         let version_and_header_length: u8 = 0x45;
-        let fields: u8 = 0x0;
+        let fields: u8 = unsafe { c::packet_getECN(*self) }.try_into().unwrap();
         let total_length: u16 = header_len + payload_len;
         let identification: u16 = 0x0;
         let flags_and_fragment: u16 = 0x4000;
         let time_to_live: u8 = 64;
-        let iana_protocol: u8 = match protocol {
-            c::_ProtocolType_PTCP => 6,
-            c::_ProtocolType_PUDP => 17,
-            _ => panic!("Unexpected packet protocol"),
-        };
+        let iana_protocol: u8 = iana_protocol(protocol);
         let header_checksum: u16 = 0x0;
         let source_ip: [u8; 4] =
             u32::from_be(unsafe { c::packet_getSourceIP(*self) }).to_be_bytes();
@@ -142,6 +214,7 @@ impl PacketDisplay for *const c::Packet {
         match protocol {
             c::_ProtocolType_PTCP => display_tcp_bytes(*self, &mut writer)?,
             c::_ProtocolType_PUDP => display_udp_bytes(*self, &mut writer)?,
+            c::_ProtocolType_PICMP => display_icmp_bytes(*self, &mut writer)?,
             _ => panic!("Unexpected packet protocol"),
         }
 
@@ -172,6 +245,16 @@ impl PacketDisplay for *const c::Packet {
     }
 }
 
+/// The protocol number used in IP headers.
+fn iana_protocol(protocol: c::ProtocolType) -> u8 {
+    match protocol {
+        c::_ProtocolType_PICMP => 1,
+        c::_ProtocolType_PTCP => 6,
+        c::_ProtocolType_PUDP => 17,
+        _ => panic!("Unexpected packet protocol"),
+    }
+}
+
 /// Helper for writing the tcp bytes of the packet.
 fn display_tcp_bytes(packet: *const c::Packet, mut writer: impl Write) -> std::io::Result<()> {
     assert_eq!(
@@ -220,6 +303,12 @@ fn display_tcp_bytes(packet: *const c::Packet, mut writer: impl Write) -> std::i
     if tcp_header.flags & c::ProtocolTCPFlags_PTCP_FIN != 0 {
         tcp_flags |= 0x01;
     }
+    if tcp_header.flags & c::ProtocolTCPFlags_PTCP_ECE != 0 {
+        tcp_flags |= 0x40;
+    }
+    if tcp_header.flags & c::ProtocolTCPFlags_PTCP_CWR != 0 {
+        tcp_flags |= 0x80;
+    }
     let window: [u8; 2] = u16::try_from(tcp_header.window).unwrap().to_be_bytes();
     let checksum: u16 = 0x0;
     let urgent_pointer: u16 = 0x0;
@@ -275,3 +364,74 @@ fn display_udp_bytes(packet: *const c::Packet, mut writer: impl Write) -> std::i
 
     Ok(())
 }
+
+/// Helper for writing the icmp bytes of the packet.
+fn display_icmp_bytes(packet: *const c::Packet, mut writer: impl Write) -> std::io::Result<()> {
+    assert_eq!(
+        unsafe { c::packet_getProtocol(packet) },
+        c::_ProtocolType_PICMP
+    );
+
+    let icmp_header = unsafe { c::packet_getICMPHeader(packet) };
+    assert!(!icmp_header.is_null());
+    let icmp_header = unsafe { icmp_header.as_ref() }.unwrap();
+
+    // write the ICMP header
+
+    let icmp_type: u8 = icmp_header.type_.try_into().unwrap();
+    let code: u8 = icmp_header.code.try_into().unwrap();
+    let checksum: u16 = 0x0;
+
+    // type: 1 byte
+    // code: 1 byte
+    writer.write_all(&[icmp_type, code])?;
+    // checksum: 2 bytes
+    writer.write_all(&checksum.to_be_bytes())?;
+
+    if icmp_header.type_ != c::ProtocolICMPType_PICMP_DEST_UNREACH {
+        // identifier: 2 bytes (already in network byte order)
+        writer.write_all(&icmp_header.identifier.to_ne_bytes())?;
+        // sequence number: 2 bytes (already in network byte order)
+        writer.write_all(&icmp_header.sequence.to_ne_bytes())?;
+        return Ok(());
+    }
+
+    // unused: 4 bytes
+    writer.write_all(&[0u8; 4])?;
+
+    // write the quoted IP header of the packet that caused the error; we don't keep the size of
+    // the original packet, so the total length only covers the quoted bytes
+
+    let total_length: u16 = u16::try_from(c::CONFIG_HEADER_SIZE_IP).unwrap() + 8;
+    let source_ip: [u8; 4] = u32::from_be(icmp_header.originalSourceIP).to_be_bytes();
+    let dest_ip: [u8; 4] = u32::from_be(icmp_header.originalDestinationIP).to_be_bytes();
+
+    // version and header length: 1 byte
+    // DSCP + ECN: 1 byte
+    writer.write_all(&[0x45, 0x0])?;
+    // total length: 2 bytes
+    writer.write_all(&total_length.to_be_bytes())?;
+    // identification: 2 bytes
+    // flags + fragment offset: 2 bytes
+    writer.write_all(&[0x0, 0x0, 0x40, 0x0])?;
+    // ttl: 1 byte
+    // protocol: 1 byte
+    writer.write_all(&[64, iana_protocol(icmp_header.originalProtocol)])?;
+    // header checksum: 2 bytes
+    writer.write_all(&[0x0, 0x0])?;
+    // source IP: 4 bytes
+    writer.write_all(&source_ip)?;
+    // destination IP: 4 bytes
+    writer.write_all(&dest_ip)?;
+
+    // write the first 8 bytes of the quoted packet's transport header; only the ports are kept
+
+    // source port: 2 bytes
+    writer.write_all(&u16::from_be(icmp_header.originalSourcePort).to_be_bytes())?;
+    // destination port: 2 bytes
+    writer.write_all(&u16::from_be(icmp_header.originalDestinationPort).to_be_bytes())?;
+    // rest of the transport header: 4 bytes
+    writer.write_all(&[0u8; 4])?;
+
+    Ok(())
+}
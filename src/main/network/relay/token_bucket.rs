@@ -31,6 +31,25 @@ impl TokenBucket {
         )
     }
 
+    /// Creates a new token bucket rate limiter that adds tokens every
+    /// `refill_interval` duration at a long term rate of `tokens_per_sec`. The
+    /// capacity is the size of a single refill plus `burst_size`, so that up to
+    /// `burst_size` unused tokens carry over to the next refill interval. At
+    /// least one token is added every refill interval. Returns None if
+    /// `tokens_per_sec` or `refill_interval` are non-positive.
+    pub fn new_with_rate(
+        tokens_per_sec: u64,
+        burst_size: u64,
+        refill_interval: SimulationTime,
+    ) -> Option<TokenBucket> {
+        TokenBucket::new_with_rate_inner(
+            tokens_per_sec,
+            burst_size,
+            refill_interval,
+            EmulatedTime::SIMULATION_START,
+        )
+    }
+
     /// Implements the functionality of `new()` allowing the caller to set the
     /// last refill time. Useful for testing.
     fn new_inner(
@@ -52,6 +71,86 @@ impl TokenBucket {
         }
     }
 
+    /// Implements the functionality of `new_with_rate()` allowing the caller to
+    /// set the last refill time. Useful for testing.
+    fn new_with_rate_inner(
+        tokens_per_sec: u64,
+        burst_size: u64,
+        refill_interval: SimulationTime,
+        last_refill: EmulatedTime,
+    ) -> Option<TokenBucket> {
+        if tokens_per_sec == 0 || refill_interval.is_zero() {
+            return None;
+        }
+
+        let refill_increment = Self::refill_increment_for_rate(tokens_per_sec, refill_interval);
+        TokenBucket::new_inner(
+            refill_increment.saturating_add(burst_size),
+            refill_increment,
+            refill_interval,
+            last_refill,
+        )
+    }
+
+    /// The number of tokens to add every `refill_interval` to reach a long term
+    /// rate of `tokens_per_sec`, rounded down but at least one.
+    fn refill_increment_for_rate(tokens_per_sec: u64, refill_interval: SimulationTime) -> u64 {
+        let increment = u128::from(tokens_per_sec) * refill_interval.as_nanos()
+            / SimulationTime::SECOND.as_nanos();
+        u64::try_from(increment).unwrap_or(u64::MAX).max(1)
+    }
+
+    /// The number of tokens that the bucket can hold in addition to a single
+    /// refill.
+    pub fn burst_size(&self) -> u64 {
+        self.capacity.saturating_sub(self.refill_increment)
+    }
+
+    /// Change the bucket's long term rate to `tokens_per_sec`, keeping its burst
+    /// size and refill interval. Refills that were due under the old rate are
+    /// applied first. Returns an error if `tokens_per_sec` is non-positive.
+    pub fn set_tokens_per_sec(&mut self, tokens_per_sec: u64) -> Result<(), &'static str> {
+        let now = Worker::current_time().unwrap();
+        self.set_tokens_per_sec_inner(tokens_per_sec, &now)
+    }
+
+    /// Implements the functionality of `set_tokens_per_sec()` without calling
+    /// into the `Worker` module. Useful for testing.
+    fn set_tokens_per_sec_inner(
+        &mut self,
+        tokens_per_sec: u64,
+        now: &EmulatedTime,
+    ) -> Result<(), &'static str> {
+        if tokens_per_sec == 0 {
+            return Err("The rate must be positive");
+        }
+
+        let refill_increment =
+            Self::refill_increment_for_rate(tokens_per_sec, self.refill_interval);
+        let capacity = refill_increment.saturating_add(self.burst_size());
+        self.set_rate_inner(capacity, refill_increment, now)
+    }
+
+    /// Change the bucket's capacity and the number of tokens added every refill interval. Refills
+    /// that were due under the old rate are applied first, and the balance is reduced to the new
+    /// capacity if it's larger. Returns an error if any of the args are non-positive.
+    fn set_rate_inner(
+        &mut self,
+        capacity: u64,
+        refill_increment: u64,
+        now: &EmulatedTime,
+    ) -> Result<(), &'static str> {
+        if capacity == 0 || refill_increment == 0 {
+            return Err("The capacity and refill increment must be positive");
+        }
+
+        self.lazy_refill(now);
+        self.capacity = capacity;
+        self.refill_increment = refill_increment;
+        self.balance = self.balance.min(capacity);
+        Ok(())
+    }
+
     /// Remove `decrement` tokens from the bucket if and only if the bucket contains at
     /// least `decrement` tokens. Returns the updated token balance on success, and the
     /// duration until the next token refill on error. Passing a 0 `decrement` always
@@ -133,6 +232,22 @@ mod export {
         ))
     }
 
+    #[no_mangle]
+    pub extern "C" fn tokenbucket_newWithRate(
+        tokens_per_sec: u64,
+        burst_size: u64,
+        refill_interval_nanos: u64,
+    ) -> *mut TokenBucket {
+        Box::into_raw(Box::new(
+            TokenBucket::new_with_rate(
+                tokens_per_sec,
+                burst_size,
+                SimulationTime::from_nanos(refill_interval_nanos),
+            )
+            .unwrap(),
+        ))
+    }
+
     #[no_mangle]
     pub extern "C" fn tokenbucket_free(tokenbucket_ptr: *mut TokenBucket) {
         if tokenbucket_ptr.is_null() {
@@ -141,6 +256,15 @@ mod export {
         unsafe { Box::from_raw(tokenbucket_ptr) };
     }
 
+    #[no_mangle]
+    pub extern "C" fn tokenbucket_setTokensPerSec(
+        tokenbucket_ptr: *mut TokenBucket,
+        tokens_per_sec: u64,
+    ) {
+        let tokenbucket = unsafe { tokenbucket_ptr.as_mut() }.unwrap();
+        tokenbucket.set_tokens_per_sec(tokens_per_sec).unwrap();
+    }
+
     #[no_mangle]
     pub extern "C" fn tokenbucket_consume(
         tokenbucket_ptr: *mut TokenBucket,
@@ -193,6 +317,80 @@ mod tests {
         assert_eq!(tb.refill_interval, SimulationTime::from_secs(1));
     }
 
+    #[test]
+    fn test_new_with_rate() {
+        let now = mock_time_millis(1000);
+
+        let tb =
+            TokenBucket::new_with_rate_inner(1_000_000, 1500, SimulationTime::from_millis(1), now)
+                .unwrap();
+        assert_eq!(tb.refill_increment, 1000);
+        assert_eq!(tb.capacity, 2500);
+        assert_eq!(tb.burst_size(), 1500);
+        assert_eq!(tb.refill_interval, SimulationTime::from_millis(1));
+
+        // A shorter refill interval adds fewer tokens more often
+        let tb = TokenBucket::new_with_rate_inner(
+            1_000_000,
+            1500,
+            SimulationTime::from_micros(100),
+            now,
+        )
+        .unwrap();
+        assert_eq!(tb.refill_increment, 100);
+        assert_eq!(tb.capacity, 1600);
+
+        // Without a burst size the capacity is a single refill
+        let tb = TokenBucket::new_with_rate_inner(1_000_000, 0, SimulationTime::from_secs(1), now)
+            .unwrap();
+        assert_eq!(tb.refill_increment, 1_000_000);
+        assert_eq!(tb.capacity, 1_000_000);
+        assert_eq!(tb.burst_size(), 0);
+
+        // At least one token is added every interval
+        let tb =
+            TokenBucket::new_with_rate_inner(10, 5, SimulationTime::from_millis(1), now).unwrap();
+        assert_eq!(tb.refill_increment, 1);
+        assert_eq!(tb.capacity, 6);
+
+        // Large rates don't overflow
+        let tb = TokenBucket::new_with_rate_inner(u64::MAX, 10, SimulationTime::from_secs(10), now)
+            .unwrap();
+        assert_eq!(tb.refill_increment, u64::MAX);
+        assert_eq!(tb.capacity, u64::MAX);
+    }
+
+    #[test]
+    fn test_new_with_rate_invalid_args() {
+        let now = mock_time_millis(1000);
+        assert!(
+            TokenBucket::new_with_rate_inner(0, 1, SimulationTime::from_millis(1), now).is_none()
+        );
+        assert!(TokenBucket::new_with_rate_inner(1, 1, SimulationTime::ZERO, now).is_none());
+    }
+
+    #[test]
+    fn test_burst_size() {
+        let interval = SimulationTime::from_millis(1);
+        let now = mock_time_millis(1000);
+        let mut tb = TokenBucket::new_with_rate_inner(1_000_000, 1500, interval, now).unwrap();
+
+        // A full bucket allows a burst larger than a single refill
+        assert_eq!(tb.conforming_remove_inner(2500, &now), Ok(0));
+
+        // Unused tokens of up to the burst size carry over to the next interval
+        let later = now + interval;
+        assert_eq!(tb.conforming_remove_inner(500, &later), Ok(500));
+        let later = later + interval;
+        assert_eq!(tb.conforming_remove_inner(0, &later), Ok(1500));
+        let later = later + interval;
+        assert_eq!(tb.conforming_remove_inner(0, &later), Ok(2500));
+
+        // The rest fall over the top of the bucket
+        let later = later + interval;
+        assert_eq!(tb.conforming_remove_inner(0, &later), Ok(2500));
+    }
+
     #[test]
     fn test_refill_after_one_interval() {
         let interval = SimulationTime::from_millis(10);
@@ -266,4 +464,69 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), SimulationTime::from_millis(123));
     }
+
+    #[test]
+    fn test_set_rate() {
+        let now = mock_time_millis(1000);
+        let interval = SimulationTime::from_millis(10);
+        let mut tb = TokenBucket::new_inner(100, 10, interval, now).unwrap();
+
+        // Remove all tokens
+        assert!(tb.conforming_remove_inner(100, &now).is_ok());
+        assert_eq!(tb.balance, 0);
+
+        // 2 refill intervals pass at the old rate before it changes
+        let later = now + SimulationTime::from_millis(20);
+        assert!(tb.set_rate_inner(200, 50, &later).is_ok());
+        assert_eq!(tb.balance, 20);
+        assert_eq!(tb.capacity, 200);
+        assert_eq!(tb.refill_increment, 50);
+
+        // 2 more refill intervals pass at the new rate
+        let later = later + SimulationTime::from_millis(20);
+        assert_eq!(tb.conforming_remove_inner(0, &later), Ok(120));
+
+        // A smaller capacity also limits the current balance
+        assert!(tb.set_rate_inner(60, 5, &later).is_ok());
+        assert_eq!(tb.balance, 60);
+    }
+
+    #[test]
+    fn test_set_rate_invalid_args() {
+        let now = mock_time_millis(1000);
+        let mut tb = TokenBucket::new_inner(100, 10, SimulationTime::from_millis(10), now).unwrap();
+
+        assert!(tb.set_rate_inner(0, 10, &now).is_err());
+        assert!(tb.set_rate_inner(100, 0, &now).is_err());
+
+        // The rate is unchanged
+        assert_eq!(tb.capacity, 100);
+        assert_eq!(tb.refill_increment, 10);
+    }
+
+    #[test]
+    fn test_set_tokens_per_sec() {
+        let now = mock_time_millis(1000);
+        let interval = SimulationTime::from_micros(500);
+        let mut tb = TokenBucket::new_with_rate_inner(2_000_000, 3000, interval, now).unwrap();
+        assert_eq!(tb.refill_increment, 1000);
+        assert_eq!(tb.capacity, 4000);
+
+        // Remove all tokens
+        assert_eq!(tb.conforming_remove_inner(4000, &now), Ok(0));
+
+        // 2 refill intervals pass at the old rate before it changes
+        let later = now + SimulationTime::from_millis(1);
+        assert!(tb.set_tokens_per_sec_inner(1_000_000, &later).is_ok());
+        assert_eq!(tb.balance, 2000);
+
+        // The burst size and refill interval are unchanged
+        assert_eq!(tb.refill_increment, 500);
+        assert_eq!(tb.capacity, 3500);
+        assert_eq!(tb.burst_size(), 3000);
+        assert_eq!(tb.refill_interval, interval);
+
+        assert!(tb.set_tokens_per_sec_inner(0, &later).is_err());
+        assert_eq!(tb.refill_increment, 500);
+    }
 }
@@ -1,7 +1,9 @@
 //! An active queue management (AQM) algorithm implementing CoDel.
 //! <https://tools.ietf.org/html/rfc8289>
 //!
-//!  The "Flow Queue" variant is not implemented.
+//!  ECN-capable packets are marked instead of dropped.
+//!
+//!  The "Flow Queue" variant is implemented in `fq_codel_queue`.
 //!  <https://tools.ietf.org/html/rfc8290>
 //!
 //!  More info:
@@ -10,27 +12,13 @@
 //!   - <https://queue.acm.org/detail.cfm?id=2209336>
 //!   - <https://queue.acm.org/appendices/codel.html>
 
-use std::{collections::VecDeque, time::Duration};
+use std::collections::VecDeque;
 
 use crate::cshadow as c;
-use crate::network::packet::{Packet, PacketStatus};
+use crate::network::packet::Packet;
+use crate::network::router::{RouterQueue, RouterQueueStats};
 use shadow_shim_helper_rs::{emulated_time::EmulatedTime, simulation_time::SimulationTime};
 
-/// The target minimum standing queue delay time, corresponding to the "TARGET"
-/// parameter in the RFC. This is recommended to be set to 5 milliseconds in
-/// internet routers, but in Shadow we increase it to 10 milliseconds.
-const TARGET: SimulationTime = SimulationTime::from_duration(Duration::from_millis(10));
-
-/// The most recent time interval over which the standing delay is computed,
-/// corresponding to the "INTERVAL" parameter in the RFC. This is recommended to
-/// be set to 100 milliseconds in internet routers.
-const INTERVAL: SimulationTime = SimulationTime::from_duration(Duration::from_millis(100));
-
-/// The maximum number of packets we will store, corresponding to the "limit"
-/// parameter in the codel man page. This is recommended to be 1000 in internet
-/// routers, but in Shadow we don't enforce a limit due to our batched sending.
-const LIMIT: usize = usize::MAX;
-
 /// Encodes if CoDel determines that the next available packet can be dropped.
 struct CoDelPopItem {
     packet: Packet,
@@ -62,6 +50,17 @@ struct CoDelElement {
 /// occasionally shrinking the queue's capacity or using a backing that is more
 /// memory-efficient (e.g. a LinkedList).
 pub struct CoDelQueue {
+    /// The target minimum standing queue delay time, corresponding to the
+    /// "TARGET" parameter in the RFC.
+    target: SimulationTime,
+    /// The most recent time interval over which the standing delay is
+    /// computed, corresponding to the "INTERVAL" parameter in the RFC.
+    interval: SimulationTime,
+    /// The maximum number of bytes we will store, if any. The "limit" parameter
+    /// in the codel man page is recommended to be 1000 packets in internet
+    /// routers, but by default Shadow doesn't enforce a limit due to our
+    /// batched sending.
+    limit: Option<usize>,
     /// A queue holding packets and insertion times.
     elements: VecDeque<CoDelElement>,
     /// The running sum of the sizes of packets stored in the queue.
@@ -77,12 +76,23 @@ pub struct CoDelQueue {
     current_drop_count: usize,
     /// The number of packets dropped the last time we were in drop mode.
     previous_drop_count: usize,
+    /// The time that the most recently popped packet spent in the queue.
+    last_sojourn_time: SimulationTime,
+    /// The counters of the packets that passed through the queue.
+    stats: RouterQueueStats,
 }
 
 impl CoDelQueue {
     /// Creates a new empty packet queue.
-    pub fn new() -> CoDelQueue {
+    pub fn new(
+        target: SimulationTime,
+        interval: SimulationTime,
+        limit: Option<usize>,
+    ) -> CoDelQueue {
         CoDelQueue {
+            target,
+            interval,
+            limit,
             elements: VecDeque::new(),
             total_bytes_stored: 0,
             mode: CoDelMode::Store,
@@ -90,25 +100,21 @@ impl CoDelQueue {
             drop_next: None,
             current_drop_count: 0,
             previous_drop_count: 0,
+            last_sojourn_time: SimulationTime::ZERO,
+            stats: RouterQueueStats::default(),
         }
     }
 
-    /// Returns the total number of packets stored in the queue.
-    pub fn len(&self) -> usize {
-        self.elements.len()
-    }
-
-    /// Returns true if the queue is holding zero packets, false otherwise.
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
-    }
-
-    /// Returns the packet at the front of the queue, or None if the queue is
-    /// empty. Note that there is no gurantee that a subsequent `pop()`
-    /// operation will return the same packet, since it could be dropped by the
-    /// queue between the `peek()` and `pop()` operations.
-    pub fn peek(&self) -> Option<&Packet> {
-        self.elements.front().map(|x| &x.packet)
+    /// Drop the packet at the front of the queue because the queue is over
+    /// its limit. Used by FQ-CoDel, which enforces a limit across all of its
+    /// flows.
+    pub fn drop_front(&mut self) {
+        if let Some(element) = self.elements.pop_front() {
+            self.total_bytes_stored = self
+                .total_bytes_stored
+                .saturating_sub(element.packet.size());
+            self.stats.drop_overlimit(element.packet);
+        }
     }
 
     /// Returns the next packet in the queue that conforms to the CoDel standing
@@ -116,9 +122,7 @@ impl CoDelQueue {
     /// packet is found. The CoDel packet dropping logic is applied during this
     /// operation, which could result in packets being dropped before a packet
     /// that conforms to the standing delay requirements is returned.
-    /// Requires the current time as an argument to avoid calling into the
-    /// worker module internally.
-    pub fn pop(&mut self, now: EmulatedTime) -> Option<Packet> {
+    fn codel_dequeue(&mut self, now: EmulatedTime) -> Option<Packet> {
         let maybe_packet = match self.codel_pop(&now) {
             Some(item) => match item.ok_to_drop {
                 true => match self.mode {
@@ -138,8 +142,9 @@ impl CoDelQueue {
             }
         };
 
+        // The packet we return is always the one we popped most recently.
         maybe_packet.map(|mut p| {
-            p.add_status(PacketStatus::RouterDequeued);
+            self.stats.dequeue(&mut p, self.last_sojourn_time);
             p
         })
     }
@@ -147,9 +152,12 @@ impl CoDelQueue {
     fn drop_from_store_mode(&mut self, now: &EmulatedTime, packet: Packet) -> Option<Packet> {
         debug_assert_eq!(self.mode, CoDelMode::Store);
 
-        // Drop one packet and move to drop mode.
-        self.drop_packet(packet);
-        let next_item = self.codel_pop(now);
+        // Drop one packet and move to drop mode. An ECN-capable packet is
+        // marked instead, and leaves the queue in place of the next packet.
+        let next_packet = match self.stats.drop_or_mark(packet) {
+            Some(marked) => Some(marked),
+            None => self.codel_pop(now).map(|x| x.packet),
+        };
         self.mode = CoDelMode::Drop;
 
         // Reset to the drop rate that was known to control the queue.
@@ -160,10 +168,10 @@ impl CoDelQueue {
             true => delta,
             false => 1,
         };
-        self.drop_next = Some(CoDelQueue::apply_control_law(now, self.current_drop_count));
+        self.drop_next = Some(self.apply_control_law(now, self.current_drop_count));
         self.previous_drop_count = self.current_drop_count;
 
-        next_item.map(|x| x.packet)
+        next_packet
     }
 
     fn drop_from_drop_mode(&mut self, now: &EmulatedTime, packet: Packet) -> Option<Packet> {
@@ -176,19 +184,26 @@ impl CoDelQueue {
 
         // Drop as many packets as the control law dictates.
         while item.is_some() && self.mode == CoDelMode::Drop && self.should_drop(now) {
-            self.drop_packet(item.unwrap().packet);
+            let dropped = self.stats.drop_or_mark(item.unwrap().packet);
             self.current_drop_count += 1;
 
+            // An ECN-capable packet is marked instead, and we stop dropping
+            // until the next drop time.
+            if let Some(marked) = dropped {
+                self.drop_next =
+                    Some(self.apply_control_law(&self.drop_next.unwrap(), self.current_drop_count));
+                return Some(marked);
+            }
+
             item = self.codel_pop(now);
 
             match item.as_ref().map_or(false, |x| x.ok_to_drop) {
                 true => {
                     // Set the next drop time based on CoDel control law.
                     // `self.drop_next` is already set in `drop_from_store_mode()`
-                    self.drop_next = Some(CoDelQueue::apply_control_law(
-                        &self.drop_next.unwrap(),
-                        self.current_drop_count,
-                    ));
+                    self.drop_next = Some(
+                        self.apply_control_law(&self.drop_next.unwrap(), self.current_drop_count),
+                    );
                 }
                 false => self.mode = CoDelMode::Store,
             }
@@ -209,6 +224,7 @@ impl CoDelQueue {
 
                 debug_assert!(now >= &element.enqueue_ts);
                 let standing_delay = now.saturating_duration_since(&element.enqueue_ts);
+                self.last_sojourn_time = standing_delay;
                 let ok_to_drop = self.process_standing_delay(now, standing_delay);
 
                 Some(CoDelPopItem {
@@ -231,7 +247,9 @@ impl CoDelQueue {
         now: &EmulatedTime,
         standing_delay: SimulationTime,
     ) -> bool {
-        if standing_delay < TARGET || self.total_bytes_stored <= c::CONFIG_MTU.try_into().unwrap() {
+        if standing_delay < self.target
+            || self.total_bytes_stored <= c::CONFIG_MTU.try_into().unwrap()
+        {
             // We are in a good state, i.e., below the target delay. We reset
             // the interval expiration, so that we wait for at least one full
             // interval if the delay exceeds the target again.
@@ -252,7 +270,7 @@ impl CoDelQueue {
                     // entered a bad state. If we stay in the bad state for a
                     // full interval, we will need to enter drop mode later.
                     // Mark the end of the interval now so we can track it.
-                    self.interval_end = Some(now.saturating_add(INTERVAL));
+                    self.interval_end = Some(now.saturating_add(self.interval));
                     false
                 }
             }
@@ -272,7 +290,7 @@ impl CoDelQueue {
         match self.drop_next {
             Some(drop_next) => {
                 // now < drop_next + interval*16
-                now.saturating_duration_since(&drop_next) < INTERVAL.saturating_mul(16)
+                now.saturating_duration_since(&drop_next) < self.interval.saturating_mul(16)
             }
             None => false, // Have not yet dropped a packet
         }
@@ -280,9 +298,9 @@ impl CoDelQueue {
 
     /// Apply the CoDel control law using the inverse sqrt of the drop count,
     /// i.e., `time + (INTERVAL / sqrt(count));`.
-    fn apply_control_law(time: &EmulatedTime, count: usize) -> EmulatedTime {
+    fn apply_control_law(&self, time: &EmulatedTime, count: usize) -> EmulatedTime {
         let increment = {
-            let interval = INTERVAL.as_nanos_f64();
+            let interval = self.interval.as_nanos_f64();
             let sqrt_count = match count {
                 0 => 1f64,
                 _ => (count as f64).sqrt(),
@@ -294,13 +312,21 @@ impl CoDelQueue {
         let adjusted = original.saturating_add(increment);
         EmulatedTime::from_abs_simtime(adjusted)
     }
+}
+
+impl RouterQueue for CoDelQueue {
+    fn name(&self) -> &'static str {
+        "codel"
+    }
 
     /// Append a packet to the end of the queue.
-    /// Requires the current time as an argument to avoid calling into the
-    /// worker module internally.
-    pub fn push(&mut self, mut packet: Packet, now: EmulatedTime) {
-        if self.elements.len() < LIMIT {
-            packet.add_status(PacketStatus::RouterEnqueued);
+    fn push(&mut self, mut packet: Packet, now: EmulatedTime) {
+        let is_full = self.limit.map_or(false, |limit| {
+            self.total_bytes_stored + packet.size() > limit
+        });
+
+        if !is_full {
+            self.stats.enqueue(&mut packet);
             self.total_bytes_stored += packet.size();
             self.elements.push_back(CoDelElement {
                 packet,
@@ -310,27 +336,52 @@ impl CoDelQueue {
             // Section 5.4 in the RFC notes that "packets arriving at a full
             // buffer will be dropped, but these drops are not counted towards
             // CoDel's computations".
-            self.drop_packet(packet);
+            self.stats.drop_overlimit(packet);
         }
     }
 
-    fn drop_packet(&self, mut packet: Packet) {
-        packet.add_status(PacketStatus::RouterDropped);
+    fn pop(&mut self, now: EmulatedTime) -> Option<Packet> {
+        self.codel_dequeue(now)
+    }
+
+    fn peek(&self) -> Option<&Packet> {
+        self.elements.front().map(|x| &x.packet)
+    }
+
+    fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn bytes(&self) -> usize {
+        self.total_bytes_stored
+    }
+
+    fn stats(&self) -> RouterQueueStats {
+        self.stats
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::*;
     use crate::network::tests::mock_time_millis;
 
+    const TARGET: SimulationTime = SimulationTime::from_duration(Duration::from_millis(10));
+    const INTERVAL: SimulationTime = SimulationTime::from_duration(Duration::from_millis(100));
+
+    fn new_queue() -> CoDelQueue {
+        CoDelQueue::new(TARGET, INTERVAL, None)
+    }
+
     // Some of the tests here don't run in miri because they cause c::packet*
     // functions to be called during the test.
 
     #[test]
     fn empty() {
         let now = mock_time_millis(1000);
-        let mut cdq = CoDelQueue::new();
+        let mut cdq = new_queue();
         assert_eq!(cdq.len(), 0);
         assert!(cdq.is_empty());
         assert!(cdq.peek().is_none());
@@ -341,7 +392,7 @@ mod tests {
     #[cfg_attr(miri, ignore)]
     fn push_pop_simple() {
         let now = mock_time_millis(1000);
-        let mut cdq = CoDelQueue::new();
+        let mut cdq = new_queue();
 
         const N: usize = 10;
 
@@ -363,11 +414,12 @@ mod tests {
     #[test]
     fn control_law() {
         let now = mock_time_millis(1000);
+        let cdq = new_queue();
 
         // The increment should be a full interval.
         for i in 0..2 {
             assert_eq!(
-                CoDelQueue::apply_control_law(&now, i).duration_since(&now),
+                cdq.apply_control_law(&now, i).duration_since(&now),
                 INTERVAL
             );
         }
@@ -375,7 +427,7 @@ mod tests {
         // The increment should reduce exponentially.
         for i in 2..20 {
             assert_eq!(
-                CoDelQueue::apply_control_law(&now, i).duration_since(&now),
+                cdq.apply_control_law(&now, i).duration_since(&now),
                 SimulationTime::from_nanos(
                     (INTERVAL.as_nanos_f64() / (i as f64).sqrt()).round() as u64
                 )
@@ -390,7 +442,7 @@ mod tests {
 
         let start = mock_time_millis(1000);
 
-        let mut cdq = CoDelQueue::new();
+        let mut cdq = new_queue();
         for _ in 0..5 {
             cdq.push(Packet::mock_new(), start);
         }
@@ -438,7 +490,7 @@ mod tests {
 
         let start = mock_time_millis(1000);
 
-        let mut cdq = CoDelQueue::new();
+        let mut cdq = new_queue();
         const N: usize = 6;
         for _ in 0..N {
             cdq.push(Packet::mock_new(), start);
@@ -483,7 +535,7 @@ mod tests {
     #[test]
     fn drop_empty() {
         let start = mock_time_millis(1000);
-        let mut cdq = CoDelQueue::new();
+        let mut cdq = new_queue();
         cdq.mode = CoDelMode::Drop;
         cdq.pop(start);
         assert_eq!(cdq.mode, CoDelMode::Store);
@@ -495,7 +547,7 @@ mod tests {
         let start = mock_time_millis(1000);
         let end = mock_time_millis(1000000);
 
-        let mut cdq = CoDelQueue::new();
+        let mut cdq = new_queue();
         const N: usize = 20;
         for _ in 0..N {
             cdq.push(Packet::mock_new(), start);
@@ -533,4 +585,38 @@ mod tests {
         assert_eq!(cdq.current_drop_count, N - 4);
         assert_eq!(cdq.mode, CoDelMode::Store);
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn mark_ecn_capable() {
+        let start = mock_time_millis(1000);
+        let end = mock_time_millis(1000000);
+
+        let mut cdq = new_queue();
+        const N: usize = 20;
+        for _ in 0..N {
+            cdq.push(Packet::mock_new_ecn_capable(), start);
+        }
+
+        // Sets the interval.
+        assert!(cdq.pop(start + TARGET).is_some());
+        assert_eq!(cdq.len(), N - 1);
+
+        // Enters Drop mode, but marks the packet instead of dropping it.
+        assert!(cdq.pop(start + TARGET + INTERVAL).is_some());
+        assert_eq!(cdq.len(), N - 2);
+        assert_eq!(cdq.current_drop_count, 1);
+        assert_eq!(cdq.mode, CoDelMode::Drop);
+
+        // In Drop mode, only one packet is marked each time we should drop.
+        assert!(cdq.pop(end).is_some());
+        assert_eq!(cdq.len(), N - 3);
+        assert_eq!(cdq.current_drop_count, 2);
+        assert_eq!(cdq.mode, CoDelMode::Drop);
+
+        let stats = cdq.stats();
+        assert_eq!(stats.marked_packets, 2);
+        assert_eq!(stats.dropped_packets, 0);
+        assert_eq!(stats.dequeued_packets, 3);
+    }
 }
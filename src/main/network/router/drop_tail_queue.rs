@@ -0,0 +1,129 @@
+//! A first-in first-out queue that drops packets arriving when the queue is full.
+//!
+//!  More info:
+//!   - <https://en.wikipedia.org/wiki/Tail_drop>
+//!   - <http://man7.org/linux/man-pages/man8/tc-bfifo.8.html>
+
+use std::collections::VecDeque;
+
+use crate::network::packet::Packet;
+use crate::network::router::{RouterQueue, RouterQueueStats};
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+/// A packet queue that stores packets until it holds `limit` bytes, and drops
+/// arriving packets that don't fit.
+pub struct DropTailQueue {
+    /// The maximum number of bytes we will store.
+    limit: usize,
+    /// A queue holding packets and insertion times.
+    elements: VecDeque<(Packet, EmulatedTime)>,
+    /// The running sum of the sizes of packets stored in the queue.
+    total_bytes_stored: usize,
+    /// The counters of the packets that passed through the queue.
+    stats: RouterQueueStats,
+}
+
+impl DropTailQueue {
+    /// Creates a new empty packet queue.
+    pub fn new(limit: usize) -> DropTailQueue {
+        DropTailQueue {
+            limit,
+            elements: VecDeque::new(),
+            total_bytes_stored: 0,
+            stats: RouterQueueStats::default(),
+        }
+    }
+}
+
+impl RouterQueue for DropTailQueue {
+    fn name(&self) -> &'static str {
+        "drop_tail"
+    }
+
+    fn push(&mut self, mut packet: Packet, now: EmulatedTime) {
+        if self.total_bytes_stored + packet.size() > self.limit {
+            self.stats.drop_overlimit(packet);
+            return;
+        }
+
+        self.stats.enqueue(&mut packet);
+        self.total_bytes_stored += packet.size();
+        self.elements.push_back((packet, now));
+    }
+
+    fn pop(&mut self, now: EmulatedTime) -> Option<Packet> {
+        let (mut packet, enqueue_ts) = self.elements.pop_front()?;
+        self.total_bytes_stored -= packet.size();
+        self.stats
+            .dequeue(&mut packet, now.saturating_duration_since(&enqueue_ts));
+        Some(packet)
+    }
+
+    fn peek(&self) -> Option<&Packet> {
+        self.elements.front().map(|(packet, _)| packet)
+    }
+
+    fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn bytes(&self) -> usize {
+        self.total_bytes_stored
+    }
+
+    fn stats(&self) -> RouterQueueStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::tests::mock_time_millis;
+    use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+    // The tests here don't run in miri because they cause c::packet* functions
+    // to be called during the test.
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn limit() {
+        let now = mock_time_millis(1000);
+        let size = Packet::mock_new().size();
+
+        let mut q = DropTailQueue::new(3 * size);
+        for _ in 0..5 {
+            q.push(Packet::mock_new(), now);
+        }
+        assert_eq!(q.len(), 3);
+        assert_eq!(q.bytes(), 3 * size);
+        assert_eq!(q.stats().enqueued_packets, 3);
+        assert_eq!(q.stats().overlimit_packets, 2);
+
+        // room for one more packet
+        assert!(q.pop(now).is_some());
+        q.push(Packet::mock_new(), now);
+        assert_eq!(q.len(), 3);
+        assert_eq!(q.stats().overlimit_packets, 2);
+
+        while q.pop(now).is_some() {}
+        assert!(q.is_empty());
+        assert_eq!(q.bytes(), 0);
+        assert_eq!(q.stats().dequeued_packets, 4);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn sojourn_time() {
+        let mut q = DropTailQueue::new(usize::MAX);
+        q.push(Packet::mock_new(), mock_time_millis(1000));
+        q.push(Packet::mock_new(), mock_time_millis(1010));
+
+        q.pop(mock_time_millis(1030));
+        q.pop(mock_time_millis(1030));
+
+        let stats = q.stats();
+        assert_eq!(stats.max_sojourn_time, SimulationTime::from_millis(30));
+        assert_eq!(stats.mean_sojourn_time(), SimulationTime::from_millis(25));
+    }
+}
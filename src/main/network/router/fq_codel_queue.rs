@@ -0,0 +1,277 @@
+//! An active queue management (AQM) algorithm implementing the "Flow Queue"
+//! variant of CoDel (FQ-CoDel).
+//! <https://tools.ietf.org/html/rfc8290>
+//!
+//!  Packets are hashed into flow queues by their protocol, addresses, and
+//!  ports. The flow queues are served with deficit round robin, giving
+//!  priority to flows that recently became active, and each flow queue applies
+//!  CoDel separately.
+//!
+//!  More info:
+//!   - <http://man7.org/linux/man-pages/man8/tc-fq_codel.8.html>
+
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use crate::cshadow as c;
+use crate::network::packet::Packet;
+use crate::network::router::codel_queue::CoDelQueue;
+use crate::network::router::{RouterQueue, RouterQueueStats};
+use shadow_shim_helper_rs::{emulated_time::EmulatedTime, simulation_time::SimulationTime};
+
+/// A flow queue and its scheduling state.
+struct Flow {
+    queue: CoDelQueue,
+    /// The number of bytes the flow may still dequeue in the current round.
+    deficit: i64,
+    /// Whether the flow is in the new or old flows list.
+    is_active: bool,
+}
+
+/// A packet queue implementing the FQ-CoDel active queue management (AQM)
+/// algorithm, which isolates flows from each other so that a flow that builds
+/// a standing queue doesn't delay the packets of other flows.
+pub struct FqCoDelQueue {
+    /// The number of bytes a flow may dequeue each round.
+    quantum: i64,
+    /// The maximum number of bytes we will store across all flows, if any.
+    limit: Option<usize>,
+    /// The flow queues that packets are hashed into.
+    flows: Vec<Flow>,
+    /// Flows that became active recently, which are served first.
+    new_flows: VecDeque<usize>,
+    /// Flows that have been active for at least one round.
+    old_flows: VecDeque<usize>,
+    /// The number of packets stored across all flows.
+    total_packets_stored: usize,
+    /// The running sum of the sizes of packets stored across all flows.
+    total_bytes_stored: usize,
+}
+
+impl FqCoDelQueue {
+    /// Creates a new empty packet queue.
+    pub fn new(
+        num_flows: usize,
+        quantum: usize,
+        target: SimulationTime,
+        interval: SimulationTime,
+        limit: Option<usize>,
+    ) -> FqCoDelQueue {
+        assert!(num_flows > 0);
+        assert!(quantum > 0);
+        FqCoDelQueue {
+            quantum: quantum.try_into().unwrap(),
+            limit,
+            flows: (0..num_flows)
+                .map(|_| Flow {
+                    queue: CoDelQueue::new(target, interval, None),
+                    deficit: 0,
+                    is_active: false,
+                })
+                .collect(),
+            new_flows: VecDeque::new(),
+            old_flows: VecDeque::new(),
+            total_packets_stored: 0,
+            total_bytes_stored: 0,
+        }
+    }
+
+    /// The index of the flow queue for the packet.
+    fn flow_index(&self, packet: &Packet) -> usize {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        let protocol = packet.protocol();
+        protocol.hash(&mut hasher);
+
+        // only these protocols have addresses and ports
+        if matches!(
+            protocol,
+            c::_ProtocolType_PTCP | c::_ProtocolType_PUDP | c::_ProtocolType_PICMP
+        ) {
+            packet.source().hash(&mut hasher);
+            packet.destination().hash(&mut hasher);
+        }
+
+        (hasher.finish() % self.flows.len() as u64) as usize
+    }
+
+    /// Run `f` on the flow queue at `index`, keeping the totals up to date.
+    fn with_flow_queue<T>(&mut self, index: usize, f: impl FnOnce(&mut CoDelQueue) -> T) -> T {
+        let queue = &mut self.flows[index].queue;
+        let (packets, bytes) = (queue.len(), queue.bytes());
+        let rv = f(queue);
+        self.total_packets_stored = self.total_packets_stored - packets + queue.len();
+        self.total_bytes_stored = self.total_bytes_stored - bytes + queue.bytes();
+        rv
+    }
+
+    /// Remove the flow at the front of the new or old flows list.
+    fn remove_front_flow(&mut self, is_new: bool) {
+        if is_new {
+            self.new_flows.pop_front();
+        } else {
+            self.old_flows.pop_front();
+        }
+    }
+
+    /// Drop packets from the front of the flow queue holding the most bytes
+    /// until we're within the limit.
+    fn enforce_limit(&mut self) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+
+        while self.total_bytes_stored > limit {
+            // the first of the largest flow queues, so that the choice is deterministic
+            let fattest = (0..self.flows.len())
+                .rev()
+                .max_by_key(|&i| self.flows[i].queue.bytes())
+                .unwrap();
+            self.with_flow_queue(fattest, |queue| queue.drop_front());
+        }
+    }
+}
+
+impl RouterQueue for FqCoDelQueue {
+    fn name(&self) -> &'static str {
+        "fq_codel"
+    }
+
+    fn push(&mut self, packet: Packet, now: EmulatedTime) {
+        let index = self.flow_index(&packet);
+        self.with_flow_queue(index, |queue| queue.push(packet, now));
+
+        let flow = &mut self.flows[index];
+        if !flow.is_active {
+            flow.is_active = true;
+            flow.deficit = self.quantum;
+            self.new_flows.push_back(index);
+        }
+
+        self.enforce_limit();
+    }
+
+    fn pop(&mut self, now: EmulatedTime) -> Option<Packet> {
+        loop {
+            let (index, is_new) = match (self.new_flows.front(), self.old_flows.front()) {
+                (Some(index), _) => (*index, true),
+                (None, Some(index)) => (*index, false),
+                (None, None) => return None,
+            };
+
+            // the flow used up its share of this round, so it moves to the
+            // back of the old flows with a new share
+            if self.flows[index].deficit <= 0 {
+                self.flows[index].deficit += self.quantum;
+                self.remove_front_flow(is_new);
+                self.old_flows.push_back(index);
+                continue;
+            }
+
+            match self.with_flow_queue(index, |queue| queue.pop(now)) {
+                Some(packet) => {
+                    self.flows[index].deficit -= i64::try_from(packet.size()).unwrap();
+                    return Some(packet);
+                }
+                None => {
+                    // an empty new flow moves to the old flows so that it can't
+                    // regain priority by immediately becoming active again
+                    self.remove_front_flow(is_new);
+
+                    if is_new && !self.old_flows.is_empty() {
+                        self.old_flows.push_back(index);
+                    } else {
+                        self.flows[index].is_active = false;
+                    }
+                }
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<&Packet> {
+        self.new_flows
+            .iter()
+            .chain(self.old_flows.iter())
+            .find_map(|&index| self.flows[index].queue.peek())
+    }
+
+    fn len(&self) -> usize {
+        self.total_packets_stored
+    }
+
+    fn bytes(&self) -> usize {
+        self.total_bytes_stored
+    }
+
+    fn stats(&self) -> RouterQueueStats {
+        self.flows
+            .iter()
+            .fold(RouterQueueStats::default(), |sum, flow| {
+                sum + flow.queue.stats()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::network::tests::mock_time_millis;
+
+    // The tests here don't run in miri because they cause c::packet* functions
+    // to be called during the test.
+
+    const TARGET: SimulationTime = SimulationTime::from_duration(Duration::from_millis(10));
+    const INTERVAL: SimulationTime = SimulationTime::from_duration(Duration::from_millis(100));
+
+    #[test]
+    fn empty() {
+        let now = mock_time_millis(1000);
+        let mut q = FqCoDelQueue::new(16, 1500, TARGET, INTERVAL, None);
+        assert!(q.is_empty());
+        assert!(q.peek().is_none());
+        assert!(q.pop(now).is_none());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn push_pop_simple() {
+        let now = mock_time_millis(1000);
+        let mut q = FqCoDelQueue::new(16, 1500, TARGET, INTERVAL, None);
+
+        const N: usize = 10;
+
+        for i in 1..=N {
+            q.push(Packet::mock_new(), now);
+            assert_eq!(q.len(), i);
+            assert!(q.peek().is_some());
+        }
+        for i in 1..=N {
+            assert!(q.pop(now).is_some());
+            assert_eq!(q.len(), N - i);
+        }
+        assert!(q.is_empty());
+        assert_eq!(q.bytes(), 0);
+        assert!(q.pop(now).is_none());
+        assert!(q.new_flows.is_empty() && q.old_flows.is_empty());
+
+        let stats = q.stats();
+        assert_eq!(stats.enqueued_packets, N as u64);
+        assert_eq!(stats.dequeued_packets, N as u64);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn limit() {
+        let now = mock_time_millis(1000);
+        let size = Packet::mock_new().size();
+        let mut q = FqCoDelQueue::new(16, 1500, TARGET, INTERVAL, Some(4 * size));
+
+        for _ in 0..10 {
+            q.push(Packet::mock_new(), now);
+        }
+        assert_eq!(q.len(), 4);
+        assert_eq!(q.bytes(), 4 * size);
+        assert_eq!(q.stats().overlimit_packets, 6);
+    }
+}
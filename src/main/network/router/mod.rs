@@ -1,13 +1,362 @@
+use std::time::Duration;
+
+use crate::core::support::configuration::RouterQueueOptions;
+use crate::core::support::units::{self, Unit};
 use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::host::Host;
-use crate::network::packet::Packet;
+use crate::network::packet::{Packet, PacketStatus};
 use crate::utility::{Magic, ObjectCounter};
 
 use self::codel_queue::CoDelQueue;
+use self::drop_tail_queue::DropTailQueue;
+use self::fq_codel_queue::FqCoDelQueue;
+use self::pie_queue::PieQueue;
+use self::red_queue::RedQueue;
 mod codel_queue;
+mod drop_tail_queue;
+mod fq_codel_queue;
+mod pie_queue;
+mod red_queue;
 
+use serde::Serialize;
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+/// A queue that holds the packets arriving at a host from the simulated network until the host
+/// can receive them. The queue decides which packets to drop, for example when it is full or when
+/// its active queue management (AQM) algorithm detects congestion.
+pub trait RouterQueue: Send {
+    /// The name of the queue's algorithm.
+    fn name(&self) -> &'static str;
+
+    /// Append a packet to the queue, or drop it.
+    /// Requires the current time as an argument to avoid calling into the
+    /// worker module internally.
+    fn push(&mut self, packet: Packet, now: EmulatedTime);
+
+    /// Returns the next packet to leave the queue, or None if the queue is empty. The queue may
+    /// drop packets before returning a packet.
+    /// Requires the current time as an argument to avoid calling into the
+    /// worker module internally.
+    fn pop(&mut self, now: EmulatedTime) -> Option<Packet>;
+
+    /// Returns the packet that is expected to leave the queue next, or None if the queue is empty.
+    /// Note that there is no guarantee that a subsequent `pop()` operation will return the same
+    /// packet, since the queue could drop it or reorder its flows.
+    fn peek(&self) -> Option<&Packet>;
+
+    /// Returns the total number of packets stored in the queue.
+    fn len(&self) -> usize;
+
+    /// Returns true if the queue is holding zero packets, false otherwise.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total size of the packets stored in the queue.
+    fn bytes(&self) -> usize;
+
+    /// Returns the counters of the packets that have passed through the queue.
+    fn stats(&self) -> RouterQueueStats;
+}
+
+/// Counters of the packets that have passed through a router queue since it was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouterQueueStats {
+    /// Packets that were stored in the queue.
+    pub enqueued_packets: u64,
+    pub enqueued_bytes: u64,
+    /// Packets that left the queue towards the host.
+    pub dequeued_packets: u64,
+    pub dequeued_bytes: u64,
+    /// Packets that the active queue management algorithm dropped, either when they arrived or
+    /// after they were stored.
+    pub dropped_packets: u64,
+    pub dropped_bytes: u64,
+    /// Packets that were dropped because the queue was full.
+    pub overlimit_packets: u64,
+    pub overlimit_bytes: u64,
+    /// ECN-capable packets that the active queue management algorithm marked instead of dropping
+    /// them.
+    pub marked_packets: u64,
+    pub marked_bytes: u64,
+    /// The sum of the times that dequeued packets spent in the queue.
+    pub total_sojourn_time: SimulationTime,
+    /// The longest time that a dequeued packet spent in the queue.
+    pub max_sojourn_time: SimulationTime,
+}
+
+impl Default for RouterQueueStats {
+    fn default() -> Self {
+        Self {
+            enqueued_packets: 0,
+            enqueued_bytes: 0,
+            dequeued_packets: 0,
+            dequeued_bytes: 0,
+            dropped_packets: 0,
+            dropped_bytes: 0,
+            overlimit_packets: 0,
+            overlimit_bytes: 0,
+            marked_packets: 0,
+            marked_bytes: 0,
+            total_sojourn_time: SimulationTime::ZERO,
+            max_sojourn_time: SimulationTime::ZERO,
+        }
+    }
+}
+
+impl RouterQueueStats {
+    /// The mean time that dequeued packets spent in the queue.
+    pub fn mean_sojourn_time(&self) -> SimulationTime {
+        if self.dequeued_packets == 0 {
+            return SimulationTime::ZERO;
+        }
+        self.total_sojourn_time / self.dequeued_packets
+    }
+
+    fn enqueue(&mut self, packet: &mut Packet) {
+        packet.add_status(PacketStatus::RouterEnqueued);
+        self.enqueued_packets += 1;
+        self.enqueued_bytes += packet.size() as u64;
+    }
+
+    fn dequeue(&mut self, packet: &mut Packet, sojourn_time: SimulationTime) {
+        packet.add_status(PacketStatus::RouterDequeued);
+        self.dequeued_packets += 1;
+        self.dequeued_bytes += packet.size() as u64;
+        self.total_sojourn_time += sojourn_time;
+        self.max_sojourn_time = std::cmp::max(self.max_sojourn_time, sojourn_time);
+    }
+
+    fn drop_packet(&mut self, mut packet: Packet) {
+        packet.add_status(PacketStatus::RouterDropped);
+        self.dropped_packets += 1;
+        self.dropped_bytes += packet.size() as u64;
+    }
+
+    fn drop_overlimit(&mut self, mut packet: Packet) {
+        packet.add_status(PacketStatus::RouterDropped);
+        self.overlimit_packets += 1;
+        self.overlimit_bytes += packet.size() as u64;
+    }
+
+    /// Signal congestion to the receiver of the packet. An ECN-capable packet is marked and
+    /// returned so that it can continue on, and any other packet is dropped.
+    fn drop_or_mark(&mut self, mut packet: Packet) -> Option<Packet> {
+        if !packet.is_ecn_capable() {
+            self.drop_packet(packet);
+            return None;
+        }
+
+        packet.mark_congestion_experienced();
+        packet.add_status(PacketStatus::RouterMarked);
+        self.marked_packets += 1;
+        self.marked_bytes += packet.size() as u64;
+        Some(packet)
+    }
+}
+
+impl std::ops::Add for RouterQueueStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            enqueued_packets: self.enqueued_packets + other.enqueued_packets,
+            enqueued_bytes: self.enqueued_bytes + other.enqueued_bytes,
+            dequeued_packets: self.dequeued_packets + other.dequeued_packets,
+            dequeued_bytes: self.dequeued_bytes + other.dequeued_bytes,
+            dropped_packets: self.dropped_packets + other.dropped_packets,
+            dropped_bytes: self.dropped_bytes + other.dropped_bytes,
+            overlimit_packets: self.overlimit_packets + other.overlimit_packets,
+            overlimit_bytes: self.overlimit_bytes + other.overlimit_bytes,
+            marked_packets: self.marked_packets + other.marked_packets,
+            marked_bytes: self.marked_bytes + other.marked_bytes,
+            total_sojourn_time: self.total_sojourn_time + other.total_sojourn_time,
+            max_sojourn_time: std::cmp::max(self.max_sojourn_time, other.max_sojourn_time),
+        }
+    }
+}
+
+/// A snapshot of the statistics of a router, suitable for logging and for the simulation's
+/// statistics file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[repr(C)]
+pub struct RouterStats {
+    pub enqueued_packets: u64,
+    pub enqueued_bytes: u64,
+    pub dequeued_packets: u64,
+    pub dequeued_bytes: u64,
+    /// Packets dropped by the active queue management algorithm.
+    pub dropped_packets: u64,
+    pub dropped_bytes: u64,
+    /// Packets dropped because the queue was full.
+    pub overlimit_packets: u64,
+    pub overlimit_bytes: u64,
+    pub marked_packets: u64,
+    pub marked_bytes: u64,
+    pub mean_sojourn_time_ns: u64,
+    pub max_sojourn_time_ns: u64,
+    /// The packets stored in the queue when the snapshot was taken.
+    pub queued_packets: u64,
+    pub queued_bytes: u64,
+}
+
+impl RouterStats {
+    fn new(queue: &dyn RouterQueue) -> Self {
+        let stats = queue.stats();
+        Self {
+            enqueued_packets: stats.enqueued_packets,
+            enqueued_bytes: stats.enqueued_bytes,
+            dequeued_packets: stats.dequeued_packets,
+            dequeued_bytes: stats.dequeued_bytes,
+            dropped_packets: stats.dropped_packets,
+            dropped_bytes: stats.dropped_bytes,
+            overlimit_packets: stats.overlimit_packets,
+            overlimit_bytes: stats.overlimit_bytes,
+            marked_packets: stats.marked_packets,
+            marked_bytes: stats.marked_bytes,
+            mean_sojourn_time_ns: stats.mean_sojourn_time().as_nanos().try_into().unwrap(),
+            max_sojourn_time_ns: stats.max_sojourn_time.as_nanos().try_into().unwrap(),
+            queued_packets: queue.len() as u64,
+            queued_bytes: queue.bytes() as u64,
+        }
+    }
+}
+
+/// Build the router queue described by the configuration options, for a link with a bandwidth of
+/// `bandwidth_bits` bits per second. Queues that drop packets randomly use their own random number
+/// generator seeded from `seed`.
+pub fn new_router_queue(
+    options: &RouterQueueOptions,
+    bandwidth_bits: u64,
+    seed: u64,
+) -> Box<dyn RouterQueue> {
+    fn time(x: units::Time<units::TimePrefix>) -> SimulationTime {
+        SimulationTime::from_nanos(x.convert(units::TimePrefix::Nano).unwrap().value())
+    }
+
+    fn bytes(x: units::Bytes<units::SiPrefixUpper>) -> usize {
+        x.convert(units::SiPrefixUpper::Base)
+            .unwrap()
+            .value()
+            .try_into()
+            .unwrap()
+    }
+
+    // the time to transmit a full-sized packet on the link
+    let mtu_bits = u64::from(c::CONFIG_MTU) * 8;
+    let packet_time = SimulationTime::from_nanos(std::cmp::max(
+        mtu_bits * 1_000_000_000 / std::cmp::max(bandwidth_bits, 1),
+        1,
+    ));
+
+    match options {
+        RouterQueueOptions::Codel(x) => Box::new(CoDelQueue::new(
+            time(x.target),
+            time(x.interval),
+            x.limit.map(bytes),
+        )),
+        RouterQueueOptions::DropTail(x) => Box::new(DropTailQueue::new(bytes(x.limit))),
+        RouterQueueOptions::Red(x) => Box::new(RedQueue::new(
+            bytes(x.min_threshold),
+            bytes(x.max_threshold),
+            x.max_probability,
+            x.weight,
+            x.limit.map(bytes),
+            packet_time,
+            seed,
+        )),
+        RouterQueueOptions::FqCodel(x) => Box::new(FqCoDelQueue::new(
+            x.flows.try_into().unwrap(),
+            bytes(x.quantum),
+            time(x.target),
+            time(x.interval),
+            x.limit.map(bytes),
+        )),
+        RouterQueueOptions::Pie(x) => Box::new(PieQueue::new(
+            time(x.target),
+            time(x.update_interval),
+            x.alpha,
+            x.beta,
+            time(x.max_burst),
+            x.limit.map(bytes),
+            seed,
+        )),
+    }
+}
+
+/// Returns an error if the options describe a queue that can't be built.
+pub fn validate_router_queue(options: &RouterQueueOptions) -> Result<(), String> {
+    fn nonzero_time(name: &str, x: units::Time<units::TimePrefix>) -> Result<(), String> {
+        if Duration::from(x).is_zero() {
+            return Err(format!("'{name}' must be greater than 0"));
+        }
+        Ok(())
+    }
+
+    // a queue with a limit of 0 bytes would drop every packet
+    fn nonzero_limit(x: Option<units::Bytes<units::SiPrefixUpper>>) -> Result<(), String> {
+        if x.map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value()) == Some(0) {
+            return Err("'limit' must be greater than 0".into());
+        }
+        Ok(())
+    }
+
+    match options {
+        RouterQueueOptions::Codel(x) => {
+            nonzero_time("interval", x.interval)?;
+            nonzero_limit(x.limit)?;
+        }
+        RouterQueueOptions::DropTail(x) => {
+            nonzero_limit(Some(x.limit))?;
+        }
+        RouterQueueOptions::Red(x) => {
+            nonzero_limit(x.limit)?;
+            if x.min_threshold
+                .convert(units::SiPrefixUpper::Base)
+                .unwrap()
+                .value()
+                >= x.max_threshold
+                    .convert(units::SiPrefixUpper::Base)
+                    .unwrap()
+                    .value()
+            {
+                return Err("'min_threshold' must be less than 'max_threshold'".into());
+            }
+            if !(x.max_probability > 0.0 && x.max_probability <= 1.0) {
+                return Err("'max_probability' must be in the range (0, 1]".into());
+            }
+            if !(x.weight > 0.0 && x.weight <= 1.0) {
+                return Err("'weight' must be in the range (0, 1]".into());
+            }
+        }
+        RouterQueueOptions::FqCodel(x) => {
+            nonzero_time("interval", x.interval)?;
+            nonzero_limit(x.limit)?;
+            if x.flows == 0 {
+                return Err("'flows' must be greater than 0".into());
+            }
+            if x.quantum
+                .convert(units::SiPrefixUpper::Base)
+                .unwrap()
+                .value()
+                == 0
+            {
+                return Err("'quantum' must be greater than 0".into());
+            }
+        }
+        RouterQueueOptions::Pie(x) => {
+            nonzero_time("update_interval", x.update_interval)?;
+            nonzero_limit(x.limit)?;
+            if !(x.alpha >= 0.0 && x.beta >= 0.0) {
+                return Err("'alpha' and 'beta' must not be negative".into());
+            }
+        }
+    }
+
+    Ok(())
+}
 
 /// A router assists with moving packets between hosts across the simulated
 /// network.
@@ -15,19 +364,29 @@ pub struct Router {
     magic: Magic<Self>,
     _counter: ObjectCounter,
     /// Packets inbound to the host from the simulated network.
-    inbound_packets: CoDelQueue,
+    inbound_packets: Box<dyn RouterQueue>,
 }
 
 impl Router {
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Router {
+    pub fn new(inbound_packets: Box<dyn RouterQueue>) -> Router {
         Router {
             magic: Magic::new(),
             _counter: ObjectCounter::new("Router"),
-            inbound_packets: CoDelQueue::new(),
+            inbound_packets,
         }
     }
 
+    /// The queue holding the packets inbound to the host.
+    pub fn queue(&self) -> &dyn RouterQueue {
+        self.inbound_packets.as_ref()
+    }
+
+    /// A snapshot of the statistics of the router's queue.
+    pub fn stats(&self) -> RouterStats {
+        self.magic.debug_check();
+        RouterStats::new(self.inbound_packets.as_ref())
+    }
+
     // Return true if the router changed from empty to non-empty.
     // TODO: This will eventually not return anything once we have
     // PacketDevice signaling implemented in rust.
@@ -105,10 +464,13 @@ mod tests {
     use super::*;
     use crate::network::tests::mock_time_millis;
 
+    const TARGET: SimulationTime = SimulationTime::from_duration(Duration::from_millis(10));
+    const INTERVAL: SimulationTime = SimulationTime::from_duration(Duration::from_millis(100));
+
     #[test]
     fn empty() {
         let now = mock_time_millis(1000);
-        let mut router = Router::new();
+        let mut router = Router::new(Box::new(CoDelQueue::new(TARGET, INTERVAL, None)));
         assert!(router.peek().is_none());
         assert!(router.pop_inner(now).is_none());
     }
@@ -118,7 +480,7 @@ mod tests {
     #[cfg_attr(miri, ignore)]
     fn push_pop_simple() {
         let now = mock_time_millis(1000);
-        let mut router = Router::new();
+        let mut router = Router::new(Box::new(CoDelQueue::new(TARGET, INTERVAL, None)));
 
         const N: usize = 10;
 
@@ -134,4 +496,44 @@ mod tests {
         assert!(router.peek().is_none());
         assert!(router.pop_inner(now).is_none());
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn stats() {
+        let mut router = Router::new(Box::new(CoDelQueue::new(TARGET, INTERVAL, None)));
+        let size = Packet::mock_new().size() as u64;
+
+        for _ in 0..3 {
+            router.push_inner(Packet::mock_new(), mock_time_millis(1000));
+        }
+        assert!(router.pop_inner(mock_time_millis(1002)).is_some());
+
+        let stats = router.stats();
+        assert_eq!(stats.enqueued_packets, 3);
+        assert_eq!(stats.enqueued_bytes, 3 * size);
+        assert_eq!(stats.dequeued_packets, 1);
+        assert_eq!(stats.max_sojourn_time_ns, 2_000_000);
+        assert_eq!(stats.mean_sojourn_time_ns, 2_000_000);
+        assert_eq!(stats.queued_packets, 2);
+        assert_eq!(stats.queued_bytes, 2 * size);
+    }
+
+    #[test]
+    fn validate() {
+        fn validate_yaml(yaml: &str) -> Result<(), String> {
+            validate_router_queue(&serde_yaml::from_str(yaml).unwrap())
+        }
+
+        assert!(validate_yaml("type: drop_tail\nlimit: 1500 B").is_ok());
+        assert!(validate_yaml("type: drop_tail\nlimit: 0 B").is_err());
+        assert!(validate_yaml("type: drop_tail\nlimit: 0").is_err());
+        assert!(validate_yaml("type: codel").is_ok());
+        assert!(validate_yaml("type: codel\nlimit: 0 KB").is_err());
+        assert!(validate_yaml("type: fq_codel\nlimit: 0 B").is_err());
+        assert!(validate_yaml("type: pie\nlimit: 0 B").is_err());
+        assert!(
+            validate_yaml("type: red\nmin_threshold: 5 KB\nmax_threshold: 15 KB\nlimit: 0 B")
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,364 @@
+//! An active queue management (AQM) algorithm implementing the proportional
+//! integral controller enhanced (PIE).
+//! <https://tools.ietf.org/html/rfc8033>
+//!
+//!  The queue delay is measured from packet timestamps rather than estimated
+//!  from the departure rate, and the optional derandomization of drops is not
+//!  implemented. ECN-capable packets are marked instead of dropped while the
+//!  drop probability is low.
+//!
+//!  More info:
+//!   - <http://man7.org/linux/man-pages/man8/tc-pie.8.html>
+
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::cshadow as c;
+use crate::network::packet::Packet;
+use crate::network::router::{RouterQueue, RouterQueueStats};
+use shadow_shim_helper_rs::{emulated_time::EmulatedTime, simulation_time::SimulationTime};
+
+/// The highest drop probability at which ECN-capable packets are marked
+/// instead of dropped, corresponding to the "mark_ecnth" parameter in the RFC.
+const MARK_ECN_THRESHOLD: f64 = 0.1;
+
+/// An entry in the PIE queue.
+struct PieElement {
+    packet: Packet,
+    enqueue_ts: EmulatedTime,
+}
+
+/// A packet queue implementing the PIE active queue management (AQM)
+/// algorithm, which drops arriving packets with a probability that is updated
+/// periodically to keep the queue delay near a target.
+pub struct PieQueue {
+    /// The queue delay to aim for, corresponding to the "QDELAY_REF" parameter
+    /// in the RFC.
+    target: SimulationTime,
+    /// How often the drop probability is updated, corresponding to the
+    /// "T_UPDATE" parameter in the RFC.
+    update_interval: SimulationTime,
+    /// The weight of the distance from the target delay, in Hz.
+    alpha: f64,
+    /// The weight of the change in queue delay, in Hz.
+    beta: f64,
+    /// How long bursts may be queued without drops, corresponding to the
+    /// "MAX_BURST" parameter in the RFC.
+    max_burst: SimulationTime,
+    /// The maximum number of bytes we will store, if any.
+    limit: Option<usize>,
+    /// A queue holding packets and insertion times.
+    elements: VecDeque<PieElement>,
+    /// The running sum of the sizes of packets stored in the queue.
+    total_bytes_stored: usize,
+    /// The probability that an arriving packet is dropped.
+    drop_probability: f64,
+    /// The queue delay of the most recently dequeued packet, or zero if the
+    /// queue is empty.
+    qdelay: SimulationTime,
+    /// The queue delay at the previous drop probability update.
+    qdelay_old: SimulationTime,
+    /// The remaining time for which bursts are allowed without drops.
+    burst_allowance: SimulationTime,
+    /// The next time the drop probability should be updated.
+    next_update: Option<EmulatedTime>,
+    /// The source of randomness for drop decisions.
+    rng: Xoshiro256PlusPlus,
+    /// The counters of the packets that passed through the queue.
+    stats: RouterQueueStats,
+}
+
+impl PieQueue {
+    /// Creates a new empty packet queue.
+    pub fn new(
+        target: SimulationTime,
+        update_interval: SimulationTime,
+        alpha: f64,
+        beta: f64,
+        max_burst: SimulationTime,
+        limit: Option<usize>,
+        seed: u64,
+    ) -> PieQueue {
+        assert!(!update_interval.is_zero());
+        PieQueue {
+            target,
+            update_interval,
+            alpha,
+            beta,
+            max_burst,
+            limit,
+            elements: VecDeque::new(),
+            total_bytes_stored: 0,
+            drop_probability: 0.0,
+            qdelay: SimulationTime::ZERO,
+            qdelay_old: SimulationTime::ZERO,
+            burst_allowance: max_burst,
+            next_update: None,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+            stats: RouterQueueStats::default(),
+        }
+    }
+
+    /// Run the drop probability updates that were due since the last time the
+    /// queue was used. Corresponds to the `calculate_drop_prob` timer in the RFC.
+    fn run_updates(&mut self, now: EmulatedTime) {
+        let mut next_update = self
+            .next_update
+            .unwrap_or_else(|| now.saturating_add(self.update_interval));
+
+        while next_update <= now {
+            next_update = next_update.saturating_add(self.update_interval);
+
+            // Once the queue has been idle long enough for the drop probability
+            // to reach zero and the burst allowance to be restored, further
+            // updates would not change anything, so skip to the next update
+            // after `now`.
+            let is_idle = self.drop_probability == 0.0
+                && self.burst_allowance == self.max_burst
+                && self.qdelay.is_zero()
+                && self.qdelay_old.is_zero();
+            if is_idle {
+                let behind = now.saturating_duration_since(&next_update);
+                let skipped = behind.as_nanos() / self.update_interval.as_nanos() + 1;
+                next_update =
+                    next_update.saturating_add(self.update_interval.saturating_mul(skipped as u64));
+                break;
+            }
+
+            self.update_drop_probability();
+        }
+
+        self.next_update = Some(next_update);
+    }
+
+    /// Update the drop probability from the current queue delay, following
+    /// section 4.2 of the RFC.
+    fn update_drop_probability(&mut self) {
+        let secs = |x: SimulationTime| x.as_nanos_f64() / 1_000_000_000.0;
+
+        let mut p = self.alpha * (secs(self.qdelay) - secs(self.target))
+            + self.beta * (secs(self.qdelay) - secs(self.qdelay_old));
+
+        // scale the adjustment so that small drop probabilities change slowly
+        p /= match self.drop_probability {
+            x if x < 0.000001 => 2048.0,
+            x if x < 0.00001 => 512.0,
+            x if x < 0.0001 => 128.0,
+            x if x < 0.001 => 32.0,
+            x if x < 0.01 => 8.0,
+            x if x < 0.1 => 2.0,
+            _ => 1.0,
+        };
+
+        // avoid large jumps when the drop probability is already high
+        if self.drop_probability >= 0.1 && p > 0.02 {
+            p = 0.02;
+        }
+
+        self.drop_probability += p;
+
+        // decay the drop probability when the queue stays empty
+        if self.qdelay.is_zero() && self.qdelay_old.is_zero() {
+            self.drop_probability *= 0.98;
+        }
+
+        self.drop_probability = self.drop_probability.clamp(0.0, 1.0);
+
+        self.burst_allowance = self.burst_allowance.saturating_sub(self.update_interval);
+
+        let half_target = self.target / 2;
+        if self.drop_probability == 0.0
+            && self.qdelay < half_target
+            && self.qdelay_old < half_target
+        {
+            self.burst_allowance = self.max_burst;
+        }
+
+        self.qdelay_old = self.qdelay;
+    }
+
+    /// Decide whether an arriving packet should be dropped early, following
+    /// section 5.1 of the RFC.
+    fn should_drop_early(&mut self) -> bool {
+        if !self.burst_allowance.is_zero() {
+            return false;
+        }
+
+        if self.qdelay_old < self.target / 2 && self.drop_probability < 0.2 {
+            return false;
+        }
+
+        let mtu: usize = c::CONFIG_MTU.try_into().unwrap();
+        if self.total_bytes_stored <= 2 * mtu {
+            return false;
+        }
+
+        self.rng.gen_bool(self.drop_probability)
+    }
+}
+
+impl RouterQueue for PieQueue {
+    fn name(&self) -> &'static str {
+        "pie"
+    }
+
+    fn push(&mut self, mut packet: Packet, now: EmulatedTime) {
+        self.run_updates(now);
+
+        if let Some(limit) = self.limit {
+            if self.total_bytes_stored + packet.size() > limit {
+                self.stats.drop_overlimit(packet);
+                return;
+            }
+        }
+
+        if self.should_drop_early() {
+            // an ECN-capable packet is marked instead of dropped, unless the drop
+            // probability is so high that marking wouldn't control the queue
+            // (section 5.1 of the RFC)
+            if self.drop_probability > MARK_ECN_THRESHOLD {
+                self.stats.drop_packet(packet);
+                return;
+            }
+            match self.stats.drop_or_mark(packet) {
+                Some(marked) => packet = marked,
+                None => return,
+            }
+        }
+
+        self.stats.enqueue(&mut packet);
+        self.total_bytes_stored += packet.size();
+        self.elements.push_back(PieElement {
+            packet,
+            enqueue_ts: now,
+        });
+    }
+
+    fn pop(&mut self, now: EmulatedTime) -> Option<Packet> {
+        self.run_updates(now);
+
+        let Some(element) = self.elements.pop_front() else {
+            self.qdelay = SimulationTime::ZERO;
+            return None;
+        };
+
+        let mut packet = element.packet;
+        self.total_bytes_stored -= packet.size();
+
+        let sojourn_time = now.saturating_duration_since(&element.enqueue_ts);
+        self.qdelay = if self.elements.is_empty() {
+            SimulationTime::ZERO
+        } else {
+            sojourn_time
+        };
+
+        self.stats.dequeue(&mut packet, sojourn_time);
+        Some(packet)
+    }
+
+    fn peek(&self) -> Option<&Packet> {
+        self.elements.front().map(|x| &x.packet)
+    }
+
+    fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn bytes(&self) -> usize {
+        self.total_bytes_stored
+    }
+
+    fn stats(&self) -> RouterQueueStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::network::tests::mock_time_millis;
+
+    const TARGET: SimulationTime = SimulationTime::from_duration(Duration::from_millis(15));
+    const MAX_BURST: SimulationTime = SimulationTime::from_duration(Duration::from_millis(150));
+
+    fn new_queue() -> PieQueue {
+        PieQueue::new(TARGET, TARGET, 0.125, 1.25, MAX_BURST, None, 0)
+    }
+
+    #[test]
+    fn empty() {
+        let now = mock_time_millis(1000);
+        let mut q = new_queue();
+        assert!(q.peek().is_none());
+        assert!(q.pop(now).is_none());
+        assert_eq!(q.drop_probability, 0.0);
+    }
+
+    #[test]
+    fn idle_updates() {
+        let mut q = new_queue();
+        q.pop(mock_time_millis(1000));
+
+        // a long idle period doesn't change the state
+        q.pop(mock_time_millis(1_000_000_000));
+        assert_eq!(q.drop_probability, 0.0);
+        assert_eq!(q.burst_allowance, MAX_BURST);
+        assert!(q.next_update.unwrap() > mock_time_millis(1_000_000_000));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn standing_queue() {
+        let mut q = new_queue();
+
+        // build a standing queue with a delay well above the target
+        let mut now = 1000;
+        for _ in 0..100 {
+            q.push(Packet::mock_new(), mock_time_millis(now));
+        }
+        for _ in 0..2000 {
+            now += 5;
+            q.push(Packet::mock_new(), mock_time_millis(now));
+            q.push(Packet::mock_new(), mock_time_millis(now));
+            q.pop(mock_time_millis(now));
+        }
+
+        // the burst allowance ran out and packets were dropped
+        assert!(q.burst_allowance.is_zero());
+        assert!(q.drop_probability > 0.0);
+        assert!(q.stats().dropped_packets > 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn mark_ecn_capable() {
+        let now = mock_time_millis(1000);
+        let mut q = new_queue();
+        q.burst_allowance = SimulationTime::ZERO;
+        q.qdelay_old = TARGET;
+        for _ in 0..3 {
+            q.push(Packet::mock_new_ecn_capable(), now);
+        }
+
+        // marked while the drop probability is low
+        q.drop_probability = MARK_ECN_THRESHOLD;
+        let len = q.len();
+        for _ in 0..100 {
+            q.push(Packet::mock_new_ecn_capable(), now);
+        }
+        assert!(q.stats().marked_packets > 0);
+        assert_eq!(q.stats().dropped_packets, 0);
+        assert_eq!(q.len(), len + 100);
+
+        // dropped once it's high
+        q.drop_probability = 0.5;
+        for _ in 0..100 {
+            q.push(Packet::mock_new_ecn_capable(), now);
+        }
+        assert!(q.stats().dropped_packets > 0);
+    }
+}
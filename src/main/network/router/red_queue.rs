@@ -0,0 +1,348 @@
+//! An active queue management (AQM) algorithm implementing random early
+//! detection (RED).
+//! <https://www.icir.org/floyd/papers/red/red.html>
+//!
+//!  The queue sizes are measured in bytes rather than packets, and the "gentle"
+//!  variant is not implemented. ECN-capable packets are marked instead of
+//!  being dropped early, but are dropped once the average queue size reaches
+//!  the maximum threshold. While the queue is empty, the average queue size
+//!  decays once for each full-sized packet that the link could have sent.
+//!
+//!  More info:
+//!   - <https://en.wikipedia.org/wiki/Random_early_detection>
+//!   - <http://man7.org/linux/man-pages/man8/tc-red.8.html>
+
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::network::packet::Packet;
+use crate::network::router::{RouterQueue, RouterQueueStats};
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+/// The congestion that RED signals to an arriving packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Congestion {
+    /// The packet is queued.
+    None,
+    /// The packet is dropped early, or marked if it's ECN-capable.
+    Early,
+    /// The average queue size reached the maximum threshold, so the packet is dropped even if
+    /// it's ECN-capable.
+    Severe,
+}
+
+/// A packet queue implementing the RED active queue management (AQM)
+/// algorithm, which drops arriving packets with a probability that grows with
+/// the average queue size.
+pub struct RedQueue {
+    /// The average queue size at which we start dropping packets.
+    min_threshold: f64,
+    /// The average queue size at which we drop every packet.
+    max_threshold: f64,
+    /// The drop probability when the average queue size reaches the maximum
+    /// threshold.
+    max_probability: f64,
+    /// The weight of the current queue size in the moving average.
+    weight: f64,
+    /// The maximum number of bytes we will store, if any.
+    limit: Option<usize>,
+    /// A queue holding packets and insertion times.
+    elements: VecDeque<(Packet, EmulatedTime)>,
+    /// The running sum of the sizes of packets stored in the queue.
+    total_bytes_stored: usize,
+    /// The exponentially weighted moving average of the queue size in bytes.
+    average_bytes: f64,
+    /// The time to transmit a full-sized packet on the link, which is how
+    /// often the average decays while the queue is empty.
+    packet_time: SimulationTime,
+    /// The time the queue became empty, if it's empty.
+    idle_since: Option<EmulatedTime>,
+    /// The number of packets that arrived since the last drop while the
+    /// average queue size was between the thresholds, or None if the average
+    /// was below the minimum threshold. This spreads the drops out more evenly
+    /// than independent random drops would.
+    count: Option<u64>,
+    /// The source of randomness for drop decisions.
+    rng: Xoshiro256PlusPlus,
+    /// The counters of the packets that passed through the queue.
+    stats: RouterQueueStats,
+}
+
+impl RedQueue {
+    /// Creates a new empty packet queue. `packet_time` is the time to transmit
+    /// a full-sized packet on the link.
+    pub fn new(
+        min_threshold: usize,
+        max_threshold: usize,
+        max_probability: f64,
+        weight: f64,
+        limit: Option<usize>,
+        packet_time: SimulationTime,
+        seed: u64,
+    ) -> RedQueue {
+        assert!(min_threshold < max_threshold);
+        assert!(!packet_time.is_zero());
+        RedQueue {
+            min_threshold: min_threshold as f64,
+            max_threshold: max_threshold as f64,
+            max_probability,
+            weight,
+            limit,
+            elements: VecDeque::new(),
+            total_bytes_stored: 0,
+            average_bytes: 0.0,
+            packet_time,
+            idle_since: None,
+            count: None,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+            stats: RouterQueueStats::default(),
+        }
+    }
+
+    /// Update the average queue size and decide whether the arriving packet
+    /// should be dropped early.
+    fn congestion(&mut self, now: EmulatedTime) -> Congestion {
+        self.average_bytes = match self.idle_since {
+            // the average decays once for each packet that could have been
+            // sent while the queue was empty, rather than staying at its value
+            // from when the queue emptied
+            Some(idle_since) => {
+                let idle_time = now.saturating_duration_since(&idle_since);
+                let m = idle_time.as_nanos_f64() / self.packet_time.as_nanos_f64();
+                // the queue stays empty if this packet is dropped
+                self.idle_since = Some(now);
+                (1.0 - self.weight).powf(m) * self.average_bytes
+            }
+            None => {
+                (1.0 - self.weight) * self.average_bytes
+                    + self.weight * self.total_bytes_stored as f64
+            }
+        };
+
+        if self.average_bytes < self.min_threshold {
+            self.count = None;
+            return Congestion::None;
+        }
+
+        if self.average_bytes >= self.max_threshold {
+            self.count = Some(0);
+            return Congestion::Severe;
+        }
+
+        let count = self.count.map_or(0, |x| x + 1);
+
+        // the drop probability grows linearly between the thresholds, and
+        // with the number of packets since the last drop
+        let base_probability = self.max_probability * (self.average_bytes - self.min_threshold)
+            / (self.max_threshold - self.min_threshold);
+        let denominator = 1.0 - count as f64 * base_probability;
+        let probability = if denominator <= 0.0 {
+            1.0
+        } else {
+            base_probability / denominator
+        };
+
+        if self.rng.gen_bool(probability.clamp(0.0, 1.0)) {
+            self.count = Some(0);
+            Congestion::Early
+        } else {
+            self.count = Some(count);
+            Congestion::None
+        }
+    }
+}
+
+impl RouterQueue for RedQueue {
+    fn name(&self) -> &'static str {
+        "red"
+    }
+
+    fn push(&mut self, packet: Packet, now: EmulatedTime) {
+        let mut packet = match self.congestion(now) {
+            Congestion::None => packet,
+            // an ECN-capable packet is marked instead of being dropped early
+            Congestion::Early => match self.stats.drop_or_mark(packet) {
+                Some(packet) => packet,
+                None => return,
+            },
+            // marking wouldn't stop the queue from growing, so every packet is dropped
+            Congestion::Severe => {
+                self.stats.drop_packet(packet);
+                return;
+            }
+        };
+
+        if let Some(limit) = self.limit {
+            if self.total_bytes_stored + packet.size() > limit {
+                self.stats.drop_overlimit(packet);
+                return;
+            }
+        }
+
+        self.stats.enqueue(&mut packet);
+        self.total_bytes_stored += packet.size();
+        self.elements.push_back((packet, now));
+        self.idle_since = None;
+    }
+
+    fn pop(&mut self, now: EmulatedTime) -> Option<Packet> {
+        let (mut packet, enqueue_ts) = self.elements.pop_front()?;
+        self.total_bytes_stored -= packet.size();
+        if self.elements.is_empty() {
+            self.idle_since = Some(now);
+        }
+        self.stats
+            .dequeue(&mut packet, now.saturating_duration_since(&enqueue_ts));
+        Some(packet)
+    }
+
+    fn peek(&self) -> Option<&Packet> {
+        self.elements.front().map(|(packet, _)| packet)
+    }
+
+    fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn bytes(&self) -> usize {
+        self.total_bytes_stored
+    }
+
+    fn stats(&self) -> RouterQueueStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::network::tests::mock_time_millis;
+
+    /// The time to transmit a 1500 byte packet at 12 Mbit/s.
+    const PACKET_TIME: SimulationTime = SimulationTime::from_duration(Duration::from_millis(1));
+
+    // The tests here don't run in miri because they cause c::packet* functions
+    // to be called during the test.
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn below_min_threshold() {
+        let now = mock_time_millis(1000);
+        let size = Packet::mock_new().size();
+
+        // the average can never reach the minimum threshold
+        let mut q = RedQueue::new(100 * size, 200 * size, 1.0, 1.0, None, PACKET_TIME, 0);
+        for _ in 0..100 {
+            q.push(Packet::mock_new(), now);
+        }
+        assert_eq!(q.len(), 100);
+        assert_eq!(q.stats().dropped_packets, 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn above_max_threshold() {
+        let now = mock_time_millis(1000);
+        let size = Packet::mock_new().size();
+
+        // with a weight of 1 the average is the current queue size, so the
+        // queue never grows past the maximum threshold
+        let mut q = RedQueue::new(size, 10 * size, 0.1, 1.0, None, PACKET_TIME, 0);
+        for _ in 0..1000 {
+            q.push(Packet::mock_new(), now);
+        }
+        assert!(q.len() <= 11);
+        assert!(q.stats().dropped_packets >= 989);
+        assert_eq!(q.stats().overlimit_packets, 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn mark_ecn_capable() {
+        let now = mock_time_millis(1000);
+        let size = Packet::mock_new().size();
+
+        // packets that would have been dropped early are marked and queued instead, until the
+        // queue reaches the maximum threshold and they're dropped
+        let mut q = RedQueue::new(size, 10 * size, 1.0, 1.0, None, PACKET_TIME, 0);
+        for _ in 0..100 {
+            q.push(Packet::mock_new_ecn_capable(), now);
+        }
+        assert_eq!(q.len(), 10);
+        assert!(q.stats().marked_packets >= 1);
+        assert_eq!(q.stats().dropped_packets, 90);
+
+        // the limit still applies to marked packets
+        let mut q = RedQueue::new(size, 10 * size, 1.0, 1.0, Some(5 * size), PACKET_TIME, 0);
+        for _ in 0..100 {
+            q.push(Packet::mock_new_ecn_capable(), now);
+        }
+        assert_eq!(q.len(), 5);
+        assert_eq!(q.stats().dropped_packets, 0);
+        assert_eq!(q.stats().overlimit_packets, 95);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn deterministic() {
+        let now = mock_time_millis(1000);
+        let size = Packet::mock_new().size();
+
+        let run = |seed| {
+            let mut q = RedQueue::new(size, 50 * size, 0.5, 0.5, None, PACKET_TIME, seed);
+            for i in 0..1000 {
+                q.push(Packet::mock_new(), now);
+                if i % 2 == 0 {
+                    q.pop(now);
+                }
+            }
+            q.stats()
+        };
+
+        assert_eq!(run(1), run(1));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn idle_decay() {
+        let size = Packet::mock_new().size();
+        let weight = 0.5;
+
+        let mut q = RedQueue::new(10 * size, 20 * size, 0.1, weight, None, PACKET_TIME, 0);
+        for _ in 0..4 {
+            q.push(Packet::mock_new(), mock_time_millis(1000));
+        }
+        while q.pop(mock_time_millis(1000)).is_some() {}
+        let average = q.average_bytes;
+        assert!(average > 0.0);
+
+        // the average decays once for each packet time that the queue was empty
+        q.push(Packet::mock_new(), mock_time_millis(1003));
+        let expected = (1.0 - weight).powi(3) * average;
+        assert!(
+            (q.average_bytes - expected).abs() < 1e-9,
+            "{}",
+            q.average_bytes
+        );
+
+        // the average isn't decayed while the queue isn't empty
+        q.push(Packet::mock_new(), mock_time_millis(1010));
+        let expected = (1.0 - weight) * expected + weight * size as f64;
+        assert!(
+            (q.average_bytes - expected).abs() < 1e-9,
+            "{}",
+            q.average_bytes
+        );
+
+        // after a long idle period the average is almost 0
+        while q.pop(mock_time_millis(1010)).is_some() {}
+        q.push(Packet::mock_new(), mock_time_millis(2000));
+        assert!(q.average_bytes < 1e-9, "{}", q.average_bytes);
+        assert_eq!(q.stats().dropped_packets, 0);
+    }
+}
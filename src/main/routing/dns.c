@@ -84,7 +84,7 @@ static gboolean _dns_isIPInRange(const in_addr_t netIP, const gchar* cidrStr) {
 }
 
 /* Address must be in network byte order. */
-static gboolean _dns_isRestricted(DNS* dns, in_addr_t netIP) {
+bool dns_isRestricted(in_addr_t netIP) {
     /* http://en.wikipedia.org/wiki/Reserved_IP_addresses#Reserved_IPv4_addresses */
     if(_dns_isIPInRange(netIP, "0.0.0.0/8") ||
             _dns_isIPInRange(netIP, "10.0.0.0/8") ||
@@ -102,9 +102,9 @@ static gboolean _dns_isRestricted(DNS* dns, in_addr_t netIP) {
             _dns_isIPInRange(netIP, "224.0.0.0/4") ||
             _dns_isIPInRange(netIP, "240.0.0.0/4") ||
             _dns_isIPInRange(netIP, "255.255.255.255/32")) {
-        return TRUE;
+        return true;
     } else {
-        return FALSE;
+        return false;
     }
 }
 
@@ -126,10 +126,10 @@ Address* dns_register(DNS* dns, HostId id, const gchar* name, in_addr_t requeste
     /* restricted is OK if this is a localhost address, otherwise it must be unique */
     if (requestedIP == address_stringToIP("127.0.0.1")) {
         isLocal = TRUE;
-    } else if (_dns_isRestricted(dns, requestedIP) || !_dns_isIPUnique(dns, requestedIP)) {
+    } else if (dns_isRestricted(requestedIP) || !_dns_isIPUnique(dns, requestedIP)) {
         gchar* ipStr = address_ipToNewString(requestedIP);
         warning("Invalid IP %s (restricted: %s, unique: %s)", ipStr,
-                _dns_isRestricted(dns, requestedIP) ? "true" : "false",
+                dns_isRestricted(requestedIP) ? "true" : "false",
                 _dns_isIPUnique(dns, requestedIP) ? "true" : "false");
         g_free(ipStr);
         g_mutex_unlock(&dns->lock);
@@ -144,9 +144,14 @@ Address* dns_register(DNS* dns, HostId id, const gchar* name, in_addr_t requeste
         g_hash_table_replace(
             dns->addressByIP, GUINT_TO_POINTER(address_toNetworkIP(address)), address);
         address_ref(address);
-        /* cast the const pointer to non-const */
-        g_hash_table_replace(dns->addressByName, (gchar*)address_toHostName(address), address);
-        address_ref(address);
+        /* a multihomed host registers several addresses under the same name, and the name
+         * resolves to the first of them (the host's default address) */
+        if (!g_hash_table_contains(dns->addressByName, address_toHostName(address))) {
+            /* cast the const pointer to non-const */
+            g_hash_table_replace(
+                dns->addressByName, (gchar*)address_toHostName(address), address);
+            address_ref(address);
+        }
     }
 
     /* Any existing hosts file needs to be (lazily) updated. */
@@ -164,7 +169,9 @@ void dns_deregister(DNS* dns, Address* address) {
 
         /* these remove functions will call address_unref as necessary */
         g_hash_table_remove(dns->addressByIP, GUINT_TO_POINTER(address_toNetworkIP(address)));
-        g_hash_table_remove(dns->addressByName, address_toHostName(address));
+        if (g_hash_table_lookup(dns->addressByName, address_toHostName(address)) == address) {
+            g_hash_table_remove(dns->addressByName, address_toHostName(address));
+        }
 
         /* Any existing hosts file needs to be (lazily) updated. */
         dns->hosts.isStale = true;
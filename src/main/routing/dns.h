@@ -9,6 +9,7 @@
 
 #include <glib.h>
 #include <netinet/in.h>
+#include <stdbool.h>
 
 #include "lib/shadow-shim-helper-rs/shim_helper.h"
 #include "main/routing/address.h"
@@ -20,6 +21,8 @@ void dns_free(DNS* dns);
 
 /* Address must be in network byte order. */
 Address* dns_register(DNS* dns, HostId id, const gchar* name, in_addr_t requestedIP);
+/* Whether the address (in network byte order) is in a reserved range that can't be registered. */
+bool dns_isRestricted(in_addr_t netIP);
 void dns_deregister(DNS* dns, Address* address);
 
 /* Address must be in network byte order. */
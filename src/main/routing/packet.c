@@ -80,6 +80,12 @@ struct _Packet {
      */
     gdouble priority;
 
+    /* if set, the packet is dropped rather than fragmented when it's larger than the path MTU */
+    gboolean dontFragment;
+
+    /* the ECN codepoint of the IP header */
+    enum ProtocolECN ecn;
+
     PacketDeliveryStatusFlags allStatus;
     GQueue* orderedStatus;
 
@@ -91,6 +97,7 @@ const gchar* protocol_toString(ProtocolType type) {
         case PLOCAL: return "LOCAL";
         case PUDP: return "UDP";
         case PTCP: return "TCP";
+        case PICMP: return "ICMP";
         case PMOCK: return "MOCK";
         default: return "UNKNOWN";
     }
@@ -152,6 +159,8 @@ Packet* packet_copy(Packet* packet) {
         copy->priority = packet->priority;
     }
 
+    copy->dontFragment = packet->dontFragment;
+    copy->ecn = packet->ecn;
     copy->allStatus = packet->allStatus;
 
     if(packet->orderedStatus) {
@@ -172,6 +181,11 @@ Packet* packet_copy(Packet* packet) {
                 break;
             }
 
+            case PICMP: {
+                copy->header = compat_static_g_memdup(packet->header, sizeof(PacketICMPHeader));
+                break;
+            }
+
             case PTCP: {
                 copy->header = compat_static_g_memdup(packet->header, sizeof(PacketTCPHeader));
 
@@ -242,6 +256,26 @@ void packet_setPriority(Packet *packet, double value) {
    packet->priority = value;
 }
 
+void packet_setDontFragment(Packet* packet, gboolean dontFragment) {
+    MAGIC_ASSERT(packet);
+    packet->dontFragment = dontFragment;
+}
+
+gboolean packet_getDontFragment(const Packet* packet) {
+    MAGIC_ASSERT(packet);
+    return packet->dontFragment;
+}
+
+void packet_setECN(Packet* packet, enum ProtocolECN ecn) {
+    MAGIC_ASSERT(packet);
+    packet->ecn = ecn;
+}
+
+enum ProtocolECN packet_getECN(const Packet* packet) {
+    MAGIC_ASSERT(packet);
+    return packet->ecn;
+}
+
 gint packet_compareTCPSequence(Packet* packet1, Packet* packet2, gpointer user_data) {
     MAGIC_ASSERT(packet1);
     MAGIC_ASSERT(packet2);
@@ -324,6 +358,72 @@ void packet_setTCP(Packet* packet, enum ProtocolTCPFlags flags,
     packet->protocol = PTCP;
 }
 
+// An echo request or reply. The addresses, identifier, and sequence must be in network byte order.
+void packet_setICMPEcho(Packet* packet, enum ProtocolICMPType type, in_addr_t sourceIP,
+                        in_addr_t destinationIP, guint16 identifier, guint16 sequence) {
+    MAGIC_ASSERT(packet);
+    utility_debugAssert(!(packet->header) && packet->protocol == PNONE);
+    utility_debugAssert(type == PICMP_ECHO_REQUEST || type == PICMP_ECHO_REPLY);
+    utility_debugAssert(sourceIP && destinationIP);
+
+    PacketICMPHeader* header = g_new0(PacketICMPHeader, 1);
+
+    header->type = type;
+    header->code = PICMP_CODE_NONE;
+    header->sourceIP = sourceIP;
+    header->destinationIP = destinationIP;
+    header->identifier = identifier;
+    header->sequence = sequence;
+
+    packet->header = header;
+    packet->protocol = PICMP;
+}
+
+// A reply to the echo request `request`, which carries the same payload as the request.
+void packet_setICMPEchoReply(Packet* packet, const Packet* request) {
+    MAGIC_ASSERT(packet);
+    MAGIC_ASSERT(request);
+    utility_debugAssert(request->protocol == PICMP);
+    utility_debugAssert(!packet->payload);
+
+    PacketICMPHeader* requestHeader = request->header;
+    utility_debugAssert(requestHeader->type == PICMP_ECHO_REQUEST);
+
+    packet_setICMPEcho(packet, PICMP_ECHO_REPLY, requestHeader->destinationIP,
+                       requestHeader->sourceIP, requestHeader->identifier,
+                       requestHeader->sequence);
+
+    if (request->payload) {
+        packet->payload = request->payload;
+        payload_ref(request->payload);
+    }
+}
+
+// An error caused by the packet `original`, which is sent back to the original packet's source.
+// The address must be in network byte order.
+void packet_setICMPError(Packet* packet, enum ProtocolICMPType type, enum ProtocolICMPCode code,
+                         in_addr_t sourceIP, const Packet* original) {
+    MAGIC_ASSERT(packet);
+    MAGIC_ASSERT(original);
+    utility_debugAssert(!(packet->header) && packet->protocol == PNONE);
+    utility_debugAssert(type == PICMP_DEST_UNREACH);
+
+    PacketICMPHeader* header = g_new0(PacketICMPHeader, 1);
+
+    header->type = type;
+    header->code = code;
+    header->sourceIP = sourceIP;
+    header->destinationIP = packet_getSourceIP(original);
+    header->originalProtocol = original->protocol;
+    header->originalSourceIP = packet_getSourceIP(original);
+    header->originalSourcePort = packet_getSourcePort(original);
+    header->originalDestinationIP = packet_getDestinationIP(original);
+    header->originalDestinationPort = packet_getDestinationPort(original);
+
+    packet->header = header;
+    packet->protocol = PICMP;
+}
+
 void packet_updateTCP(Packet* packet, guint acknowledgement, GList* selectiveACKs, guint window,
                       CSimulationTime timestampValue, CSimulationTime timestampEcho) {
     MAGIC_ASSERT(packet);
@@ -349,6 +449,74 @@ void packet_updateTCP(Packet* packet, guint acknowledgement, GList* selectiveACK
     header->timestampEcho = timestampEcho;
 }
 
+void packet_setSource(Packet* packet, in_addr_t ip, in_port_t port) {
+    MAGIC_ASSERT(packet);
+
+    switch (packet->protocol) {
+        case PUDP: {
+            PacketUDPHeader* header = packet->header;
+            header->sourceIP = ip;
+            header->sourcePort = port;
+            break;
+        }
+
+        case PTCP: {
+            PacketTCPHeader* header = packet->header;
+            header->sourceIP = ip;
+            header->sourcePort = port;
+            break;
+        }
+
+        case PICMP: {
+            /* the identifier of an echo message is its port */
+            PacketICMPHeader* header = packet->header;
+            utility_debugAssert(header->type != PICMP_DEST_UNREACH);
+            header->sourceIP = ip;
+            header->identifier = port;
+            break;
+        }
+
+        default: {
+            utility_panic("unrecognized protocol");
+            break;
+        }
+    }
+}
+
+void packet_setDestination(Packet* packet, in_addr_t ip, in_port_t port) {
+    MAGIC_ASSERT(packet);
+
+    switch (packet->protocol) {
+        case PUDP: {
+            PacketUDPHeader* header = packet->header;
+            header->destinationIP = ip;
+            header->destinationPort = port;
+            break;
+        }
+
+        case PTCP: {
+            PacketTCPHeader* header = packet->header;
+            header->destinationIP = ip;
+            header->destinationPort = port;
+            break;
+        }
+
+        case PICMP: {
+            /* the identifier of an echo message is its port */
+            PacketICMPHeader* header = packet->header;
+            utility_debugAssert(header->type != PICMP_DEST_UNREACH);
+            header->destinationIP = ip;
+            header->identifier = port;
+            break;
+        }
+
+        default: {
+            utility_panic("unrecognized protocol");
+            break;
+        }
+    }
+}
+
 gsize packet_getTotalSize(const Packet* packet) {
     MAGIC_ASSERT(packet);
     return packet_getPayloadSize(packet) + packet_getHeaderSize(packet);
@@ -367,9 +535,17 @@ gsize packet_getPayloadSize(const Packet* packet) {
 
 gsize packet_getHeaderSize(const Packet* packet) {
     MAGIC_ASSERT(packet);
-    gsize size = packet->protocol == PUDP   ? CONFIG_HEADER_SIZE_UDPIP
-                 : packet->protocol == PTCP ? CONFIG_HEADER_SIZE_TCPIP
-                                            : 0;
+    gsize size = packet->protocol == PUDP    ? CONFIG_HEADER_SIZE_UDPIP
+                 : packet->protocol == PTCP  ? CONFIG_HEADER_SIZE_TCPIP
+                 : packet->protocol == PICMP ? CONFIG_HEADER_SIZE_ICMPIP
+                                             : 0;
+
+    /* error messages quote the IP header and first 8 bytes of the packet that caused the error */
+    if (packet->protocol == PICMP &&
+        ((PacketICMPHeader*)packet->header)->type == PICMP_DEST_UNREACH) {
+        size += CONFIG_HEADER_SIZE_IP + 8;
+    }
+
     return size;
 }
 
@@ -401,6 +577,12 @@ in_addr_t packet_getDestinationIP(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            PacketICMPHeader* header = packet->header;
+            ip = header->destinationIP;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -435,6 +617,14 @@ in_port_t packet_getDestinationPort(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            /* echo messages use the identifier as both ports, like the kernel does when looking
+             * up the ping socket; error messages have no ports */
+            PacketICMPHeader* header = packet->header;
+            port = header->type == PICMP_DEST_UNREACH ? 0 : header->identifier;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -468,6 +658,12 @@ in_addr_t packet_getSourceIP(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            PacketICMPHeader* header = packet->header;
+            ip = header->sourceIP;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -502,6 +698,14 @@ in_port_t packet_getSourcePort(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            /* echo messages use the identifier as both ports, like the kernel does when looking
+             * up the ping socket; error messages have no ports */
+            PacketICMPHeader* header = packet->header;
+            port = header->type == PICMP_DEST_UNREACH ? 0 : header->identifier;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -560,6 +764,12 @@ PacketTCPHeader* packet_getTCPHeader(const Packet* packet) {
     return (PacketTCPHeader*)packet->header;
 }
 
+PacketICMPHeader* packet_getICMPHeader(const Packet* packet) {
+    MAGIC_ASSERT(packet);
+    utility_debugAssert(packet->protocol == PICMP);
+    return (PacketICMPHeader*)packet->header;
+}
+
 static const gchar* _packet_deliveryStatusToAscii(PacketDeliveryStatusFlags status) {
     switch (status) {
         case PDS_NONE: return "NONE";
@@ -583,6 +793,9 @@ static const gchar* _packet_deliveryStatusToAscii(PacketDeliveryStatusFlags stat
         case PDS_RCV_SOCKET_BUFFERED: return "RCV_SOCKET_BUFFERED";
         case PDS_RCV_SOCKET_DELIVERED: return "RCV_SOCKET_DELIVERED";
         case PDS_DESTROYED: return "PDS_DESTROYED";
+        case PDS_INET_CORRUPTED: return "INET_CORRUPTED";
+        case PDS_SND_INTERFACE_DROPPED: return "SND_INTERFACE_DROPPED";
+        case PDS_ROUTER_MARKED: return "ROUTER_MARKED";
         default: return "UKNOWN";
     }
 }
@@ -676,6 +889,12 @@ gchar* packet_toString(Packet* packet) {
                 if(header->flags & PTCP_DUPACK) {
                     g_string_append_printf(packetString, "DUPACK");
                 }
+                if(header->flags & PTCP_ECE) {
+                    g_string_append_printf(packetString, "ECE");
+                }
+                if(header->flags & PTCP_CWR) {
+                    g_string_append_printf(packetString, "CWR");
+                }
             }
 
             g_string_append_printf(packetString, " tsval=%"G_GUINT64_FORMAT" tsechoreply=%"G_GUINT64_FORMAT,
@@ -686,6 +905,36 @@ gchar* packet_toString(Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            PacketICMPHeader* header = packet->header;
+            gchar* sourceIPString = address_ipToNewString(header->sourceIP);
+            gchar* destinationIPString = address_ipToNewString(header->destinationIP);
+
+            g_string_append_printf(packetString, "%s -> %s type=%u code=%u ",
+                    sourceIPString, destinationIPString, (guint)header->type, (guint)header->code);
+
+            if (header->type == PICMP_DEST_UNREACH) {
+                gchar* originalSourceIPString = address_ipToNewString(header->originalSourceIP);
+                gchar* originalDestinationIPString =
+                    address_ipToNewString(header->originalDestinationIP);
+
+                g_string_append_printf(packetString, "original=%s %s:%u -> %s:%u",
+                        protocol_toString(header->originalProtocol),
+                        originalSourceIPString, ntohs(header->originalSourcePort),
+                        originalDestinationIPString, ntohs(header->originalDestinationPort));
+
+                g_free(originalSourceIPString);
+                g_free(originalDestinationIPString);
+            } else {
+                g_string_append_printf(packetString, "id=%u seq=%u bytes=%u",
+                        ntohs(header->identifier), ntohs(header->sequence), payloadLength);
+            }
+
+            g_free(sourceIPString);
+            g_free(destinationIPString);
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -39,6 +39,29 @@ struct _PacketTCPHeader {
     CSimulationTime timestampEcho;
 };
 
+typedef struct _PacketICMPHeader PacketICMPHeader;
+struct _PacketICMPHeader {
+    enum ProtocolICMPType type;
+    enum ProtocolICMPCode code;
+
+    // address is in network byte order
+    in_addr_t sourceIP;
+    // address is in network byte order
+    in_addr_t destinationIP;
+
+    // echo messages only; both are in network byte order
+    guint16 identifier;
+    guint16 sequence;
+
+    // error messages only; the headers of the packet that caused the error, which are quoted in
+    // the error message (addresses and ports are in network byte order)
+    ProtocolType originalProtocol;
+    in_addr_t originalSourceIP;
+    in_port_t originalSourcePort;
+    in_addr_t originalDestinationIP;
+    in_port_t originalDestinationPort;
+};
+
 const gchar* protocol_toString(ProtocolType type);
 
 Packet* packet_new(const Host* host);
@@ -58,6 +81,16 @@ static inline void packet_unrefTaskFreeFunc(gpointer packet) { packet_unref(pack
 void packet_setPriority(Packet *packet, double value);
 gdouble packet_getPriority(const Packet* packet);
 
+// Packets with the "don't fragment" (DF) flag are dropped in the network if they're larger than
+// the path MTU.
+void packet_setDontFragment(Packet* packet, gboolean dontFragment);
+gboolean packet_getDontFragment(const Packet* packet);
+
+// The ECN codepoint of the IP header. Routers mark ECN-capable packets with `PECN_CE` instead of
+// dropping them.
+void packet_setECN(Packet* packet, enum ProtocolECN ecn);
+enum ProtocolECN packet_getECN(const Packet* packet);
+
 // The port must be in network byte order.
 void packet_setLocal(Packet* packet, enum ProtocolLocalFlags flags,
         gint sourceDescriptorHandle, gint destinationDescriptorHandle, in_port_t port);
@@ -72,9 +105,26 @@ void packet_setTCP(Packet* packet, enum ProtocolTCPFlags flags,
         in_addr_t sourceIP, in_port_t sourcePort,
         in_addr_t destinationIP, in_port_t destinationPort, guint sequence);
 
+// An echo request or reply. The addresses, identifier, and sequence must be in network byte order.
+void packet_setICMPEcho(Packet* packet, enum ProtocolICMPType type, in_addr_t sourceIP,
+                        in_addr_t destinationIP, guint16 identifier, guint16 sequence);
+
+// A reply to the echo request `request`, which carries the same payload as the request.
+void packet_setICMPEchoReply(Packet* packet, const Packet* request);
+
+// An error caused by the packet `original`, which is sent back to the original packet's source.
+// The address must be in network byte order.
+void packet_setICMPError(Packet* packet, enum ProtocolICMPType type, enum ProtocolICMPCode code,
+                         in_addr_t sourceIP, const Packet* original);
+
 void packet_updateTCP(Packet* packet, guint acknowledgement, GList* selectiveACKs, guint window,
                       CSimulationTime timestampValue, CSimulationTime timestampEcho);
 
+// Rewrite the addresses of a UDP, TCP, or ICMP echo packet, as a NAT does. The port of an ICMP echo
+// packet is its identifier. The address and port must be in network byte order.
+void packet_setSource(Packet* packet, in_addr_t ip, in_port_t port);
+void packet_setDestination(Packet* packet, in_addr_t ip, in_port_t port);
+
 gsize packet_getTotalSize(const Packet* packet);
 gsize packet_getPayloadSize(const Packet* packet);
 gsize packet_getHeaderSize(const Packet* packet);
@@ -97,6 +147,7 @@ guint packet_copyPayloadShadow(const Packet* packet, gsize payloadOffset, void*
                                gsize bufferLength);
 GList* packet_copyTCPSelectiveACKs(Packet* packet);
 PacketTCPHeader* packet_getTCPHeader(const Packet* packet);
+PacketICMPHeader* packet_getICMPHeader(const Packet* packet);
 gint packet_compareTCPSequence(Packet* packet1, Packet* packet2, gpointer user_data);
 
 void packet_addDeliveryStatus(Packet* packet, PacketDeliveryStatusFlags status);
@@ -32,6 +32,9 @@ enum _PacketDeliveryStatusFlags {
     PDS_RCV_SOCKET_BUFFERED = 1 << 18,
     PDS_RCV_SOCKET_DELIVERED = 1 << 19,
     PDS_DESTROYED = 1 << 20,
+    PDS_INET_CORRUPTED = 1 << 21,
+    PDS_SND_INTERFACE_DROPPED = 1 << 22,
+    PDS_ROUTER_MARKED = 1 << 23,
 };
 
 typedef struct _PacketTCPHeader PacketTCPHeader;
@@ -0,0 +1,83 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests that a host's bandwidth schedule, loaded from a CSV trace file, changes the rate at which
+//! the host receives data. Must be run with the hosts in bandwidth-schedule.yaml.
+
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+const SERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 5000);
+
+/// The payload size of each datagram, which makes each packet 1500 bytes with its UDP and IP
+/// headers.
+const PAYLOAD_SIZE: usize = 1472;
+const PACKET_SIZE: usize = 1500;
+
+/// How long the client sends for, which is until after the server stops measuring.
+const SEND_DURATION: Duration = Duration::from_secs(9);
+
+/// The server's downstream bandwidth before and after the change in bandwidth-trace.csv, in the
+/// whole KiB/s that its token buckets are refilled at.
+const BYTES_PER_SEC_BEFORE: u64 = 10_000_000 / (8 * 1024) * 1024;
+const BYTES_PER_SEC_AFTER: u64 = 2_000_000 / (8 * 1024) * 1024;
+
+/// The times since the server started (1 s into the simulation) over which it measures its receive
+/// rate, before and after the change at 6 s into the simulation. The client starts sending at 2 s.
+const WINDOW_BEFORE: (Duration, Duration) = (Duration::from_secs(2), Duration::from_secs(4));
+const WINDOW_AFTER: (Duration, Duration) = (Duration::from_secs(6), Duration::from_secs(8));
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(),
+        _ => panic!("Usage: {} client|server", args[0]),
+    }
+}
+
+/// Send datagrams to the server as fast as the client's uplink allows, which is much faster than
+/// the server's downlink.
+fn client() {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    socket.connect(SERVER).unwrap();
+
+    let start = Instant::now();
+    while start.elapsed() < SEND_DURATION {
+        assert_eq!(socket.send(&[0; PAYLOAD_SIZE]).unwrap(), PAYLOAD_SIZE);
+    }
+}
+
+fn server() {
+    let start = Instant::now();
+    let socket = UdpSocket::bind(SERVER).unwrap();
+
+    // the number of bytes received in each window, counting the datagrams' headers
+    let (mut bytes_before, mut bytes_after) = (0, 0);
+    let mut buf = [0; PAYLOAD_SIZE];
+    while start.elapsed() < WINDOW_AFTER.1 {
+        assert_eq!(socket.recv(&mut buf).unwrap(), PAYLOAD_SIZE);
+
+        let elapsed = start.elapsed();
+        if elapsed >= WINDOW_BEFORE.0 && elapsed < WINDOW_BEFORE.1 {
+            bytes_before += PACKET_SIZE as u64;
+        } else if elapsed >= WINDOW_AFTER.0 && elapsed < WINDOW_AFTER.1 {
+            bytes_after += PACKET_SIZE as u64;
+        }
+    }
+
+    assert_rate(bytes_before, WINDOW_BEFORE, BYTES_PER_SEC_BEFORE);
+    assert_rate(bytes_after, WINDOW_AFTER, BYTES_PER_SEC_AFTER);
+}
+
+/// Check that `bytes` received over `window` is within 5% of `bytes_per_sec`.
+fn assert_rate(bytes: u64, window: (Duration, Duration), bytes_per_sec: u64) {
+    let rate = bytes as f64 / (window.1 - window.0).as_secs_f64();
+    let expected = bytes_per_sec as f64;
+    assert!(
+        (rate - expected).abs() < 0.05 * expected,
+        "{rate} bytes/s, expected {expected} bytes/s"
+    );
+}
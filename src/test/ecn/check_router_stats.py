@@ -0,0 +1,24 @@
+#!/usr/bin/env python3
+
+'''
+Checks the final router statistics in a sim-stats.json file: the router of the given host and
+address must have marked some packets, and must not have dropped any.
+
+Usage: check_router_stats.py <sim-stats.json> <hostname> <address>
+'''
+
+import json
+import sys
+
+def main():
+    (path, hostname, address) = sys.argv[1:]
+
+    with open(path) as f:
+        stats = json.load(f)['routers'][hostname][address]
+
+    assert stats['marked_packets'] > 0, stats
+    assert stats['dropped_packets'] == 0, stats
+    assert stats['overlimit_packets'] == 0, stats
+
+if __name__ == '__main__':
+    main()
@@ -0,0 +1,83 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests that TCP connections negotiate explicit congestion notification (ECN) during the
+//! handshake when `experimental.use_tcp_ecn` is enabled. Must be run with the hosts in
+//! ecn-enabled.yaml or ecn-disabled.yaml.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+
+const SERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 5000);
+
+/// The offset of `tcpi_options` in `struct tcp_info`.
+const TCPI_OPTIONS_OFFSET: usize = 5;
+/// The `tcpi_options` flag of a connection that uses ECN.
+const TCPI_OPT_ECN: u8 = 8;
+
+/// The number of bytes sent on the connection, which are all new data that may be marked.
+const TRANSFER_BYTES: usize = 100_000;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let expect_ecn = match args.get(2).map(String::as_str) {
+        Some("enabled") => true,
+        Some("disabled") => false,
+        _ => panic!("Usage: {} client|server enabled|disabled", args[0]),
+    };
+
+    match args[1].as_str() {
+        "client" => client(expect_ecn),
+        "server" => server(expect_ecn),
+        _ => panic!("Usage: {} client|server enabled|disabled", args[0]),
+    }
+}
+
+fn server(expect_ecn: bool) {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, SERVER.port())).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    // the server always agrees to use ECN when the client asks for it
+    assert_eq!(uses_ecn(&stream), expect_ecn);
+
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), TRANSFER_BYTES);
+    stream.write_all(b"done").unwrap();
+}
+
+fn client(expect_ecn: bool) {
+    let mut stream = TcpStream::connect(SERVER).unwrap();
+    assert_eq!(uses_ecn(&stream), expect_ecn);
+
+    // data still flows normally on a connection that uses ECN
+    stream.write_all(&vec![0; TRANSFER_BYTES]).unwrap();
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert_eq!(reply, "done");
+
+    assert_eq!(uses_ecn(&stream), expect_ecn);
+}
+
+/// Whether the connection uses ECN, from the `tcpi_options` of its `TCP_INFO`.
+fn uses_ecn(stream: &TcpStream) -> bool {
+    // only the start of `struct tcp_info` is needed, and the kernel copies as much as fits
+    let mut info = [0u8; 8];
+    let mut len = info.len() as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            info.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+    assert_eq!(len as usize, info.len());
+    info[TCPI_OPTIONS_OFFSET] & TCPI_OPT_ECN != 0
+}
@@ -0,0 +1,101 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests that a bulk TCP transfer on a connection that uses explicit congestion notification
+//! (ECN) backs off when the router queue of a slow downlink marks its packets, without any packets
+//! being lost. Must be run with the hosts in ecn-aqm.yaml, whose router statistics are checked by
+//! check_router_stats.py.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+
+const SERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 5000);
+
+/// The offsets of fields in `struct tcp_info`.
+const TCPI_OPTIONS_OFFSET: usize = 5;
+const TCPI_SND_CWND_OFFSET: usize = 80;
+const TCPI_TOTAL_RETRANS_OFFSET: usize = 100;
+/// The `tcpi_options` flag of a connection that uses ECN.
+const TCPI_OPT_ECN: u8 = 8;
+
+/// The number of bytes sent on the connection, which takes about 4 seconds on the server's 10
+/// Mbit/s downlink.
+const TRANSFER_BYTES: usize = 5_000_000;
+/// The number of bytes written between samples of the congestion window.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(),
+        _ => panic!("Usage: {} client|server", args[0]),
+    }
+}
+
+fn server() {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, SERVER.port())).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    assert!(tcp_info(&stream)[TCPI_OPTIONS_OFFSET] & TCPI_OPT_ECN != 0);
+
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), TRANSFER_BYTES);
+    stream.write_all(b"done").unwrap();
+}
+
+fn client() {
+    let mut stream = TcpStream::connect(SERVER).unwrap();
+    assert!(tcp_info(&stream)[TCPI_OPTIONS_OFFSET] & TCPI_OPT_ECN != 0);
+
+    // the congestion window only shrinks when the sender hears about a marked packet, since no
+    // packets are lost
+    let mut max_cwnd = 0;
+    let mut has_reduced_cwnd = false;
+    let chunk = vec![0; CHUNK_BYTES];
+    let mut remaining = TRANSFER_BYTES;
+    while remaining > 0 {
+        let len = std::cmp::min(remaining, chunk.len());
+        stream.write_all(&chunk[..len]).unwrap();
+        remaining -= len;
+
+        let cwnd = tcp_info_u32(&stream, TCPI_SND_CWND_OFFSET);
+        has_reduced_cwnd |= cwnd < max_cwnd;
+        max_cwnd = std::cmp::max(max_cwnd, cwnd);
+    }
+
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert_eq!(reply, "done");
+
+    assert!(has_reduced_cwnd, "{max_cwnd}");
+    assert_eq!(tcp_info_u32(&stream, TCPI_TOTAL_RETRANS_OFFSET), 0);
+}
+
+/// The connection's `struct tcp_info`, as bytes.
+fn tcp_info(stream: &TcpStream) -> [u8; 104] {
+    let mut info = [0u8; 104];
+    let mut len = info.len() as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            info.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+    assert_eq!(len as usize, info.len());
+    info
+}
+
+/// The `u32` field of the connection's `struct tcp_info` at `offset`.
+fn tcp_info_u32(stream: &TcpStream, offset: usize) -> u32 {
+    let info = tcp_info(stream);
+    u32::from_ne_bytes(info[offset..][..4].try_into().unwrap())
+}
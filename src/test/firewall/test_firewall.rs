@@ -0,0 +1,95 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests for host firewall rules that drop or reject packets. Must be run with the hosts and
+//! firewall rules in firewall.yaml.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream, UdpSocket};
+use std::time::Duration;
+
+const SERVER_IP: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 2);
+
+/// The server's firewall allows TCP port 80, rejects TCP port 81, and drops other TCP ports.
+const TCP_PORTS: [u16; 4] = [80, 81, 82, 83];
+/// The server's firewall rejects UDP port 91, and drops other UDP ports.
+const UDP_PORTS: [u16; 3] = [91, 92, 93];
+
+/// How long to wait for packets that should be dropped.
+const DROP_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(),
+        _ => panic!("Usage: {} client | server", args[0]),
+    }
+}
+
+/// Listen on all of the ports, and echo the data of a single connection to port 80.
+fn server() {
+    let listeners: Vec<_> = TCP_PORTS
+        .iter()
+        .map(|port| TcpListener::bind((Ipv4Addr::UNSPECIFIED, *port)).unwrap())
+        .collect();
+    let _udp: Vec<_> = UDP_PORTS
+        .iter()
+        .map(|port| UdpSocket::bind((Ipv4Addr::UNSPECIFIED, *port)).unwrap())
+        .collect();
+
+    let (mut stream, _) = listeners[0].accept().unwrap();
+    std::io::copy(&mut stream.try_clone().unwrap(), &mut stream).unwrap();
+    drop(stream);
+
+    // keep the sockets open while the client tests the other ports
+    std::thread::sleep(Duration::from_secs(20));
+}
+
+fn client() {
+    // allowed
+    let mut stream = TcpStream::connect(SocketAddrV4::new(SERVER_IP, 80)).unwrap();
+    stream.write_all(b"hello").unwrap();
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert_eq!(reply, "hello");
+
+    // rejected by the server with a reset
+    let err = TcpStream::connect(SocketAddrV4::new(SERVER_IP, 81)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+
+    // dropped by the server, so the connection never completes
+    let err = TcpStream::connect_timeout(&SocketAddrV4::new(SERVER_IP, 82).into(), DROP_TIMEOUT)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+    // rejected by the client's own firewall before it leaves the host
+    let err = TcpStream::connect(SocketAddrV4::new(SERVER_IP, 83)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+
+    // rejected by the server with an ICMP port unreachable error
+    let socket = udp_send(91);
+    let err = socket.recv(&mut [0; 16]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+
+    // dropped by the server or by the client's own firewall, so no error is reported
+    for port in [92, 93] {
+        let socket = udp_send(port);
+        std::thread::sleep(DROP_TIMEOUT);
+        socket.set_nonblocking(true).unwrap();
+        let err = socket.recv(&mut [0; 16]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock, "port {port}");
+        assert!(socket.take_error().unwrap().is_none(), "port {port}");
+    }
+}
+
+/// Send a datagram from a socket that's connected to the server's UDP `port`.
+fn udp_send(port: u16) -> UdpSocket {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    socket.connect(SocketAddrV4::new(SERVER_IP, port)).unwrap();
+    socket.send(b"hello").unwrap();
+    socket
+}
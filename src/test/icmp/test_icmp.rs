@@ -0,0 +1,201 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests for ICMP echo through ping sockets and for ICMP host unreachable errors. Must be run with
+//! the hosts and addresses in icmp.yaml.
+
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+/// A host that answers echo requests.
+const PEER_IP: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 2);
+/// An address that no host has.
+const UNKNOWN_IP: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 99);
+
+const ICMP_HEADER_SIZE: usize = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+fn main() {
+    test_ping();
+    test_ping_host_unreachable();
+    test_udp_host_unreachable();
+}
+
+/// Echo requests are answered with replies that carry the same sequence number and payload. Like
+/// the kernel, shadow replaces the identifier with the socket's port.
+fn test_ping() {
+    let fd = ping_socket();
+    let addr = sockaddr(SocketAddrV4::new(PEER_IP, 0));
+
+    let request = echo_request(1234, 7, b"hello");
+    let rv = unsafe {
+        libc::sendto(
+            fd.as_raw_fd(),
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+            &addr as *const _ as *const libc::sockaddr,
+            std::mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    };
+    assert_eq!(
+        rv,
+        request.len() as isize,
+        "{}",
+        std::io::Error::last_os_error()
+    );
+
+    let mut reply = [0u8; 64];
+    let mut from: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    let mut from_len = std::mem::size_of_val(&from) as libc::socklen_t;
+    let rv = unsafe {
+        libc::recvfrom(
+            fd.as_raw_fd(),
+            reply.as_mut_ptr() as *mut libc::c_void,
+            reply.len(),
+            0,
+            &mut from as *mut _ as *mut libc::sockaddr,
+            &mut from_len,
+        )
+    };
+    assert_eq!(
+        rv,
+        request.len() as isize,
+        "{}",
+        std::io::Error::last_os_error()
+    );
+    let reply = &reply[..rv as usize];
+
+    // the reply comes from the peer, and ping sockets don't report a port
+    assert_eq!(Ipv4Addr::from(u32::from_be(from.sin_addr.s_addr)), PEER_IP);
+    assert_eq!(from.sin_port, 0);
+
+    let local_port = local_port(&fd);
+    assert_eq!(reply[0], ICMP_ECHO_REPLY);
+    assert_eq!(reply[1], 0);
+    assert_eq!(u16::from_be_bytes([reply[4], reply[5]]), local_port);
+    assert_eq!(u16::from_be_bytes([reply[6], reply[7]]), 7);
+    assert_eq!(&reply[ICMP_HEADER_SIZE..], b"hello");
+}
+
+/// A connected ping socket reports the host unreachable error for an address that no host has.
+fn test_ping_host_unreachable() {
+    let fd = ping_socket();
+    let addr = sockaddr(SocketAddrV4::new(UNKNOWN_IP, 0));
+    let rv = unsafe {
+        libc::connect(
+            fd.as_raw_fd(),
+            &addr as *const _ as *const libc::sockaddr,
+            std::mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+
+    let request = echo_request(0, 1, b"hello");
+    let rv = unsafe {
+        libc::send(
+            fd.as_raw_fd(),
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+        )
+    };
+    assert_eq!(
+        rv,
+        request.len() as isize,
+        "{}",
+        std::io::Error::last_os_error()
+    );
+
+    let mut reply = [0u8; 64];
+    let rv = unsafe {
+        libc::recv(
+            fd.as_raw_fd(),
+            reply.as_mut_ptr() as *mut libc::c_void,
+            reply.len(),
+            0,
+        )
+    };
+    assert_eq!(rv, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().raw_os_error(),
+        Some(libc::EHOSTUNREACH)
+    );
+}
+
+/// Connecting a UDP socket to an address that no host has succeeds, but the host unreachable
+/// error is reported once a datagram has been sent. The error is reported once, either by
+/// `SO_ERROR` or by the next socket operation.
+fn test_udp_host_unreachable() {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    socket.connect((UNKNOWN_IP, 5000)).unwrap();
+    assert_eq!(socket.take_error().unwrap().map(|e| e.raw_os_error()), None);
+
+    socket.send(b"hello").unwrap();
+
+    // wait for the error to arrive
+    let mut buf = [0u8; 16];
+    let err = socket.recv(&mut buf).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EHOSTUNREACH));
+
+    // the error was cleared when it was reported
+    socket.set_nonblocking(true).unwrap();
+    let err = socket.recv(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+    // a second error is reported by SO_ERROR
+    socket.set_nonblocking(false).unwrap();
+    socket.send(b"hello").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(
+        socket.take_error().unwrap().and_then(|e| e.raw_os_error()),
+        Some(libc::EHOSTUNREACH)
+    );
+    assert_eq!(socket.take_error().unwrap().map(|e| e.raw_os_error()), None);
+}
+
+/// Create an unprivileged "ping" socket.
+fn ping_socket() -> OwnedFd {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, libc::IPPROTO_ICMP) };
+    assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+    unsafe { OwnedFd::from_raw_fd(fd) }
+}
+
+/// An echo request with an ICMP header and `payload`. The checksum is left empty since the kernel
+/// computes it for ping sockets.
+fn echo_request(identifier: u16, sequence: u16, payload: &[u8]) -> Vec<u8> {
+    let mut request = vec![ICMP_ECHO_REQUEST, 0, 0, 0];
+    request.extend(identifier.to_be_bytes());
+    request.extend(sequence.to_be_bytes());
+    request.extend(payload);
+    request
+}
+
+fn sockaddr(addr: SocketAddrV4) -> libc::sockaddr_in {
+    libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: addr.port().to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: u32::from(*addr.ip()).to_be(),
+        },
+        sin_zero: [0; 8],
+    }
+}
+
+/// The port that the socket is bound to, which is the identifier of its echo requests.
+fn local_port(fd: &OwnedFd) -> u16 {
+    let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of_val(&addr) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockname(
+            fd.as_raw_fd(),
+            &mut addr as *mut _ as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+    u16::from_be(addr.sin_port)
+}
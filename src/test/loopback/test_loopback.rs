@@ -0,0 +1,116 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests that the `loopback_latency` and `loopback_bandwidth` host options delay and limit
+//! packets on the loopback interface, and that packets a host sends to its own internet address
+//! aren't affected. Must be run with the hosts in loopback.yaml.
+
+use std::net::{Ipv4Addr, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// The number of datagrams in each burst.
+const NUM_DATAGRAMS: usize = 100;
+/// The payload size of each datagram, which makes each packet 1000 bytes with its UDP and IP
+/// headers.
+const PAYLOAD_SIZE: usize = 972;
+const PACKET_SIZE: u64 = 1000;
+
+/// The configured loopback latency of the "limited" host.
+const LOOPBACK_LATENCY: Duration = Duration::from_millis(10);
+/// The configured loopback bandwidth of 1 Mbit of the "limited" host, which its token buckets
+/// are refilled at in whole KiB/s.
+const LOOPBACK_BYTES_PER_SEC: u64 = 1_000_000 / (8 * 1024) * 1024;
+/// The default burst size of the token buckets.
+const BURST_SIZE: u64 = 1500;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let internet_addr: Ipv4Addr = args
+        .get(2)
+        .unwrap_or_else(|| panic!("Usage: {} limited|default <ip>", args[0]))
+        .parse()
+        .unwrap();
+
+    match args[1].as_str() {
+        "limited" => limited(internet_addr),
+        "default" => default(internet_addr),
+        x => panic!("Unexpected host type '{x}'"),
+    }
+}
+
+fn limited(internet_addr: Ipv4Addr) {
+    let latency = latency(Ipv4Addr::LOCALHOST);
+    assert!(latency >= LOOPBACK_LATENCY, "{latency:?}");
+    assert!(
+        latency < LOOPBACK_LATENCY + Duration::from_millis(1),
+        "{latency:?}"
+    );
+
+    // all but the bucket's initial tokens must wait for the loopback bandwidth
+    let spread = burst_spread(Ipv4Addr::LOCALHOST);
+    let min_spread = Duration::from_secs_f64(
+        (NUM_DATAGRAMS as u64 * PACKET_SIZE - BURST_SIZE) as f64 / LOOPBACK_BYTES_PER_SEC as f64,
+    );
+    assert!(spread >= min_spread, "{spread:?}");
+    assert!(
+        spread < min_spread + Duration::from_millis(100),
+        "{spread:?}"
+    );
+
+    // local packets on the internet interface aren't limited by the host's 1 Mbit bandwidth
+    unlimited(internet_addr);
+}
+
+fn default(internet_addr: Ipv4Addr) {
+    unlimited(Ipv4Addr::LOCALHOST);
+    unlimited(internet_addr);
+}
+
+/// Check that packets sent to `addr` arrive almost immediately and without a bandwidth limit.
+fn unlimited(addr: Ipv4Addr) {
+    let latency = latency(addr);
+    assert!(latency < Duration::from_millis(1), "{latency:?}");
+
+    let spread = burst_spread(addr);
+    assert!(spread < Duration::from_millis(10), "{spread:?}");
+}
+
+/// The time for a datagram sent to `addr` to arrive.
+fn latency(addr: Ipv4Addr) -> Duration {
+    let (sender, receiver) = socket_pair(addr);
+
+    let start = Instant::now();
+    sender.send(&[0; PAYLOAD_SIZE]).unwrap();
+    let mut buf = [0; PAYLOAD_SIZE];
+    assert_eq!(receiver.recv(&mut buf).unwrap(), PAYLOAD_SIZE);
+    start.elapsed()
+}
+
+/// Send a burst of datagrams to `addr` as fast as possible, and return the time between the
+/// arrivals of the first and last datagrams. The datagrams fit in the sender's send buffer and
+/// the receiver's receive buffer.
+fn burst_spread(addr: Ipv4Addr) -> Duration {
+    let (sender, receiver) = socket_pair(addr);
+
+    for _ in 0..NUM_DATAGRAMS {
+        assert_eq!(sender.send(&[0; PAYLOAD_SIZE]).unwrap(), PAYLOAD_SIZE);
+    }
+
+    let mut buf = [0; PAYLOAD_SIZE];
+    assert_eq!(receiver.recv(&mut buf).unwrap(), PAYLOAD_SIZE);
+    let first = Instant::now();
+    for _ in 1..NUM_DATAGRAMS {
+        assert_eq!(receiver.recv(&mut buf).unwrap(), PAYLOAD_SIZE);
+    }
+    first.elapsed()
+}
+
+/// A receiving socket bound to `addr`, and a sending socket connected to it.
+fn socket_pair(addr: Ipv4Addr) -> (UdpSocket, UdpSocket) {
+    let receiver = UdpSocket::bind((addr, 0)).unwrap();
+    let sender = UdpSocket::bind((addr, 0)).unwrap();
+    sender.connect(receiver.local_addr().unwrap()).unwrap();
+    (sender, receiver)
+}
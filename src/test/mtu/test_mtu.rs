@@ -0,0 +1,106 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests for the path MTU of a network graph edge with an `mtu`. Must be run with the hosts and
+//! graph in mtu.yaml.
+
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::os::unix::io::AsRawFd;
+
+const PORT: u16 = 5000;
+
+/// The server, which is reached through an edge with an MTU of 1280 bytes.
+const SERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), PORT);
+const PATH_MTU: libc::c_int = 1280;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(args[2].parse().unwrap()),
+        _ => panic!("Usage: {} client | server <datagrams>", args[0]),
+    }
+}
+
+/// Reply to each datagram with its length.
+fn server(num_datagrams: usize) {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT)).unwrap();
+
+    for _ in 0..num_datagrams {
+        let mut buf = [0; 8192];
+        let (len, peer) = socket.recv_from(&mut buf).unwrap();
+        socket.send_to(len.to_string().as_bytes(), peer).unwrap();
+    }
+}
+
+fn client() {
+    // an unconnected socket has no path MTU
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    assert_eq!(get_ip_option(&socket, libc::IP_MTU), Err(libc::ENOTCONN));
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    socket.connect(SERVER).unwrap();
+    assert_eq!(get_ip_option(&socket, libc::IP_MTU), Ok(PATH_MTU));
+    assert_eq!(
+        get_ip_option(&socket, libc::IP_MTU_DISCOVER),
+        Ok(libc::IP_PMTUDISC_WANT)
+    );
+
+    // datagrams that can't be fragmented must fit within the path MTU
+    set_ip_option(&socket, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_DO);
+    let err = socket.send(&[0; 1300]).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EMSGSIZE));
+    assert_eq!(send_and_reply(&socket, 1000), "1000");
+
+    // other datagrams are fragmented and reassembled at the server
+    set_ip_option(&socket, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_WANT);
+    assert_eq!(send_and_reply(&socket, 4000), "4000");
+
+    // when probing, datagrams only need to fit within the interface MTU, and those that don't
+    // fit within the path MTU are dropped in the network
+    set_ip_option(&socket, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_PROBE);
+    assert_eq!(socket.send(&[0; 1300]).unwrap(), 1300);
+    assert_eq!(send_and_reply(&socket, 10), "10");
+}
+
+/// Send a datagram of `len` bytes on the connected `socket`, and return the server's reply.
+fn send_and_reply(socket: &UdpSocket, len: usize) -> String {
+    assert_eq!(socket.send(&vec![0; len]).unwrap(), len);
+    let mut buf = [0; 16];
+    let len = socket.recv(&mut buf).unwrap();
+    String::from_utf8(buf[..len].to_vec()).unwrap()
+}
+
+fn get_ip_option(socket: &UdpSocket, option: libc::c_int) -> Result<libc::c_int, libc::c_int> {
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of_val(&value) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            option,
+            &mut value as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if rv != 0 {
+        return Err(std::io::Error::last_os_error().raw_os_error().unwrap());
+    }
+    assert_eq!(len as usize, std::mem::size_of_val(&value));
+    Ok(value)
+}
+
+fn set_ip_option(socket: &UdpSocket, option: libc::c_int, value: libc::c_int) {
+    let rv = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            option,
+            &value as *const _ as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+}
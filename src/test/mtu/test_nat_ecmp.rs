@@ -0,0 +1,140 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests that the path MTU of a socket behind a NAT is the MTU of the equal-cost path that its
+//! packets take, which is chosen using the source address and port given by the NAT. Must be run
+//! with the hosts and graph in nat-ecmp.yaml.
+
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+const PORT: u16 = 5000;
+
+/// The server, which is reached through two equal-cost paths with MTUs of 1280 and 1400 bytes.
+const SERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), PORT);
+const PATH_MTUS: [libc::c_int; 2] = [1280, 1400];
+
+/// The number of client sockets, each of which is a separate flow.
+const NUM_SOCKETS: usize = 16;
+/// The size of the UDP and IP headers.
+const HEADER_SIZE: usize = 28;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(),
+        _ => panic!("Usage: {} client | server", args[0]),
+    }
+}
+
+/// Reply to each datagram with its length, until an empty datagram is received.
+fn server() {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT)).unwrap();
+
+    loop {
+        let mut buf = [0; 8192];
+        let (len, peer) = socket.recv_from(&mut buf).unwrap();
+        if len == 0 {
+            break;
+        }
+        socket.send_to(len.to_string().as_bytes(), peer).unwrap();
+    }
+}
+
+fn client() {
+    let sockets: Vec<UdpSocket> = (0..NUM_SOCKETS)
+        .map(|_| {
+            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+            socket.connect(SERVER).unwrap();
+            socket
+        })
+        .collect();
+
+    // the path MTU is known before the NAT has a mapping for the flow
+    let mtus: Vec<libc::c_int> = sockets
+        .iter()
+        .map(|x| get_ip_option(x, libc::IP_MTU))
+        .collect();
+    for mtu in &mtus {
+        assert!(PATH_MTUS.contains(mtu), "{mtu}");
+    }
+    // the flows are spread over both paths
+    for mtu in &PATH_MTUS {
+        assert!(mtus.contains(mtu), "{mtus:?}");
+    }
+
+    for (socket, mtu) in sockets.iter().zip(&mtus) {
+        // packets that fit within the larger MTU only arrive if the flow takes the path with the
+        // larger MTU
+        set_ip_option(socket, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_PROBE);
+        let len = PATH_MTUS[1] as usize - HEADER_SIZE;
+        assert_eq!(socket.send(&vec![0; len]).unwrap(), len);
+        let reply = recv_reply(socket);
+        if *mtu == PATH_MTUS[1] {
+            assert_eq!(reply, Some(len.to_string()));
+        } else {
+            assert_eq!(reply, None);
+        }
+
+        // packets that fit within the path MTU always arrive
+        let len = *mtu as usize - HEADER_SIZE;
+        assert_eq!(socket.send(&vec![0; len]).unwrap(), len);
+        assert_eq!(recv_reply(socket), Some(len.to_string()));
+
+        // the path MTU doesn't change once the NAT has a mapping for the flow
+        assert_eq!(get_ip_option(socket, libc::IP_MTU), *mtu);
+    }
+
+    sockets[0].send(&[]).unwrap();
+}
+
+/// Wait for the server's reply on the connected `socket`, if there is one.
+fn recv_reply(socket: &UdpSocket) -> Option<String> {
+    // the round trip time is 20 ms
+    std::thread::sleep(Duration::from_millis(100));
+
+    socket.set_nonblocking(true).unwrap();
+    let mut buf = [0; 16];
+    let rv = socket.recv(&mut buf);
+    socket.set_nonblocking(false).unwrap();
+
+    match rv {
+        Ok(len) => Some(String::from_utf8(buf[..len].to_vec()).unwrap()),
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => None,
+        Err(e) => panic!("{e}"),
+    }
+}
+
+fn get_ip_option(socket: &UdpSocket, option: libc::c_int) -> libc::c_int {
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of_val(&value) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            option,
+            &mut value as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+    assert_eq!(len as usize, std::mem::size_of_val(&value));
+    value
+}
+
+fn set_ip_option(socket: &UdpSocket, option: libc::c_int, value: libc::c_int) {
+    let rv = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            option,
+            &value as *const _ as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+}
@@ -0,0 +1,124 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests for a multihomed host, which has a second interface on a different network node. Must
+//! be run with the hosts and addresses in multihome.yaml.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream, UdpSocket};
+use std::os::unix::io::FromRawFd;
+use std::time::{Duration, Instant};
+
+use nix::sys::socket::{self, AddressFamily, SockFlag, SockType, SockaddrIn};
+
+const PORT: u16 = 5000;
+
+/// The address of the multihomed host's default interface, on network node 0.
+const DEFAULT_IP: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 1);
+/// The address of the multihomed host's second interface, on network node 1. Its upload
+/// bandwidth is 1 Mbit.
+const SECOND_IP: Ipv4Addr = Ipv4Addr::new(11, 0, 1, 1);
+/// A server on network node 0.
+const SERVER_0: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), PORT);
+/// A server on network node 1.
+const SERVER_1: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 1, 2), PORT);
+
+/// The number of bytes sent to measure the bandwidth of an interface.
+const TRANSFER_BYTES: usize = 250_000;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(args[2].parse().unwrap(), args[3].parse().unwrap()),
+        _ => panic!(
+            "Usage: {} client | server <datagrams> <connections>",
+            args[0]
+        ),
+    }
+}
+
+/// Reply to each datagram and to each connection with the source address of the peer.
+fn server(num_datagrams: usize, num_connections: usize) {
+    let udp = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT)).unwrap();
+    let tcp = TcpListener::bind((Ipv4Addr::UNSPECIFIED, PORT)).unwrap();
+
+    for _ in 0..num_datagrams {
+        let mut buf = [0; 16];
+        let (_, peer) = udp.recv_from(&mut buf).unwrap();
+        udp.send_to(peer.ip().to_string().as_bytes(), peer).unwrap();
+    }
+
+    for _ in 0..num_connections {
+        let (mut stream, peer) = tcp.accept().unwrap();
+        // read until the client shuts down its side of the connection
+        std::io::copy(&mut stream, &mut std::io::sink()).unwrap();
+        stream.write_all(peer.ip().to_string().as_bytes()).unwrap();
+    }
+}
+
+fn client() {
+    // an unbound socket sends from the interface with the lowest latency to the destination
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    assert_eq!(udp_source(&socket, SERVER_0), DEFAULT_IP);
+    assert_eq!(udp_source(&socket, SERVER_1), SECOND_IP);
+
+    // a socket bound to the second interface always sends from it
+    let socket = UdpSocket::bind((SECOND_IP, 0)).unwrap();
+    assert_eq!(udp_source(&socket, SERVER_0), SECOND_IP);
+
+    // a connected socket is bound to the interface it reaches the peer through
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    socket.connect(SERVER_1).unwrap();
+    assert_eq!(socket.local_addr().unwrap().ip(), SECOND_IP);
+    assert_eq!(udp_source(&socket, SERVER_1), SECOND_IP);
+
+    // each interface is limited by its own bandwidth: the default interface's 100 Mbit and the
+    // second interface's 1 Mbit
+    let (source, elapsed) = tcp_transfer(None, SERVER_0);
+    assert_eq!(source, DEFAULT_IP);
+    assert!(elapsed < Duration::from_millis(500), "{elapsed:?}");
+
+    let (source, elapsed) = tcp_transfer(Some(SECOND_IP), SERVER_1);
+    assert_eq!(source, SECOND_IP);
+    let min_elapsed = Duration::from_secs_f64((TRANSFER_BYTES * 8) as f64 / 1_000_000.0);
+    assert!(elapsed >= min_elapsed, "{elapsed:?}");
+}
+
+/// Send a datagram to `server`, and return the source address that the server received it from.
+fn udp_source(socket: &UdpSocket, server: SocketAddrV4) -> Ipv4Addr {
+    socket.send_to(b"hello", server).unwrap();
+    let mut buf = [0; 16];
+    let (len, _) = socket.recv_from(&mut buf).unwrap();
+    std::str::from_utf8(&buf[..len]).unwrap().parse().unwrap()
+}
+
+/// Send [`TRANSFER_BYTES`] to `server` over a TCP connection from a socket that is optionally
+/// bound to `bind_ip`. Returns the source address that the server saw and the time until the
+/// server received all of the bytes.
+fn tcp_transfer(bind_ip: Option<Ipv4Addr>, server: SocketAddrV4) -> (Ipv4Addr, Duration) {
+    let fd = socket::socket(
+        AddressFamily::Inet,
+        SockType::Stream,
+        SockFlag::empty(),
+        None,
+    )
+    .unwrap();
+    if let Some(bind_ip) = bind_ip {
+        socket::bind(fd, &SockaddrIn::from(SocketAddrV4::new(bind_ip, 0))).unwrap();
+    }
+
+    let start = Instant::now();
+    socket::connect(fd, &SockaddrIn::from(server)).unwrap();
+    let mut stream = unsafe { TcpStream::from_raw_fd(fd) };
+
+    stream.write_all(&vec![0; TRANSFER_BYTES]).unwrap();
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+
+    (reply.parse().unwrap(), start.elapsed())
+}
@@ -0,0 +1,101 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests that the network interface doesn't send a socket's packets faster than its
+//! `SO_MAX_PACING_RATE`. Must be run with the hosts in pacing.yaml.
+
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+const SERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 5000);
+
+/// The number of datagrams that the client sends in each burst.
+const NUM_DATAGRAMS: usize = 20;
+/// The payload size of each datagram, which makes each packet 1000 bytes with its UDP and IP
+/// headers.
+const PAYLOAD_SIZE: usize = 972;
+const PACKET_SIZE: u32 = 1000;
+
+/// The pacing rate in bytes per second, which allows one packet every 80 ms.
+const PACING_RATE: u32 = 12_500;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(args[2].parse().unwrap()),
+        _ => panic!("Usage: {} client | server <bursts>", args[0]),
+    }
+}
+
+/// Receive bursts of [`NUM_DATAGRAMS`] datagrams, and reply to each burst with the number of
+/// microseconds between the arrivals of its first and last datagrams.
+fn server(num_bursts: usize) {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, SERVER.port())).unwrap();
+
+    for _ in 0..num_bursts {
+        let mut buf = [0; PAYLOAD_SIZE];
+        let (_, peer) = socket.recv_from(&mut buf).unwrap();
+        let first = Instant::now();
+        for _ in 1..NUM_DATAGRAMS {
+            let (_, from) = socket.recv_from(&mut buf).unwrap();
+            assert_eq!(from, peer);
+        }
+        let elapsed = first.elapsed().as_micros();
+        socket
+            .send_to(elapsed.to_string().as_bytes(), peer)
+            .unwrap();
+    }
+}
+
+fn client() {
+    // each packet after the first waits for the previous one to be sent at the pacing rate
+    let paced = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    set_max_pacing_rate(&paced, PACING_RATE);
+    let spread = send_burst(&paced);
+    let min_spread = Duration::from_secs_f64(
+        ((NUM_DATAGRAMS - 1) as u32 * PACKET_SIZE) as f64 / PACING_RATE as f64,
+    );
+    assert!(spread >= min_spread, "{spread:?}");
+    assert!(
+        spread < min_spread + Duration::from_millis(100),
+        "{spread:?}"
+    );
+
+    // without a pacing rate, the burst is only limited by the host's bandwidth
+    let unpaced = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    let spread = send_burst(&unpaced);
+    assert!(spread < Duration::from_millis(10), "{spread:?}");
+}
+
+/// Send a burst of datagrams as fast as possible, and return the time between the arrivals of
+/// the first and last datagrams at the server.
+fn send_burst(socket: &UdpSocket) -> Duration {
+    for _ in 0..NUM_DATAGRAMS {
+        assert_eq!(
+            socket.send_to(&[0; PAYLOAD_SIZE], SERVER).unwrap(),
+            PAYLOAD_SIZE
+        );
+    }
+
+    let mut buf = [0; 32];
+    let len = socket.recv(&mut buf).unwrap();
+    let micros = std::str::from_utf8(&buf[..len]).unwrap().parse().unwrap();
+    Duration::from_micros(micros)
+}
+
+fn set_max_pacing_rate(socket: &UdpSocket, rate: u32) {
+    let rv = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_MAX_PACING_RATE,
+            &rate as *const _ as *const libc::c_void,
+            std::mem::size_of_val(&rate) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+}
@@ -0,0 +1,95 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Tests that the priority interface queueing discipline sends the packets of a high-priority
+//! socket before those of a socket that saturates the uplink. Must be run with the hosts and
+//! options in priority-qdisc.yaml.
+
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+const SERVER_IP: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 2);
+/// The server answers each datagram sent to this port.
+const ECHO_PORT: u16 = 5000;
+/// The server discards the datagrams sent to this port.
+const SINK_PORT: u16 = 5001;
+
+/// The bulk socket queues this many datagrams of [`BULK_SIZE`] bytes, which take about half a
+/// second to send over the client's 1 Mbit uplink.
+const BULK_DATAGRAMS: usize = 60;
+const BULK_SIZE: usize = 1000;
+
+/// The `SO_PRIORITY` of interactive traffic, which is in the highest priority band.
+const TC_PRIO_INTERACTIVE: libc::c_int = 6;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("client") => client(),
+        Some("server") => server(args[2].parse().unwrap()),
+        _ => panic!("Usage: {} client | server <echoes>", args[0]),
+    }
+}
+
+fn server(num_echoes: usize) {
+    let echo = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, ECHO_PORT)).unwrap();
+    let _sink = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, SINK_PORT)).unwrap();
+
+    for _ in 0..num_echoes {
+        let mut buf = [0; 16];
+        let (len, peer) = echo.recv_from(&mut buf).unwrap();
+        echo.send_to(&buf[..len], peer).unwrap();
+    }
+}
+
+fn client() {
+    let bulk = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    let interactive = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    set_priority(&interactive, TC_PRIO_INTERACTIVE);
+    // the default priority is in the same band as the bulk socket
+    let normal = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+
+    // saturate the uplink, then measure the round trip time of each socket while the bulk
+    // datagrams are still queued
+    let sink = SocketAddrV4::new(SERVER_IP, SINK_PORT);
+    for _ in 0..BULK_DATAGRAMS {
+        bulk.send_to(&[0; BULK_SIZE], sink).unwrap();
+    }
+
+    // the interactive datagram is sent before the queued bulk datagrams
+    let rtt = round_trip(&interactive);
+    assert!(rtt < Duration::from_millis(50), "{rtt:?}");
+
+    // the bulk datagrams are still queued after the interactive round trip, so a datagram with
+    // the default priority waits for them
+    let rtt = round_trip(&normal);
+    assert!(rtt > Duration::from_millis(300), "{rtt:?}");
+}
+
+/// Send a datagram to the server's echo port, and return the time until its reply arrives.
+fn round_trip(socket: &UdpSocket) -> Duration {
+    let start = Instant::now();
+    socket
+        .send_to(b"ping", SocketAddrV4::new(SERVER_IP, ECHO_PORT))
+        .unwrap();
+    let mut buf = [0; 16];
+    let len = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"ping");
+    start.elapsed()
+}
+
+fn set_priority(socket: &UdpSocket, priority: libc::c_int) {
+    let rv = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PRIORITY,
+            &priority as *const _ as *const libc::c_void,
+            std::mem::size_of_val(&priority) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0, "{}", std::io::Error::last_os_error());
+}
@@ -283,11 +283,7 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                             move || {
                                 test_recvfrom_econnrefused_after_sendto(method, sock_type, flag)
                             },
-                            match method.domain() {
-                                // TODO: enable if shadow ever supports ICMP
-                                libc::AF_INET => set![TestEnv::Libc],
-                                _ => set![TestEnv::Libc, TestEnv::Shadow],
-                            },
+                            set![TestEnv::Libc, TestEnv::Shadow],
                         ),
                     ]);
                 }
@@ -1165,6 +1161,9 @@ fn test_recvfrom_econnrefused_after_sendto(
         // send 3 bytes; unix sockets will return an error
         simple_sendto_helper(fd_client, &[1u8, 2, 3], expected_errnos, true)?;
 
+        // shadow needs to run events so that the ICMP error arrives
+        assert_eq!(unsafe { libc::usleep(10000) }, 0);
+
         let expected_errnos = match (init_method.domain(), sock_type) {
             // connectionless unix sockets
             (libc::AF_UNIX, libc::SOCK_DGRAM) => &[libc::EAGAIN][..],
@@ -185,6 +185,21 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     move || test_tcp_congestion(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_ip_tos"),
+                    move || test_ip_tos(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_priority"),
+                    move || test_so_priority(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
+                test_utils::ShadowTest::new(
+                    &append_args("test_so_max_pacing_rate"),
+                    move || test_so_max_pacing_rate(domain, sock_type),
+                    set![TestEnv::Libc, TestEnv::Shadow],
+                ),
             ];
 
             tests.extend(more_tests);
@@ -807,6 +822,148 @@ fn test_tcp_congestion(domain: libc::c_int, sock_type: libc::c_int) -> Result<()
     })
 }
 
+/// Test getsockopt() and setsockopt() using the IP_TOS option.
+fn test_ip_tos(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_IP;
+    let optname = libc::IP_TOS;
+
+    // low delay, throughput, expedited forwarding, and the default
+    let optvals = [0x10i32, 0x08, 0xb8, 0x00];
+
+    test_utils::run_and_close_fds(&[fd], || {
+        for &optval in &optvals {
+            let mut get_args =
+                GetsockoptArguments::new(fd, level, optname, Some(0i32.to_ne_bytes().into()));
+            let mut set_args =
+                SetsockoptArguments::new(fd, level, optname, Some(optval.to_ne_bytes().into()));
+
+            check_setsockopt_call(&mut set_args, &[])?;
+            check_getsockopt_call(&mut get_args, &[])?;
+
+            let returned_optval =
+                i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+            test_utils::result_assert_eq(returned_optval, optval, "Unexpected value for IP_TOS")?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() and setsockopt() using the SO_PRIORITY option.
+fn test_so_priority(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_PRIORITY;
+
+    // linux only allows larger priorities with CAP_NET_ADMIN
+    let optvals = [6i32, 3, 0];
+
+    test_utils::run_and_close_fds(&[fd], || {
+        let mut get_args =
+            GetsockoptArguments::new(fd, level, optname, Some(0i32.to_ne_bytes().into()));
+        check_getsockopt_call(&mut get_args, &[])?;
+
+        let returned_optval =
+            i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 0, "Unexpected default SO_PRIORITY")?;
+
+        for &optval in &optvals {
+            let mut get_args =
+                GetsockoptArguments::new(fd, level, optname, Some(0i32.to_ne_bytes().into()));
+            let mut set_args =
+                SetsockoptArguments::new(fd, level, optname, Some(optval.to_ne_bytes().into()));
+
+            check_setsockopt_call(&mut set_args, &[])?;
+            check_getsockopt_call(&mut get_args, &[])?;
+
+            let returned_optval =
+                i32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+            test_utils::result_assert_eq(
+                returned_optval,
+                optval,
+                "Unexpected value for SO_PRIORITY",
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Test getsockopt() and setsockopt() using the SO_MAX_PACING_RATE option.
+fn test_so_max_pacing_rate(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_MAX_PACING_RATE;
+
+    test_utils::run_and_close_fds(&[fd], || {
+        // the default is unlimited, which is capped when read as 32 bits
+        let mut get_args =
+            GetsockoptArguments::new(fd, level, optname, Some(0u32.to_ne_bytes().into()));
+        check_getsockopt_call(&mut get_args, &[])?;
+
+        let returned_optval =
+            u32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, u32::MAX, "Unexpected default rate")?;
+
+        let mut get_args =
+            GetsockoptArguments::new(fd, level, optname, Some(0u64.to_ne_bytes().into()));
+        check_getsockopt_call(&mut get_args, &[])?;
+
+        let returned_optval =
+            u64::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, u64::MAX, "Unexpected default rate")?;
+
+        // a 32-bit rate can be read back as 64 bits
+        let mut set_args =
+            SetsockoptArguments::new(fd, level, optname, Some(125_000u32.to_ne_bytes().into()));
+        check_setsockopt_call(&mut set_args, &[])?;
+
+        let mut get_args =
+            GetsockoptArguments::new(fd, level, optname, Some(0u64.to_ne_bytes().into()));
+        check_getsockopt_call(&mut get_args, &[])?;
+
+        let returned_optval =
+            u64::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, 125_000, "Unexpected rate")?;
+
+        // a 64-bit rate larger than 32 bits is capped when read as 32 bits
+        let rate = 1u64 << 40;
+        let mut set_args =
+            SetsockoptArguments::new(fd, level, optname, Some(rate.to_ne_bytes().into()));
+        check_setsockopt_call(&mut set_args, &[])?;
+
+        let mut get_args =
+            GetsockoptArguments::new(fd, level, optname, Some(0u32.to_ne_bytes().into()));
+        check_getsockopt_call(&mut get_args, &[])?;
+
+        let returned_optval =
+            u32::from_ne_bytes(get_args.optval.as_ref().unwrap()[..].try_into().unwrap());
+        test_utils::result_assert_eq(returned_optval, u32::MAX, "Unexpected capped rate")?;
+
+        if sock_type == libc::SOCK_STREAM {
+            // the libc package's 'struct tcp_info' is missing the newer fields, so we read
+            // 'tcpi_max_pacing_rate' from its offset in linux's struct
+            let mut get_args =
+                GetsockoptArguments::new(fd, libc::SOL_TCP, libc::TCP_INFO, Some(vec![0; 120]));
+            check_getsockopt_call(&mut get_args, &[])?;
+
+            test_utils::result_assert_eq(get_args.optlen, Some(120), "Unexpected TCP_INFO len")?;
+            let returned_optval =
+                u64::from_ne_bytes(get_args.optval.as_ref().unwrap()[112..].try_into().unwrap());
+            test_utils::result_assert_eq(returned_optval, rate, "Unexpected tcpi_max_pacing_rate")?;
+        }
+
+        Ok(())
+    })
+}
+
 fn check_getsockopt_call(
     args: &mut GetsockoptArguments,
     expected_errnos: &[libc::c_int],